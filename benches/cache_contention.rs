@@ -0,0 +1,181 @@
+//! Cache contention benchmark
+//!
+//! Runs 1k concurrent tasks of cache lookups and inserts, the access pattern
+//! of `/get_pot` under load, against a single `RwLock` around the cache and
+//! against the sharded [`ShardedMap`] the session manager uses, and prints
+//! the wall time and throughput of each. A last run inserts new tokens into
+//! a full `SessionManager` cache, so every insert evicts an entry.
+//!
+//! Run with `cargo bench --bench cache_contention`.
+
+use bgutil_ytdlp_pot_provider::{
+    Settings,
+    session::{CacheKey, CacheKeyMap, SessionManager, ShardedMap},
+    types::SessionData,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+const TASKS: usize = 1_000;
+const OPS_PER_TASK: usize = 1_000;
+const ENTRIES: usize = 1_000;
+/// One in `WRITE_EVERY` operations is an insert, the rest lookups
+const WRITE_EVERY: usize = 10;
+const ROUNDS: u32 = 5;
+
+/// Cache under test
+trait Cache: Send + Sync + 'static {
+    fn get(&self, key: &CacheKey) -> impl Future<Output = Option<Arc<String>>> + Send;
+    fn insert(&self, key: CacheKey, value: Arc<String>) -> impl Future<Output = ()> + Send;
+}
+
+impl Cache for RwLock<CacheKeyMap<Arc<String>>> {
+    async fn get(&self, key: &CacheKey) -> Option<Arc<String>> {
+        self.read().await.get(key).cloned()
+    }
+
+    async fn insert(&self, key: CacheKey, value: Arc<String>) {
+        self.write().await.insert(key, value);
+    }
+}
+
+impl Cache for ShardedMap<Arc<String>> {
+    async fn get(&self, key: &CacheKey) -> Option<Arc<String>> {
+        ShardedMap::get(self, key).await
+    }
+
+    async fn insert(&self, key: CacheKey, value: Arc<String>) {
+        ShardedMap::insert(self, key, value).await;
+    }
+}
+
+/// Run `TASKS` concurrent tasks against `cache` and return the wall time
+async fn run<C: Cache>(cache: Arc<C>, keys: Arc<Vec<CacheKey>>) -> Duration {
+    let token = Arc::new("token".repeat(30));
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let cache = cache.clone();
+            let keys = keys.clone();
+            let token = token.clone();
+            tokio::spawn(async move {
+                for op in 0..OPS_PER_TASK {
+                    let key = &keys[(task * 7919 + op) % ENTRIES];
+                    if op % WRITE_EVERY == 0 {
+                        cache.insert(key.clone(), token.clone()).await;
+                    } else {
+                        std::hint::black_box(cache.get(key).await);
+                    }
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    start.elapsed()
+}
+
+fn bench<C: Cache>(
+    runtime: &tokio::runtime::Runtime,
+    name: &str,
+    new_cache: impl Fn() -> C,
+    keys: &Arc<Vec<CacheKey>>,
+) {
+    // Warm up the runtime's worker threads
+    runtime.block_on(run(Arc::new(new_cache()), keys.clone()));
+
+    let mut total = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        total += runtime.block_on(run(Arc::new(new_cache()), keys.clone()));
+    }
+    let elapsed = total / ROUNDS;
+    let ops = (TASKS * OPS_PER_TASK) as f64;
+    println!(
+        "{:<28} {:>10.1} ms {:>12.0} ops/s",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        ops / elapsed.as_secs_f64()
+    );
+}
+
+/// Insert `TASKS` x `OPS_PER_TASK / WRITE_EVERY` new tokens into a full
+/// session data cache of `ENTRIES` entries and return the wall time
+async fn run_full_session_cache() -> Duration {
+    let mut settings = Settings::default();
+    settings.token.max_cache_entries = ENTRIES;
+    settings.cache.enable_file_cache = false;
+    let manager = Arc::new(SessionManager::new(settings));
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(6);
+    for i in 0..ENTRIES {
+        let binding = format!("video_{:08}", i);
+        let data = SessionData::new("token".repeat(30), binding.as_str(), expires_at);
+        manager
+            .cache_session_data(CacheKey::new(&binding), &data)
+            .await;
+    }
+
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                for op in 0..OPS_PER_TASK / WRITE_EVERY {
+                    let binding = format!("new_{}_{}", task, op);
+                    let expires_at = expires_at + chrono::Duration::seconds(op as i64);
+                    let data = SessionData::new("token".repeat(30), binding.as_str(), expires_at);
+                    manager
+                        .cache_session_data(CacheKey::new(&binding), &data)
+                        .await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let keys: Arc<Vec<CacheKey>> = Arc::new(
+        (0..ENTRIES)
+            .map(|i| CacheKey::new(format!("video_{:08}", i)))
+            .collect(),
+    );
+
+    println!(
+        "{} tasks x {} ops, 1 in {} an insert, {} worker threads",
+        TASKS,
+        OPS_PER_TASK,
+        WRITE_EVERY,
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+    bench(
+        &runtime,
+        "single RwLock<HashMap>",
+        || RwLock::new(CacheKeyMap::default()),
+        &keys,
+    );
+    bench(&runtime, "ShardedMap", ShardedMap::new, &keys);
+
+    runtime.block_on(run_full_session_cache());
+    let mut total = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        total += runtime.block_on(run_full_session_cache());
+    }
+    let elapsed = total / ROUNDS;
+    let inserts = (TASKS * OPS_PER_TASK / WRITE_EVERY) as f64;
+    println!(
+        "{:<28} {:>10.1} ms {:>12.0} inserts/s",
+        "full SessionManager cache",
+        elapsed.as_secs_f64() * 1000.0,
+        inserts / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,97 @@
+//! Cache key benchmark
+//!
+//! Compares deriving the minter cache key by formatting the label string
+//! first, as done before keys were hashed from their parts, against hashing
+//! the proxy spec fields directly, and string keys against precomputed
+//! hashed keys for session cache lookups.
+//!
+//! Run with `cargo bench --bench cache_key`.
+
+use bgutil_ytdlp_pot_provider::session::{CacheKey, CacheKeyMap, MinterCacheKey, ProxySpec};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 1_000_000;
+const ENTRIES: usize = 1_000;
+
+/// Run `f` for `ITERATIONS` rounds and print the mean time per round
+fn bench(name: &str, mut f: impl FnMut(u32)) {
+    // Warm up caches and branch predictors
+    for i in 0..ITERATIONS / 10 {
+        f(i);
+    }
+
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<40} {:>10.1} ns/iter",
+        name,
+        per_iter_nanos(elapsed, ITERATIONS)
+    );
+}
+
+fn per_iter_nanos(elapsed: Duration, iterations: u32) -> f64 {
+    elapsed.as_nanos() as f64 / f64::from(iterations)
+}
+
+/// Minter cache key derived through the formatted label and a
+/// [`MinterCacheKey`], hashing the label twice
+fn string_path_cache_key(spec: &ProxySpec) -> CacheKey {
+    CacheKey::from(MinterCacheKey::new(spec.cache_key(None), None))
+}
+
+fn main() {
+    let spec = ProxySpec::new()
+        .with_proxy("http://proxy.example.com:8080")
+        .with_source_address("192.168.1.10");
+
+    bench("proxy_spec/cache_key", |_| {
+        black_box(black_box(&spec).cache_key(None));
+    });
+    bench("proxy_spec/string path hashed key", |_| {
+        black_box(string_path_cache_key(black_box(&spec)));
+    });
+    bench("proxy_spec/hashed_cache_key", |_| {
+        black_box(black_box(&spec).hashed_cache_key(None));
+    });
+    bench("proxy_spec/hashed_cache_key remote_host", |_| {
+        black_box(black_box(&spec).hashed_cache_key(Some("203.0.113.7")));
+    });
+
+    let bindings: Vec<String> = (0..ENTRIES).map(|i| format!("video_{:08}", i)).collect();
+    let string_map: HashMap<String, usize> = bindings
+        .iter()
+        .enumerate()
+        .map(|(i, binding)| (binding.clone(), i))
+        .collect();
+    let hashed_keys: Vec<CacheKey> = bindings.iter().map(CacheKey::new).collect();
+    let hashed_map: CacheKeyMap<usize> = hashed_keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.clone(), i))
+        .collect();
+
+    bench("session_cache/string lookup", |i| {
+        let binding = &bindings[i as usize % ENTRIES];
+        black_box(string_map.get(black_box(binding.as_str())));
+    });
+    bench("session_cache/hashed lookup", |i| {
+        let key = &hashed_keys[i as usize % ENTRIES];
+        black_box(hashed_map.get(black_box(key)));
+    });
+    bench("session_cache/string lookup+insert key", |i| {
+        let binding = &bindings[i as usize % ENTRIES];
+        black_box(string_map.get(binding.as_str()));
+        black_box(binding.to_string());
+    });
+    bench("session_cache/hashed lookup+insert key", |i| {
+        let key = &hashed_keys[i as usize % ENTRIES];
+        black_box(hashed_map.get(key));
+        black_box(key.clone());
+    });
+}
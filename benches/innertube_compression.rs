@@ -0,0 +1,131 @@
+//! Innertube response compression benchmark
+//!
+//! Fetches a browse-sized JSON response over loopback without compression,
+//! gzip and brotli encoded, and prints the bytes on the wire, the mean
+//! latency of the client including decompression, and how long the bytes
+//! take on slow links. Compression pays off whenever the transfer time it
+//! saves exceeds the decompression time it adds.
+//!
+//! Run with `cargo bench --bench innertube_compression`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers::path};
+
+const ITERATIONS: u32 = 200;
+
+/// Link speeds in bits per second to estimate transfer times for
+const LINKS: &[(&str, f64)] = &[
+    ("2 Mbit/s", 2e6),
+    ("20 Mbit/s", 20e6),
+    ("200 Mbit/s", 200e6),
+];
+
+/// Build a JSON body shaped and sized like a browse response
+fn browse_response() -> Vec<u8> {
+    let items: Vec<_> = (0..400)
+        .map(|i| {
+            serde_json::json!({
+                "richItemRenderer": {
+                    "content": {
+                        "videoRenderer": {
+                            "videoId": format!("video{:06}", i),
+                            "thumbnail": {"thumbnails": [
+                                {"url": format!("https://i.ytimg.com/vi/video{:06}/hqdefault.jpg", i), "width": 480, "height": 270}
+                            ]},
+                            "title": {"runs": [{"text": format!("Recommended video number {}", i)}]},
+                            "viewCountText": {"simpleText": format!("{} views", i * 1337)},
+                            "trackingParams": format!("CAAQ{:032x}", i * 7919),
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    serde_json::to_vec(&serde_json::json!({
+        "responseContext": {"visitorData": "CgtDZjBSbE5uZDJlQSij6bbFBjIKCgJVUxIEGgAgYA%3D%3D"},
+        "contents": {"richGridRenderer": {"contents": items}},
+    }))
+    .unwrap()
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(body: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+    encoder.write_all(body).unwrap();
+    drop(encoder);
+    compressed
+}
+
+#[tokio::main]
+async fn main() {
+    let body = browse_response();
+    let server = MockServer::start().await;
+    let encodings = [
+        ("identity", None, body.clone()),
+        ("gzip", Some("gzip"), gzip(&body)),
+        ("br", Some("br"), brotli(&body)),
+    ];
+    for (name, encoding, encoded) in &encodings {
+        let mut response = ResponseTemplate::new(200).set_body_bytes(encoded.clone());
+        if let Some(encoding) = encoding {
+            response = response.insert_header("content-encoding", *encoding);
+        }
+        Mock::given(path(format!("/{}", name)))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+    }
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    print!("{:<10} {:>10} {:>14}", "encoding", "bytes", "loopback");
+    for (link, _) in LINKS {
+        print!(" {:>12}", link);
+    }
+    println!();
+
+    for (name, _, encoded) in &encodings {
+        let url = format!("{}/{}", server.uri(), name);
+        let fetch = || async {
+            let decoded = client
+                .get(&url)
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            assert_eq!(decoded.len(), body.len());
+        };
+        // Warm up the connection pool
+        fetch().await;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            fetch().await;
+        }
+        let latency = start.elapsed() / ITERATIONS;
+
+        print!(
+            "{:<10} {:>10} {:>11.0} µs",
+            name,
+            encoded.len(),
+            micros(latency)
+        );
+        for (_, bits_per_sec) in LINKS {
+            let transfer = Duration::from_secs_f64(encoded.len() as f64 * 8.0 / bits_per_sec);
+            print!(" {:>9.1} ms", (latency + transfer).as_secs_f64() * 1000.0);
+        }
+        println!();
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
@@ -0,0 +1,47 @@
+//! Response body benchmark
+//!
+//! Compares serializing the `/get_pot` response per cache hit against
+//! serving the body serialized when the token was cached.
+//!
+//! Run with `cargo bench --bench response_body`.
+
+use bgutil_ytdlp_pot_provider::types::{PotResponse, SessionData};
+use chrono::{Duration, Utc};
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 1_000_000;
+
+/// Run `f` for `ITERATIONS` rounds and print the mean time per round
+fn bench(name: &str, mut f: impl FnMut()) {
+    // Warm up caches and branch predictors
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<40} {:>10.1} ns/iter",
+        name,
+        elapsed.as_nanos() as f64 / f64::from(ITERATIONS)
+    );
+}
+
+fn main() {
+    let po_token = "M".repeat(160);
+    let data = SessionData::new(po_token, "dQw4w9WgXcQ", Utc::now() + Duration::hours(6));
+    data.response_body();
+
+    bench("cache_hit/serialize response", || {
+        let response = PotResponse::from_session_data(black_box(&data).clone());
+        black_box(serde_json::to_vec(&response).unwrap());
+    });
+    bench("cache_hit/pre-serialized body", || {
+        black_box(black_box(&data).clone().response_body());
+    });
+}
@@ -3,16 +3,16 @@
 //! This example demonstrates how to use the SessionManager to generate POT tokens
 //! using the rustypipe-botguard integration.
 
-use bgutil_ytdlp_pot_provider::{SessionManager, Settings, types::PotRequest};
+use bgutil_ytdlp_pot_provider::{SessionManager, Settings, types::PotRequest, utils::logging};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt().init();
-
     // Create default settings
     let settings = Settings::default();
 
+    // Initialize logging
+    let _logging = logging::init(&settings.logging);
+
     // Create session manager
     let session_manager = SessionManager::new(settings);
 
@@ -3,13 +3,13 @@
 //! This example demonstrates various ways to configure the BgUtils POT Provider,
 //! including loading from environment variables and configuration files.
 
-use bgutil_ytdlp_pot_provider::{Settings, config::ConfigLoader};
+use bgutil_ytdlp_pot_provider::{Settings, config::ConfigLoader, utils::logging};
 use std::env;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
-    tracing_subscriber::fmt().init();
+    let _logging = logging::init(&Settings::default().logging);
 
     println!("🔧 BgUtils POT Provider - Configuration Examples");
     println!("================================================");
@@ -0,0 +1,53 @@
+//! Embedded server example
+//!
+//! This example shows how to mount the provider routes into an existing Axum
+//! application under a prefix, sharing one session manager between the
+//! provider routes and a route of the application itself.
+
+use axum::{Json, Router, extract::State, routing::get};
+use bgutil_ytdlp_pot_provider::{
+    Settings,
+    server::{AppState, create_router},
+    session::SessionManagerBuilder,
+    utils::logging,
+};
+use tokio::net::TcpListener;
+
+/// A route of the embedding application using the shared session manager
+async fn token_stats(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let stats = state.session_manager.request_stats();
+    Json(serde_json::json!({
+        "requests": stats.requests_total,
+        "cache_hits": stats.cache_hits,
+    }))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::default();
+    let _logging = logging::init(&settings.logging);
+
+    let session_manager = SessionManagerBuilder::new(settings.clone()).start();
+    let state = AppState::new(settings, session_manager.clone());
+
+    // The provider answers under /pot, e.g. POST /pot/get_pot
+    let app = Router::new()
+        .route("/token_stats", get(token_stats))
+        .with_state(state.clone())
+        .nest("/pot", create_router(state));
+
+    let listener = TcpListener::bind("127.0.0.1:8080").await?;
+    println!("Listening on http://127.0.0.1:8080");
+    println!("  POST /pot/get_pot   provider endpoint");
+    println!("  GET  /token_stats   application endpoint");
+    println!("Press Ctrl+C to stop the server.");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    session_manager.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Token generation example
+//!
+//! This example shows how to build a session manager with
+//! `SessionManagerBuilder`, plug in a file cache of its own, and generate a
+//! POT token through it.
+
+use bgutil_ytdlp_pot_provider::{
+    Settings, session::SessionManagerBuilder, types::PotRequest, utils::cache::FileCache,
+    utils::logging,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::default();
+    let _logging = logging::init(&settings.logging);
+
+    // Persist tokens to a cache file of our own instead of the default location
+    let cache_path = std::env::temp_dir().join("bgutil-example-cache.json");
+    let session_manager = SessionManagerBuilder::new(settings)
+        .with_file_cache(FileCache::new(cache_path.clone()))
+        .start();
+
+    let request = PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+    let result = session_manager.generate_pot_token(&request).await;
+
+    // Writes the cached token to the cache file and stops BotGuard
+    session_manager.shutdown().await;
+
+    let response = result?;
+    println!("Token: {}", response.po_token);
+    println!("Expires at: {}", response.expires_at);
+    println!("Cached in: {}", cache_path.display());
+
+    Ok(())
+}
@@ -3,16 +3,16 @@
 //! This example shows how to start an HTTP server and handle POT token requests
 //! using the BgUtils POT Provider.
 
-use bgutil_ytdlp_pot_provider::{Settings, server::create_app};
+use bgutil_ytdlp_pot_provider::{Settings, server::create_app, utils::logging};
 use tokio::net::TcpListener;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging with INFO level
-    tracing_subscriber::fmt().with_env_filter("info").init();
-
     // Create settings with custom configuration
     let mut settings = Settings::default();
+
+    // Initialize logging with the default INFO level
+    let _logging = logging::init(&settings.logging);
     settings.server.host = "127.0.0.1".to_string();
     settings.server.port = 8080;
     settings.token.ttl_hours = 12; // Extended TTL for this example
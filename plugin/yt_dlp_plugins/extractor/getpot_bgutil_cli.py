@@ -83,7 +83,7 @@ class BgUtilCliPTP(BgUtilPTPBase):
 
     def _get_executable_path(self, cli_path):
         """Get the actual executable path, checking PATH or file existence."""
-        # For relative names (like 'bgutil-pot-generate'), search in PATH
+        # For relative names (like 'bgutil-pot'), search in PATH
         if os.path.sep not in cli_path:
             executable_path = shutil.which(cli_path)
             if executable_path:
@@ -39,4 +39,4 @@ cargo build
 echo "DONE!"
 echo -e "Use the following commands:"
 echo -e "  Start the server: ./target/debug/bgutil-pot server"
-echo -e "  Generate single token: ./target/debug/bgutil-pot --content-binding 'test'"
+echo -e "  Generate single token: ./target/debug/bgutil-pot generate --content-binding 'test'"
@@ -0,0 +1,237 @@
+//! Cache mode CLI logic
+//!
+//! `bgutil-pot cache export` writes the cached tokens and token minters to a
+//! JSON file that `bgutil-pot cache import` adds to the caches of another
+//! instance, so operators moving the provider to a new host keep its caches
+//! warm. Without `--url` the commands work on the file cache of the local
+//! configuration, with it on a running server through its admin endpoints.
+
+use crate::{
+    Settings,
+    cli::generate::{GenerateArgs, load_generate_settings},
+    session::SessionManager,
+    types::{CacheDump, CacheImportResponse},
+    utils::cache::{FileCache, get_cache_path_for},
+};
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Timeout of requests to a running server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cache subcommands
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum CacheCommand {
+    /// Write the cached tokens and minters to a JSON file
+    Export {
+        /// File to write the dump to
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+
+        #[command(flatten)]
+        target: CacheTarget,
+    },
+    /// Add the entries of a dump written by `cache export` to the caches
+    Import {
+        /// File to read the dump from
+        #[arg(long = "in", value_name = "FILE")]
+        input: PathBuf,
+
+        #[command(flatten)]
+        target: CacheTarget,
+    },
+}
+
+/// Caches the cache subcommands work on
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct CacheTarget {
+    /// Base URL of a running server (default: the local file cache)
+    #[arg(long, value_name = "URL")]
+    pub url: Option<String>,
+
+    /// Admin token of the server (default: `server.admin_token`)
+    #[arg(long, value_name = "TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Configuration file path
+    #[arg(long, value_name = "CONFIG")]
+    pub config: Option<String>,
+
+    /// Configuration profile to merge over the base settings (e.g. prod)
+    #[arg(long, value_name = "PROFILE")]
+    pub profile: Option<String>,
+}
+
+/// Run cache mode with the given subcommand
+pub async fn run_cache_mode(command: CacheCommand) -> Result<()> {
+    match command {
+        CacheCommand::Export { out, target } => {
+            let settings = load_cache_settings(&target)?;
+            let dump = match &target.url {
+                Some(url) => {
+                    let response = server_request(
+                        url,
+                        &target,
+                        &settings,
+                        reqwest::Method::GET,
+                        "cache/export",
+                    )?
+                    .send()
+                    .await?;
+                    parse_response(response).await?
+                }
+                None => local_manager(&settings)?.export_caches().await,
+            };
+            std::fs::write(&out, serde_json::to_vec_pretty(&dump)?)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+            eprintln!(
+                "Exported {} cached tokens and {} minters to {}",
+                dump.session_data.len(),
+                dump.minters.len(),
+                out.display()
+            );
+        }
+        CacheCommand::Import { input, target } => {
+            let settings = load_cache_settings(&target)?;
+            let dump = read_dump(&input)?;
+            let imported: CacheImportResponse = match &target.url {
+                Some(url) => {
+                    let response = server_request(
+                        url,
+                        &target,
+                        &settings,
+                        reqwest::Method::POST,
+                        "cache/import",
+                    )?
+                    .json(&dump)
+                    .send()
+                    .await?;
+                    parse_response(response).await?
+                }
+                None => {
+                    let manager = local_manager(&settings)?;
+                    let imported = manager.import_caches(dump).await;
+                    manager.flush_caches().await?;
+                    imported
+                }
+            };
+            eprintln!(
+                "Imported {} cached tokens and {} minters from {}",
+                imported.session_data,
+                imported.minters,
+                input.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Load the settings for a cache subcommand
+fn load_cache_settings(target: &CacheTarget) -> Result<Settings> {
+    Ok(load_generate_settings(&GenerateArgs {
+        config: target.config.clone(),
+        profile: target.profile.clone(),
+        ..Default::default()
+    })?)
+}
+
+/// Read a dump written by `cache export`
+fn read_dump(path: &Path) -> Result<CacheDump> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("Invalid cache dump {}", path.display()))
+}
+
+/// Session manager on the file cache of the local configuration
+fn local_manager(settings: &Settings) -> Result<SessionManager> {
+    if !settings.cache.enable_file_cache {
+        bail!("The file cache is disabled, pass --url to use a running server");
+    }
+    let cache_path = get_cache_path_for(&settings.cache)?;
+    Ok(SessionManager::new(settings.clone()).with_file_cache(
+        FileCache::new(cache_path).with_revealed_bindings(settings.logging.reveal_content_bindings),
+    ))
+}
+
+/// Request to `endpoint` of the server at `url`, carrying the admin token
+fn server_request(
+    url: &str,
+    target: &CacheTarget,
+    settings: &Settings,
+    method: reqwest::Method,
+    endpoint: &str,
+) -> Result<reqwest::RequestBuilder> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let mut request = client.request(
+        method,
+        format!("{}/{}", url.trim_end_matches('/'), endpoint),
+    );
+    if let Some(token) = target
+        .admin_token
+        .as_ref()
+        .or(settings.server.admin_token.as_ref())
+    {
+        request = request.bearer_auth(token);
+    }
+    Ok(request)
+}
+
+/// Parse a successful response of the server, or fail with its error
+async fn parse_response<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Server returned {}: {}", status, body);
+    }
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::CacheKey;
+    use crate::types::SessionData;
+
+    #[tokio::test]
+    async fn test_export_and_import_through_server() {
+        let settings = Settings::default();
+        let source = std::sync::Arc::new(SessionManager::new(settings.clone()));
+        source
+            .cache_session_data(
+                CacheKey::new("video"),
+                &SessionData::new(
+                    "token",
+                    "video",
+                    chrono::Utc::now() + chrono::Duration::hours(1),
+                ),
+            )
+            .await;
+        let app = crate::server::app::create_app_with_session_manager(settings, source);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let dir = tempfile::tempdir().unwrap();
+        let dump = dir.path().join("dump.json");
+        let target = CacheTarget {
+            url: Some(url),
+            ..Default::default()
+        };
+        run_cache_mode(CacheCommand::Export {
+            out: dump.clone(),
+            target: target.clone(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(read_dump(&dump).unwrap().session_data.len(), 1);
+
+        run_cache_mode(CacheCommand::Import {
+            input: dump,
+            target,
+        })
+        .await
+        .unwrap();
+    }
+}
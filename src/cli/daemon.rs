@@ -0,0 +1,380 @@
+//! Script mode daemon
+//!
+//! `bgutil-pot generate --daemon` sends its request to a provider server on
+//! a unix socket in the cache directory, spawning it with `bgutil-pot daemon`
+//! when none is running. Later invocations then get their tokens from the
+//! warm BotGuard runtime of the daemon instead of paying the cold start.
+//!
+//! The daemon exits after `server.daemon_idle_timeout` seconds without
+//! requests and removes its socket. A socket left behind by a daemon that
+//! died is replaced by the next daemon.
+
+use crate::{
+    Settings,
+    cli::generate::{GenerateArgs, load_generate_settings},
+    client::PotClient,
+    server::app,
+    session::SessionManagerBuilder,
+    types::{PotRequest, PotResponse},
+    utils::{
+        cache::{FileCache, get_cache_path_for},
+        logging, version,
+    },
+};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// How long to wait for a spawned daemon to accept connections
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between connection attempts to a spawned daemon
+const DAEMON_START_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Arguments for daemon mode
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct DaemonArgs {
+    /// Unix socket to listen on (default: daemon.sock in the cache directory)
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<String>,
+
+    /// Configuration file path
+    #[arg(long, value_name = "CONFIG")]
+    pub config: Option<String>,
+
+    /// Configuration profile to merge over the base settings (e.g. prod)
+    #[arg(long, value_name = "PROFILE")]
+    pub profile: Option<String>,
+
+    /// Enable verbose logging
+    #[arg(long)]
+    pub verbose: bool,
+}
+
+impl DaemonArgs {
+    /// Get the socket the daemon listens on
+    pub fn socket_path(&self, settings: &Settings) -> Result<PathBuf> {
+        match &self.socket {
+            Some(socket) => Ok(PathBuf::from(socket)),
+            None => Ok(get_cache_path_for(&settings.cache)?.with_file_name("daemon.sock")),
+        }
+    }
+}
+
+/// Run daemon mode with the given arguments
+pub async fn run_daemon_mode(args: DaemonArgs) -> Result<()> {
+    let settings = load_generate_settings(&GenerateArgs {
+        config: args.config.clone(),
+        profile: args.profile.clone(),
+        verbose: args.verbose,
+        ..Default::default()
+    })?;
+    let _logging = logging::init(&settings.logging);
+
+    let socket = args.socket_path(&settings)?;
+    let Some(listener) = bind_socket(&socket)? else {
+        info!("A daemon is already listening on {:?}", socket);
+        return Ok(());
+    };
+
+    // Share the file cache with script invocations not using the daemon
+    let mut builder = SessionManagerBuilder::new(settings.clone());
+    if settings.cache.enable_file_cache {
+        builder = builder.with_file_cache(
+            FileCache::new(get_cache_path_for(&settings.cache)?)
+                .with_revealed_bindings(settings.logging.reveal_content_bindings),
+        );
+    }
+    let session_manager = builder.start();
+
+    let idle = Arc::new(IdleTimer::new());
+    let app = app::create_app_with_session_manager(settings.clone(), session_manager.clone())
+        .layer(axum::middleware::from_fn({
+            let idle = idle.clone();
+            move |request, next: axum::middleware::Next| {
+                let idle = idle.clone();
+                async move { idle.track(next.run(request)).await }
+            }
+        }));
+
+    info!(
+        "POT daemon v{} listening on {:?}",
+        version::get_version(),
+        socket
+    );
+    let idle_timeout = Duration::from_secs(settings.server.daemon_idle_timeout.max(1));
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown({
+            let socket = socket.clone();
+            async move {
+                tokio::select! {
+                    _ = super::server::shutdown_signal() => {}
+                    _ = idle.wait(idle_timeout) => {
+                        info!("Idle for {:?}, shutting down", idle_timeout);
+                    }
+                }
+                // New invocations start a fresh daemon instead of connecting
+                // to this one while it shuts down
+                remove_socket(&socket);
+            }
+        })
+        .await;
+
+    remove_socket(&socket);
+    session_manager.shutdown().await;
+    result?;
+    Ok(())
+}
+
+/// Bind the daemon socket, replacing a stale one
+///
+/// Returns `None` if another daemon is listening on it already.
+fn bind_socket(path: &Path) -> Result<Option<tokio::net::UnixListener>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Ok(None);
+        }
+        info!("Removing stale daemon socket {:?}", path);
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = match tokio::net::UnixListener::bind(path) {
+        Ok(listener) => listener,
+        // Another daemon spawned at the same time won the race
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    // Only the user running the daemon may request tokens from it
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(Some(listener))
+}
+
+/// Remove the daemon socket, if it still exists
+fn remove_socket(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("Failed to remove daemon socket {:?}: {}", path, e);
+    }
+}
+
+/// Get the token for `request` from the daemon, starting it if none runs
+///
+/// Returns `None` if the daemon can't be reached or started, so the token is
+/// generated locally instead. Errors returned by the daemon are passed on.
+pub async fn generate_via_daemon(
+    args: &DaemonArgs,
+    settings: &Settings,
+    request: &PotRequest,
+) -> Option<crate::Result<PotResponse>> {
+    let socket = match args.socket_path(settings) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Failed to resolve daemon socket, generating locally: {}", e);
+            return None;
+        }
+    };
+    let client = match daemon_client(&socket, settings) {
+        Ok(client) => client,
+        Err(e) => return Some(Err(e)),
+    };
+
+    match client.get_pot(request).await {
+        Err(crate::Error::Http(e)) => debug!("No daemon on {:?}: {}", socket, e),
+        result => return Some(result),
+    }
+
+    info!("Starting daemon on {:?}", socket);
+    if let Err(e) = spawn_daemon(args, &socket) {
+        warn!("Failed to start daemon, generating locally: {}", e);
+        return None;
+    }
+    if !wait_for_socket(&socket).await {
+        warn!(
+            "Daemon didn't start within {:?}, generating locally",
+            DAEMON_START_TIMEOUT
+        );
+        return None;
+    }
+    match client.get_pot(request).await {
+        Err(crate::Error::Http(e)) => {
+            warn!("Failed to reach daemon, generating locally: {}", e);
+            None
+        }
+        result => Some(result),
+    }
+}
+
+/// Create a client sending requests to the daemon on `socket`
+fn daemon_client(socket: &Path, settings: &Settings) -> crate::Result<PotClient> {
+    let http = reqwest::Client::builder()
+        .unix_socket(socket)
+        .timeout(Duration::from_secs(settings.token.pot_generation_timeout))
+        .build()?;
+    Ok(PotClient::new("http://localhost")?
+        .with_http_client(http)
+        .with_max_retries(0)
+        .with_cache(false))
+}
+
+/// Start a daemon listening on `socket` in the background
+fn spawn_daemon(args: &DaemonArgs, socket: &Path) -> std::io::Result<()> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command.arg("daemon").arg("--socket").arg(socket);
+    if let Some(config) = &args.config {
+        command.args(["--config", config]);
+    }
+    if let Some(profile) = &args.profile {
+        command.args(["--profile", profile]);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Outlive Ctrl-C sent to the process group of the invoking script
+        .process_group(0)
+        .spawn()?;
+    Ok(())
+}
+
+/// Wait until a daemon accepts connections on `socket`
+async fn wait_for_socket(socket: &Path) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < DAEMON_START_TIMEOUT {
+        if tokio::net::UnixStream::connect(socket).await.is_ok() {
+            return true;
+        }
+        tokio::time::sleep(DAEMON_START_POLL_INTERVAL).await;
+    }
+    false
+}
+
+/// Tracks requests to tell when the daemon became idle
+#[derive(Debug)]
+struct IdleTimer {
+    /// Requests being answered
+    in_flight: AtomicUsize,
+    /// When the last request finished, or the daemon started
+    last_request: std::sync::Mutex<tokio::time::Instant>,
+}
+
+impl IdleTimer {
+    fn new() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            last_request: std::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Count `request` as in flight until it finishes or is dropped
+    async fn track<F: std::future::Future>(&self, request: F) -> F::Output {
+        /// Marks the request finished when dropped
+        struct InFlight<'a>(&'a IdleTimer);
+
+        impl Drop for InFlight<'_> {
+            fn drop(&mut self) {
+                *self
+                    .0
+                    .last_request
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()) = tokio::time::Instant::now();
+                self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let _in_flight = InFlight(self);
+        request.await
+    }
+
+    /// Time since the last request finished, `None` while requests are in flight
+    fn idle_for(&self) -> Option<Duration> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(
+            self.last_request
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .elapsed(),
+        )
+    }
+
+    /// Resolve once no request was answered for `timeout`
+    async fn wait(&self, timeout: Duration) {
+        loop {
+            let remaining = match self.idle_for() {
+                Some(idle) if idle >= timeout => return,
+                Some(idle) => timeout - idle,
+                None => timeout,
+            };
+            tokio::time::sleep(remaining).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bind_socket_replaces_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+
+        // Left behind by a daemon that died
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        let listener = bind_socket(&socket).unwrap().unwrap();
+
+        // Taken while the daemon listens
+        assert!(bind_socket(&socket).unwrap().is_none());
+        drop(listener);
+        remove_socket(&socket);
+        assert!(!socket.exists());
+    }
+
+    #[tokio::test]
+    async fn test_daemon_client_over_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let listener = bind_socket(&socket).unwrap().unwrap();
+        let settings = Settings::default();
+        let app = app::create_app(settings.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = daemon_client(&socket, &settings).unwrap();
+        let ping = client.ping().await.unwrap();
+        assert_eq!(ping.version, version::get_version());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timer() {
+        let idle = IdleTimer::new();
+        let timeout = Duration::from_secs(50);
+
+        // Requests in flight keep the daemon up
+        let request = idle.track(tokio::time::sleep(Duration::from_secs(120)));
+        tokio::pin!(request);
+        tokio::select! {
+            _ = &mut request => {}
+            _ = idle.wait(timeout) => panic!("idle while a request is in flight"),
+        }
+        assert!(idle.idle_for().is_some());
+
+        let started = tokio::time::Instant::now();
+        idle.wait(timeout).await;
+        assert!(started.elapsed() >= timeout);
+    }
+}
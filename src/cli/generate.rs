@@ -3,103 +3,332 @@
 //! Contains the core logic for the script mode POT token generation.
 
 use anyhow::Result;
+use std::time::Duration;
 use tracing::{debug, info, warn};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
     SessionManager, Settings,
-    types::PotRequest,
+    client::PotClient,
+    config::ConfigLoader,
+    types::{PotRequest, PotResponse},
     utils::{
         VERSION,
-        cache::{FileCache, get_cache_path},
+        cache::{FileCache, get_cache_path_for},
+        logging,
+        messages::{Locale, Message},
+        privacy::LoggedBinding,
+        progress::ProgressReporter,
     },
 };
 
+/// How long to wait for a connection to the `--remote` server before
+/// generating the token locally
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait for a candidate server to answer `GET /ping` when
+/// discovering a server with `--remote auto`
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Port the server listens on by default
+const DEFAULT_SERVER_PORT: u16 = 4416;
+
+/// Exit codes of script mode, so wrapper scripts can branch on the failure type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptExitCode {
+    /// Token generated
+    Success = 0,
+    /// Any other failure
+    General = 1,
+    /// Invalid configuration
+    Config = 2,
+    /// Network, Innertube or proxy connection failure
+    Network = 3,
+    /// BotGuard challenge, integrity token or minting failure
+    BotGuard = 4,
+    /// Invalid or deprecated arguments
+    InvalidInput = 5,
+    /// Cache read or write failure
+    Cache = 6,
+}
+
+/// Exit code table shown in `--help`
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  General failure
+  2  Configuration error
+  3  Network error (Innertube, proxy connection, rate limit)
+  4  BotGuard failure (challenge, integrity token, minting)
+  5  Invalid input (bad or deprecated arguments, invalid proxy URL)
+  6  Cache failure";
+
+impl ScriptExitCode {
+    /// Classify an error into an exit code
+    pub fn from_error(error: &crate::Error) -> Self {
+        use crate::Error;
+
+        match error {
+            Error::Config { .. } | Error::ConfigLegacy(..) | Error::Toml(..) => Self::Config,
+            Error::Http(..)
+            | Error::Network { .. }
+            | Error::Timeout { .. }
+            | Error::RateLimit { .. }
+            | Error::VisitorData { .. }
+            | Error::VisitorDataLegacy { .. } => Self::Network,
+            Error::BotGuard { .. }
+            | Error::BotGuardLegacy { .. }
+            | Error::TokenGeneration { .. }
+            | Error::TokenGenerationLegacy(..)
+            | Error::IntegrityToken { .. }
+            | Error::IntegrityTokenLegacy { .. }
+            | Error::Challenge { .. }
+            | Error::ChallengeLegacy { .. } => Self::BotGuard,
+            Error::Validation { .. }
+            | Error::Proxy { .. }
+            | Error::ProxyLegacy { .. }
+            | Error::Url(..)
+            | Error::MissingVideoId => Self::InvalidInput,
+            Error::Cache { .. } | Error::CacheLegacy { .. } => Self::Cache,
+            _ => Self::General,
+        }
+    }
+
+    /// Get the process exit code
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Exit the process with this code
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+
 /// Arguments for generate mode
-#[derive(Debug)]
+#[derive(Debug, Default, clap::Args)]
 pub struct GenerateArgs {
+    /// Content binding (video ID, visitor data, etc.)
+    #[arg(
+        short,
+        long,
+        value_name = "CONTENT_BINDING",
+        allow_hyphen_values = true
+    )]
     pub content_binding: Option<String>,
+
+    /// Visitor data (DEPRECATED: use --content-binding instead)
+    #[arg(short = 'v', long, value_name = "VISITOR_DATA")]
     pub visitor_data: Option<String>,
+
+    /// Data sync ID (DEPRECATED: use --content-binding instead)
+    #[arg(short = 'd', long, value_name = "DATA_SYNC_ID")]
     pub data_sync_id: Option<String>,
+
+    /// Proxy server URL (http://host:port, socks5://host:port, etc.)
+    #[arg(short, long, value_name = "PROXY")]
     pub proxy: Option<String>,
+
+    /// Bypass cache and force new token generation
+    #[arg(short = 'b', long)]
     pub bypass_cache: bool,
+
+    /// Source IP address for outbound connections
+    #[arg(short, long, value_name = "SOURCE_ADDRESS")]
     pub source_address: Option<String>,
+
+    /// Disable TLS certificate verification
+    #[arg(long)]
     pub disable_tls_verification: bool,
+
+    /// Print the version and exit
+    #[arg(skip)]
     pub version: bool,
+
+    /// Enable verbose logging
+    #[arg(long)]
     pub verbose: bool,
+
+    /// Emit JSONL progress events for each stage on stderr (requires --verbose)
+    #[arg(long, requires = "verbose")]
+    pub progress_json: bool,
+
+    /// Write a Chrome trace-event timeline of the request to this file
+    #[arg(long, value_name = "TRACE_FILE")]
+    pub trace_file: Option<String>,
+
+    /// Get the token from the provider server at this URL, generating it
+    /// locally if the server is unreachable (`auto` to look for a running
+    /// server)
+    #[arg(long, value_name = "URL")]
+    pub remote: Option<String>,
+
+    /// Get the token from a background daemon kept warm between
+    /// invocations, starting it if needed (Unix only)
+    #[arg(long, conflicts_with = "remote")]
+    pub daemon: bool,
+
+    /// Configuration file path
+    #[arg(long, value_name = "CONFIG")]
+    pub config: Option<String>,
+
+    /// Configuration profile to merge over the base settings (e.g. prod)
+    #[arg(long, value_name = "PROFILE")]
+    pub profile: Option<String>,
+
+    /// Invoked with bare flags instead of the `generate` subcommand
+    #[arg(skip)]
+    pub bare_flags: bool,
+}
+
+/// Load the settings for generate mode
+///
+/// Uses the same precedence as server mode: command line arguments, then
+/// environment variables, then the configuration file from `--config`,
+/// `BGUTIL_CONFIG` or the default location, then defaults.
+pub fn load_generate_settings(args: &GenerateArgs) -> crate::Result<Settings> {
+    let profile = args.profile.clone().or_else(ConfigLoader::get_profile);
+    let config_path = match &args.config {
+        Some(config) => Some(std::path::PathBuf::from(config)),
+        None => ConfigLoader::get_config_path(),
+    };
+
+    let mut settings = ConfigLoader::new()
+        .with_profile(profile)
+        .load(config_path.as_deref())?;
+    settings.logging.verbose = args.verbose;
+    if let Some(trace_file) = &args.trace_file {
+        settings.logging.trace_file = Some(std::path::PathBuf::from(trace_file));
+    }
+    Ok(settings)
 }
 
 /// Run generate mode with the given arguments
 pub async fn run_generate_mode(args: GenerateArgs) -> Result<()> {
+    let settings = if args.version {
+        Settings::default()
+    } else {
+        match load_generate_settings(&args) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    Locale::from_env().format(Message::ConfigInvalid, &[&e])
+                );
+                ScriptExitCode::Config.exit();
+            }
+        }
+    };
+    run_generate_with_settings(args, settings).await
+}
+
+/// Run generate mode with already loaded settings
+async fn run_generate_with_settings(args: GenerateArgs, mut settings: Settings) -> Result<()> {
     // Handle version flag early
     if args.version {
         println!("{}", VERSION);
         return Ok(());
     }
 
-    // Initialize logging (minimal for script mode)
-    if args.verbose {
-        tracing_subscriber::registry()
-            .with(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| "debug".into()),
-            )
-            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
-            .init();
-    } else {
-        tracing_subscriber::registry()
-            .with(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| "error".into()),
-            )
-            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
-            .init();
+    // Script mode only logs errors unless verbose, stdout carries the token
+    if !settings.logging.verbose {
+        settings.logging.level = "error".to_string();
+    }
+    let _logging = logging::init(&settings.logging);
+
+    let locale = Locale::from_settings(&settings);
+    if args.bare_flags {
+        eprintln!("{}", locale.format(Message::BareFlagsDeprecated, &[]));
     }
 
     // Handle deprecated parameters
     if let Some(ref _data_sync_id) = args.data_sync_id {
-        eprintln!("Data sync id is deprecated, use --content-binding instead");
-        std::process::exit(1);
+        eprintln!("{}", locale.format(Message::DeprecatedDataSyncId, &[]));
+        ScriptExitCode::InvalidInput.exit();
     }
 
     if let Some(ref _visitor_data) = args.visitor_data {
-        eprintln!("Visitor data is deprecated, use --content-binding instead");
-        std::process::exit(1);
+        eprintln!("{}", locale.format(Message::DeprecatedVisitorData, &[]));
+        ScriptExitCode::InvalidInput.exit();
     }
 
+    let content_binding = args.content_binding.as_deref().map(|binding| {
+        LoggedBinding::new(binding, settings.logging.reveal_content_bindings).to_string()
+    });
     debug!(
         "Starting POT generation with parameters: content_binding={:?}, proxy={:?}, bypass_cache={}",
-        args.content_binding, args.proxy, args.bypass_cache
+        content_binding, args.proxy, args.bypass_cache
     );
 
-    // Initialize file cache
-    let cache_path = get_cache_path()?;
-    let file_cache = FileCache::new(cache_path);
-
-    // Load existing cache
-    let session_data_caches = file_cache.load_cache().await.unwrap_or_else(|e| {
-        warn!("Failed to load cache: {}. Starting with empty cache.", e);
-        std::collections::HashMap::new()
-    });
-
-    // Initialize session manager with cache
-    let settings = Settings::default();
-    let session_manager = SessionManager::new(settings);
-    session_manager
-        .set_session_data_caches(session_data_caches)
-        .await;
-
     // Build POT request
     let request = build_pot_request(&args)?;
 
+    // Let a running server answer, skipping the BotGuard cold start
+    let remote = match args.remote.as_deref() {
+        Some("auto") => {
+            let found = discover_server(&settings).await;
+            if found.is_none() {
+                debug!("No running server found, generating locally");
+            }
+            found
+        }
+        remote => remote.map(str::to_string),
+    };
+    let delegated = if let Some(url) = &remote {
+        let result = generate_remote(url, &request, &settings).await;
+        if result.is_none() {
+            warn!("Server at {} is unreachable, generating locally", url);
+        }
+        result
+    } else if args.daemon {
+        generate_with_daemon(&args, &request, &settings).await
+    } else {
+        None
+    };
+    match delegated {
+        Some(Ok(response)) => {
+            println!("{}", serde_json::to_string(&response)?);
+            info!(
+                "Got POT token from a running server for content binding: {:?}",
+                content_binding
+            );
+            return Ok(());
+        }
+        Some(Err(e)) => {
+            eprintln!("{}", locale.format(Message::GenerationFailed, &[&e]));
+            println!("{{}}");
+            ScriptExitCode::from_error(&e).exit();
+        }
+        None => {}
+    }
+
+    // Initialize session manager backed by the file cache
+    let file_cache = if settings.cache.enable_file_cache {
+        match get_cache_path_for(&settings.cache) {
+            Ok(cache_path) => Some(
+                FileCache::new(cache_path)
+                    .with_revealed_bindings(settings.logging.reveal_content_bindings),
+            ),
+            Err(e) => {
+                eprintln!("{}", locale.format(Message::CachePathFailed, &[&e]));
+                ScriptExitCode::Cache.exit();
+            }
+        }
+    } else {
+        None
+    };
+    let mut session_manager = SessionManager::new(settings);
+    if let Some(file_cache) = file_cache {
+        session_manager = session_manager.with_file_cache(file_cache);
+    }
+    if args.verbose && args.progress_json {
+        session_manager = session_manager.with_progress(ProgressReporter::stderr());
+    }
+
     // Generate POT token
     match session_manager.generate_pot_token(&request).await {
         Ok(response) => {
-            // Save updated cache
-            if let Err(e) = file_cache
-                .save_cache(session_manager.get_session_data_caches(true).await)
-                .await
-            {
+            // Save newly minted tokens
+            if let Err(e) = session_manager.flush_caches().await {
                 warn!("Failed to save cache: {}", e);
             }
 
@@ -109,7 +338,7 @@ pub async fn run_generate_mode(args: GenerateArgs) -> Result<()> {
 
             info!(
                 "Successfully generated POT token for content binding: {:?}",
-                request.content_binding
+                content_binding
             );
 
             // Shutdown session manager to properly cleanup V8 isolates
@@ -120,17 +349,133 @@ pub async fn run_generate_mode(args: GenerateArgs) -> Result<()> {
             // Shutdown session manager before exiting on error
             session_manager.shutdown().await;
 
-            eprintln!("Failed while generating POT. Error: {}", e);
+            eprintln!("{}", locale.format(Message::GenerationFailed, &[&e]));
 
             // Output empty JSON on error (matching TypeScript behavior)
             println!("{{}}");
-            std::process::exit(1);
+            ScriptExitCode::from_error(&e).exit();
         }
     }
 
     Ok(())
 }
 
+/// Find a running provider server for `--remote auto`
+///
+/// Probes the URL in `POT_SERVER_URL`, the configured server address and
+/// the default port on localhost in this order, returning the first
+/// answering `GET /ping`.
+async fn discover_server(settings: &Settings) -> Option<String> {
+    let http = reqwest::Client::builder()
+        .timeout(DISCOVERY_TIMEOUT)
+        .no_proxy()
+        .build()
+        .ok()?;
+    let env_url = std::env::var("POT_SERVER_URL").ok();
+    for url in discovery_candidates(env_url, settings) {
+        let Ok(client) = PotClient::new(&url) else {
+            warn!("Ignoring invalid server URL {:?}", url);
+            continue;
+        };
+        match client
+            .with_http_client(http.clone())
+            .with_max_retries(0)
+            .ping()
+            .await
+        {
+            Ok(ping) => {
+                debug!("Found server {} at {}", ping.version, url);
+                return Some(url);
+            }
+            Err(e) => debug!("No server at {}: {}", url, e),
+        }
+    }
+    None
+}
+
+/// URLs probed by [`discover_server`], without duplicates
+fn discovery_candidates(env_url: Option<String>, settings: &Settings) -> Vec<String> {
+    // A server listening on all interfaces is reached through localhost
+    let host = match settings.server.host.parse::<std::net::IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => "127.0.0.1".to_string(),
+        Ok(std::net::IpAddr::V6(ip)) => format!("[{}]", ip),
+        _ => settings.server.host.clone(),
+    };
+
+    let mut candidates = Vec::new();
+    for url in env_url.into_iter().chain([
+        format!("http://{}:{}", host, settings.server.port),
+        format!("http://127.0.0.1:{}", DEFAULT_SERVER_PORT),
+    ]) {
+        if !candidates.contains(&url) {
+            candidates.push(url);
+        }
+    }
+    candidates
+}
+
+/// Get the token for `request` from the provider server at `url`
+///
+/// Returns `None` if the server can't be reached, so the token is generated
+/// locally instead. Errors returned by a reachable server are passed on.
+async fn generate_remote(
+    url: &str,
+    request: &PotRequest,
+    settings: &Settings,
+) -> Option<crate::Result<PotResponse>> {
+    let http = reqwest::Client::builder()
+        .connect_timeout(REMOTE_CONNECT_TIMEOUT)
+        .timeout(Duration::from_secs(settings.token.pot_generation_timeout))
+        // The server is usually local, don't route to it through a proxy
+        .no_proxy()
+        .build();
+    let client = match (http, PotClient::new(url)) {
+        (Ok(http), Ok(client)) => client
+            .with_http_client(http)
+            .with_max_retries(0)
+            .with_cache(false),
+        (Err(e), _) => return Some(Err(e.into())),
+        (_, Err(e)) => return Some(Err(e)),
+    };
+
+    match client.get_pot(request).await {
+        Err(crate::Error::Http(e)) if e.is_connect() || e.is_timeout() => {
+            debug!("Failed to reach server at {}: {}", url, e);
+            None
+        }
+        result => Some(result),
+    }
+}
+
+/// Get the token for `request` from the script mode daemon
+///
+/// Returns `None` if the daemon is unavailable, so the token is generated
+/// locally instead.
+#[cfg(unix)]
+async fn generate_with_daemon(
+    args: &GenerateArgs,
+    request: &PotRequest,
+    settings: &Settings,
+) -> Option<crate::Result<PotResponse>> {
+    let daemon = super::daemon::DaemonArgs {
+        config: args.config.clone(),
+        profile: args.profile.clone(),
+        ..Default::default()
+    };
+    super::daemon::generate_via_daemon(&daemon, settings, request).await
+}
+
+/// The daemon listens on a unix socket, so it isn't available here
+#[cfg(not(unix))]
+async fn generate_with_daemon(
+    _args: &GenerateArgs,
+    _request: &PotRequest,
+    _settings: &Settings,
+) -> Option<crate::Result<PotResponse>> {
+    warn!("--daemon is only supported on Unix, generating locally");
+    None
+}
+
 /// Build POT request from CLI arguments
 fn build_pot_request(args: &GenerateArgs) -> Result<PotRequest> {
     let mut request = PotRequest::new();
@@ -173,11 +518,7 @@ mod tests {
             bypass_cache: true,
             source_address: Some("192.168.1.100".to_string()),
             disable_tls_verification: true,
-            // ... other fields with default values
-            visitor_data: None,
-            data_sync_id: None,
-            version: false,
-            verbose: false,
+            ..Default::default()
         };
 
         let request = build_pot_request(&args).unwrap();
@@ -189,4 +530,147 @@ mod tests {
         assert_eq!(request.disable_tls_verification, Some(true));
         assert_eq!(request.disable_innertube, Some(true)); // Should be forced to true
     }
+
+    #[tokio::test]
+    async fn test_generate_remote() {
+        use wiremock::{
+            Mock, MockServer, ResponseTemplate,
+            matchers::{method, path},
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/get_pot"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "poToken": "remote_token",
+                "contentBinding": "video",
+                "expiresAt": "2030-01-01T00:00:00Z",
+            })))
+            .mount(&server)
+            .await;
+
+        let settings = Settings::default();
+        let request = PotRequest::new().with_content_binding("video");
+        let response = generate_remote(&server.uri(), &request, &settings)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.po_token, "remote_token");
+
+        // Nothing listens on a port just released, fall back to local generation
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("http://127.0.0.1:{}", port);
+        assert!(generate_remote(&url, &request, &settings).await.is_none());
+
+        // An invalid URL is an input error, not an unreachable server
+        let error = generate_remote("not a url", &request, &settings)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            ScriptExitCode::from_error(&error),
+            ScriptExitCode::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_discovery_candidates() {
+        let mut settings = Settings::default();
+        settings.server.host = "::".to_string();
+        assert_eq!(
+            discovery_candidates(Some("http://pot:8080".to_string()), &settings),
+            ["http://pot:8080", "http://127.0.0.1:4416"]
+        );
+
+        settings.server.host = "::1".to_string();
+        settings.server.port = 8080;
+        assert_eq!(
+            discovery_candidates(None, &settings),
+            ["http://[::1]:8080", "http://127.0.0.1:4416"]
+        );
+    }
+
+    #[test]
+    fn test_load_generate_settings_from_config() {
+        use std::io::Write;
+
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            config,
+            r#"
+[token]
+pot_generation_timeout = 7
+
+[profiles.prod.token]
+pot_generation_timeout = 12
+"#
+        )
+        .unwrap();
+
+        let mut args = GenerateArgs {
+            trace_file: Some("/tmp/trace.json".to_string()),
+            config: Some(config.path().to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let settings = load_generate_settings(&args).unwrap();
+        assert_eq!(settings.token.pot_generation_timeout, 7);
+        assert_eq!(
+            settings.logging.trace_file,
+            Some(std::path::PathBuf::from("/tmp/trace.json"))
+        );
+
+        args.profile = Some("prod".to_string());
+        assert_eq!(
+            load_generate_settings(&args)
+                .unwrap()
+                .token
+                .pot_generation_timeout,
+            12
+        );
+
+        args.profile = Some("missing".to_string());
+        assert!(load_generate_settings(&args).is_err());
+    }
+
+    #[test]
+    fn test_exit_code_from_error() {
+        let cases = [
+            (
+                crate::Error::config("port", "Invalid port"),
+                ScriptExitCode::Config,
+            ),
+            (crate::Error::network("timed out"), ScriptExitCode::Network),
+            (
+                crate::Error::rate_limit("429", Some(30)),
+                ScriptExitCode::Network,
+            ),
+            (
+                crate::Error::botguard("500", "VM failed"),
+                ScriptExitCode::BotGuard,
+            ),
+            (
+                crate::Error::token_generation("mint failed"),
+                ScriptExitCode::BotGuard,
+            ),
+            (
+                crate::Error::proxy("proxy:8080", "missing scheme"),
+                ScriptExitCode::InvalidInput,
+            ),
+            (
+                crate::Error::cache("write", "disk full"),
+                ScriptExitCode::Cache,
+            ),
+            (crate::Error::internal("boom"), ScriptExitCode::General),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(ScriptExitCode::from_error(&error), expected, "{}", error);
+        }
+        assert_eq!(ScriptExitCode::Success.code(), 0);
+        assert_eq!(ScriptExitCode::Cache.code(), 6);
+    }
 }
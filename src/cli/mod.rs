@@ -1,6 +1,11 @@
 //! Command-line interface modules
 //!
-//! This module contains the CLI logic for both server and generate modes.
+//! This module contains the CLI logic for the server, generate, daemon, top
+//! and cache modes.
 
+pub mod cache;
+#[cfg(unix)]
+pub mod daemon;
 pub mod generate;
 pub mod server;
+pub mod top;
@@ -2,21 +2,123 @@
 //!
 //! Contains the core logic for running the HTTP server mode.
 
-use crate::{Settings, config::ConfigLoader, server::app, utils::version};
+use crate::{
+    Settings,
+    config::ConfigLoader,
+    server::{app, lock::InstanceLock},
+    utils::{
+        logging,
+        messages::{Locale, Message},
+        version,
+    },
+};
 use anyhow::Result;
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 /// Arguments for server mode
-#[derive(Debug)]
+///
+/// The settings flags override the environment and the configuration file, so
+/// containers can be configured purely through arguments.
+#[derive(Debug, Default, clap::Args)]
 pub struct ServerArgs {
+    /// Port to listen on
+    #[arg(short, long)]
     pub port: Option<u16>,
+
+    /// Host to bind to
+    #[arg(long)]
     pub host: Option<String>,
+
+    /// Ports to try in order when the port is in use, e.g. 4416-4426 (server.port_range)
+    #[arg(long, value_name = "START-END")]
+    pub port_range: Option<String>,
+
+    /// Write the port listened on to this file (server.port_file)
+    #[arg(long, value_name = "PATH")]
+    pub port_file: Option<String>,
+
+    /// Configuration file path
+    #[arg(long)]
     pub config: Option<String>,
+
+    /// Configuration profile to merge over the base settings (e.g. prod)
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Enable verbose logging
+    #[arg(short, long)]
     pub verbose: bool,
+
+    /// Write Chrome trace-event timelines of every request to this file
+    #[arg(long, value_name = "TRACE_FILE")]
+    pub trace_file: Option<String>,
+
+    /// Run N worker processes, each with its own BotGuard runtime, behind a load balancer
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub workers: usize,
+
+    /// Run as worker INDEX of `--workers`, reporting its port through `--port-file`
+    #[arg(long, hide = true, value_name = "INDEX")]
+    pub worker: Option<usize>,
+
+    /// Take over the lock of the data directories held by another server
+    #[arg(long)]
+    pub force: bool,
+
+    /// Token TTL in hours (token.ttl_hours)
+    #[arg(long, value_name = "HOURS")]
+    pub ttl_hours: Option<u64>,
+
+    /// BotGuard snapshot file (botguard.snapshot_path)
+    #[arg(long, value_name = "PATH", conflicts_with = "disable_snapshot")]
+    pub snapshot_path: Option<String>,
+
+    /// Don't load or save BotGuard snapshots (botguard.disable_snapshot)
+    #[arg(long)]
+    pub disable_snapshot: bool,
+
+    /// Bearer token required by the admin endpoints (server.admin_token)
+    #[arg(long, value_name = "TOKEN", alias = "api-key")]
+    pub admin_token: Option<String>,
+
+    /// Upstream requests per second across all proxies, 0 is unlimited (network.upstream_qps)
+    #[arg(long, value_name = "QPS", alias = "rate-limit")]
+    pub upstream_qps: Option<f64>,
+}
+
+impl ServerArgs {
+    /// Get the settings flags, to pass them on to worker processes
+    pub fn settings_args(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(ttl_hours) = self.ttl_hours {
+            flags.extend(["--ttl-hours".to_string(), ttl_hours.to_string()]);
+        }
+        if let Some(snapshot_path) = &self.snapshot_path {
+            flags.extend(["--snapshot-path".to_string(), snapshot_path.clone()]);
+        }
+        if self.disable_snapshot {
+            flags.push("--disable-snapshot".to_string());
+        }
+        if let Some(admin_token) = &self.admin_token {
+            flags.extend(["--admin-token".to_string(), admin_token.clone()]);
+        }
+        if let Some(upstream_qps) = self.upstream_qps {
+            flags.extend(["--upstream-qps".to_string(), upstream_qps.to_string()]);
+        }
+        flags
+    }
 }
 
 /// Run server mode with the given arguments
 pub async fn run_server_mode(args: ServerArgs) -> Result<()> {
+    let settings = load_server_settings(&args)?;
+    run_server_with_settings(args, settings).await
+}
+
+/// Load server settings from the configuration file, environment and arguments
+///
+/// Separate from [`run_server_with_settings`] so the runtime can be built from
+/// the `[runtime]` settings before entering it.
+pub fn load_server_settings(args: &ServerArgs) -> Result<Settings> {
     // Load configuration FIRST, before initializing logging
     // This ensures we can use the logging.level from config file
     //
@@ -25,7 +127,9 @@ pub async fn run_server_mode(args: ServerArgs) -> Result<()> {
     // 2. Environment variables
     // 3. Configuration file (from --config, BGUTIL_CONFIG or default location)
     // 4. Default values (lowest priority)
-    let config_loader = ConfigLoader::new();
+    // Determine profile: CLI arg > BGUTIL_PROFILE environment variable
+    let profile = args.profile.clone().or_else(ConfigLoader::get_profile);
+    let config_loader = ConfigLoader::new().with_profile(profile);
 
     // Determine config path: CLI arg > environment variable > default location
     let config_path = if let Some(config) = &args.config {
@@ -39,49 +143,108 @@ pub async fn run_server_mode(args: ServerArgs) -> Result<()> {
         .unwrap_or_else(|e| {
             // Can't use tracing here since it's not initialized yet
             eprintln!(
-                "Warning: Failed to load configuration: {}. Using defaults.",
-                e
+                "{}",
+                Locale::from_env().format(Message::ConfigLoadFailed, &[&e])
             );
             Settings::default()
         });
 
     // Override with CLI arguments if provided (highest priority)
-    if let Some(host) = args.host {
-        settings.server.host = host;
+    if let Some(host) = &args.host {
+        settings.server.host = host.clone();
     }
     if let Some(port) = args.port {
         settings.server.port = port;
     }
+    if let Some(port_range) = &args.port_range {
+        settings.server.port_range = Some(port_range.clone());
+    }
+    if args.worker.is_some() {
+        // Only the load balancer scans ports and writes the configured port file
+        settings.server.port_range = None;
+        settings.server.port_file = None;
+    }
+    if let Some(port_file) = &args.port_file {
+        settings.server.port_file = Some(std::path::PathBuf::from(port_file));
+    }
     settings.logging.verbose = args.verbose;
+    if let Some(trace_file) = &args.trace_file {
+        settings.logging.trace_file = Some(std::path::PathBuf::from(trace_file));
+    }
+    if let Some(ttl_hours) = args.ttl_hours {
+        settings.token.ttl_hours = ttl_hours;
+    }
+    if let Some(snapshot_path) = &args.snapshot_path {
+        settings.botguard.snapshot_path = Some(std::path::PathBuf::from(snapshot_path));
+        settings.botguard.snapshot_dir = None;
+        settings.botguard.disable_snapshot = false;
+    }
+    if args.disable_snapshot {
+        settings.botguard.disable_snapshot = true;
+        settings.botguard.snapshot_path = None;
+        settings.botguard.snapshot_dir = None;
+    }
+    if let Some(admin_token) = &args.admin_token {
+        settings.server.admin_token = Some(admin_token.clone());
+    }
+    if let Some(upstream_qps) = args.upstream_qps {
+        settings.network.upstream_qps = upstream_qps;
+    }
 
-    // Initialize logging with proper precedence:
-    // 1. CLI --verbose flag (highest priority) -> debug level
-    // 2. RUST_LOG environment variable
-    // 3. Config file logging.level
-    // 4. Default: info (lowest priority)
-    let env_filter = if args.verbose {
-        // CLI --verbose flag takes highest priority
-        EnvFilter::new("debug")
-    } else if std::env::var("RUST_LOG").is_ok() {
-        // RUST_LOG environment variable takes second priority
-        EnvFilter::from_default_env()
-    } else {
-        // Use config file logging.level or default to "info"
-        EnvFilter::new(&settings.logging.level)
-    };
+    if let Some(index) = args.worker {
+        isolate_worker_data(&mut settings, index)?;
+    }
+
+    // Arguments bypass the validation done while loading
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Give worker `index` its own snapshot and cache directories
+///
+/// Workers would overwrite each other's snapshot and cache journal otherwise;
+/// each one persists to a `worker-<index>` subdirectory of the configured
+/// directories and locks it like a standalone server.
+fn isolate_worker_data(settings: &mut Settings, index: usize) -> Result<()> {
+    let subdir = format!("worker-{}", index);
+
+    let botguard = &mut settings.botguard;
+    if let Some(dir) = &botguard.snapshot_dir {
+        botguard.snapshot_dir = Some(dir.join(&subdir));
+    } else if let Some(path) = &botguard.snapshot_path
+        && let (Some(parent), Some(name)) = (path.parent(), path.file_name())
+    {
+        botguard.snapshot_path = Some(parent.join(&subdir).join(name));
+    }
+
+    let cache_path = crate::utils::cache::get_cache_path_for(&settings.cache)?;
+    if let Some(parent) = cache_path.parent() {
+        settings.cache.cache_dir = Some(parent.join(&subdir).to_string_lossy().into_owned());
+    }
+    Ok(())
+}
 
-    tracing_subscriber::registry()
-        .with(env_filter)
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+/// Run server mode with already loaded settings
+pub async fn run_server_with_settings(args: ServerArgs, settings: Settings) -> Result<()> {
+    // Logging precedence: --verbose, then RUST_LOG, then logging.level
+    let _logging = logging::init(&settings.logging);
 
     tracing::info!("Starting POT server v{}", version::get_version());
 
+    // Workers lock their own subdirectories, see `isolate_worker_data`
+    let _instance_lock = InstanceLock::acquire(&settings, args.force)?;
+
+    if args.workers > 1 {
+        return run_worker_pool(&settings, args).await;
+    }
+
     // Create the Axum application
-    let app = app::create_app(settings.clone());
+    let (app, session_manager) = app::create_app_with_manager(settings.clone());
 
-    // Parse address and attempt IPv6/IPv4 fallback like TypeScript implementation
-    let addr = parse_and_bind_address(&settings.server.host, settings.server.port).await?;
+    // Bind once, with the IPv6/IPv4 fallback of the TypeScript implementation
+    let listener = server_listener(&settings).await?;
+    let addr = listener.local_addr()?;
+    let port_file = PortFile::write(&settings, addr.port())?;
 
     tracing::info!(
         "POT server v{} listening on {}",
@@ -90,62 +253,248 @@ pub async fn run_server_mode(args: ServerArgs) -> Result<()> {
     );
 
     // Start the server
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await;
+
+    tracing::info!("Shutting down POT server");
+    session_manager.shutdown().await;
+    drop(port_file);
+    result?;
     Ok(())
 }
 
-/// Parse host string and attempt to bind to the address
+/// Wait for Ctrl-C, or SIGTERM on Unix as sent by container runtimes
+pub(crate) async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Run the load balancer in front of `args.workers` server processes
 ///
-/// Implements the same IPv6 fallback logic as TypeScript implementation:
-/// - First try to bind to IPv6 (::)
-/// - If that fails, fall back to IPv4 (0.0.0.0)
-pub async fn parse_and_bind_address(host: &str, port: u16) -> Result<std::net::SocketAddr> {
-    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+/// Each worker owns its own BotGuard runtime and caches; the parent only
+/// forwards requests.
+async fn run_worker_pool(settings: &Settings, args: ServerArgs) -> Result<()> {
+    use crate::server::workers::{WorkerCommand, WorkerPool, create_balancer_app, spawn_workers};
+    use std::sync::Arc;
+
+    let workers = args.workers;
+    let pool = Arc::new(WorkerPool::new(workers));
+    let command = WorkerCommand {
+        program: std::env::current_exe()?,
+        settings_args: args.settings_args(),
+        config: args.config,
+        profile: args.profile,
+        verbose: args.verbose,
+    };
+    let supervisors = spawn_workers(pool.clone(), command)?;
+
+    let app = create_balancer_app(pool, settings.server.max_body_size);
+    let listener = server_listener(settings).await?;
+    let addr = listener.local_addr()?;
+    let _port_file = PortFile::write(settings, addr.port())?;
+    tracing::info!(
+        "POT server v{} listening on {} with {} workers",
+        version::get_version(),
+        addr,
+        workers
+    );
 
-    // Try to parse as IP address first
-    if let Ok(ip) = host.parse::<IpAddr>() {
-        let addr = SocketAddr::new(ip, port);
-        tracing::debug!("Parsed address: {}", addr);
-        return Ok(addr);
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await;
+
+    // Dropping the supervisors kills the workers
+    for supervisor in supervisors {
+        supervisor.abort();
     }
+    result?;
+    Ok(())
+}
 
-    // Handle special cases like "::" for IPv6 any
-    match host {
-        "::" => {
-            let addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port);
-            tracing::debug!("Using IPv6 any address: {}", addr);
+/// Get the server listener, from socket activation or by binding the settings' address
+async fn server_listener(settings: &Settings) -> Result<tokio::net::TcpListener> {
+    if let Some(listener) = activated_listener()? {
+        tracing::info!("Using socket-activated listener");
+        return Ok(listener);
+    }
+    bind_listener_in_range(
+        &settings.server.host,
+        settings.server.port,
+        settings.server.port_range()?,
+    )
+    .await
+}
 
-            // Test if we can bind to IPv6
-            match tokio::net::TcpListener::bind(addr).await {
-                Ok(_) => {
-                    tracing::info!("Successfully bound to IPv6 address {}", addr);
-                    Ok(addr)
-                }
-                Err(e) => {
-                    tracing::warn!(
-                        "Could not listen on [::]:{} (Caused by {}), falling back to 0.0.0.0",
-                        port,
-                        e
-                    );
-                    let fallback_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-                    tracing::info!("Using IPv4 fallback address: {}", fallback_addr);
-                    Ok(fallback_addr)
-                }
+/// Bind the server listener on `port`, or on the next free port of `range`
+/// above it while ports are in use
+///
+/// Lets a server start when another service took its port; the port taken
+/// is logged and written to the port file.
+pub async fn bind_listener_in_range(
+    host: &str,
+    port: u16,
+    range: Option<std::ops::RangeInclusive<u16>>,
+) -> Result<tokio::net::TcpListener> {
+    let error = match bind_listener(host, port).await {
+        Ok(listener) => return Ok(listener),
+        Err(e) if is_addr_in_use(&e) => e,
+        Err(e) => return Err(e),
+    };
+    let Some(range) = range else {
+        return Err(error.context(format!(
+            "Port {} is already in use, e.g. by another bgutil-pot server; choose another with --port or scan for a free one with --port-range",
+            port
+        )));
+    };
+
+    let (start, end) = (*range.start(), *range.end());
+    for candidate in range.filter(|candidate| *candidate > port) {
+        match bind_listener(host, candidate).await {
+            Ok(listener) => {
+                tracing::warn!(
+                    "Port {} is in use, listening on port {} instead",
+                    port,
+                    candidate
+                );
+                return Ok(listener);
+            }
+            Err(e) if is_addr_in_use(&e) => {
+                tracing::debug!("Port {} is in use", candidate);
             }
+            Err(e) => return Err(e),
         }
-        "0.0.0.0" => {
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-            tracing::info!("Using IPv4 any address: {}", addr);
-            Ok(addr)
+    }
+    Err(error.context(format!(
+        "Port {} and the ports of the range {}-{} above it are all in use",
+        port, start, end
+    )))
+}
+
+/// Whether binding failed because the address is already in use
+fn is_addr_in_use(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::AddrInUse)
+}
+
+/// Port file written for `server.port_file`, removed when dropped
+struct PortFile(Option<std::path::PathBuf>);
+
+impl PortFile {
+    /// Write `port` to the configured port file, if any
+    ///
+    /// Written through a temporary file, so readers never see a partial port.
+    fn write(settings: &Settings, port: u16) -> Result<Self> {
+        let Some(path) = &settings.server.port_file else {
+            return Ok(Self(None));
+        };
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        std::fs::write(&temp, format!("{}\n", port))?;
+        std::fs::rename(&temp, path)?;
+        tracing::info!("Wrote port {} to {}", port, path.display());
+        Ok(Self(Some(path.clone())))
+    }
+}
+
+impl Drop for PortFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0
+            && let Err(e) = std::fs::remove_file(path)
+        {
+            tracing::warn!("Failed to remove port file {}: {}", path.display(), e);
         }
-        _ => {
-            anyhow::bail!(
+    }
+}
+
+/// Take the listener passed by systemd-style socket activation, if any
+///
+/// Only the first passed socket (fd 3) is used, when `LISTEN_PID` names this
+/// process.
+#[cfg(unix)]
+fn activated_listener() -> Result<Option<tokio::net::TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    /// First file descriptor passed by socket activation
+    const SD_LISTEN_FDS_START: i32 = 3;
+
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(std::process::id());
+    let count: i32 = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.parse().ok())
+        .unwrap_or(0);
+    if !for_us || count < 1 {
+        return Ok(None);
+    }
+
+    // SAFETY: the service manager passed this descriptor to us as an open
+    // listening socket, and nothing else takes ownership of it
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(tokio::net::TcpListener::from_std(listener)?))
+}
+
+/// Socket activation is only supported on Unix
+#[cfg(not(unix))]
+fn activated_listener() -> Result<Option<tokio::net::TcpListener>> {
+    Ok(None)
+}
+
+/// Parse the host and bind the server listener
+///
+/// The socket is bound once and returned, so the port can't be taken between
+/// checking the address and serving on it. Port 0 binds an ephemeral port,
+/// read it back with [`tokio::net::TcpListener::local_addr`].
+///
+/// Implements the same IPv6 fallback logic as TypeScript implementation:
+/// - First try to bind to IPv6 (::)
+/// - If that fails, fall back to IPv4 (0.0.0.0)
+pub async fn bind_listener(host: &str, port: u16) -> Result<tokio::net::TcpListener> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use tokio::net::TcpListener;
+
+    let ip = match host {
+        "::" => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        "0.0.0.0" => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        _ => host.parse::<IpAddr>().map_err(|_| {
+            anyhow::anyhow!(
                 "Invalid host address: {}. Use '::' for IPv6 or '0.0.0.0' for IPv4",
                 host
+            )
+        })?,
+    };
+    let addr = SocketAddr::new(ip, port);
+    tracing::debug!("Binding to {}", addr);
+
+    match TcpListener::bind(addr).await {
+        Ok(listener) => Ok(listener),
+        Err(e) if ip == IpAddr::V6(Ipv6Addr::UNSPECIFIED) => {
+            tracing::warn!(
+                "Could not listen on [::]:{} (Caused by {}), falling back to 0.0.0.0",
+                port,
+                e
             );
+            let fallback_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+            Ok(TcpListener::bind(fallback_addr).await?)
         }
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -155,11 +504,11 @@ mod tests {
     use std::io::Write;
 
     #[tokio::test]
-    async fn test_parse_and_bind_ipv4_address() {
-        let result = parse_and_bind_address("127.0.0.1", 0).await; // Use port 0 to get any available port
+    async fn test_bind_listener_ipv4_address() {
+        let result = bind_listener("127.0.0.1", 0).await; // Use port 0 to get any available port
         assert!(result.is_ok());
 
-        let addr = result.unwrap();
+        let addr = result.unwrap().local_addr().unwrap();
         assert_eq!(
             addr.ip(),
             std::net::IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1))
@@ -167,11 +516,15 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_ipv6_address() {
-        let result = parse_and_bind_address("::1", 0).await; // Use port 0 to get any available port
+    async fn test_bind_listener_ipv6_address() {
+        // Skip on hosts without IPv6
+        if std::net::TcpListener::bind("[::1]:0").is_err() {
+            return;
+        }
+        let result = bind_listener("::1", 0).await; // Use port 0 to get any available port
         assert!(result.is_ok());
 
-        let addr = result.unwrap();
+        let addr = result.unwrap().local_addr().unwrap();
         assert_eq!(
             addr.ip(),
             std::net::IpAddr::V6(std::net::Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1))
@@ -179,11 +532,11 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_ipv4_any_address() {
-        let result = parse_and_bind_address("0.0.0.0", 0).await; // Use port 0 to get any available port
+    async fn test_bind_listener_ipv4_any_address() {
+        let result = bind_listener("0.0.0.0", 0).await; // Use port 0 to get any available port
         assert!(result.is_ok());
 
-        let addr = result.unwrap();
+        let addr = result.unwrap().local_addr().unwrap();
         assert_eq!(
             addr.ip(),
             std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)
@@ -191,12 +544,12 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_ipv6_any_fallback() {
+    async fn test_bind_listener_ipv6_any_fallback() {
         // Test IPv6 any address - this should work or fallback to IPv4
-        let result = parse_and_bind_address("::", 0).await; // Use port 0 to get any available port
+        let result = bind_listener("::", 0).await; // Use port 0 to get any available port
         assert!(result.is_ok());
 
-        let addr = result.unwrap();
+        let addr = result.unwrap().local_addr().unwrap();
         // Should be either IPv6 unspecified or IPv4 unspecified (fallback)
         assert!(
             addr.ip() == std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED)
@@ -205,8 +558,55 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_invalid_address() {
-        let result = parse_and_bind_address("invalid-host", 8080).await;
+    async fn test_bind_listener_in_range() {
+        let taken = bind_listener("127.0.0.1", 0).await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let error = bind_listener_in_range("127.0.0.1", port, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("--port-range"));
+
+        // Scans upward, skipping ports below the configured one
+        let range = port.saturating_sub(5)..=port.saturating_add(20);
+        let listener = bind_listener_in_range("127.0.0.1", port, Some(range))
+            .await
+            .unwrap();
+        assert!(listener.local_addr().unwrap().port() > port);
+
+        assert!(
+            bind_listener_in_range("127.0.0.1", port, Some(port..=port))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_port_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bgutil-pot.port");
+        let mut settings = Settings::default();
+        settings.server.port_file = Some(path.clone());
+
+        let port_file = PortFile::write(&settings, 4417).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "4417\n");
+        drop(port_file);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_listener_keeps_port() {
+        let listener = bind_listener("127.0.0.1", 0).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+
+        // The returned listener holds the port, nothing binds it in between
+        assert!(bind_listener("127.0.0.1", addr.port()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_listener_invalid_address() {
+        let result = bind_listener("invalid-host", 8080).await;
         assert!(result.is_err());
 
         let error = result.unwrap_err();
@@ -218,8 +618,8 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_empty_address() {
-        let result = parse_and_bind_address("", 8080).await;
+    async fn test_bind_listener_empty_address() {
+        let result = bind_listener("", 8080).await;
         assert!(result.is_err());
 
         let error = result.unwrap_err();
@@ -227,9 +627,9 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_parse_and_bind_localhost_fails() {
+    async fn test_bind_listener_localhost_fails() {
         // localhost should fail since we only accept IP addresses or :: and 0.0.0.0
-        let result = parse_and_bind_address("localhost", 8080).await;
+        let result = bind_listener("localhost", 8080).await;
         assert!(result.is_err());
     }
 
@@ -240,7 +640,11 @@ mod tests {
             port: None,
             host: None,
             config: None,
+            profile: None,
             verbose: false,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
         assert!(args.port.is_none());
         assert!(args.host.is_none());
@@ -252,7 +656,11 @@ mod tests {
             port: Some(8080),
             host: Some("127.0.0.1".to_string()),
             config: Some("/path/to/config.toml".to_string()),
+            profile: None,
             verbose: true,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
         assert_eq!(args.port, Some(8080));
         assert_eq!(args.host, Some("127.0.0.1".to_string()));
@@ -260,6 +668,79 @@ mod tests {
         assert!(args.verbose);
     }
 
+    #[test]
+    fn test_settings_flags_override_config() {
+        use std::io::Write;
+
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            config,
+            r#"
+[token]
+ttl_hours = 24
+
+[botguard]
+snapshot_dir = "/var/lib/bgutil"
+"#
+        )
+        .unwrap();
+
+        let mut args = ServerArgs {
+            config: Some(config.path().to_string_lossy().into_owned()),
+            ttl_hours: Some(3),
+            disable_snapshot: true,
+            admin_token: Some("secret".to_string()),
+            upstream_qps: Some(2.5),
+            ..Default::default()
+        };
+        let settings = load_server_settings(&args).unwrap();
+        assert_eq!(settings.token.ttl_hours, 3);
+        assert!(settings.botguard.disable_snapshot);
+        assert!(settings.botguard.snapshot_dir.is_none());
+        assert!(settings.botguard.snapshot_path.is_none());
+        assert_eq!(settings.server.admin_token.as_deref(), Some("secret"));
+        assert_eq!(settings.network.upstream_qps, 2.5);
+        assert_eq!(
+            args.settings_args(),
+            [
+                "--ttl-hours",
+                "3",
+                "--disable-snapshot",
+                "--admin-token",
+                "secret",
+                "--upstream-qps",
+                "2.5"
+            ]
+        );
+
+        // Invalid values fail instead of being silently replaced
+        args.ttl_hours = Some(0);
+        assert!(load_server_settings(&args).is_err());
+    }
+
+    #[test]
+    fn test_workers_get_their_own_data_dirs() {
+        let mut settings = Settings::default();
+        settings.botguard.snapshot_path = Some("/data/snapshot.bin".into());
+        settings.cache.cache_dir = Some("/data/cache".to_string());
+        isolate_worker_data(&mut settings, 1).unwrap();
+        assert_eq!(
+            settings.botguard.snapshot_path.as_deref(),
+            Some(std::path::Path::new("/data/worker-1/snapshot.bin"))
+        );
+        assert_eq!(
+            settings.cache.cache_dir.as_deref(),
+            Some("/data/cache/worker-1")
+        );
+
+        settings.botguard.snapshot_dir = Some("/snapshots".into());
+        isolate_worker_data(&mut settings, 2).unwrap();
+        assert_eq!(
+            settings.botguard.snapshot_dir.as_deref(),
+            Some(std::path::Path::new("/snapshots/worker-2"))
+        );
+    }
+
     #[tokio::test]
     async fn test_run_server_mode_with_invalid_config() {
         use std::sync::Mutex;
@@ -285,7 +766,11 @@ mod tests {
             port: Some(0), // Use port 0 to get any available port
             host: Some("127.0.0.1".to_string()),
             config: None, // Don't override with CLI arg
+            profile: None,
             verbose: false,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
 
         // Spawn the server in a separate task and cancel it immediately
@@ -338,7 +823,11 @@ port = 4416
             port: Some(0), // Use port 0 to get any available port
             host: Some("127.0.0.1".to_string()),
             config: None, // Don't override with CLI arg
+            profile: None,
             verbose: false,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
 
         // Spawn the server in a separate task and cancel it immediately
@@ -364,7 +853,11 @@ port = 4416
             port: Some(0),
             host: Some("127.0.0.1".to_string()),
             config: None,
+            profile: None,
             verbose: true,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
 
         // Spawn the server in a separate task and cancel it immediately
@@ -401,7 +894,11 @@ ttl_hours = 24
             port: Some(0), // Use port 0 to get any available port (override config)
             host: Some("127.0.0.1".to_string()),
             config: Some(temp_file.path().to_str().unwrap().to_string()),
+            profile: None,
             verbose: false,
+            trace_file: None,
+            workers: 1,
+            ..Default::default()
         };
 
         // Spawn the server in a separate task and cancel it immediately
@@ -450,9 +947,7 @@ level = "error"
             "Config file logging.level should be 'error'"
         );
 
-        // Test the EnvFilter creation logic (same as in run_server_mode)
         // When verbose=false and RUST_LOG is not set, should use config level
-        let verbose = false;
 
         // Temporarily ensure RUST_LOG is not set for this test
         let original_rust_log = std::env::var("RUST_LOG").ok();
@@ -460,13 +955,7 @@ level = "error"
             std::env::remove_var("RUST_LOG");
         }
 
-        let env_filter = if verbose {
-            EnvFilter::new("debug")
-        } else if std::env::var("RUST_LOG").is_ok() {
-            EnvFilter::from_default_env()
-        } else {
-            EnvFilter::new(&settings.logging.level)
-        };
+        let env_filter = logging::env_filter(&settings.logging);
 
         // Verify the filter is created with the config level
         // EnvFilter debug output shows "LevelFilter::ERROR" (uppercase)
@@ -517,15 +1006,8 @@ level = "error"
             std::env::set_var("RUST_LOG", "warn");
         }
 
-        let verbose = false;
-        let env_filter = if verbose {
-            EnvFilter::new("debug")
-        } else if std::env::var("RUST_LOG").is_ok() {
-            // This branch should be taken when RUST_LOG is set
-            EnvFilter::from_default_env()
-        } else {
-            EnvFilter::new(&settings.logging.level)
-        };
+        // RUST_LOG should be taken over the config level
+        let env_filter = logging::env_filter(&settings.logging);
 
         // Verify RUST_LOG was used (should contain "warn", not "error")
         // EnvFilter debug output shows "LevelFilter::WARN" (uppercase)
@@ -569,7 +1051,7 @@ level = "error"
         temp_file.flush().unwrap();
 
         let config_loader = ConfigLoader::new();
-        let _settings = config_loader.load(Some(temp_file.path())).unwrap();
+        let mut settings = config_loader.load(Some(temp_file.path())).unwrap();
 
         // Set RUST_LOG to "warn" as well
         let original_rust_log = std::env::var("RUST_LOG").ok();
@@ -578,14 +1060,8 @@ level = "error"
         }
 
         // But verbose=true should override everything
-        let verbose = true;
-        let env_filter = if verbose {
-            EnvFilter::new("debug")
-        } else if std::env::var("RUST_LOG").is_ok() {
-            EnvFilter::from_default_env()
-        } else {
-            EnvFilter::new("error")
-        };
+        settings.logging.verbose = true;
+        let env_filter = logging::env_filter(&settings.logging);
 
         // Verify verbose flag resulted in "debug" level
         // EnvFilter debug output shows "LevelFilter::DEBUG" (uppercase)
@@ -0,0 +1,362 @@
+//! Top mode CLI logic
+//!
+//! Polls a running server's `GET /stats` endpoint and renders a live
+//! terminal dashboard of request rate, cache hit ratio, minter expiries and
+//! recent errors for operators without a metrics stack. Server events are
+//! followed through `GET /events` as they happen.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+
+use crate::types::StatsResponse;
+
+/// ANSI sequence clearing the screen and moving the cursor home
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+/// ANSI sequences hiding and showing the cursor
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// Number of minters, errors and events listed on the dashboard
+const MAX_ROWS: usize = 8;
+
+/// Most recent server events, newest first
+type EventLog = Arc<Mutex<VecDeque<String>>>;
+
+/// Arguments for top mode
+#[derive(Debug)]
+pub struct TopArgs {
+    pub url: String,
+    pub interval: u64,
+}
+
+/// Run top mode with the given arguments until interrupted
+pub async fn run_top_mode(args: TopArgs) -> Result<()> {
+    let stats_url = format!("{}/stats", args.url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let interval = Duration::from_secs(args.interval.max(1));
+
+    let events = EventLog::default();
+    let follower = tokio::spawn(follow_events(
+        format!("{}/events", args.url.trim_end_matches('/')),
+        interval,
+        events.clone(),
+    ));
+
+    let mut previous: Option<(Instant, u64)> = None;
+    print!("{}", HIDE_CURSOR);
+    let result = loop {
+        let frame = match fetch_stats(&client, &stats_url).await {
+            Ok(stats) => {
+                let now = Instant::now();
+                let rate = previous.map(|(at, requests)| {
+                    stats.requests_total.saturating_sub(requests) as f64
+                        / now.duration_since(at).as_secs_f64()
+                });
+                previous = Some((now, stats.requests_total));
+                let mut events = events.lock().unwrap_or_else(|e| e.into_inner());
+                render_dashboard(&args.url, &stats, rate, events.make_contiguous())
+            }
+            Err(e) => format!(
+                "bgutil-pot top - {}\n\nFailed to fetch stats: {:#}\n",
+                args.url, e
+            ),
+        };
+
+        print!("{}{}", CLEAR_SCREEN, frame);
+        if let Err(e) = std::io::stdout().flush() {
+            break Err(e.into());
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+    follower.abort();
+    println!("{}", SHOW_CURSOR);
+    result
+}
+
+/// Follow the server event stream into `events`, reconnecting after `retry`
+async fn follow_events(url: String, retry: Duration, events: EventLog) {
+    // No overall timeout, the stream stays open
+    let client = match reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .build()
+    {
+        Ok(client) => client,
+        Err(_) => return,
+    };
+
+    loop {
+        if let Ok(response) = client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            let mut stream = response.bytes_stream();
+            let mut buffer = String::new();
+            while let Some(Ok(chunk)) = stream.next().await {
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                while let Some(end) = buffer.find("\n\n") {
+                    let message: String = buffer.drain(..end + 2).collect();
+                    if let Some(line) = parse_event_message(&message) {
+                        let mut events = events.lock().unwrap_or_else(|e| e.into_inner());
+                        events.truncate(MAX_ROWS - 1);
+                        events.push_front(line);
+                    }
+                }
+            }
+        }
+        tokio::time::sleep(retry).await;
+    }
+}
+
+/// Format one server-sent event message as a dashboard line
+///
+/// Returns `None` for keep-alive comments and unrecognized data.
+fn parse_event_message(message: &str) -> Option<String> {
+    let data: String = message
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    let event: serde_json::Value = serde_json::from_str(&data).ok()?;
+
+    let timestamp = event["timestamp"]
+        .as_str()
+        .and_then(|t| t.parse::<DateTime<Utc>>().ok())?;
+    let kind = event["type"].as_str()?;
+    let detail = ["content_binding", "scope", "message"]
+        .iter()
+        .find_map(|field| event[field].as_str())
+        .unwrap_or_default();
+    Some(format!(
+        "{}  {:<18} {}",
+        timestamp.format("%H:%M:%S"),
+        kind,
+        truncate(detail, 80)
+    ))
+}
+
+/// Fetch the server statistics
+async fn fetch_stats(client: &reqwest::Client, url: &str) -> Result<StatsResponse> {
+    client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("GET {}", url))?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Invalid stats response")
+}
+
+/// Render one dashboard frame
+///
+/// `rate` is the request rate since the previous poll, unknown on the first.
+/// `events` are formatted server events, newest first.
+pub fn render_dashboard(
+    url: &str,
+    stats: &StatsResponse,
+    rate: Option<f64>,
+    events: &[String],
+) -> String {
+    let mut out = String::new();
+    let now = Utc::now();
+
+    let _ = writeln!(out, "bgutil-pot top - {}  (Ctrl-C to quit)", url);
+    let _ = writeln!(out, "uptime {}\n", format_duration(stats.server_uptime));
+    let _ = writeln!(
+        out,
+        "Requests  {:>8}   rate {:>7} req/s",
+        stats.requests_total,
+        rate.map_or_else(|| "-".to_string(), |rate| format!("{:.2}", rate))
+    );
+    let _ = writeln!(
+        out,
+        "Cache     {:>8}   hit ratio {:>5.1}%",
+        stats.cache_hits,
+        stats.cache_hit_ratio() * 100.0
+    );
+    let budget = match stats.cache_max_bytes {
+        0 => String::new(),
+        max => format!(" of {}", format_bytes(max)),
+    };
+    let _ = writeln!(
+        out,
+        "Cached    {:>8}   memory {}{}",
+        stats.cache_entries,
+        format_bytes(stats.cache_bytes),
+        budget
+    );
+    let _ = writeln!(out, "Errors    {:>8}\n", stats.errors_total);
+
+    let _ = writeln!(out, "Minters ({})", stats.minters.len());
+    if stats.minters.is_empty() {
+        let _ = writeln!(out, "  none");
+    }
+    for minter in stats.minters.iter().take(MAX_ROWS) {
+        let remaining = (minter.expires_at - now).num_seconds();
+        let expiry = if remaining > 0 {
+            format!("expires in {}", format_duration(remaining as u64))
+        } else {
+            "expired".to_string()
+        };
+        let _ = writeln!(out, "  {:<40} {}", truncate(&minter.key, 40), expiry);
+    }
+
+    let _ = writeln!(out, "\nRecent errors");
+    if stats.recent_errors.is_empty() {
+        let _ = writeln!(out, "  none");
+    }
+    for error in stats.recent_errors.iter().take(MAX_ROWS) {
+        let _ = writeln!(
+            out,
+            "  {}  {:<16} {}",
+            error.timestamp.format("%H:%M:%S"),
+            error.category,
+            truncate(&error.message, 80)
+        );
+    }
+
+    let _ = writeln!(out, "\nRecent events");
+    if events.is_empty() {
+        let _ = writeln!(out, "  none");
+    }
+    for event in events.iter().take(MAX_ROWS) {
+        let _ = writeln!(out, "  {}", event);
+    }
+    out
+}
+
+/// Format seconds as e.g. `2h 05m 09s`
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Format `bytes` with a binary unit, e.g. `1.5 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Shorten `text` to at most `max` characters
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max.saturating_sub(3)).collect();
+        short.push_str("...");
+        short
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MinterStatus, RecentError};
+
+    #[test]
+    fn test_render_dashboard() {
+        let stats = StatsResponse {
+            server_uptime: 3725,
+            requests_total: 40,
+            cache_hits: 30,
+            errors_total: 1,
+            minters: vec![MinterStatus {
+                key: "http://proxy:8080".to_string(),
+                expires_at: Utc::now() + chrono::Duration::minutes(90),
+            }],
+            recent_errors: vec![RecentError {
+                timestamp: Utc::now(),
+                category: "network".to_string(),
+                message: "Network error: connection refused".to_string(),
+            }],
+            upstream_connections: Vec::new(),
+            duplicates_suppressed: 0,
+            clock_skew_ms: None,
+            innertube_response_context: None,
+            cache_entries: 12,
+            cache_bytes: 3 * 1024 * 1024 / 2,
+            cache_max_bytes: 4 * 1024 * 1024,
+        };
+
+        let events = vec!["12:00:00  token_minted       dQw4w9WgXcQ".to_string()];
+        let frame = render_dashboard("http://127.0.0.1:4416", &stats, Some(1.5), &events);
+        assert!(frame.contains("uptime 1h 02m 05s"));
+        assert!(frame.contains("rate    1.50 req/s"));
+        assert!(frame.contains("hit ratio  75.0%"));
+        assert!(frame.contains("      12   memory 1.5 MiB of 4.0 MiB"));
+        assert!(frame.contains("http://proxy:8080"));
+        assert!(frame.contains("expires in 1h 29m"));
+        assert!(frame.contains("connection refused"));
+        assert!(frame.contains("token_minted       dQw4w9WgXcQ"));
+    }
+
+    #[test]
+    fn test_render_dashboard_empty() {
+        let stats = StatsResponse {
+            server_uptime: 5,
+            requests_total: 0,
+            cache_hits: 0,
+            errors_total: 0,
+            minters: Vec::new(),
+            recent_errors: Vec::new(),
+            upstream_connections: Vec::new(),
+            duplicates_suppressed: 0,
+            clock_skew_ms: None,
+            innertube_response_context: None,
+            cache_entries: 0,
+            cache_bytes: 0,
+            cache_max_bytes: 0,
+        };
+
+        let frame = render_dashboard("http://127.0.0.1:4416", &stats, None, &[]);
+        assert!(frame.contains("rate       - req/s"));
+        assert_eq!(frame.matches("  none").count(), 3);
+    }
+
+    #[test]
+    fn test_parse_event_message() {
+        let message = "event: cache_invalidated\ndata: {\"timestamp\":\"2025-01-01T12:30:45Z\",\"type\":\"cache_invalidated\",\"scope\":\"caches\"}\n\n";
+        assert_eq!(
+            parse_event_message(message).unwrap(),
+            "12:30:45  cache_invalidated  caches"
+        );
+
+        // Keep-alive comments carry no data
+        assert!(parse_event_message(":\n\n").is_none());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a very long message", 10), "a very ...");
+    }
+}
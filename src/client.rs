@@ -0,0 +1,379 @@
+//! HTTP client for a running provider
+//!
+//! Talks to `bgutil-pot server` over its HTTP API using the same request and
+//! response types as the server. Built without the `provider` feature too,
+//! so tools compiled to `wasm32` can use it.
+//!
+//! Code generating tokens through [`TokenProvider`] works the same with a
+//! local [`SessionManager`](crate::SessionManager) and a remote [`PotClient`]:
+//!
+//! ```rust,no_run
+//! use bgutil_ytdlp_pot_provider::{PotRequest, client::{PotClient, TokenProvider}};
+//!
+//! async fn token(provider: &impl TokenProvider) -> bgutil_ytdlp_pot_provider::Result<String> {
+//!     let request = PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+//!     Ok(provider.generate_pot_token(&request).await?.po_token)
+//! }
+//!
+//! # async fn example() -> bgutil_ytdlp_pot_provider::Result<()> {
+//! let client = PotClient::new("http://127.0.0.1:4416")?;
+//! println!("{}", token(&client).await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    Error, Result,
+    types::{ErrorResponse, PingResponse, PotRequest, PotResponse, PotTokenType},
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default number of retries of a failed request
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Delay before the first retry, doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest `Retry-After` delay honored before giving up
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Something generating POT tokens
+///
+/// Implemented by the local [`SessionManager`](crate::SessionManager) and by
+/// [`PotClient`] for a remote provider.
+// Futures of the browser HTTP client aren't `Send`
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait TokenProvider: Send + Sync {
+    /// Generate, or look up a cached, POT token for `request`
+    async fn generate_pot_token(&self, request: &PotRequest) -> Result<PotResponse>;
+}
+
+/// Client for the HTTP API of a provider
+///
+/// Retries requests failing with network errors, timeouts or rate limits,
+/// and caches tokens per content binding until they expire, like the
+/// provider does. Clones share the cache.
+#[derive(Debug, Clone)]
+pub struct PotClient {
+    /// Base URL of the provider, e.g. `http://127.0.0.1:4416/`
+    base_url: url::Url,
+    /// HTTP client sending the requests
+    http: reqwest::Client,
+    /// Retries of a failed request
+    max_retries: u32,
+    /// Tokens by content binding, `None` when caching is disabled
+    cache: Option<Arc<Mutex<HashMap<String, PotResponse>>>>,
+}
+
+impl PotClient {
+    /// Create a client for the provider at `base_url`
+    ///
+    /// The URL may carry a path prefix the provider routes are mounted under.
+    pub fn new(base_url: &str) -> Result<Self> {
+        let mut base_url = url::Url::parse(base_url)?;
+        // Join endpoints below the prefix instead of replacing its last segment
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Ok(Self {
+            base_url,
+            http: reqwest::Client::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            cache: Some(Default::default()),
+        })
+    }
+
+    /// Retry failed requests up to `max_retries` times (default 2)
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Enable or disable caching tokens in the client (enabled by default)
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(Default::default);
+        self
+    }
+
+    /// Send requests with `http`, e.g. to set timeouts or a proxy
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Get the base URL of the provider
+    pub fn base_url(&self) -> &url::Url {
+        &self.base_url
+    }
+
+    /// Request a POT token with `POST /get_pot`
+    ///
+    /// Answered from the client cache unless the request bypasses the cache,
+    /// is single-use or has no content binding. Fallback tokens aren't cached.
+    pub async fn get_pot(&self, request: &PotRequest) -> Result<PotResponse> {
+        let cache_key = self.cache_key(request);
+        if let Some(key) = &cache_key
+            && !request.bypass_cache.unwrap_or(false)
+            && let Some(cached) = self.cached(key)
+        {
+            return Ok(cached);
+        }
+
+        let url = self.base_url.join("get_pot")?;
+        let response: PotResponse = self
+            .with_retries(|| async {
+                parse_response(self.http.post(url.clone()).json(request).send().await?).await
+            })
+            .await?;
+        if let (Some(key), Some(cache)) = (cache_key, &self.cache)
+            && !response.fallback
+        {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(key, response.clone());
+        }
+        Ok(response)
+    }
+
+    /// Check the provider is up with `GET /ping`
+    pub async fn ping(&self) -> Result<PingResponse> {
+        let url = self.base_url.join("ping")?;
+        self.with_retries(|| async {
+            parse_response(self.http.get(url.clone()).send().await?).await
+        })
+        .await
+    }
+
+    /// Drop the tokens cached in the client
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+
+    /// Key the token for `request` is cached under, if it may be cached
+    ///
+    /// Like the server's session data cache, tokens are keyed by content
+    /// binding and token type, inferred from the binding when not set.
+    fn cache_key(&self, request: &PotRequest) -> Option<String> {
+        if self.cache.is_none() || request.is_single_use() {
+            return None;
+        }
+        let binding = request.content_binding.as_deref()?;
+        let token_type = request
+            .token_type
+            .unwrap_or_else(|| PotTokenType::for_content_binding(binding));
+        Some(format!("{}#{}", binding, token_type.as_str()))
+    }
+
+    /// Get the unexpired token cached under `key`
+    fn cached(&self, key: &str) -> Option<PotResponse> {
+        let mut cache = self
+            .cache
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, response| !response.is_expired());
+        cache.get(key).cloned()
+    }
+
+    /// Run `send`, retrying retryable failures with exponential backoff
+    async fn with_retries<T, F, Fut>(&self, send: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match send().await {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let backoff = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                    let delay = match &e {
+                        Error::RateLimit {
+                            retry_after: Some(secs),
+                            ..
+                        } => Duration::from_secs(*secs),
+                        _ => backoff,
+                    };
+                    if delay > MAX_RETRY_AFTER {
+                        return Err(e);
+                    }
+                    tracing::debug!("Request to provider failed, retrying in {:?}: {}", delay, e);
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for PotClient {
+    async fn generate_pot_token(&self, request: &PotRequest) -> Result<PotResponse> {
+        self.get_pot(request).await
+    }
+}
+
+/// Wait `delay` before retrying
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(delay: Duration) {
+    tokio::time::sleep(delay).await;
+}
+
+/// Wait `delay` before retrying
+///
+/// There is no timer without a JavaScript runtime binding, so retries on
+/// `wasm32` follow each other immediately.
+#[cfg(target_arch = "wasm32")]
+async fn sleep(_delay: Duration) {}
+
+/// Parse a successful response body, or turn an error response into an error
+async fn parse_response<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ErrorResponse>(&body)
+        .map(|error| error.error)
+        .unwrap_or(body);
+    Err(match status.as_u16() {
+        400 | 422 => Error::validation("request".to_string(), message),
+        401 | 403 => Error::Auth {
+            reason: message,
+            endpoint: None,
+        },
+        429 => Error::rate_limit(message, retry_after),
+        _ => Error::server(format!("Provider returned {}: {}", status, message)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    #[tokio::test]
+    async fn test_get_pot() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/prefix/get_pot"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "poToken": "token",
+                "contentBinding": "video",
+                "expiresAt": "2030-01-01T00:00:00Z",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = PotClient::new(&format!("{}/prefix", server.uri())).unwrap();
+        let request = PotRequest::new().with_content_binding("video");
+        let response = client.get_pot(&request).await.unwrap();
+        assert_eq!(response.po_token, "token");
+        assert_eq!(response.content_binding, "video");
+
+        // Cached in the client, unless bypassed
+        let response = client.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.po_token, "token");
+        client
+            .get_pot(&request.clone().with_bypass_cache(true))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_keyed_by_token_type() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/get_pot"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "poToken": "token",
+                "contentBinding": "video",
+                "expiresAt": "2030-01-01T00:00:00Z",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = PotClient::new(&server.uri()).unwrap();
+        let request = PotRequest::new().with_content_binding("video");
+        client.get_pot(&request).await.unwrap();
+        client.get_pot(&request).await.unwrap();
+
+        // Another token type for the same binding isn't served from the cache
+        let request = request.with_token_type(PotTokenType::ColdStart);
+        client.get_pot(&request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_unavailable() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/ping"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ping"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"server_uptime": 1, "version": "1.0.0"})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = PotClient::new(&server.uri()).unwrap();
+        assert_eq!(client.ping().await.unwrap().server_uptime, 1);
+
+        let client = client.with_max_retries(0);
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/ping"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
+        assert!(client.ping().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_error_responses() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/get_pot"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "300")
+                    .set_body_json(ErrorResponse::new("Too many requests")),
+            )
+            .mount(&server)
+            .await;
+
+        // Retry-After delays above the limit aren't waited out
+        let client = PotClient::new(&server.uri()).unwrap();
+        let error = client.get_pot(&PotRequest::new()).await.unwrap_err();
+        assert!(matches!(
+            error,
+            Error::RateLimit {
+                retry_after: Some(300),
+                ..
+            }
+        ));
+    }
+}
@@ -6,6 +6,7 @@
 //! Based on TypeScript environment variable usage throughout the project.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
 // Helper functions for serde defaults
@@ -37,6 +38,10 @@ fn default_log_format() -> String {
     "text".to_string()
 }
 
+fn default_log_locale() -> String {
+    "en".to_string()
+}
+
 fn default_connect_timeout() -> u64 {
     30
 }
@@ -45,6 +50,18 @@ fn default_request_timeout() -> u64 {
     60
 }
 
+fn default_pool_idle_timeout() -> u64 {
+    90
+}
+
+fn default_http_version() -> String {
+    "auto".to_string()
+}
+
+fn default_upstream_burst() -> u32 {
+    5
+}
+
 fn default_max_retries() -> u32 {
     3
 }
@@ -57,6 +74,18 @@ fn default_user_agent() -> String {
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".to_string()
 }
 
+fn default_innertube_base_url() -> String {
+    "https://www.youtube.com/youtubei/v1".to_string()
+}
+
+fn default_innertube_hl() -> String {
+    "en".to_string()
+}
+
+fn default_innertube_gl() -> String {
+    "US".to_string()
+}
+
 fn default_request_key() -> String {
     "O43z0dpjhgX20SCx4KAo".to_string()
 }
@@ -69,6 +98,14 @@ fn default_memory_cache_size() -> usize {
     100
 }
 
+fn default_flush_interval() -> u64 {
+    30
+}
+
+fn default_retention_interval() -> u64 {
+    3600
+}
+
 fn default_pot_cache_duration() -> u64 {
     1800 // 30 minutes
 }
@@ -77,10 +114,21 @@ fn default_pot_generation_timeout() -> u64 {
     30 // 30 seconds
 }
 
+fn default_cancelled_mint() -> String {
+    "complete".to_string()
+}
+
+fn default_clock_skew_warn_secs() -> u64 {
+    60
+}
+
 fn default_ttl_hours() -> u64 {
     6
 }
 
+/// Longest token TTL accepted, a year
+pub(crate) const MAX_TTL_HOURS: u64 = 24 * 365;
+
 // Duration serialization module
 mod duration_secs {
     use serde::{Deserialize, Deserializer, Serializer};
@@ -117,22 +165,173 @@ pub struct Settings {
     /// Network configuration
     #[serde(default)]
     pub network: NetworkSettings,
+    /// Innertube API configuration
+    #[serde(default)]
+    pub innertube: InnertubeSettings,
     /// BotGuard configuration
     #[serde(default)]
     pub botguard: BotGuardSettings,
     /// Cache configuration
     #[serde(default)]
     pub cache: CacheSettings,
+    /// Async runtime configuration
+    #[serde(default)]
+    pub runtime: RuntimeSettings,
+    /// Fault injection, applied when built with the `chaos` feature
+    #[serde(default)]
+    pub chaos: ChaosSettings,
+}
+
+/// Placeholder for secret values in logged settings
+const REDACTED: &str = "redacted";
+
+/// Header names whose values are redacted in logs
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "x-goog-api-key",
+    "x-goog-visitor-id",
+    "x-youtube-identity-token",
+];
+
+/// Slowest upstream request rate accepted, one request every ~17 minutes
+const MIN_UPSTREAM_QPS: f64 = 0.001;
+
+/// Get a proxy URL safe to log or return, with its password redacted
+pub fn redact_proxy_url(proxy: &str) -> String {
+    if let Ok(mut url) = url::Url::parse(proxy)
+        && url.password().is_some()
+        && url.set_password(Some(REDACTED)).is_ok()
+    {
+        return url.to_string();
+    }
+    proxy.to_string()
+}
+
+/// Value of header `name` for logs and recordings, redacted if sensitive
+pub fn redact_header(name: &str, value: &str) -> String {
+    if SENSITIVE_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+        "<redacted>".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Read environment variable `name`, or the file named by `<name>_FILE`
+///
+/// The `_FILE` form follows the Docker secrets convention, so secrets don't
+/// have to be passed as plain environment variables.
+fn env_or_file(name: &str) -> crate::Result<Option<String>> {
+    if let Ok(value) = std::env::var(name) {
+        return Ok(Some(value));
+    }
+    match std::env::var(format!("{}_FILE", name)) {
+        Ok(path) => read_secret_file(std::path::Path::new(&path)).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Read a secret from `path` without its trailing newline
+///
+/// Errors name the file but never include its content.
+fn read_secret_file(path: &std::path::Path) -> crate::Result<String> {
+    std::fs::read_to_string(path)
+        .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| {
+            crate::Error::config(
+                "secret_file",
+                &format!("Failed to read secret file {:?}: {}", path, e),
+            )
+        })
+}
+
+/// Merge `overrides` into `base`, recursing into tables present in both
+fn merge_toml_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge_toml_tables(base, overrides)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Profiles selectable without defining them in the configuration file
+///
+/// A `[profiles.<name>]` table of the same name in the file takes precedence.
+const BUILTIN_PROFILES: &[(&str, &str)] = &[(
+    "minimal-memory",
+    r#"
+[server]
+# Rewriting keys to camelCase buffers every response body
+camel_case_responses = false
+max_body_size = 65536
+
+[token]
+max_cache_entries = 100
+max_cache_bytes = 262144
+
+[cache]
+memory_cache_size = 10
+persist_server_cache = false
+
+[botguard]
+disable_snapshot = true
+
+[runtime]
+worker_threads = 1
+max_blocking_threads = 4
+"#,
+)];
+
+/// Get the built-in profile `name`
+fn builtin_profile(name: &str) -> Option<toml::Table> {
+    BUILTIN_PROFILES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, content)| toml::from_str(content).expect("built-in profiles are valid TOML"))
+}
+
+fn default_max_blocking_threads() -> usize {
+    512
+}
+
+fn default_slow_response_ms() -> u64 {
+    2000
 }
 
 fn default_host() -> String {
     "::".to_string()
 }
 
+fn default_usage_retention_days() -> u32 {
+    90
+}
+
 fn default_port() -> u16 {
     4416
 }
 
+fn default_daemon_idle_timeout() -> u64 {
+    300
+}
+
+fn default_slo_target() -> f64 {
+    0.99
+}
+
+fn default_slo_latency_ms() -> u64 {
+    5000
+}
+
+fn default_slo_window() -> u64 {
+    3600
+}
+
 /// HTTP server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerSettings {
@@ -151,6 +350,47 @@ pub struct ServerSettings {
     /// Maximum request body size
     #[serde(default = "default_max_body_size")]
     pub max_body_size: usize,
+    /// Serialize every JSON response with camelCase keys
+    #[serde(default)]
+    pub camel_case_responses: bool,
+    /// Bearer token required by the cache invalidation endpoints, open if unset
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// File holding the admin token (e.g. a Docker secret), read when `admin_token` is unset
+    #[serde(default)]
+    pub admin_token_file: Option<std::path::PathBuf>,
+    /// Count POT requests per day, token type and outcome for `GET /stats/usage`
+    #[serde(default)]
+    pub usage_analytics: bool,
+    /// Days of usage counts kept
+    #[serde(default = "default_usage_retention_days")]
+    pub usage_retention_days: u32,
+    /// Seconds without requests after which the script mode daemon exits
+    #[serde(default = "default_daemon_idle_timeout")]
+    pub daemon_idle_timeout: u64,
+    /// Fraction of token requests that must succeed, and succeed within
+    /// `slo_latency_ms`, for `GET /slo` to report compliance
+    #[serde(default = "default_slo_target")]
+    pub slo_target: f64,
+    /// Latency target of token requests in milliseconds
+    #[serde(default = "default_slo_latency_ms")]
+    pub slo_latency_ms: u64,
+    /// Rolling window `GET /slo` covers, in seconds
+    #[serde(default = "default_slo_window")]
+    pub slo_window: u64,
+    /// Milliseconds for which a byte-identical `/get_pot` body is answered
+    /// with the response of the first one, 0 to disable
+    #[serde(default)]
+    pub dedupe_window_ms: u64,
+    /// Ports above `port` to try in order when it is in use, e.g. `4416-4426`
+    #[serde(default)]
+    pub port_range: Option<String>,
+    /// File the port actually listened on is written to, removed on shutdown
+    #[serde(default)]
+    pub port_file: Option<std::path::PathBuf>,
+    /// Token types of the `/get_pot/{alias}` endpoints, e.g. `gvs = "session_bound"`
+    #[serde(default)]
+    pub token_type_aliases: HashMap<String, crate::types::PotTokenType>,
 }
 
 /// Token generation and caching configuration
@@ -165,7 +405,10 @@ pub struct TokenSettings {
     /// Maximum cache entries
     #[serde(default = "default_max_cache_entries")]
     pub max_cache_entries: usize,
-    /// Cache cleanup interval in minutes
+    /// Approximate memory budget of the server's POT cache in bytes, unlimited if 0
+    #[serde(default)]
+    pub max_cache_bytes: u64,
+    /// Interval in minutes of removing expired tokens from the cache, 0 to disable
     #[serde(default = "default_cache_cleanup_interval")]
     pub cache_cleanup_interval: u64,
     /// POT Token cache duration in seconds
@@ -174,6 +417,21 @@ pub struct TokenSettings {
     /// POT token generation timeout in seconds
     #[serde(default = "default_pot_generation_timeout")]
     pub pot_generation_timeout: u64,
+    /// Refresh a cached token in the background when a cache hit finds less
+    /// than this fraction of its lifetime left (0 disables)
+    #[serde(default)]
+    pub refresh_ahead_fraction: f64,
+    /// What happens to the token being minted for a request whose client
+    /// disconnected: `complete` mints and caches it, `abort` stops minting
+    #[serde(default = "default_cancelled_mint")]
+    pub cancelled_mint: String,
+    /// Skew of the system clock against Innertube's `Date` header, in
+    /// seconds, above which a warning is logged
+    #[serde(default = "default_clock_skew_warn_secs")]
+    pub clock_skew_warn_secs: u64,
+    /// Shorten token lifetimes by the measured clock skew
+    #[serde(default)]
+    pub correct_clock_skew: bool,
 }
 
 /// Logging configuration
@@ -191,6 +449,18 @@ pub struct LoggingSettings {
     /// Enable request/response logging
     #[serde(default = "default_true")]
     pub log_requests: bool,
+    /// Write per-request phase timelines to this file in Chrome trace-event format
+    #[serde(default)]
+    pub trace_file: Option<std::path::PathBuf>,
+    /// Language of user-facing CLI messages (en, zh-TW)
+    #[serde(default = "default_log_locale")]
+    pub locale: String,
+    /// `RUST_LOG`-style filter directives (e.g. `bgutil=debug,hyper=warn`), overrides `level`
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Show content bindings in logs and events instead of a short hash of them
+    #[serde(default)]
+    pub reveal_content_bindings: bool,
 }
 
 /// Network and proxy configuration
@@ -205,12 +475,36 @@ pub struct NetworkSettings {
     /// All protocols proxy URL (corresponds to TypeScript ALL_PROXY)
     #[serde(default)]
     pub all_proxy: Option<String>,
+    /// Upstream Innertube/BotGuard requests per second across all proxies (`0` is unlimited)
+    #[serde(default)]
+    pub upstream_qps: f64,
+    /// Upstream Innertube/BotGuard requests per second through each proxy (`0` is unlimited)
+    #[serde(default)]
+    pub upstream_qps_per_proxy: f64,
+    /// Requests allowed in a burst before the upstream rate applies
+    #[serde(default = "default_upstream_burst")]
+    pub upstream_burst: u32,
     /// Connection timeout in seconds
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout: u64,
     /// Request timeout in seconds
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
+    /// Idle pooled connection timeout in seconds
+    #[serde(default = "default_pool_idle_timeout")]
+    pub pool_idle_timeout: u64,
+    /// HTTP version for outbound requests: `auto` (ALPN), `http1` or `http2`
+    #[serde(default = "default_http_version")]
+    pub http_version: String,
+    /// Minimum TLS version for outbound requests (e.g. `1.2`)
+    #[serde(default)]
+    pub tls_min_version: Option<String>,
+    /// Maximum TLS version for outbound requests (e.g. `1.3`)
+    #[serde(default)]
+    pub tls_max_version: Option<String>,
+    /// Accept gzip and brotli compressed responses
+    #[serde(default = "default_true")]
+    pub compression: bool,
     /// Number of retry attempts
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
@@ -220,6 +514,38 @@ pub struct NetworkSettings {
     /// User agent string
     #[serde(default = "default_user_agent")]
     pub user_agent: String,
+    /// Extra headers sent with Innertube requests (e.g. `Accept-Language`)
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    /// File holding `user:password` credentials for proxies configured without any
+    #[serde(default)]
+    pub proxy_credentials_file: Option<std::path::PathBuf>,
+    /// Forward the `traceparent` of server requests to Innertube requests
+    #[serde(default)]
+    pub propagate_trace_context: bool,
+    /// JSON lines file every Innertube request and response is appended to
+    #[serde(default)]
+    pub innertube_record: Option<std::path::PathBuf>,
+    /// Recording written by `innertube_record` to serve instead of contacting Innertube
+    #[serde(default)]
+    pub innertube_replay: Option<std::path::PathBuf>,
+}
+
+/// Innertube API configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InnertubeSettings {
+    /// Base URL of the Innertube API, e.g. a mirror or a debugging proxy
+    #[serde(default = "default_innertube_base_url")]
+    pub base_url: String,
+    /// API key sent as `X-Goog-Api-Key` with every Innertube request
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Interface language of generated client contexts, e.g. `en`
+    #[serde(default = "default_innertube_hl")]
+    pub hl: String,
+    /// Region of generated client contexts, e.g. `US`
+    #[serde(default = "default_innertube_gl")]
+    pub gl: String,
 }
 
 /// BotGuard specific configuration
@@ -243,12 +569,64 @@ pub struct BotGuardSettings {
     /// BotGuard snapshot file path for caching
     #[serde(default)]
     pub snapshot_path: Option<std::path::PathBuf>,
+    /// Directory of snapshots keyed by user agent and proxy, overrides `snapshot_path`
+    #[serde(default)]
+    pub snapshot_dir: Option<std::path::PathBuf>,
     /// Custom User Agent for BotGuard
     #[serde(default)]
     pub user_agent: Option<String>,
     /// Disable snapshot functionality
     #[serde(default)]
     pub disable_snapshot: bool,
+    /// CPUs the BotGuard worker thread is pinned to, unpinned if empty (Linux only)
+    #[serde(default)]
+    pub cpu_affinity: Vec<usize>,
+    /// Niceness of the BotGuard worker thread, from -20 to 19 (Linux only)
+    #[serde(default)]
+    pub nice: Option<i32>,
+}
+
+/// Tokio runtime configuration, applied in server mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeSettings {
+    /// Async worker threads, one per CPU if unset
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+    /// Maximum threads for blocking work such as BotGuard calls
+    #[serde(default = "default_max_blocking_threads")]
+    pub max_blocking_threads: usize,
+    /// Stack size of runtime threads in bytes, tokio's default if unset
+    #[serde(default)]
+    pub thread_stack_size: Option<usize>,
+}
+
+/// Fault injection for resilience testing
+///
+/// Only applied when built with the `chaos` feature, so a stray `[chaos]`
+/// section can't break a production build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChaosSettings {
+    /// Probability that an Innertube request fails with a 500 response
+    #[serde(default)]
+    pub innertube_error_rate: f64,
+    /// Probability that a BotGuard call times out
+    #[serde(default)]
+    pub botguard_timeout_rate: f64,
+    /// Probability that an Innertube request or BotGuard call is delayed
+    #[serde(default)]
+    pub slow_response_rate: f64,
+    /// Delay of slow responses in milliseconds
+    #[serde(default = "default_slow_response_ms")]
+    pub slow_response_ms: u64,
+}
+
+impl ChaosSettings {
+    /// Whether any fault is injected
+    pub fn is_enabled(&self) -> bool {
+        self.innertube_error_rate > 0.0
+            || self.botguard_timeout_rate > 0.0
+            || self.slow_response_rate > 0.0
+    }
 }
 
 /// Cache configuration
@@ -266,6 +644,21 @@ pub struct CacheSettings {
     /// Enable cache compression
     #[serde(default)]
     pub enable_compression: bool,
+    /// Back the server's in-memory cache with the file cache
+    #[serde(default)]
+    pub persist_server_cache: bool,
+    /// Write-behind flush interval to the file cache in seconds
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: u64,
+    /// Maximum size of the server's file cache in bytes, unlimited if 0
+    #[serde(default)]
+    pub retention_max_size: u64,
+    /// Remove BotGuard snapshots in `botguard.snapshot_dir` unused for this many days, never if 0
+    #[serde(default)]
+    pub retention_max_age_days: u64,
+    /// Interval of the retention sweep in seconds
+    #[serde(default = "default_retention_interval")]
+    pub retention_interval: u64,
 }
 
 impl Default for ServerSettings {
@@ -276,7 +669,42 @@ impl Default for ServerSettings {
             timeout: default_timeout(),
             enable_cors: default_true(),
             max_body_size: default_max_body_size(),
+            camel_case_responses: false,
+            admin_token: None,
+            admin_token_file: None,
+            usage_analytics: false,
+            usage_retention_days: default_usage_retention_days(),
+            daemon_idle_timeout: default_daemon_idle_timeout(),
+            slo_target: default_slo_target(),
+            slo_latency_ms: default_slo_latency_ms(),
+            slo_window: default_slo_window(),
+            dedupe_window_ms: 0,
+            port_range: None,
+            port_file: None,
+            token_type_aliases: HashMap::new(),
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Parse `port_range`, `None` if unset
+    pub fn port_range(&self) -> crate::Result<Option<std::ops::RangeInclusive<u16>>> {
+        let Some(range) = &self.port_range else {
+            return Ok(None);
+        };
+        let invalid = || {
+            crate::Error::config(
+                "port_range",
+                &format!("Invalid port range: {} (expected e.g. 4416-4426)", range),
+            )
+        };
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let start: u16 = start.trim().parse().map_err(|_| invalid())?;
+        let end: u16 = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || start > end {
+            return Err(invalid());
         }
+        Ok(Some(start..=end))
     }
 }
 
@@ -286,9 +714,14 @@ impl Default for TokenSettings {
             ttl_hours: 6,
             enable_cache: default_true(),
             max_cache_entries: default_max_cache_entries(),
+            max_cache_bytes: 0,
             cache_cleanup_interval: default_cache_cleanup_interval(),
             pot_cache_duration: default_pot_cache_duration(),
             pot_generation_timeout: default_pot_generation_timeout(),
+            refresh_ahead_fraction: 0.0,
+            cancelled_mint: default_cancelled_mint(),
+            clock_skew_warn_secs: default_clock_skew_warn_secs(),
+            correct_clock_skew: false,
         }
     }
 }
@@ -300,6 +733,31 @@ impl Default for LoggingSettings {
             verbose: false,
             format: default_log_format(),
             log_requests: default_true(),
+            trace_file: None,
+            locale: default_log_locale(),
+            filter: None,
+            reveal_content_bindings: false,
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Iterate mutably over every configured proxy URL
+    fn proxies_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.https_proxy
+            .iter_mut()
+            .chain(self.http_proxy.iter_mut())
+            .chain(self.all_proxy.iter_mut())
+    }
+}
+
+impl Default for InnertubeSettings {
+    fn default() -> Self {
+        Self {
+            base_url: default_innertube_base_url(),
+            api_key: None,
+            hl: default_innertube_hl(),
+            gl: default_innertube_gl(),
         }
     }
 }
@@ -310,11 +768,24 @@ impl Default for NetworkSettings {
             https_proxy: None,
             http_proxy: None,
             all_proxy: None,
+            upstream_qps: 0.0,
+            upstream_qps_per_proxy: 0.0,
+            upstream_burst: default_upstream_burst(),
             connect_timeout: default_connect_timeout(),
             request_timeout: default_request_timeout(),
+            pool_idle_timeout: default_pool_idle_timeout(),
+            http_version: default_http_version(),
+            tls_min_version: None,
+            tls_max_version: None,
+            compression: true,
             max_retries: default_max_retries(),
             retry_interval: default_retry_interval(),
             user_agent: default_user_agent(),
+            extra_headers: HashMap::new(),
+            proxy_credentials_file: None,
+            propagate_trace_context: false,
+            innertube_record: None,
+            innertube_replay: None,
         }
     }
 }
@@ -332,8 +803,11 @@ impl Default for BotGuardSettings {
                     .join("bgutil-pot")
                     .join("botguard_snapshot.bin"),
             ),
+            snapshot_dir: None,
             user_agent: None, // Use rustypipe-botguard default
             disable_snapshot: false,
+            cpu_affinity: Vec::new(),
+            nice: None,
         }
     }
 }
@@ -345,10 +819,54 @@ impl Default for CacheSettings {
             enable_file_cache: default_true(),
             memory_cache_size: default_memory_cache_size(),
             enable_compression: false,
+            persist_server_cache: false,
+            flush_interval: default_flush_interval(),
+            retention_max_size: 0,
+            retention_max_age_days: 0,
+            retention_interval: default_retention_interval(),
+        }
+    }
+}
+
+impl Default for ChaosSettings {
+    fn default() -> Self {
+        Self {
+            innertube_error_rate: 0.0,
+            botguard_timeout_rate: 0.0,
+            slow_response_rate: 0.0,
+            slow_response_ms: default_slow_response_ms(),
+        }
+    }
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            worker_threads: None,
+            max_blocking_threads: default_max_blocking_threads(),
+            thread_stack_size: None,
         }
     }
 }
 
+impl RuntimeSettings {
+    /// Build a multi-threaded tokio runtime with these settings
+    #[cfg(feature = "provider")]
+    pub fn build_runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder
+            .enable_all()
+            .max_blocking_threads(self.max_blocking_threads);
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(thread_stack_size) = self.thread_stack_size {
+            builder.thread_stack_size(thread_stack_size);
+        }
+        builder.build()
+    }
+}
+
 impl Settings {
     /// Create new settings with default values
     pub fn new() -> Self {
@@ -379,6 +897,8 @@ impl Settings {
             settings.server.timeout = Duration::from_secs(timeout_secs);
         }
 
+        settings.server.admin_token = env_or_file("POT_ADMIN_TOKEN")?;
+
         // Load token settings (TOKEN_TTL from TypeScript)
         if let Ok(ttl) = std::env::var("TOKEN_TTL") {
             settings.token.ttl_hours = ttl
@@ -387,9 +907,9 @@ impl Settings {
         }
 
         // Load network/proxy settings (from TypeScript)
-        settings.network.https_proxy = std::env::var("HTTPS_PROXY").ok();
-        settings.network.http_proxy = std::env::var("HTTP_PROXY").ok();
-        settings.network.all_proxy = std::env::var("ALL_PROXY").ok();
+        settings.network.https_proxy = env_or_file("HTTPS_PROXY")?;
+        settings.network.http_proxy = env_or_file("HTTP_PROXY")?;
+        settings.network.all_proxy = env_or_file("ALL_PROXY")?;
 
         // Load logging settings
         if let Ok(level) = std::env::var("LOG_LEVEL") {
@@ -400,6 +920,10 @@ impl Settings {
             settings.logging.verbose = verbose.parse().unwrap_or(false);
         }
 
+        if let Ok(locale) = std::env::var("LOG_LOCALE") {
+            settings.logging.locale = locale;
+        }
+
         // Load BotGuard settings
         if let Ok(disable_innertube) = std::env::var("DISABLE_INNERTUBE") {
             settings.botguard.disable_innertube = disable_innertube.parse().unwrap_or(false);
@@ -413,11 +937,76 @@ impl Settings {
 
     /// Load settings from configuration file
     pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> crate::Result<Self> {
+        Self::from_file_with_profile(path, None)
+    }
+
+    /// Load settings from a TOML file, applying the named profile
+    ///
+    /// Profiles are tables under `[profiles.<name>]` shaped like the base
+    /// configuration, e.g. `[profiles.prod.server]`. The selected profile is
+    /// merged over the base settings, key by key.
+    pub fn from_file_with_profile<P: AsRef<std::path::Path>>(
+        path: P,
+        profile: Option<&str>,
+    ) -> crate::Result<Self> {
         let content = std::fs::read_to_string(path).map_err(|e| {
             crate::Error::config("file", &format!("Failed to read config file: {}", e))
         })?;
 
-        let settings: Settings = toml::from_str(&content).map_err(|e| {
+        let table: toml::Table = toml::from_str(&content).map_err(|e| {
+            crate::Error::config("file", &format!("Failed to parse config file: {}", e))
+        })?;
+
+        Self::from_table_with_profile(table, profile)
+    }
+
+    /// Get the default settings with the built-in profile `name` applied
+    ///
+    /// Used when a profile is selected without a configuration file.
+    pub fn from_builtin_profile(name: &str) -> crate::Result<Self> {
+        Self::from_table_with_profile(toml::Table::new(), Some(name))
+    }
+
+    /// Deserialize a configuration table, applying the named profile
+    fn from_table_with_profile(
+        mut table: toml::Table,
+        profile: Option<&str>,
+    ) -> crate::Result<Self> {
+        let mut profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => {
+                return Err(crate::Error::config(
+                    "profiles",
+                    "Expected a table of named profiles",
+                ));
+            }
+            None => toml::Table::new(),
+        };
+        if let Some(name) = profile {
+            match profiles
+                .remove(name)
+                .or_else(|| builtin_profile(name).map(toml::Value::Table))
+            {
+                Some(toml::Value::Table(overrides)) => merge_toml_tables(&mut table, overrides),
+                Some(_) => {
+                    return Err(crate::Error::config(
+                        "profiles",
+                        &format!("Profile '{}' must be a table", name),
+                    ));
+                }
+                None => {
+                    return Err(crate::Error::config(
+                        "profiles",
+                        &format!(
+                            "Profile '{}' not found in config file or built-in profiles",
+                            name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let settings: Settings = table.try_into().map_err(|e| {
             crate::Error::config("file", &format!("Failed to parse config file: {}", e))
         })?;
 
@@ -437,10 +1026,18 @@ impl Settings {
             self.server.port = env_settings.server.port;
         }
 
+        if env_settings.server.admin_token.is_some() {
+            self.server.admin_token = env_settings.server.admin_token;
+        }
+
         if env_settings.token.ttl_hours != Self::default().token.ttl_hours {
             self.token.ttl_hours = env_settings.token.ttl_hours;
         }
 
+        if env_settings.logging.locale != Self::default().logging.locale {
+            self.logging.locale = env_settings.logging.locale;
+        }
+
         // Merge proxy settings (always override if present)
         if env_settings.network.https_proxy.is_some() {
             self.network.https_proxy = env_settings.network.https_proxy;
@@ -455,6 +1052,57 @@ impl Settings {
         Ok(self)
     }
 
+    /// Read the secrets configured as files into the settings
+    ///
+    /// `server.admin_token_file` applies when no admin token is set, and the
+    /// `network.proxy_credentials_file` credentials are added to every
+    /// configured proxy URL without credentials of its own.
+    pub fn load_secret_files(&mut self) -> crate::Result<()> {
+        if self.server.admin_token.is_none()
+            && let Some(path) = &self.server.admin_token_file
+        {
+            self.server.admin_token = Some(read_secret_file(path)?);
+        }
+
+        if let Some(path) = &self.network.proxy_credentials_file {
+            let credentials = read_secret_file(path)?;
+            let (username, password) = credentials
+                .split_once(':')
+                .unwrap_or((credentials.as_str(), ""));
+            for proxy in self.network.proxies_mut() {
+                if let Ok(mut url) = url::Url::parse(proxy)
+                    && url.username().is_empty()
+                    && url.set_username(username).is_ok()
+                    && url
+                        .set_password(Some(password).filter(|p| !p.is_empty()))
+                        .is_ok()
+                {
+                    *proxy = url.to_string();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get a copy of the settings safe to log, with secrets redacted
+    pub fn redacted(&self) -> Self {
+        let mut settings = self.clone();
+        if settings.server.admin_token.is_some() {
+            settings.server.admin_token = Some(REDACTED.to_string());
+        }
+        if settings.innertube.api_key.is_some() {
+            settings.innertube.api_key = Some(REDACTED.to_string());
+        }
+        for proxy in settings.network.proxies_mut() {
+            *proxy = redact_proxy_url(proxy);
+        }
+        for (name, value) in &mut settings.network.extra_headers {
+            *value = redact_header(name, value);
+        }
+        settings
+    }
+
     /// Get effective proxy URL based on priority
     ///
     /// Corresponds to TypeScript proxy selection logic in session_manager.ts
@@ -468,53 +1116,385 @@ impl Settings {
     }
 
     /// Validate configuration settings
+    ///
+    /// Checks every field and conflicting combinations of fields, returning a
+    /// single [`Error::Validation`](crate::Error::Validation) that lists all
+    /// problems at once.
     pub fn validate(&self) -> crate::Result<()> {
+        let mut problems: Vec<(&str, String)> = Vec::new();
+
         // Validate server settings
-        if self.server.port == 0 {
-            return Err(crate::Error::config(
-                "port",
-                "Invalid server port: cannot be 0",
+        if self.server.timeout.is_zero() {
+            problems.push(("timeout", "Invalid server timeout: cannot be 0".to_string()));
+        }
+        if let Err(crate::Error::Config { message, .. }) = self.server.port_range() {
+            problems.push(("port_range", message));
+        }
+        for alias in self.server.token_type_aliases.keys() {
+            if alias.is_empty() || alias.contains('/') {
+                problems.push((
+                    "token_type_aliases",
+                    format!(
+                        "Invalid token type alias: {:?} (must be a single path segment, e.g. gvs)",
+                        alias
+                    ),
+                ));
+            }
+        }
+        if self.server.max_body_size == 0 {
+            problems.push((
+                "max_body_size",
+                "Invalid maximum body size: cannot be 0".to_string(),
+            ));
+        }
+        if self
+            .server
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            problems.push((
+                "admin_token",
+                "Invalid admin token: cannot be empty".to_string(),
+            ));
+        }
+
+        if self.server.usage_analytics && self.server.usage_retention_days == 0 {
+            problems.push((
+                "usage_retention_days",
+                "Invalid usage retention: cannot be 0 while usage analytics are enabled"
+                    .to_string(),
+            ));
+        }
+
+        if !(self.server.slo_target > 0.0 && self.server.slo_target < 1.0) {
+            problems.push((
+                "slo_target",
+                format!(
+                    "Invalid SLO target: {} (must be above 0 and below 1)",
+                    self.server.slo_target
+                ),
             ));
         }
+        if self.server.slo_window == 0 {
+            problems.push(("slo_window", "Invalid SLO window: cannot be 0".to_string()));
+        }
 
         // Validate token settings
         if self.token.ttl_hours == 0 {
-            return Err(crate::Error::config(
+            problems.push(("ttl_hours", "Invalid token TTL: cannot be 0".to_string()));
+        } else if self.token.ttl_hours > MAX_TTL_HOURS {
+            problems.push((
                 "ttl_hours",
-                "Invalid token TTL: cannot be 0",
+                format!("Invalid token TTL: cannot exceed {} hours", MAX_TTL_HOURS),
+            ));
+        }
+        if self.token.enable_cache && self.token.max_cache_entries == 0 {
+            problems.push((
+                "max_cache_entries",
+                "Invalid cache size: cannot be 0 while token caching is enabled".to_string(),
+            ));
+        }
+        if self.token.pot_generation_timeout == 0 {
+            problems.push((
+                "pot_generation_timeout",
+                "Invalid POT generation timeout: cannot be 0".to_string(),
+            ));
+        }
+        if !(0.0..1.0).contains(&self.token.refresh_ahead_fraction) {
+            problems.push((
+                "refresh_ahead_fraction",
+                format!(
+                    "Invalid refresh-ahead fraction: {} (must be at least 0 and below 1)",
+                    self.token.refresh_ahead_fraction
+                ),
+            ));
+        }
+        #[cfg(feature = "provider")]
+        if crate::session::CancelledMint::parse(&self.token.cancelled_mint).is_none() {
+            problems.push((
+                "cancelled_mint",
+                format!(
+                    "Invalid cancelled mint handling: {} (expected complete or abort)",
+                    self.token.cancelled_mint
+                ),
             ));
         }
 
         // Validate log level
         match self.logging.level.to_lowercase().as_str() {
             "trace" | "debug" | "info" | "warn" | "error" => {}
-            _ => {
-                return Err(crate::Error::config(
-                    "log_level",
-                    &format!("Invalid log level: {}", self.logging.level),
+            _ => problems.push((
+                "log_level",
+                format!("Invalid log level: {}", self.logging.level),
+            )),
+        }
+        if let Some(filter) = &self.logging.filter
+            && let Err(e) = tracing_subscriber::EnvFilter::try_new(filter)
+        {
+            problems.push((
+                "log_filter",
+                format!("Invalid log filter: {} ({})", filter, e),
+            ));
+        }
+        if crate::utils::messages::Locale::parse(&self.logging.locale).is_none() {
+            problems.push((
+                "log_locale",
+                format!(
+                    "Invalid log locale: {} (supported: {})",
+                    self.logging.locale,
+                    crate::utils::messages::Locale::SUPPORTED.join(", ")
+                ),
+            ));
+        }
+
+        // Validate cache settings
+        if self.cache.memory_cache_size == 0 {
+            problems.push((
+                "memory_cache_size",
+                "Invalid memory cache size: cannot be 0".to_string(),
+            ));
+        }
+        if self.cache.retention_interval == 0 {
+            problems.push((
+                "retention_interval",
+                "Invalid retention interval: cannot be 0".to_string(),
+            ));
+        }
+        if self.cache.persist_server_cache && !self.cache.enable_file_cache {
+            problems.push((
+                "persist_server_cache",
+                "Conflicting options: persist_server_cache requires enable_file_cache".to_string(),
+            ));
+        }
+
+        // Validate runtime settings; tokio panics on zero sizes
+        if self.runtime.worker_threads == Some(0) {
+            problems.push((
+                "worker_threads",
+                "Invalid runtime worker threads: cannot be 0".to_string(),
+            ));
+        }
+        if self.runtime.max_blocking_threads == 0 {
+            problems.push((
+                "max_blocking_threads",
+                "Invalid runtime blocking threads: cannot be 0".to_string(),
+            ));
+        }
+        if self.runtime.thread_stack_size == Some(0) {
+            problems.push((
+                "thread_stack_size",
+                "Invalid runtime thread stack size: cannot be 0".to_string(),
+            ));
+        }
+
+        if self.network.innertube_record.is_some() && self.network.innertube_replay.is_some() {
+            problems.push((
+                "innertube_replay",
+                "Conflicting options: innertube_record and innertube_replay".to_string(),
+            ));
+        }
+
+        // Validate Innertube settings
+        match url::Url::parse(&self.innertube.base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(url) => problems.push((
+                "base_url",
+                format!(
+                    "Invalid Innertube base URL scheme: {} (expected http or https)",
+                    url.scheme()
+                ),
+            )),
+            Err(e) => problems.push((
+                "base_url",
+                format!(
+                    "Invalid Innertube base URL {:?}: {}",
+                    self.innertube.base_url, e
+                ),
+            )),
+        }
+        if self
+            .innertube
+            .api_key
+            .as_ref()
+            .is_some_and(|key| key.trim().is_empty())
+        {
+            problems.push((
+                "api_key",
+                "Invalid Innertube API key: cannot be empty".to_string(),
+            ));
+        }
+        for (field, value) in [("hl", &self.innertube.hl), ("gl", &self.innertube.gl)] {
+            if value.trim().is_empty() {
+                problems.push((
+                    field,
+                    format!("Invalid Innertube {}: cannot be empty", field),
+                ));
+            }
+        }
+
+        // Validate fault injection probabilities
+        for (field, rate) in [
+            ("innertube_error_rate", self.chaos.innertube_error_rate),
+            ("botguard_timeout_rate", self.chaos.botguard_timeout_rate),
+            ("slow_response_rate", self.chaos.slow_response_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                problems.push((
+                    field,
+                    format!(
+                        "Invalid fault probability: {} must be between 0 and 1",
+                        rate
+                    ),
+                ));
+            }
+        }
+
+        // Validate BotGuard settings
+        if let Some(nice) = self.botguard.nice
+            && !(-20..=19).contains(&nice)
+        {
+            problems.push((
+                "nice",
+                format!("Invalid BotGuard niceness: {} (expected -20 to 19)", nice),
+            ));
+        }
+        if self.botguard.vm_timeout == 0 {
+            problems.push(("vm_timeout", "Invalid VM timeout: cannot be 0".to_string()));
+        }
+        if self.botguard.disable_snapshot && self.botguard.snapshot_path.is_some() {
+            problems.push((
+                "snapshot_path",
+                "Conflicting options: snapshot_path is set but disable_snapshot is true"
+                    .to_string(),
+            ));
+        }
+        if self.botguard.disable_snapshot && self.botguard.snapshot_dir.is_some() {
+            problems.push((
+                "snapshot_dir",
+                "Conflicting options: snapshot_dir is set but disable_snapshot is true".to_string(),
+            ));
+        }
+
+        // Validate outbound protocol options
+        match self.network.http_version.to_lowercase().as_str() {
+            "auto" | "http1" | "http2" => {}
+            _ => problems.push((
+                "http_version",
+                format!(
+                    "Invalid HTTP version: {} (expected auto, http1 or http2)",
+                    self.network.http_version
+                ),
+            )),
+        }
+        // Checked with the networking code, which isn't built without `provider`
+        #[cfg(feature = "provider")]
+        {
+            let mut tls_versions = [None, None];
+            for (index, (name, version)) in [
+                ("tls_min_version", &self.network.tls_min_version),
+                ("tls_max_version", &self.network.tls_max_version),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                if let Some(version) = version {
+                    tls_versions[index] = crate::session::network::parse_tls_version(version);
+                    if tls_versions[index].is_none() {
+                        problems.push((
+                            name,
+                            format!("Invalid TLS version: {} (expected 1.2 or 1.3)", version),
+                        ));
+                    }
+                }
+            }
+            if let [Some(min), Some(max)] = tls_versions
+                && min > max
+            {
+                problems.push((
+                    "tls_min_version",
+                    "Conflicting options: tls_min_version is above tls_max_version".to_string(),
                 ));
             }
         }
 
+        // Validate timeouts
+        if self.network.connect_timeout == 0 || self.network.request_timeout == 0 {
+            problems.push((
+                "request_timeout",
+                "Invalid network timeout: cannot be 0".to_string(),
+            ));
+        } else if self.network.connect_timeout > self.network.request_timeout {
+            problems.push((
+                "connect_timeout",
+                format!(
+                    "Conflicting options: connect_timeout ({}s) exceeds request_timeout ({}s)",
+                    self.network.connect_timeout, self.network.request_timeout
+                ),
+            ));
+        }
+
+        for (name, rate) in [
+            ("upstream_qps", self.network.upstream_qps),
+            (
+                "upstream_qps_per_proxy",
+                self.network.upstream_qps_per_proxy,
+            ),
+        ] {
+            if !rate.is_finite() || rate < 0.0 {
+                problems.push((
+                    name,
+                    format!("Invalid request rate: {} (must be 0 or positive)", rate),
+                ));
+            } else if rate > 0.0 && rate < MIN_UPSTREAM_QPS {
+                problems.push((
+                    name,
+                    format!(
+                        "Invalid request rate: {} (must be 0 or at least {})",
+                        rate, MIN_UPSTREAM_QPS
+                    ),
+                ));
+            }
+        }
+        if self.network.upstream_burst == 0
+            && (self.network.upstream_qps > 0.0 || self.network.upstream_qps_per_proxy > 0.0)
+        {
+            problems.push((
+                "upstream_burst",
+                "Invalid upstream burst: cannot be 0 while an upstream rate is set".to_string(),
+            ));
+        }
+
+        #[cfg(feature = "provider")]
+        if let Err(e) = crate::session::innertube::validate_headers(&self.network.extra_headers) {
+            problems.push(("extra_headers", e.to_string()));
+        }
+
         // Validate proxy URLs if present
         for (name, proxy_url) in [
             ("https_proxy", &self.network.https_proxy),
             ("http_proxy", &self.network.http_proxy),
             ("all_proxy", &self.network.all_proxy),
-        ]
-        .iter()
-        {
+        ] {
             if let Some(url_str) = proxy_url
                 && let Err(e) = url::Url::parse(url_str)
             {
-                return Err(crate::Error::config(
-                    *name,
-                    &format!("Invalid proxy URL '{}': {}", url_str, e),
-                ));
+                problems.push((name, format!("Invalid proxy URL '{}': {}", url_str, e)));
             }
         }
 
-        Ok(())
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let fields: Vec<&str> = problems.iter().map(|(field, _)| *field).collect();
+        let messages: Vec<&str> = problems
+            .iter()
+            .map(|(_, message)| message.as_str())
+            .collect();
+        Err(crate::Error::validation(
+            fields.join(", "),
+            messages.join("; "),
+        ))
     }
 }
 
@@ -571,6 +1551,66 @@ ttl_hours = 12
         assert_eq!(settings.token.ttl_hours, 12);
     }
 
+    #[test]
+    fn test_load_from_file_with_profile() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            r#"
+[server]
+host = "localhost"
+port = 8080
+
+[token]
+ttl_hours = 12
+
+[profiles.prod.server]
+host = "0.0.0.0"
+
+[profiles.prod.logging]
+level = "warn"
+        "#
+        )
+        .unwrap();
+
+        // The base settings ignore profiles
+        let settings = Settings::from_file(temp_file.path()).unwrap();
+        assert_eq!(settings.server.host, "localhost");
+        assert_eq!(settings.logging.level, "info");
+
+        let settings = Settings::from_file_with_profile(temp_file.path(), Some("prod")).unwrap();
+        assert_eq!(settings.server.host, "0.0.0.0");
+        assert_eq!(settings.server.port, 8080);
+        assert_eq!(settings.token.ttl_hours, 12);
+        assert_eq!(settings.logging.level, "warn");
+
+        let error = Settings::from_file_with_profile(temp_file.path(), Some("staging"));
+        assert!(error.is_err());
+
+        // Built-in profiles apply over the file
+        let settings =
+            Settings::from_file_with_profile(temp_file.path(), Some("minimal-memory")).unwrap();
+        assert_eq!(settings.server.port, 8080);
+        assert_eq!(settings.token.max_cache_entries, 100);
+    }
+
+    #[test]
+    fn test_builtin_profiles() {
+        for (name, _) in BUILTIN_PROFILES {
+            let settings = Settings::from_builtin_profile(name).unwrap();
+            assert!(settings.validate().is_ok(), "{}", name);
+        }
+
+        let settings = Settings::from_builtin_profile("minimal-memory").unwrap();
+        assert!(settings.botguard.disable_snapshot);
+        assert!(settings.botguard.snapshot_path.is_none());
+        assert!(!settings.server.camel_case_responses);
+        assert_eq!(settings.runtime.worker_threads, Some(1));
+        assert_eq!(settings.token.max_cache_bytes, 262144);
+
+        assert!(Settings::from_builtin_profile("staging").is_err());
+    }
+
     #[test]
     fn test_env_var_override() {
         let _lock = ENV_TEST_MUTEX.lock().unwrap();
@@ -609,6 +1649,21 @@ ttl_hours = 12
         assert_eq!(settings.get_proxy_url().unwrap(), "socks5://proxy3:1080");
     }
 
+    #[test]
+    fn test_redacted_hides_sensitive_headers() {
+        let mut settings = Settings::default();
+        settings.network.extra_headers = HashMap::from([
+            ("Authorization".to_string(), "Bearer secret".to_string()),
+            ("X-Goog-Visitor-Id".to_string(), "CgtWaXNpdG9y".to_string()),
+            ("Accept-Language".to_string(), "en".to_string()),
+        ]);
+
+        let redacted = settings.redacted().network.extra_headers;
+        assert_eq!(redacted["Authorization"], "<redacted>");
+        assert_eq!(redacted["X-Goog-Visitor-Id"], "<redacted>");
+        assert_eq!(redacted["Accept-Language"], "en");
+    }
+
     #[test]
     fn test_validation_success() {
         let settings = Settings::default();
@@ -616,10 +1671,11 @@ ttl_hours = 12
     }
 
     #[test]
-    fn test_validation_invalid_port() {
+    fn test_validation_ephemeral_port() {
+        // Port 0 binds an ephemeral port
         let mut settings = Settings::default();
         settings.server.port = 0;
-        assert!(settings.validate().is_err());
+        assert!(settings.validate().is_ok());
     }
 
     #[test]
@@ -628,4 +1684,250 @@ ttl_hours = 12
         settings.network.https_proxy = Some("invalid-url".to_string());
         assert!(settings.validate().is_err());
     }
+
+    #[test]
+    fn test_validation_extra_headers() {
+        let mut settings = Settings::default();
+        settings
+            .network
+            .extra_headers
+            .insert("Accept-Language".to_string(), "en-US".to_string());
+        assert!(settings.validate().is_ok());
+
+        settings
+            .network
+            .extra_headers
+            .insert("Bad Header".to_string(), "x".to_string());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_ttl_hours() {
+        let mut settings = Settings::default();
+        settings.token.ttl_hours = MAX_TTL_HOURS;
+        assert!(settings.validate().is_ok());
+
+        for invalid in [0, MAX_TTL_HOURS + 1, u64::MAX] {
+            settings.token.ttl_hours = invalid;
+            assert!(settings.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validation_refresh_ahead_fraction() {
+        let mut settings = Settings::default();
+        settings.token.refresh_ahead_fraction = 0.2;
+        assert!(settings.validate().is_ok());
+
+        for invalid in [1.0, -0.1, f64::NAN] {
+            settings.token.refresh_ahead_fraction = invalid;
+            assert!(settings.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validation_slo() {
+        let mut settings = Settings::default();
+        settings.server.slo_target = 0.999;
+        assert!(settings.validate().is_ok());
+
+        for invalid in [0.0, 1.0, f64::NAN] {
+            settings.server.slo_target = invalid;
+            assert!(settings.validate().is_err(), "{}", invalid);
+        }
+
+        settings.server.slo_target = 0.99;
+        settings.server.slo_window = 0;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_cancelled_mint() {
+        let mut settings = Settings::default();
+        settings.token.cancelled_mint = "abort".to_string();
+        assert!(settings.validate().is_ok());
+
+        settings.token.cancelled_mint = "retry".to_string();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_upstream_qps() {
+        let mut settings = Settings::default();
+        settings.network.upstream_qps = 2.5;
+        assert!(settings.validate().is_ok());
+
+        settings.network.upstream_qps_per_proxy = -1.0;
+        assert!(settings.validate().is_err());
+
+        settings.network.upstream_qps_per_proxy = 1e-300;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_protocol_options() {
+        let mut settings = Settings::default();
+        settings.network.http_version = "HTTP2".to_string();
+        settings.network.tls_min_version = Some("1.3".to_string());
+        assert!(settings.validate().is_ok());
+
+        settings.network.http_version = "h3".to_string();
+        assert!(settings.validate().is_err());
+
+        settings.network.http_version = "auto".to_string();
+        settings.network.tls_max_version = Some("2.0".to_string());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_reports_every_problem() {
+        let mut settings = Settings::default();
+        settings.server.timeout = Duration::ZERO;
+        settings.token.ttl_hours = 0;
+        settings.botguard.disable_snapshot = true;
+        settings.botguard.snapshot_path = Some(std::path::PathBuf::from("snapshot.bin"));
+
+        match settings.validate() {
+            Err(crate::Error::Validation { field, message, .. }) => {
+                assert_eq!(field, "timeout, ttl_hours, snapshot_path");
+                assert!(message.contains("server timeout"));
+                assert!(message.contains("token TTL"));
+                assert!(message.contains("disable_snapshot"));
+            }
+            other => panic!("Expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validation_conflicting_options() {
+        let mut settings = Settings::default();
+        settings.cache.persist_server_cache = true;
+        settings.cache.enable_file_cache = false;
+        assert!(settings.validate().is_err());
+
+        let mut settings = Settings::default();
+        settings.network.tls_min_version = Some("1.3".to_string());
+        settings.network.tls_max_version = Some("1.2".to_string());
+        assert!(settings.validate().is_err());
+
+        let mut settings = Settings::default();
+        settings.network.connect_timeout = settings.network.request_timeout + 1;
+        assert!(settings.validate().is_err());
+
+        let mut settings = Settings::default();
+        settings.network.upstream_qps = 1.0;
+        settings.network.upstream_burst = 0;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_log_filter() {
+        let mut settings = Settings::default();
+        settings.logging.filter = Some("bgutil_ytdlp_pot_provider=debug,hyper=warn".to_string());
+        assert!(settings.validate().is_ok());
+
+        settings.logging.filter = Some("hyper=loud".to_string());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_runtime() {
+        let mut settings = Settings::default();
+        settings.runtime.worker_threads = Some(2);
+        assert!(settings.validate().is_ok());
+
+        settings.runtime.worker_threads = Some(0);
+        assert!(settings.validate().is_err());
+
+        settings.runtime.worker_threads = None;
+        settings.runtime.max_blocking_threads = 0;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_port_range() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.server.port_range().unwrap(), None);
+
+        settings.server.port_range = Some("4416 - 4426".to_string());
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.server.port_range().unwrap(), Some(4416..=4426));
+
+        for invalid in ["4426-4416", "4416", "0-10", "4416-70000"] {
+            settings.server.port_range = Some(invalid.to_string());
+            assert!(settings.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validation_token_type_aliases() {
+        let mut settings = Settings::default();
+        settings
+            .server
+            .token_type_aliases
+            .insert("gvs".to_string(), crate::types::PotTokenType::SessionBound);
+        assert!(settings.validate().is_ok());
+
+        settings.server.token_type_aliases.insert(
+            "gvs/web".to_string(),
+            crate::types::PotTokenType::SessionBound,
+        );
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_chaos() {
+        let mut settings = Settings::default();
+        settings.chaos.innertube_error_rate = 1.0;
+        settings.chaos.slow_response_rate = 0.5;
+        assert!(settings.validate().is_ok());
+        assert!(settings.chaos.is_enabled());
+
+        settings.chaos.botguard_timeout_rate = 1.5;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_innertube() {
+        let mut settings = Settings::default();
+        settings.innertube.base_url = "http://127.0.0.1:8080/youtubei/v1".to_string();
+        settings.innertube.api_key = Some("AIzaSyTest".to_string());
+        assert!(settings.validate().is_ok());
+
+        settings.innertube.base_url = "ftp://mirror.example/youtubei/v1".to_string();
+        assert!(settings.validate().is_err());
+        settings.innertube.base_url = "not a url".to_string();
+        assert!(settings.validate().is_err());
+
+        settings.innertube.base_url = default_innertube_base_url();
+        settings.innertube.api_key = Some(" ".to_string());
+        assert!(settings.validate().is_err());
+
+        settings.innertube.api_key = None;
+        settings.innertube.gl = String::new();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_innertube_recording() {
+        let mut settings = Settings::default();
+        settings.network.innertube_record = Some("innertube.jsonl".into());
+        assert!(settings.validate().is_ok());
+
+        settings.network.innertube_replay = Some("innertube.jsonl".into());
+        assert!(settings.validate().is_err());
+    }
+
+    #[cfg(feature = "provider")]
+    #[test]
+    fn test_build_runtime() {
+        let settings = RuntimeSettings {
+            worker_threads: Some(2),
+            max_blocking_threads: 4,
+            thread_stack_size: Some(4 * 1024 * 1024),
+        };
+        let runtime = settings.build_runtime().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+        assert_eq!(runtime.block_on(async { 1 + 1 }), 2);
+    }
 }
@@ -222,7 +222,7 @@ mod tests {
                 .contains("timed out")
         );
         assert_eq!(api_response["category"].as_str().unwrap(), "timeout");
-        assert_eq!(api_response["retryable"].as_bool().unwrap(), true);
+        assert!(api_response["retryable"].as_bool().unwrap());
         assert!(api_response["timestamp"].is_string());
     }
 
@@ -120,6 +120,13 @@ pub enum Error {
         duration_secs: u64,
     },
 
+    /// Cancelled operations, e.g. after the client disconnected
+    #[error("Operation cancelled: {operation}")]
+    Cancelled {
+        /// The operation that was cancelled
+        operation: String,
+    },
+
     /// Authentication/authorization errors
     #[error("Authentication failed: {reason}")]
     Auth {
@@ -328,6 +335,21 @@ impl Error {
         }
     }
 
+    /// Create a cancellation error
+    pub fn cancelled<S: Into<String>>(operation: S) -> Self {
+        Self::Cancelled {
+            operation: operation.into(),
+        }
+    }
+
+    /// Create a rate limit error
+    pub fn rate_limit<S: Into<String>>(message: S, retry_after: Option<u64>) -> Self {
+        Self::RateLimit {
+            message: message.into(),
+            retry_after,
+        }
+    }
+
     /// Create a validation error
     pub fn validation<S: Into<String>>(field: S, message: S) -> Self {
         Self::Validation {
@@ -374,6 +396,7 @@ impl Error {
             Error::Proxy { .. } => "proxy",
             Error::Network { .. } => "network",
             Error::Timeout { .. } => "timeout",
+            Error::Cancelled { .. } => "cancelled",
             Error::Auth { .. } => "auth",
             Error::RateLimit { .. } => "rate_limit",
             Error::Validation { .. } => "validation",
@@ -545,6 +568,27 @@ mod tests {
         assert!(err.to_string().contains("Proxy error"));
     }
 
+    #[test]
+    fn test_rate_limit_error() {
+        let err = Error::rate_limit("Innertube returned 429", Some(30));
+        assert!(matches!(
+            err,
+            Error::RateLimit {
+                retry_after: Some(30),
+                ..
+            }
+        ));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_cancelled_error() {
+        let err = Error::cancelled("botguard_init");
+        assert_eq!(err.category(), "cancelled");
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains("botguard_init"));
+    }
+
     #[test]
     fn test_date_parse_error() {
         let date_err = chrono::DateTime::parse_from_rfc3339("invalid date");
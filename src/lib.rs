@@ -15,7 +15,8 @@
 //!
 //! # Architecture
 //!
-//! The project consists of two main operation modes:
+//! The `bgutil-pot` binary is a thin wrapper over the `cli` modules and has
+//! two main operation modes:
 //! - **HTTP Server Mode**: An always-running REST API service for token generation
 //! - **Script Mode**: A command-line tool for one-time token generation
 //!
@@ -24,19 +25,33 @@
 //! ## HTTP Server Mode
 //!
 //! ```bash
-//! bgutil-pot-server --port 4416 --host 0.0.0.0
+//! bgutil-pot server --port 4416 --host 0.0.0.0
 //! ```
 //!
 //! ## Script Mode
 //!
 //! ```bash
-//! bgutil-pot-generate --content-binding "video_id"
+//! bgutil-pot generate --content-binding "video_id"
 //! ```
 //!
+//! # Cargo Features
+//!
+//! - `provider` (default): token generation with BotGuard, the session
+//!   manager, server mode and the CLI. Without it only [`types`], [`config`],
+//!   [`error`] and the HTTP [`client`] are built, which also compile to
+//!   `wasm32-unknown-unknown`.
+//! - `fake-tokens`: deterministic fake tokens instead of BotGuard, for tests,
+//!   used only when asked for with `BGUTIL_FAKE_TOKENS=1` or
+//!   `SessionManagerBuilder::with_botguard`.
+//! - `chaos`: inject the Innertube and BotGuard faults configured in
+//!   `[chaos]`, for resilience testing.
+//!
 //! # Examples
 //!
+//! The [`prelude`] exports the stable types:
+//!
 //! ```rust
-//! use bgutil_ytdlp_pot_provider::{SessionManager, Settings};
+//! use bgutil_ytdlp_pot_provider::prelude::*;
 //!
 //! # async fn example() -> anyhow::Result<()> {
 //! let settings = Settings::default();
@@ -45,15 +60,26 @@
 //! # }
 //! ```
 
+// Implementation of the `bgutil-pot` binary, not part of the library API
+#[cfg(feature = "provider")]
+#[doc(hidden)]
 pub mod cli;
+pub mod client;
 pub mod config;
 pub mod error;
+pub mod prelude;
+#[cfg(feature = "provider")]
 pub mod server;
+#[cfg(feature = "provider")]
 pub mod session;
 pub mod types;
+// Internal helpers, public only for the binary and integration tests
+#[doc(hidden)]
 pub mod utils;
 
+pub use client::{PotClient, TokenProvider};
 pub use config::{ConfigLoader, Settings};
 pub use error::{Error, Result};
+#[cfg(feature = "provider")]
 pub use session::SessionManager;
 pub use types::{ErrorResponse, PingResponse, PotRequest, PotResponse};
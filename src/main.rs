@@ -12,7 +12,28 @@
 //!
 //! ## Generate Mode
 //! ```bash
-//! bgutil-pot --content-binding "video_id" --verbose
+//! bgutil-pot generate --content-binding "video_id" --verbose
+//! ```
+//!
+//! Passing the generate options without the subcommand still works but is
+//! deprecated.
+//!
+//! ## Daemon Mode
+//! ```bash
+//! bgutil-pot daemon --socket /tmp/bgutil-pot.sock
+//! ```
+//!
+//! Started by `bgutil-pot generate --daemon` when none is running.
+//!
+//! ## Top Mode
+//! ```bash
+//! bgutil-pot top --url http://127.0.0.1:4416
+//! ```
+//!
+//! ## Cache Mode
+//! ```bash
+//! bgutil-pot cache export --out dump.json
+//! bgutil-pot cache import --in dump.json --url http://new-host:4416
 //! ```
 //!
 //! ## Help and Version
@@ -24,113 +45,96 @@
 
 use clap::{Parser, Subcommand};
 
+#[cfg(unix)]
+use bgutil_ytdlp_pot_provider::cli::daemon::{DaemonArgs, run_daemon_mode};
 use bgutil_ytdlp_pot_provider::cli::{
-    generate::{GenerateArgs, run_generate_mode},
-    server::{ServerArgs, run_server_mode},
+    cache::{CacheCommand, run_cache_mode},
+    generate::{EXIT_CODES_HELP, GenerateArgs, run_generate_mode},
+    server::{ServerArgs, load_server_settings, run_server_with_settings},
+    top::{TopArgs, run_top_mode},
 };
+use bgutil_ytdlp_pot_provider::config::settings::RuntimeSettings;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(name = "bgutil-pot")]
+#[command(after_help = EXIT_CODES_HELP)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    // Generate mode options (when no subcommand is provided)
-    /// Content binding (video ID, visitor data, etc.)
-    #[arg(
-        short,
-        long,
-        value_name = "CONTENT_BINDING",
-        allow_hyphen_values = true
+    /// Generate mode options without the `generate` subcommand (deprecated)
+    #[command(
+        flatten,
+        next_help_heading = "Generate options (deprecated, use `generate`)"
     )]
-    content_binding: Option<String>,
-
-    /// Visitor data (DEPRECATED: use --content-binding instead)
-    #[arg(short = 'v', long, value_name = "VISITOR_DATA")]
-    visitor_data: Option<String>,
-
-    /// Data sync ID (DEPRECATED: use --content-binding instead)
-    #[arg(short = 'd', long, value_name = "DATA_SYNC_ID")]
-    data_sync_id: Option<String>,
-
-    /// Proxy server URL (http://host:port, socks5://host:port, etc.)
-    #[arg(short, long, value_name = "PROXY")]
-    proxy: Option<String>,
-
-    /// Bypass cache and force new token generation
-    #[arg(short = 'b', long)]
-    bypass_cache: bool,
-
-    /// Source IP address for outbound connections
-    #[arg(short, long, value_name = "SOURCE_ADDRESS")]
-    source_address: Option<String>,
-
-    /// Disable TLS certificate verification
-    #[arg(long)]
-    disable_tls_verification: bool,
-
-    /// Enable verbose logging
-    #[arg(long)]
-    verbose: bool,
+    generate: GenerateArgs,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Generate a single POT token and print it as JSON
+    #[command(after_help = EXIT_CODES_HELP)]
+    Generate(GenerateArgs),
     /// Start HTTP server mode
-    Server {
-        /// Port to listen on
-        #[arg(short, long)]
-        port: Option<u16>,
-
-        /// Host to bind to
-        #[arg(long)]
-        host: Option<String>,
-
-        /// Configuration file path
-        #[arg(long)]
-        config: Option<String>,
-
-        /// Enable verbose logging
-        #[arg(short, long)]
-        verbose: bool,
+    Server(ServerArgs),
+    /// Serve `generate --daemon` requests on a unix socket until idle
+    #[cfg(unix)]
+    Daemon(DaemonArgs),
+    /// Show a live dashboard of a running server
+    Top {
+        /// Base URL of the server
+        #[arg(long, default_value = "http://127.0.0.1:4416")]
+        url: String,
+
+        /// Refresh interval in seconds
+        #[arg(short, long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Export or import the caches, e.g. to move them to a new host
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
     },
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
+    // Server mode sizes the runtime from its `[runtime]` settings, so the
+    // runtime is built after loading them instead of via `#[tokio::main]`
+
     match cli.command {
-        Some(Commands::Server {
-            port,
-            host,
-            config,
-            verbose,
-        }) => {
-            // Server mode logic
-            let args = ServerArgs {
-                port,
-                host,
-                config,
-                verbose,
-            };
-            run_server_mode(args).await
+        Some(Commands::Server(args)) => {
+            let settings = load_server_settings(&args)?;
+            settings
+                .runtime
+                .build_runtime()?
+                .block_on(run_server_with_settings(args, settings))
         }
+        #[cfg(unix)]
+        Some(Commands::Daemon(args)) => RuntimeSettings::default()
+            .build_runtime()?
+            .block_on(run_daemon_mode(args)),
+        Some(Commands::Top { url, interval }) => RuntimeSettings::default()
+            .build_runtime()?
+            .block_on(run_top_mode(TopArgs { url, interval })),
+        Some(Commands::Cache { command }) => RuntimeSettings::default()
+            .build_runtime()?
+            .block_on(run_cache_mode(command)),
+        Some(Commands::Generate(args)) => RuntimeSettings::default()
+            .build_runtime()?
+            .block_on(run_generate_mode(args)),
         None => {
-            // Generate mode logic (default when no subcommand)
+            // Bare flags, kept as a deprecated alias of `generate`
             let args = GenerateArgs {
-                content_binding: cli.content_binding,
-                visitor_data: cli.visitor_data,
-                data_sync_id: cli.data_sync_id,
-                proxy: cli.proxy,
-                bypass_cache: cli.bypass_cache,
-                source_address: cli.source_address,
-                disable_tls_verification: cli.disable_tls_verification,
-                version: false, // Version is handled by clap itself
-                verbose: cli.verbose,
+                bare_flags: true,
+                ..cli.generate
             };
-            run_generate_mode(args).await
+            RuntimeSettings::default()
+                .build_runtime()?
+                .block_on(run_generate_mode(args))
         }
     }
 }
@@ -152,12 +156,10 @@ mod tests {
         ]);
 
         match cli.command {
-            Some(Commands::Server {
-                port, host, config, ..
-            }) => {
-                assert_eq!(port, Some(8080));
-                assert_eq!(host, Some("0.0.0.0".to_string()));
-                assert_eq!(config, None);
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.port, Some(8080));
+                assert_eq!(args.host, Some("0.0.0.0".to_string()));
+                assert_eq!(args.config, None);
             }
             _ => panic!("Expected server subcommand"),
         }
@@ -168,8 +170,25 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot", "--content-binding", "test", "--verbose"]);
 
         assert!(cli.command.is_none());
-        assert_eq!(cli.content_binding, Some("test".to_string()));
-        assert!(cli.verbose);
+        assert_eq!(cli.generate.content_binding, Some("test".to_string()));
+        assert!(cli.generate.verbose);
+    }
+
+    #[test]
+    fn test_generate_subcommand() {
+        let cli = Cli::parse_from(["bgutil-pot", "generate", "-c", "-6OjhRWNLfk", "--verbose"]);
+        match cli.command {
+            Some(Commands::Generate(args)) => {
+                assert_eq!(args.content_binding, Some("-6OjhRWNLfk".to_string()));
+                assert!(args.verbose);
+                assert!(!args.bare_flags);
+            }
+            _ => panic!("Expected generate subcommand"),
+        }
+
+        assert!(Cli::try_parse_from(["bgutil-pot", "generate", "--progress-json"]).is_err());
+        // Bare flags can't be combined with a subcommand
+        assert!(Cli::try_parse_from(["bgutil-pot", "-c", "test", "generate"]).is_err());
     }
 
     #[test]
@@ -187,16 +206,16 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot", "server"]);
 
         match cli.command {
-            Some(Commands::Server {
-                port,
-                host,
-                config,
-                verbose,
-            }) => {
-                assert_eq!(port, None);
-                assert_eq!(host, None);
-                assert_eq!(config, None);
-                assert!(!verbose);
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.port, None);
+                assert_eq!(args.host, None);
+                assert_eq!(args.config, None);
+                assert_eq!(args.profile, None);
+                assert!(!args.verbose);
+                assert_eq!(args.trace_file, None);
+                assert_eq!(args.workers, 1);
+                assert_eq!(args.ttl_hours, None);
+                assert!(!args.disable_snapshot);
             }
             _ => panic!("Expected server subcommand"),
         }
@@ -207,8 +226,19 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot", "server", "--config", "/path/to/config.toml"]);
 
         match cli.command {
-            Some(Commands::Server { config, .. }) => {
-                assert_eq!(config, Some("/path/to/config.toml".to_string()));
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.config, Some("/path/to/config.toml".to_string()));
+            }
+            _ => panic!("Expected server subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_server_profile_option() {
+        let cli = Cli::parse_from(["bgutil-pot", "server", "--profile", "prod"]);
+        match cli.command {
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.profile, Some("prod".to_string()));
             }
             _ => panic!("Expected server subcommand"),
         }
@@ -219,9 +249,139 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot"]);
 
         assert!(cli.command.is_none());
-        assert!(cli.content_binding.is_none());
-        assert!(!cli.bypass_cache);
-        assert!(!cli.verbose);
+        assert!(cli.generate.content_binding.is_none());
+        assert!(!cli.generate.bypass_cache);
+        assert!(!cli.generate.verbose);
+    }
+
+    #[test]
+    fn test_trace_file_option() {
+        let cli = Cli::parse_from(["bgutil-pot", "--trace-file", "/tmp/trace.json"]);
+        assert_eq!(cli.generate.trace_file, Some("/tmp/trace.json".to_string()));
+
+        let cli = Cli::parse_from(["bgutil-pot", "server", "--trace-file", "/tmp/trace.json"]);
+        match cli.command {
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.trace_file, Some("/tmp/trace.json".to_string()));
+            }
+            _ => panic!("Expected server subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_generate_config_options() {
+        let cli = Cli::parse_from([
+            "bgutil-pot",
+            "--content-binding",
+            "test",
+            "--config",
+            "/path/to/config.toml",
+            "--profile",
+            "prod",
+        ]);
+        assert!(cli.command.is_none());
+        assert_eq!(
+            cli.generate.config,
+            Some("/path/to/config.toml".to_string())
+        );
+        assert_eq!(cli.generate.profile, Some("prod".to_string()));
+    }
+
+    #[test]
+    fn test_server_workers_flag() {
+        let cli = Cli::parse_from(["bgutil-pot", "server", "--workers", "4"]);
+        match cli.command {
+            Some(Commands::Server(args)) => assert_eq!(args.workers, 4),
+            _ => panic!("Expected server subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_server_settings_flags() {
+        let cli = Cli::parse_from([
+            "bgutil-pot",
+            "server",
+            "--ttl-hours",
+            "3",
+            "--snapshot-path",
+            "/data/snapshot.bin",
+            "--api-key",
+            "secret",
+            "--rate-limit",
+            "2.5",
+        ]);
+        match cli.command {
+            Some(Commands::Server(args)) => {
+                assert_eq!(args.ttl_hours, Some(3));
+                assert_eq!(args.snapshot_path, Some("/data/snapshot.bin".to_string()));
+                assert_eq!(args.admin_token, Some("secret".to_string()));
+                assert_eq!(args.upstream_qps, Some(2.5));
+            }
+            _ => panic!("Expected server subcommand"),
+        }
+
+        let result = Cli::try_parse_from([
+            "bgutil-pot",
+            "server",
+            "--snapshot-path",
+            "/data/snapshot.bin",
+            "--disable-snapshot",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_top_subcommand() {
+        let cli = Cli::parse_from(["bgutil-pot", "top"]);
+        match cli.command {
+            Some(Commands::Top { url, interval }) => {
+                assert_eq!(url, "http://127.0.0.1:4416");
+                assert_eq!(interval, 2);
+            }
+            _ => panic!("Expected top subcommand"),
+        }
+
+        let cli = Cli::parse_from(["bgutil-pot", "top", "--url", "http://pot:4416", "-i", "5"]);
+        match cli.command {
+            Some(Commands::Top { url, interval }) => {
+                assert_eq!(url, "http://pot:4416");
+                assert_eq!(interval, 5);
+            }
+            _ => panic!("Expected top subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_cache_subcommand() {
+        let cli = Cli::parse_from(["bgutil-pot", "cache", "import", "--in", "dump.json"]);
+        match cli.command {
+            Some(Commands::Cache {
+                command: CacheCommand::Import { input, target },
+            }) => {
+                assert_eq!(input, std::path::PathBuf::from("dump.json"));
+                assert_eq!(target.url, None);
+            }
+            _ => panic!("Expected cache import subcommand"),
+        }
+
+        assert!(Cli::try_parse_from(["bgutil-pot", "cache", "export"]).is_err());
+    }
+
+    #[test]
+    fn test_progress_json_requires_verbose() {
+        let cli = Cli::parse_from(["bgutil-pot", "--verbose", "--progress-json"]);
+        assert!(cli.generate.progress_json);
+
+        assert!(Cli::try_parse_from(["bgutil-pot", "--progress-json"]).is_err());
+    }
+
+    #[test]
+    fn test_help_lists_exit_codes() {
+        use clap::CommandFactory;
+
+        let help = Cli::command().render_help().to_string();
+        assert!(help.contains("Exit codes:"));
+        assert!(help.contains("4  BotGuard failure"));
     }
 
     #[test]
@@ -230,7 +390,10 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot", "-c", "-6OjhRWNLfk"]);
 
         assert!(cli.command.is_none());
-        assert_eq!(cli.content_binding, Some("-6OjhRWNLfk".to_string()));
+        assert_eq!(
+            cli.generate.content_binding,
+            Some("-6OjhRWNLfk".to_string())
+        );
     }
 
     #[test]
@@ -239,6 +402,9 @@ mod tests {
         let cli = Cli::parse_from(&["bgutil-pot", "--content-binding", "-6OjhRWNLfk"]);
 
         assert!(cli.command.is_none());
-        assert_eq!(cli.content_binding, Some("-6OjhRWNLfk".to_string()));
+        assert_eq!(
+            cli.generate.content_binding,
+            Some("-6OjhRWNLfk".to_string())
+        );
     }
 }
@@ -0,0 +1,18 @@
+//! Commonly used types
+//!
+//! The stable part of the library API, for a single glob import:
+//!
+//! ```rust
+//! use bgutil_ytdlp_pot_provider::prelude::*;
+//!
+//! let request = PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+//! let manager = SessionManager::new(Settings::default());
+//! # let _ = (request, manager);
+//! ```
+
+pub use crate::client::{PotClient, TokenProvider};
+pub use crate::config::{ConfigLoader, Settings};
+pub use crate::error::{Error, Result};
+#[cfg(feature = "provider")]
+pub use crate::session::SessionManager;
+pub use crate::types::{ErrorResponse, PotRequest, PotResponse, PotTokenType};
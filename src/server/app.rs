@@ -2,11 +2,14 @@
 //!
 //! Creates and configures the Axum application with routes and middleware.
 
-use crate::{config::Settings, session::SessionManager};
-use axum::{
-    Router, middleware,
-    routing::{get, post},
+use super::dedupe::RequestDeduper;
+use super::interceptors::{RequestInterceptor, ResponseInterceptor};
+use super::routes::{Access, Endpoint};
+use crate::{
+    config::Settings,
+    session::{SessionManager, SessionManagerBuilder},
 };
+use axum::{Router, middleware};
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
@@ -20,44 +23,146 @@ pub struct AppState {
     pub settings: Arc<Settings>,
     /// Server start time for uptime calculation
     pub start_time: std::time::Instant,
+    /// Hooks run before `POST /get_pot` generates a token, in order
+    pub request_interceptors: Vec<Arc<dyn RequestInterceptor>>,
+    /// Hooks run on `POST /get_pot` responses, in order
+    pub response_interceptors: Vec<Arc<dyn ResponseInterceptor>>,
+    /// Deduplication of identical `POST /get_pot` bodies, from `server.dedupe_window_ms`
+    pub deduper: Option<Arc<RequestDeduper>>,
+}
+
+impl AppState {
+    /// Create the state serving `session_manager`, starting the uptime now
+    pub fn new(settings: Settings, session_manager: Arc<SessionManager>) -> Self {
+        Self {
+            session_manager,
+            deduper: RequestDeduper::from_settings(&settings).map(Arc::new),
+            settings: Arc::new(settings),
+            start_time: std::time::Instant::now(),
+            request_interceptors: Vec::new(),
+            response_interceptors: Vec::new(),
+        }
+    }
+
+    /// Run `interceptor` before each token is generated
+    pub fn with_request_interceptor(
+        mut self,
+        interceptor: impl RequestInterceptor + 'static,
+    ) -> Self {
+        self.request_interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Run `interceptor` on each generated token response
+    pub fn with_response_interceptor(
+        mut self,
+        interceptor: impl ResponseInterceptor + 'static,
+    ) -> Self {
+        self.response_interceptors.push(Arc::new(interceptor));
+        self
+    }
 }
 
 /// Create the main Axum application with routes and middleware
+///
+/// Starts the background tasks of the session manager, so it must be called
+/// within a Tokio runtime.
 pub fn create_app(settings: Settings) -> Router {
-    let session_manager = Arc::new(SessionManager::new(settings.clone()));
+    create_app_with_manager(settings).0
+}
 
-    let state = AppState {
+/// Create the main Axum application, also returning its session manager
+///
+/// The caller should call [`SessionManager::shutdown`] once the server has
+/// stopped, so pending cache writes and the BotGuard snapshot are saved.
+pub fn create_app_with_manager(settings: Settings) -> (Router, Arc<SessionManager>) {
+    let session_manager = SessionManagerBuilder::new(settings.clone()).start();
+    (
+        create_app_with_session_manager(settings, session_manager.clone()),
         session_manager,
-        settings: Arc::new(settings),
-        start_time: std::time::Instant::now(),
+    )
+}
+
+/// Create the main Axum application serving `session_manager`
+///
+/// For applications that build the session manager themselves, e.g. with
+/// [`SessionManagerBuilder`] to plug in their own stores, and share it with
+/// the routes. The returned router can be nested into an existing app.
+pub fn create_app_with_session_manager(
+    settings: Settings,
+    session_manager: Arc<SessionManager>,
+) -> Router {
+    create_router(AppState::new(settings, session_manager))
+}
+
+/// Create the routes and middleware serving `state`
+///
+/// The building block of the other constructors, for applications that
+/// compose the routes into their own app, e.g. under a prefix with
+/// [`Router::nest`], and keep a handle on the state they share.
+pub fn create_router(state: AppState) -> Router {
+    let camel_case_responses = state.settings.server.camel_case_responses;
+
+    // Routes of the endpoint registry with `access`
+    let routes = |access| {
+        Endpoint::with_access(access).fold(Router::new(), |router, endpoint| {
+            router.route(endpoint.path(), endpoint.method_router())
+        })
     };
 
-    Router::new()
-        .route("/get_pot", post(super::handlers::generate_pot))
+    let admin_routes = routes(Access::Admin).route_layer(middleware::from_fn_with_state(
+        state.clone(),
+        super::handlers::require_admin_token,
+    ));
+
+    let app = routes(Access::Public)
+        .merge(admin_routes)
+        // Only inspects `/get_pot` requests
         .layer(middleware::from_fn(
             super::handlers::validate_deprecated_fields_middleware,
         ))
-        .route("/ping", get(super::handlers::ping))
-        .route(
-            "/invalidate_caches",
-            post(super::handlers::invalidate_caches),
-        )
-        .route("/invalidate_it", post(super::handlers::invalidate_it))
-        .route("/minter_cache", get(super::handlers::minter_cache))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
+                .layer(middleware::from_fn(
+                    super::handlers::trace_context_middleware,
+                ))
                 .layer(CorsLayer::permissive()),
         )
-        .with_state(state)
+        .with_state(state);
+
+    if camel_case_responses {
+        app.layer(middleware::from_fn(
+            super::handlers::camel_case_response_middleware,
+        ))
+    } else {
+        app
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_create_app() {
+    #[tokio::test]
+    async fn test_create_router_nests_under_prefix() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let settings = Settings::default();
+        let state = AppState::new(settings.clone(), Arc::new(SessionManager::new(settings)));
+        let app = Router::new().nest("/pot", create_router(state));
+
+        let request = Request::builder()
+            .uri("/pot/ping")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_create_app() {
         let settings = Settings::default();
         let _app = create_app(settings);
 
@@ -0,0 +1,188 @@
+//! Content negotiation of `/get_pot` bodies
+//!
+//! Requests are decoded by their `Content-Type` and responses encoded by the
+//! `Accept` header, as JSON or MessagePack. High-QPS consumers use
+//! MessagePack to avoid JSON overhead; everything else keeps JSON.
+
+use axum::{
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// MessagePack media type
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Media types announcing MessagePack, registered and legacy
+const MSGPACK_MEDIA_TYPES: &[&str] = &[MSGPACK_CONTENT_TYPE, "application/x-msgpack"];
+
+/// Body encoding of a request or response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// `application/json`
+    #[default]
+    Json,
+    /// `application/msgpack`
+    MessagePack,
+}
+
+impl Codec {
+    /// Codec of the request body, by its `Content-Type`
+    pub fn of_request(headers: &HeaderMap) -> Self {
+        match headers.get(header::CONTENT_TYPE) {
+            Some(value) if is_msgpack(value) => Self::MessagePack,
+            _ => Self::Json,
+        }
+    }
+
+    /// Codec of the response, MessagePack only if the `Accept` header asks for it
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let accepts_msgpack = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|range| {
+                let (media_type, params) = range.split_once(';').unwrap_or((range, ""));
+                // `q=0` explicitly refuses the media type
+                let refused = params
+                    .split(';')
+                    .any(|param| param.trim().strip_prefix("q=").is_some_and(is_zero));
+                !refused
+                    && MSGPACK_MEDIA_TYPES
+                        .iter()
+                        .any(|msgpack| media_type.trim().eq_ignore_ascii_case(msgpack))
+            });
+        if accepts_msgpack {
+            Self::MessagePack
+        } else {
+            Self::Json
+        }
+    }
+
+    /// Name of the encoding in error messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::MessagePack => "MessagePack",
+        }
+    }
+
+    /// Media type of bodies of this codec
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MessagePack => MSGPACK_CONTENT_TYPE,
+        }
+    }
+
+    /// Decode a body
+    pub fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Result<T, String> {
+        match self {
+            Self::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+            Self::MessagePack => rmp_serde::from_slice(body).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Encode `value` as a response body in this codec
+    ///
+    /// Values are serialized straight into the codec, MessagePack as a map
+    /// with the same field names as the JSON body.
+    pub fn encode<T: Serialize>(self, status: StatusCode, value: &T) -> Response {
+        let encoded = match self {
+            Self::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Self::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        };
+        match encoded {
+            Ok(body) => {
+                (status, [(header::CONTENT_TYPE, self.content_type())], body).into_response()
+            }
+            Err(e) => {
+                tracing::error!("Failed to encode response as {}: {}", self.name(), e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+/// Whether a `Content-Type` value is a MessagePack media type
+fn is_msgpack(value: &HeaderValue) -> bool {
+    value.to_str().is_ok_and(|value| {
+        let media_type = value.split(';').next().unwrap_or_default().trim();
+        MSGPACK_MEDIA_TYPES
+            .iter()
+            .any(|msgpack| media_type.eq_ignore_ascii_case(msgpack))
+    })
+}
+
+/// Whether a quality value is zero, e.g. `0` or `0.000`
+fn is_zero(quality: &str) -> bool {
+    quality.trim().parse::<f32>().is_ok_and(|q| q == 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Codec::negotiate(&HeaderMap::new()), Codec::Json);
+        assert_eq!(
+            Codec::negotiate(&headers(header::ACCEPT, "application/json")),
+            Codec::Json
+        );
+        assert_eq!(
+            Codec::negotiate(&headers(
+                header::ACCEPT,
+                "application/json;q=0.5, application/msgpack"
+            )),
+            Codec::MessagePack
+        );
+        assert_eq!(
+            Codec::negotiate(&headers(header::ACCEPT, "application/x-msgpack")),
+            Codec::MessagePack
+        );
+        assert_eq!(
+            Codec::negotiate(&headers(header::ACCEPT, "application/msgpack;q=0")),
+            Codec::Json
+        );
+
+        assert_eq!(
+            Codec::of_request(&headers(
+                header::CONTENT_TYPE,
+                "application/msgpack; charset=binary"
+            )),
+            Codec::MessagePack
+        );
+        assert_eq!(Codec::of_request(&HeaderMap::new()), Codec::Json);
+    }
+
+    #[tokio::test]
+    async fn test_encode_as_msgpack() {
+        let response = crate::types::PotResponse::new(
+            "token",
+            "video",
+            "2024-08-25T12:00:00Z".parse().unwrap(),
+        );
+        let encoded = Codec::MessagePack.encode(StatusCode::OK, &response);
+        assert_eq!(
+            encoded.headers()[header::CONTENT_TYPE],
+            MSGPACK_CONTENT_TYPE
+        );
+        let body = axum::body::to_bytes(encoded.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let decoded: serde_json::Value = Codec::MessagePack.decode(&body).unwrap();
+        assert_eq!(decoded, serde_json::to_value(&response).unwrap());
+
+        let encoded = Codec::Json.encode(StatusCode::ACCEPTED, &response);
+        assert_eq!(encoded.status(), StatusCode::ACCEPTED);
+        assert_eq!(encoded.headers()[header::CONTENT_TYPE], "application/json");
+    }
+}
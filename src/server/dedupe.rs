@@ -0,0 +1,166 @@
+//! Deduplication of identical token requests
+//!
+//! Misconfigured clients retrying in a tight loop send the same `/get_pot`
+//! body many times a second. With `server.dedupe_window_ms` set, a body
+//! identical to one still being answered, or answered within the window,
+//! gets a copy of that response instead of going through generation again.
+
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Response shared with the duplicates of a request
+#[derive(Debug)]
+struct SharedResponse {
+    /// When the response was completed
+    completed_at: Instant,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    fn to_response(&self) -> Response {
+        (self.status, self.headers.clone(), self.body.clone()).into_response()
+    }
+}
+
+/// Answers of recent requests keyed by the hash of their body
+#[derive(Debug)]
+pub struct RequestDeduper {
+    /// How long a completed response is shared after it was completed
+    window: Duration,
+    /// Randomly keyed, so clients can't craft colliding bodies
+    hasher: std::collections::hash_map::RandomState,
+    /// Responses in flight or completed within the window
+    responses: Mutex<HashMap<u64, Arc<OnceCell<SharedResponse>>>>,
+    /// Requests answered with the response of an identical one
+    suppressed: AtomicU64,
+}
+
+impl RequestDeduper {
+    /// Create a deduper sharing responses for `window` after completion
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            hasher: Default::default(),
+            responses: Mutex::new(HashMap::new()),
+            suppressed: AtomicU64::new(0),
+        }
+    }
+
+    /// Create the deduper of `server.dedupe_window_ms`, `None` if it is 0
+    pub fn from_settings(settings: &crate::config::Settings) -> Option<Self> {
+        (settings.server.dedupe_window_ms > 0)
+            .then(|| Self::new(Duration::from_millis(settings.server.dedupe_window_ms)))
+    }
+
+    /// Requests answered with the response of an identical one so far
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+
+    /// Answer the request identified by `parts` with `respond`, or with the
+    /// response of an identical request in flight or within the window
+    ///
+    /// If the request answering its duplicates is dropped, one of them takes
+    /// over.
+    pub async fn run<F, Fut>(&self, parts: &[&[u8]], respond: F) -> Response
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let key = self.hasher.hash_one(parts);
+        let cell = {
+            let mut responses = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            responses.retain(|_, cell| {
+                cell.get()
+                    .is_none_or(|shared| now.duration_since(shared.completed_at) < self.window)
+            });
+            match responses.get(&key) {
+                Some(cell) => {
+                    self.suppressed.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("Answering duplicate POT request with a shared response");
+                    cell.clone()
+                }
+                None => responses.entry(key).or_default().clone(),
+            }
+        };
+
+        let mut own = None;
+        let shared = cell
+            .get_or_init(|| async {
+                let response = respond().await;
+                let (parts, body) = response.into_parts();
+                let body = match axum::body::to_bytes(body, usize::MAX).await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        tracing::error!("Failed to buffer response for duplicates: {}", e);
+                        Bytes::new()
+                    }
+                };
+                let shared = SharedResponse {
+                    completed_at: Instant::now(),
+                    status: parts.status,
+                    headers: parts.headers,
+                    body,
+                };
+                own = Some(shared.to_response());
+                shared
+            })
+            .await;
+        own.unwrap_or_else(|| shared.to_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_identical_requests_share_response() {
+        let deduper = Arc::new(RequestDeduper::new(Duration::from_millis(200)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let respond = |calls: Arc<AtomicUsize>| async move {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            (StatusCode::OK, format!("response {}", call)).into_response()
+        };
+        let body = |response: Response| async {
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap()
+        };
+
+        // Duplicates in flight wait for the first response
+        let (first, second) = tokio::join!(
+            deduper.run(&[b"a"], || respond(calls.clone())),
+            deduper.run(&[b"a"], || respond(calls.clone())),
+        );
+        assert_eq!(body(first).await, "response 0");
+        assert_eq!(body(second).await, "response 0");
+
+        // Within the window the response is still shared, other bodies aren't
+        let third = deduper.run(&[b"a"], || respond(calls.clone())).await;
+        assert_eq!(body(third).await, "response 0");
+        let other = deduper.run(&[b"b"], || respond(calls.clone())).await;
+        assert_eq!(body(other).await, "response 1");
+        assert_eq!(deduper.suppressed(), 2);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let expired = deduper.run(&[b"a"], || respond(calls.clone())).await;
+        assert_eq!(body(expired).await, "response 2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! Hooks around token generation
+//!
+//! Embedding applications register interceptors on [`AppState`] to add
+//! custom authentication, tag requests or enrich responses of
+//! `POST /get_pot` without forking the handlers. Request interceptors run in
+//! registration order before the token is generated, response interceptors
+//! in registration order after it was generated successfully.
+//!
+//! [`AppState`]: crate::server::AppState
+
+use crate::server::codec::Codec;
+use crate::types::{ErrorResponse, PotRequest};
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Hook run before a token is generated
+#[async_trait::async_trait]
+pub trait RequestInterceptor: Send + Sync {
+    /// Inspect or modify `request`, received with `headers`
+    ///
+    /// Returning an error answers the request with it instead of a token.
+    async fn intercept_request(
+        &self,
+        headers: &HeaderMap,
+        request: &mut PotRequest,
+    ) -> Result<(), Rejection>;
+}
+
+/// Hook run after a token was generated
+#[async_trait::async_trait]
+pub trait ResponseInterceptor: Send + Sync {
+    /// Inspect or modify the JSON `response` answering `request`
+    ///
+    /// `response` is the serialized [`crate::types::PotResponse`]; fields
+    /// added to it are returned to the client.
+    async fn intercept_response(&self, request: &PotRequest, response: &mut serde_json::Value);
+}
+
+/// Error response of a request rejected by a [`RequestInterceptor`]
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    /// Response status
+    pub status: StatusCode,
+    /// Error message returned to the client
+    pub message: String,
+}
+
+impl Rejection {
+    /// Create a rejection answered with `status` and `message`
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl Rejection {
+    /// Answer the rejected request in `codec`
+    pub fn into_response_with(self, codec: Codec) -> Response {
+        codec.encode(
+            self.status,
+            &ErrorResponse::with_context(self.message, "interceptor"),
+        )
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        self.into_response_with(Codec::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SessionManager, Settings, server::AppState};
+    use axum::{body::Body, http::Request};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Requires an `x-api-key` header and pins the content binding
+    struct ApiKey;
+
+    #[async_trait::async_trait]
+    impl RequestInterceptor for ApiKey {
+        async fn intercept_request(
+            &self,
+            headers: &HeaderMap,
+            request: &mut PotRequest,
+        ) -> Result<(), Rejection> {
+            if headers.get("x-api-key").is_none() {
+                return Err(Rejection::new(StatusCode::UNAUTHORIZED, "Missing API key"));
+            }
+            request.content_binding = Some("intercepted".to_string());
+            Ok(())
+        }
+    }
+
+    /// Tags responses with the tenant
+    struct Tenant;
+
+    #[async_trait::async_trait]
+    impl ResponseInterceptor for Tenant {
+        async fn intercept_response(
+            &self,
+            _request: &PotRequest,
+            response: &mut serde_json::Value,
+        ) {
+            response["tenant"] = "example".into();
+        }
+    }
+
+    fn get_pot(api_key: bool) -> Request<Body> {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/get_pot")
+            .header("content-type", "application/json");
+        if api_key {
+            request = request.header("x-api-key", "secret");
+        }
+        request
+            .body(Body::from(r#"{"content_binding": "original"}"#))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_around_generation() {
+        let settings = Settings::default();
+        let state = AppState::new(settings.clone(), Arc::new(SessionManager::new(settings)))
+            .with_request_interceptor(ApiKey)
+            .with_response_interceptor(Tenant);
+        let app = crate::server::create_router(state);
+
+        let response = app.clone().oneshot(get_pot(false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(get_pot(true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["contentBinding"], "intercepted");
+        assert_eq!(body["tenant"], "example");
+    }
+}
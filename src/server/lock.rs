@@ -0,0 +1,220 @@
+//! Instance lock of the data directories
+//!
+//! Two servers sharing a snapshot or cache directory overwrite each other's
+//! snapshot and journal. The server locks `.bgutil-pot.lock` in each data
+//! directory it writes to and records its PID there, so a second server
+//! fails to start with the PID of the first instead. The lock is released by
+//! the OS when the process exits, even on a crash; `--force` starts anyway
+//! while the lock is held, e.g. a stale lock on a network filesystem, and
+//! acquires it in the background once the holder releases it.
+
+use crate::{Result, config::Settings};
+use std::fs::{File, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the lock file in each data directory
+pub const LOCK_FILE_NAME: &str = ".bgutil-pot.lock";
+
+/// Locks held on the data directories, released when dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    /// Lock files opened, locked unless still held by the process `force` took over from
+    files: Vec<(PathBuf, Arc<File>)>,
+}
+
+impl InstanceLock {
+    /// Lock every data directory of `settings`
+    ///
+    /// With `force`, a lock held by another process is taken over with a
+    /// warning instead of failing, and acquired as soon as it is released.
+    pub fn acquire(settings: &Settings, force: bool) -> Result<Self> {
+        let mut files = Vec::new();
+        for dir in data_dirs(settings) {
+            let path = dir.join(LOCK_FILE_NAME);
+            let file = lock_file(&dir, &path, force)?;
+            tracing::debug!("Locked data directory {}", dir.display());
+            files.push((path, file));
+        }
+        Ok(Self { files })
+    }
+
+    /// Lock files held
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // The lock file is kept, removing it would race with a server
+        // locking it right now; clearing the PID is enough
+        for (path, file) in &self.files {
+            if let Err(e) = file.set_len(0) {
+                tracing::debug!("Failed to clear lock file {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Directories the server writes state to, deduplicated
+///
+/// The directory of the BotGuard snapshot, unless snapshots are disabled, and
+/// the directory of the file cache when `cache.persist_server_cache` is set.
+pub fn data_dirs(settings: &Settings) -> Vec<PathBuf> {
+    let snapshot_dir = crate::session::snapshot::resolve_snapshot_path(settings)
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    let cache_dir = (settings.cache.persist_server_cache && settings.cache.enable_file_cache)
+        .then(|| crate::utils::cache::get_cache_path_for(&settings.cache).ok())
+        .flatten()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+
+    let mut dirs: Vec<PathBuf> = snapshot_dir
+        .into_iter()
+        .chain(cache_dir)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+    dirs.dedup();
+    dirs
+}
+
+/// Open and lock `path` in `dir`, then record the PID of this process
+fn lock_file(dir: &Path, path: &Path, force: bool) -> Result<Arc<File>> {
+    let io_error = |action: &str, e: std::io::Error| {
+        crate::Error::server(format!("Failed to {} {}: {}", action, path.display(), e))
+    };
+    std::fs::create_dir_all(dir).map_err(|e| io_error("create the directory of", e))?;
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| io_error("open", e))?;
+
+    let locked = match file.try_lock() {
+        Ok(()) => true,
+        Err(TryLockError::WouldBlock) => {
+            let holder = read_pid(&mut file)
+                .map(|pid| format!("PID {}", pid))
+                .unwrap_or_else(|| "an unknown process".to_string());
+            if !force {
+                return Err(crate::Error::server(format!(
+                    "Data directory {} is in use by another bgutil-pot server ({}); give each server its own snapshot and cache paths, or pass --force if the lock is stale",
+                    dir.display(),
+                    holder
+                )));
+            }
+            tracing::warn!(
+                "Taking over the lock of data directory {} held by {}",
+                dir.display(),
+                holder
+            );
+            false
+        }
+        Err(TryLockError::Error(e)) => return Err(io_error("lock", e)),
+    };
+
+    write_pid(&file).map_err(|e| io_error("write", e))?;
+    let file = Arc::new(file);
+    if !locked {
+        wait_for_lock(Arc::clone(&file), path)?;
+    }
+    Ok(file)
+}
+
+/// Record the PID of this process in `file`
+fn write_pid(mut file: &File) -> std::io::Result<()> {
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{}", std::process::id())
+}
+
+/// Acquire the lock of `file` in the background once its holder releases it
+///
+/// The PID is recorded again then, since the holder clears it on exit.
+fn wait_for_lock(file: Arc<File>, path: &Path) -> Result<()> {
+    let path = path.to_path_buf();
+    std::thread::Builder::new()
+        .name("lock-waiter".to_string())
+        .spawn(move || match file.lock().and_then(|()| write_pid(&file)) {
+            Ok(()) => tracing::info!("Acquired the lock {}", path.display()),
+            Err(e) => tracing::warn!("Failed to acquire the lock {}: {}", path.display(), e),
+        })
+        .map_err(|e| crate::Error::server(format!("Failed to wait for the lock: {}", e)))?;
+    Ok(())
+}
+
+/// PID recorded in a lock file, if any
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_in(dir: &Path) -> Settings {
+        let mut settings = Settings::default();
+        settings.botguard.snapshot_path = Some(dir.join("botguard_snapshot.bin"));
+        settings
+    }
+
+    #[test]
+    fn test_data_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut settings = settings_in(dir.path());
+        assert_eq!(data_dirs(&settings), vec![dir.path().to_path_buf()]);
+
+        settings.cache.persist_server_cache = true;
+        settings.cache.cache_dir = Some(dir.path().join("cache").display().to_string());
+        assert_eq!(
+            data_dirs(&settings),
+            vec![dir.path().to_path_buf(), dir.path().join("cache")]
+        );
+
+        settings.botguard.disable_snapshot = true;
+        settings.cache.persist_server_cache = false;
+        assert!(data_dirs(&settings).is_empty());
+    }
+
+    #[test]
+    fn test_second_instance_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = settings_in(dir.path());
+        let lock_path = dir.path().join(LOCK_FILE_NAME);
+
+        let first = InstanceLock::acquire(&settings, false).unwrap();
+        assert_eq!(first.paths().collect::<Vec<_>>(), vec![lock_path.as_path()]);
+        assert_eq!(
+            std::fs::read_to_string(&lock_path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+
+        let error = InstanceLock::acquire(&settings, false).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("in use by another bgutil-pot server"));
+        assert!(message.contains(&format!("PID {}", std::process::id())));
+
+        // --force starts anyway and acquires the lock once it is released,
+        // so a third server is refused again
+        let forced = InstanceLock::acquire(&settings, true).unwrap();
+        drop(first);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while InstanceLock::acquire(&settings, false).is_ok()
+            || std::fs::read_to_string(&lock_path).unwrap().is_empty()
+        {
+            assert!(std::time::Instant::now() < deadline, "lock not acquired");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        drop(forced);
+        assert_eq!(std::fs::read_to_string(&lock_path).unwrap(), "");
+
+        // Released locks can be taken again
+        assert!(InstanceLock::acquire(&settings, false).is_ok());
+    }
+}
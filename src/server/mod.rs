@@ -3,6 +3,16 @@
 //! This module contains the HTTP server implementation using Axum framework.
 
 pub mod app;
-pub mod handlers;
+pub mod codec;
+pub mod dedupe;
+#[deny(dead_code)]
+pub(crate) mod handlers;
+pub mod interceptors;
+pub mod lock;
+pub mod routes;
+pub mod workers;
 
-pub use app::create_app;
+pub use app::{
+    AppState, create_app, create_app_with_manager, create_app_with_session_manager, create_router,
+};
+pub use interceptors::{Rejection, RequestInterceptor, ResponseInterceptor};
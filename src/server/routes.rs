@@ -0,0 +1,249 @@
+//! Registry of the HTTP endpoints
+//!
+//! Every endpoint is declared once below, with its method, path, access and
+//! handler. The router, the OpenAPI document of `GET /openapi.json` and the
+//! endpoint table of `docs/api-reference.md` are all built from it. The
+//! handlers module denies `dead_code`, so a handler can't exist without
+//! being mounted or documented.
+
+use super::app::AppState;
+use axum::{
+    http::Method,
+    routing::{MethodFilter, MethodRouter, on},
+};
+
+/// Who may call an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Open to every client
+    Public,
+    /// Requires `server.admin_token` when it is set
+    Admin,
+}
+
+macro_rules! endpoints {
+    ($(
+        #[doc = $summary:literal]
+        $variant:ident => $method:ident $path:literal, $access:ident, $handler:ident;
+    )*) => {
+        /// Endpoint served by the HTTP server
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Endpoint {
+            $(
+                #[doc = $summary]
+                $variant,
+            )*
+        }
+
+        impl Endpoint {
+            /// Every endpoint, in the order of the documentation
+            pub const ALL: &'static [Endpoint] = &[$(Self::$variant),*];
+
+            /// HTTP method
+            pub fn method(self) -> Method {
+                match self {
+                    $(Self::$variant => Method::$method,)*
+                }
+            }
+
+            /// Path, with `{name}` segments for path parameters
+            pub fn path(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $path,)*
+                }
+            }
+
+            /// Who may call the endpoint
+            pub fn access(self) -> Access {
+                match self {
+                    $(Self::$variant => Access::$access,)*
+                }
+            }
+
+            /// One-line description
+            pub fn summary(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $summary.trim(),)*
+                }
+            }
+
+            /// Name of the handler function, also the OpenAPI operation ID
+            pub fn handler_name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($handler),)*
+                }
+            }
+
+            /// Route serving the endpoint
+            pub fn method_router(self) -> MethodRouter<AppState> {
+                match self {
+                    $(Self::$variant => on(MethodFilter::$method, super::handlers::$handler),)*
+                }
+            }
+        }
+    };
+}
+
+endpoints! {
+    /// Generate or retrieve a cached POT token
+    GetPot => POST "/get_pot", Public, generate_pot;
+    /// Generate a POT token of the type `server.token_type_aliases` maps the alias to
+    GetPotAlias => POST "/get_pot/{alias}", Public, generate_pot_alias;
+    /// Health check with version and uptime
+    Ping => GET "/ping", Public, ping;
+    /// Clear the session data and minter caches
+    InvalidateCaches => POST "/invalidate_caches", Admin, invalidate_caches;
+    /// Invalidate the integrity tokens
+    InvalidateIt => POST "/invalidate_it", Admin, invalidate_it;
+    /// Remove cached tokens matching a content binding or namespace
+    Purge => POST "/purge", Admin, purge;
+    /// Export the session data and minter caches
+    ExportCaches => GET "/cache/export", Admin, export_caches;
+    /// Import caches exported by another instance
+    ImportCaches => POST "/cache/import", Admin, import_caches;
+    /// Get or create the token minter of a request
+    GetMinter => POST "/get_minter", Admin, get_minter;
+    /// List the keys of the minter cache
+    MinterCache => GET "/minter_cache", Public, minter_cache;
+    /// Mint outcomes and latency per proxy
+    ProxyHealth => GET "/proxy_health", Admin, proxy_health;
+    /// Request, cache and error counters
+    Stats => GET "/stats", Public, stats;
+    /// Token request SLO compliance
+    Slo => GET "/slo", Public, slo;
+    /// Requests per day, token type and outcome
+    UsageStats => GET "/stats/usage", Public, usage_stats;
+    /// Server-sent events of minted tokens, invalidations and errors
+    Events => GET "/events", Public, events;
+    /// Web status page
+    Index => GET "/", Public, index;
+    /// OpenAPI document of these endpoints
+    OpenApi => GET "/openapi.json", Public, openapi;
+}
+
+impl Endpoint {
+    /// Endpoints with `access`
+    pub fn with_access(access: Access) -> impl Iterator<Item = Endpoint> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(move |endpoint| endpoint.access() == access)
+    }
+
+    /// Names of the path parameters, e.g. `alias` for `/get_pot/{alias}`
+    pub fn path_params(self) -> impl Iterator<Item = &'static str> {
+        self.path().split('/').filter_map(|segment| {
+            segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'))
+        })
+    }
+}
+
+/// Build the OpenAPI 3.1 document of the endpoints
+///
+/// Covers paths, methods, parameters and access; request and response
+/// bodies are described in `docs/api-reference.md`.
+pub fn openapi_spec() -> serde_json::Value {
+    let mut paths = serde_json::Map::new();
+    for endpoint in Endpoint::ALL {
+        let mut operation = serde_json::json!({
+            "operationId": endpoint.handler_name(),
+            "summary": endpoint.summary(),
+            "responses": {
+                "default": {"description": "See docs/api-reference.md"}
+            },
+        });
+        let parameters: Vec<_> = endpoint
+            .path_params()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": {"type": "string"},
+                })
+            })
+            .collect();
+        if !parameters.is_empty() {
+            operation["parameters"] = parameters.into();
+        }
+        if endpoint.access() == Access::Admin {
+            operation["security"] = serde_json::json!([{"adminToken": []}]);
+        }
+        paths
+            .entry(endpoint.path())
+            .or_insert_with(|| serde_json::json!({}))[endpoint.method().as_str().to_lowercase()] =
+            operation;
+    }
+
+    serde_json::json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "bgutil-ytdlp-pot-provider",
+            "version": crate::utils::version::get_version(),
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "adminToken": {"type": "http", "scheme": "bearer"}
+            }
+        },
+    })
+}
+
+/// Render the endpoint table of `docs/api-reference.md`
+pub fn markdown_table() -> String {
+    let mut table = String::from("| Method | Path | Access | Description |\n|---|---|---|---|\n");
+    for endpoint in Endpoint::ALL {
+        let access = match endpoint.access() {
+            Access::Public => "public",
+            Access::Admin => "admin token",
+        };
+        table.push_str(&format!(
+            "| `{}` | `{}` | {} | {} |\n",
+            endpoint.method(),
+            endpoint.path(),
+            access,
+            endpoint.summary()
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_handler_is_registered_once() {
+        // Unregistered handlers are rejected at compile time by the
+        // `dead_code` lint of the handlers module
+        let mut handlers: Vec<_> = Endpoint::ALL.iter().map(|e| e.handler_name()).collect();
+        handlers.sort_unstable();
+        handlers.dedup();
+        assert_eq!(handlers.len(), Endpoint::ALL.len());
+    }
+
+    #[test]
+    fn test_openapi_spec() {
+        let spec = openapi_spec();
+        assert_eq!(
+            spec["paths"]["/get_pot"]["post"]["operationId"],
+            "generate_pot"
+        );
+        assert_eq!(
+            spec["paths"]["/get_pot/{alias}"]["post"]["parameters"][0]["name"],
+            "alias"
+        );
+        assert!(spec["paths"]["/purge"]["post"]["security"].is_array());
+        assert!(spec["paths"]["/ping"]["get"].get("security").is_none());
+        let operations: usize = spec["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|methods| methods.as_object().unwrap().len())
+            .sum();
+        assert_eq!(operations, Endpoint::ALL.len());
+    }
+}
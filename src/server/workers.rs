@@ -0,0 +1,590 @@
+//! Pre-fork worker mode
+//!
+//! Runs several server processes, each owning its own BotGuard runtime, behind
+//! a parent process that load-balances requests over them. This sidesteps
+//! V8's one-runtime-per-thread constraint for high-throughput deployments.
+
+use crate::types::ErrorResponse;
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Endpoints forwarded to every worker instead of just one
+const BROADCAST_PATHS: &[&str] = &[
+    "/invalidate_caches",
+    "/invalidate_it",
+    "/purge",
+    "/cache/import",
+];
+
+/// Hop-by-hop headers not forwarded between parent and workers
+const HOP_BY_HOP_HEADERS: &[header::HeaderName] = &[
+    header::CONNECTION,
+    header::HOST,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// How long a starting worker has to answer `GET /ping`
+const WORKER_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before restarting a worker that exited, doubled while it keeps exiting
+const WORKER_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay before restarting a worker
+const MAX_WORKER_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Addresses of the running workers
+#[derive(Debug)]
+pub struct WorkerPool {
+    /// Listen address per worker, `None` while it is (re)starting
+    slots: Vec<RwLock<Option<SocketAddr>>>,
+    /// Round-robin position
+    next: AtomicUsize,
+}
+
+impl WorkerPool {
+    /// Create a pool of `size` workers, none of them running yet
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: (0..size).map(|_| RwLock::new(None)).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of workers in the pool
+    pub fn size(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Set the listen address of worker `index`, `None` when it is down
+    pub fn set_worker(&self, index: usize, addr: Option<SocketAddr>) {
+        *self.slots[index].write().unwrap_or_else(|e| e.into_inner()) = addr;
+    }
+
+    /// Get the running workers
+    pub fn workers(&self) -> Vec<SocketAddr> {
+        self.slots
+            .iter()
+            .filter_map(|slot| *slot.read().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+
+    /// Pick the next running worker, round robin
+    pub fn next_worker(&self) -> Option<SocketAddr> {
+        let workers = self.workers();
+        if workers.is_empty() {
+            return None;
+        }
+        Some(workers[self.next.fetch_add(1, Ordering::Relaxed) % workers.len()])
+    }
+}
+
+/// How to start a worker process
+#[derive(Debug, Clone)]
+pub struct WorkerCommand {
+    /// Server executable, normally the current one
+    pub program: PathBuf,
+    /// Configuration file passed to the workers
+    pub config: Option<String>,
+    /// Configuration profile passed to the workers
+    pub profile: Option<String>,
+    /// Enable verbose logging in the workers
+    pub verbose: bool,
+    /// Settings flags passed on to the workers, e.g. `--ttl-hours`
+    pub settings_args: Vec<String>,
+}
+
+impl WorkerCommand {
+    /// Build the command starting worker `index` on an ephemeral port, written to `port_file`
+    ///
+    /// The worker binds the port itself, so no other process can take it
+    /// between picking and binding it.
+    fn command(&self, index: usize, port_file: &std::path::Path) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .arg("server")
+            .args(["--host", "127.0.0.1", "--port", "0"])
+            .arg("--port-file")
+            .arg(port_file)
+            .args(["--worker", &index.to_string()])
+            .kill_on_drop(true);
+        if let Some(config) = &self.config {
+            command.args(["--config", config]);
+        }
+        if let Some(profile) = &self.profile {
+            command.args(["--profile", profile]);
+        }
+        if self.verbose {
+            command.arg("--verbose");
+        }
+        command.args(&self.settings_args);
+        command
+    }
+}
+
+/// State shared by the load balancer handlers
+#[derive(Debug, Clone)]
+struct BalancerState {
+    pool: Arc<WorkerPool>,
+    client: reqwest::Client,
+    max_body_size: usize,
+}
+
+/// Create the load balancer forwarding every request to the workers
+///
+/// Cache invalidation requests go to every worker, everything else to one
+/// worker picked round robin.
+pub fn create_balancer_app(pool: Arc<WorkerPool>, max_body_size: usize) -> Router {
+    // No overall timeout, `GET /events` streams stay open
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .no_proxy()
+        .build()
+        .unwrap_or_default();
+
+    Router::new().fallback(forward).with_state(BalancerState {
+        pool,
+        client,
+        max_body_size,
+    })
+}
+
+/// Forward a request to the workers
+async fn forward(State(state): State<BalancerState>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, state.max_body_size).await {
+        Ok(body) => body,
+        Err(e) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Failed to read request body: {}", e),
+            );
+        }
+    };
+
+    let path = parts.uri.path();
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map_or(path, |path_and_query| path_and_query.as_str());
+    let mut headers = parts.headers.clone();
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(name);
+    }
+    let send = |worker: SocketAddr| {
+        state
+            .client
+            .request(
+                parts.method.clone(),
+                format!("http://{}{}", worker, path_and_query),
+            )
+            .headers(headers.clone())
+            .body(body.clone())
+            .send()
+    };
+
+    if BROADCAST_PATHS.contains(&path) {
+        let workers = state.pool.workers();
+        if workers.is_empty() {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "No worker available");
+        }
+        let mut replies = tokio::task::JoinSet::new();
+        for worker in workers {
+            let request = send(worker);
+            replies.spawn(async move {
+                let reply = match request.await {
+                    Ok(response) => {
+                        let status = response.status();
+                        response.bytes().await.map(|body| (status, body))
+                    }
+                    Err(e) => Err(e),
+                };
+                (worker, reply)
+            });
+        }
+        let mut replies = replies.join_all().await;
+        replies.sort_by_key(|(worker, _)| *worker);
+        return aggregate(path, replies);
+    }
+
+    let Some(worker) = state.pool.next_worker() else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "No worker available");
+    };
+    match send(worker).await {
+        Ok(response) => into_response(response),
+        Err(e) => {
+            tracing::warn!("Failed to forward {} to worker {}: {}", path, worker, e);
+            error_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Worker {} unavailable", worker),
+            )
+        }
+    }
+}
+
+/// Combine the replies of every worker to a broadcast request
+///
+/// Succeeds when every worker succeeded, summing the counts of their JSON
+/// bodies, e.g. `purged`. When every worker rejected the request with the
+/// same status, e.g. `422` for an invalid body, that reply is returned as is;
+/// any other mix of failures is reported as `502 Bad Gateway`.
+fn aggregate(
+    path: &str,
+    replies: Vec<(SocketAddr, reqwest::Result<(StatusCode, bytes::Bytes)>)>,
+) -> Response {
+    let mut failed = Vec::new();
+    let mut statuses = Vec::new();
+    let mut counts: Option<serde_json::Map<String, serde_json::Value>> = None;
+    let mut last = None;
+    for (worker, reply) in replies {
+        let (status, body) = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::warn!("Failed to forward {} to worker {}: {}", path, worker, e);
+                failed.push(worker);
+                continue;
+            }
+        };
+        statuses.push(status);
+        if !status.is_success() {
+            tracing::warn!("Worker {} answered {} with {}", worker, path, status);
+            failed.push(worker);
+        } else if let Ok(serde_json::Value::Object(reply)) = serde_json::from_slice(&body) {
+            let counts = counts.get_or_insert_with(serde_json::Map::new);
+            for (name, value) in reply {
+                let sum = counts
+                    .get(&name)
+                    .and_then(serde_json::Value::as_u64)
+                    .zip(value.as_u64())
+                    .map(|(sum, count)| sum + count);
+                counts.insert(name, sum.map_or(value, Into::into));
+            }
+        }
+        last = Some((status, body));
+    }
+
+    if failed.is_empty() {
+        return match counts {
+            Some(counts) => Json(counts).into_response(),
+            None => last.map_or_else(
+                || StatusCode::NO_CONTENT.into_response(),
+                |(status, _)| status.into_response(),
+            ),
+        };
+    }
+    if let Some((status, body)) = last
+        && failed.len() == statuses.len()
+        && statuses.iter().all(|other| *other == status)
+    {
+        return (status, [(header::CONTENT_TYPE, "application/json")], body).into_response();
+    }
+    error_response(
+        StatusCode::BAD_GATEWAY,
+        format!(
+            "Workers {} failed",
+            failed
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    )
+}
+
+/// Convert a worker response into a response to the client, streaming its body
+fn into_response(response: reqwest::Response) -> Response {
+    let mut builder = Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        if !HOP_BY_HOP_HEADERS.contains(name) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder
+        .body(Body::from_stream(response.bytes_stream()))
+        .unwrap_or_else(|e| error_response(StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+/// Build a JSON error response
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(ErrorResponse::with_context(message, "worker_pool")),
+    )
+        .into_response()
+}
+
+/// Start and supervise every worker of `pool`, restarting workers that exit
+///
+/// Workers are killed when the returned tasks are aborted. Their port files
+/// live in a private directory, so another local user can't announce a port
+/// of their own and receive the forwarded requests.
+pub fn spawn_workers(
+    pool: Arc<WorkerPool>,
+    command: WorkerCommand,
+) -> std::io::Result<Vec<tokio::task::JoinHandle<()>>> {
+    let port_dir = Arc::new(
+        tempfile::Builder::new()
+            .prefix("bgutil-pot-workers-")
+            .tempdir()?,
+    );
+    Ok((0..pool.size())
+        .map(|index| {
+            tokio::spawn(supervise_worker(
+                index,
+                pool.clone(),
+                command.clone(),
+                port_dir.clone(),
+            ))
+        })
+        .collect())
+}
+
+/// Run worker `index`, restarting it whenever it exits
+///
+/// The restart delay doubles up to [`MAX_WORKER_RESTART_DELAY`] while the
+/// worker keeps exiting, and starts over once it ran for that long.
+async fn supervise_worker(
+    index: usize,
+    pool: Arc<WorkerPool>,
+    command: WorkerCommand,
+    port_dir: Arc<tempfile::TempDir>,
+) {
+    let port_file = port_dir.path().join(format!("worker-{}.port", index));
+    let mut delay = WORKER_RESTART_DELAY;
+    loop {
+        let started = tokio::time::Instant::now();
+        match run_worker(index, &pool, &command, &port_file).await {
+            Ok(status) => tracing::warn!("Worker {} exited with {}, restarting", index, status),
+            Err(e) => tracing::error!("Worker {} failed: {}, restarting", index, e),
+        }
+        pool.set_worker(index, None);
+
+        if started.elapsed() >= MAX_WORKER_RESTART_DELAY {
+            delay = WORKER_RESTART_DELAY;
+        }
+        tracing::debug!("Restarting worker {} in {:?}", index, delay);
+        tokio::time::sleep(delay).await;
+        delay = next_restart_delay(delay);
+    }
+}
+
+/// Get the restart delay following `delay`
+fn next_restart_delay(delay: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_WORKER_RESTART_DELAY)
+}
+
+/// Start worker `index`, reporting its port through `port_file`, and wait until it exits
+async fn run_worker(
+    index: usize,
+    pool: &WorkerPool,
+    command: &WorkerCommand,
+    port_file: &std::path::Path,
+) -> std::io::Result<std::process::ExitStatus> {
+    // Left behind by a worker that crashed
+    let _ = std::fs::remove_file(port_file);
+
+    let mut child = command.command(index, port_file).spawn()?;
+    tracing::info!(
+        "Started worker {} (pid {})",
+        index,
+        child.id().unwrap_or_default()
+    );
+
+    let addr = tokio::select! {
+        status = child.wait() => return status,
+        addr = wait_until_ready(port_file) => match addr {
+            Some(addr) => addr,
+            None => {
+                tracing::error!("Worker {} did not become ready", index);
+                child.kill().await?;
+                return child.wait().await;
+            }
+        }
+    };
+
+    pool.set_worker(index, Some(addr));
+    tracing::info!("Worker {} ready on {}", index, addr);
+    child.wait().await
+}
+
+/// Wait for a starting worker to write `port_file`, then poll its `GET /ping`
+/// until it answers
+///
+/// Returns the worker's address, or `None` if it isn't ready in time.
+async fn wait_until_ready(port_file: &std::path::Path) -> Option<SocketAddr> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(1))
+        .no_proxy()
+        .build()
+        .unwrap_or_default();
+    let deadline = tokio::time::Instant::now() + WORKER_STARTUP_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        // The port file is renamed into place, so it is never read half written
+        let port = tokio::fs::read_to_string(port_file)
+            .await
+            .ok()
+            .and_then(|port| port.trim().parse::<u16>().ok());
+        if let Some(port) = port {
+            let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            if client
+                .get(format!("http://{}/ping", addr))
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success())
+            {
+                return Some(addr);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_worker_pool_round_robin() {
+        let pool = WorkerPool::new(3);
+        assert!(pool.next_worker().is_none());
+
+        let first: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+        let third: SocketAddr = "127.0.0.1:5003".parse().unwrap();
+        pool.set_worker(0, Some(first));
+        pool.set_worker(2, Some(third));
+
+        // Workers that are down are skipped
+        let picked: Vec<_> = (0..4).filter_map(|_| pool.next_worker()).collect();
+        assert_eq!(picked, vec![first, third, first, third]);
+
+        pool.set_worker(0, None);
+        assert_eq!(pool.workers(), vec![third]);
+    }
+
+    #[tokio::test]
+    async fn test_balancer_forwards_to_workers() {
+        let pool = Arc::new(WorkerPool::new(2));
+        for index in 0..2 {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            pool.set_worker(index, Some(listener.local_addr().unwrap()));
+            let app = crate::server::create_app(Settings::default());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+        }
+        let app = create_balancer_app(pool, 1024 * 1024);
+
+        let request = Request::builder().uri("/ping").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/invalidate_caches")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_replies_are_aggregated() {
+        let first: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:5002".parse().unwrap();
+        let reply = |status: StatusCode, body: &'static str| Ok((status, bytes::Bytes::from(body)));
+
+        // Counts are summed over the workers
+        let response = aggregate(
+            "/purge",
+            vec![
+                (first, reply(StatusCode::OK, r#"{"purged":2}"#)),
+                (second, reply(StatusCode::OK, r#"{"purged":3}"#)),
+            ],
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"purged":5}"#);
+
+        let response = aggregate(
+            "/invalidate_it",
+            vec![
+                (first, reply(StatusCode::NO_CONTENT, "")),
+                (second, reply(StatusCode::NO_CONTENT, "")),
+            ],
+        );
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        // A request every worker rejects keeps its status
+        let response = aggregate(
+            "/purge",
+            vec![
+                (first, reply(StatusCode::BAD_REQUEST, "{}")),
+                (second, reply(StatusCode::BAD_REQUEST, "{}")),
+            ],
+        );
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // A worker failing while the others succeeded
+        let response = aggregate(
+            "/invalidate_it",
+            vec![
+                (first, reply(StatusCode::NO_CONTENT, "")),
+                (second, reply(StatusCode::INTERNAL_SERVER_ERROR, "{}")),
+            ],
+        );
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_restart_delay_backs_off() {
+        let delays: Vec<_> = std::iter::successors(Some(WORKER_RESTART_DELAY), |delay| {
+            Some(next_restart_delay(*delay))
+        })
+        .take(8)
+        .map(|delay| delay.as_secs())
+        .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_ready_reads_port_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let port_file = dir.path().join("worker.port");
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = crate::server::create_app(Settings::default());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let writer = {
+            let port_file = port_file.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                std::fs::write(port_file, format!("{}\n", addr.port())).unwrap();
+            }
+        };
+        let (ready, ()) = tokio::join!(wait_until_ready(&port_file), writer);
+        assert_eq!(ready, Some(addr));
+    }
+
+    #[tokio::test]
+    async fn test_balancer_without_workers() {
+        let app = create_balancer_app(Arc::new(WorkerPool::new(1)), 1024);
+        let request = Request::builder().uri("/ping").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -2,6 +2,13 @@
 //!
 //! This module handles the interaction with Google's BotGuard system using
 //! the rustypipe-botguard crate for real POT token generation.
+//!
+//! The `Botguard` instance holds a V8 isolate and isn't `Send`, so
+//! [`BotGuardClient::initialize`] starts a dedicated `botguard-worker`
+//! thread owning it, driven by a single current-thread runtime built once.
+//! Callers send commands over a channel and await the reply on a oneshot;
+//! the instance is reused for every mint until it expires and
+//! [`BotGuardClient::reinitialize`] replaces the worker.
 
 use crate::Result;
 use std::path::PathBuf;
@@ -11,29 +18,154 @@ use tokio::sync::{mpsc, oneshot};
 // Global mutex to serialize BotGuard operations to prevent V8 runtime conflicts
 static BOTGUARD_MUTEX: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
 
+/// How long to wait for the worker thread to write its snapshot and stop
+const WORKER_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// Commands that can be sent to the BotGuard worker
-#[allow(dead_code)]
 enum BotGuardCommand {
     GenerateToken {
         identifier: String,
         response: oneshot::Sender<Result<String>>,
     },
-    GetExpiryInfo {
-        response: oneshot::Sender<Option<(OffsetDateTime, u32)>>,
+    GetInstanceInfo {
+        response: oneshot::Sender<InstanceInfo>,
     },
     Shutdown,
 }
 
+/// State of the BotGuard instance kept by the worker
+#[derive(Debug, Clone, Copy)]
+struct InstanceInfo {
+    created_at: OffsetDateTime,
+    valid_until: OffsetDateTime,
+    lifetime: u32,
+    from_snapshot: bool,
+}
+
+/// Trait for BotGuard operations to enable testing with mocks
+///
+/// Implemented by [`BotGuardClient`]; alternative backends can be passed to
+/// [`SessionManagerGeneric::new_with_providers`](crate::session::SessionManagerGeneric::new_with_providers).
+#[async_trait::async_trait]
+pub trait BotGuardProvider: Send + Sync {
+    /// Start the provider, e.g. load the BotGuard VM
+    async fn initialize(&self) -> Result<()>;
+
+    /// Check whether the provider is ready to mint
+    async fn is_initialized(&self) -> bool;
+
+    /// Restart the provider to refresh an expired BotGuard instance
+    async fn reinitialize(&self) -> Result<()>;
+
+    /// Mint a POT token bound to `identifier`
+    async fn generate_po_token(&self, identifier: &str) -> Result<String>;
+
+    /// Get when the BotGuard instance expires and its lifetime in seconds
+    async fn get_expiry_info(&self) -> Option<(OffsetDateTime, u32)>;
+
+    /// Stop the provider and release its resources
+    async fn shutdown(&self) {}
+}
+
+/// BotGuard provider used by [`SessionManager`](crate::session::SessionManager)
+///
+/// Real BotGuard unless fake tokens are asked for explicitly, so enabling the
+/// `fake-tokens` feature alone, e.g. through feature unification, never
+/// changes the tokens served.
+#[derive(Debug)]
+pub enum DefaultBotGuardProvider {
+    /// BotGuard run by rustypipe-botguard
+    BotGuard(BotGuardClient),
+    /// Deterministic fake tokens
+    #[cfg(feature = "fake-tokens")]
+    Fake(super::fake_botguard::FakeBotGuard),
+}
+
+impl DefaultBotGuardProvider {
+    /// Create the provider for the `[botguard]` settings
+    ///
+    /// Mints fake tokens only when built with the `fake-tokens` feature and
+    /// [`FAKE_TOKENS_ENV`](super::fake_botguard::FAKE_TOKENS_ENV) is set to `1`.
+    pub fn from_settings(settings: &crate::config::settings::BotGuardSettings) -> Self {
+        #[cfg(feature = "fake-tokens")]
+        if super::fake_botguard::FakeBotGuard::requested() {
+            return Self::Fake(super::fake_botguard::FakeBotGuard::from_settings(settings));
+        }
+        #[cfg(not(feature = "fake-tokens"))]
+        if std::env::var_os("BGUTIL_FAKE_TOKENS").is_some() {
+            tracing::warn!("Ignoring BGUTIL_FAKE_TOKENS: built without the fake-tokens feature");
+        }
+        Self::BotGuard(BotGuardClient::from_settings(settings))
+    }
+}
+
+impl From<BotGuardClient> for DefaultBotGuardProvider {
+    fn from(client: BotGuardClient) -> Self {
+        Self::BotGuard(client)
+    }
+}
+
+#[cfg(feature = "fake-tokens")]
+impl From<super::fake_botguard::FakeBotGuard> for DefaultBotGuardProvider {
+    fn from(fake: super::fake_botguard::FakeBotGuard) -> Self {
+        Self::Fake(fake)
+    }
+}
+
+/// Call `$call` on the provider wrapped by a [`DefaultBotGuardProvider`]
+macro_rules! dispatch {
+    ($provider:expr, $inner:ident => $call:expr) => {
+        match $provider {
+            DefaultBotGuardProvider::BotGuard($inner) => $call,
+            #[cfg(feature = "fake-tokens")]
+            DefaultBotGuardProvider::Fake($inner) => $call,
+        }
+    };
+}
+
+#[async_trait::async_trait]
+impl BotGuardProvider for DefaultBotGuardProvider {
+    async fn initialize(&self) -> Result<()> {
+        dispatch!(self, provider => BotGuardProvider::initialize(provider).await)
+    }
+
+    async fn is_initialized(&self) -> bool {
+        dispatch!(self, provider => BotGuardProvider::is_initialized(provider).await)
+    }
+
+    async fn reinitialize(&self) -> Result<()> {
+        dispatch!(self, provider => BotGuardProvider::reinitialize(provider).await)
+    }
+
+    async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+        dispatch!(self, provider => BotGuardProvider::generate_po_token(provider, identifier).await)
+    }
+
+    async fn get_expiry_info(&self) -> Option<(OffsetDateTime, u32)> {
+        dispatch!(self, provider => BotGuardProvider::get_expiry_info(provider).await)
+    }
+
+    async fn shutdown(&self) {
+        dispatch!(self, provider => BotGuardProvider::shutdown(provider).await)
+    }
+}
+
 /// BotGuard client using rustypipe-botguard crate
 pub struct BotGuardClient {
     /// Snapshot file path for caching
     snapshot_path: Option<PathBuf>,
     /// Custom User Agent
     user_agent: Option<String>,
+    /// CPUs the worker thread is pinned to, unpinned if empty
+    cpu_affinity: Vec<usize>,
+    /// Niceness of the worker thread
+    nice: Option<i32>,
     /// Indicates if client is configured (using atomic for thread safety)
     initialized: std::sync::atomic::AtomicBool,
     /// Command sender to the BotGuard worker thread
     command_tx: std::sync::Arc<tokio::sync::RwLock<Option<mpsc::UnboundedSender<BotGuardCommand>>>>,
+    /// Completes once the worker thread has stopped
+    worker_stopped: std::sync::Mutex<Option<oneshot::Receiver<()>>>,
 }
 
 impl std::fmt::Debug for BotGuardClient {
@@ -41,6 +173,8 @@ impl std::fmt::Debug for BotGuardClient {
         f.debug_struct("BotGuardClient")
             .field("snapshot_path", &self.snapshot_path)
             .field("user_agent", &self.user_agent)
+            .field("cpu_affinity", &self.cpu_affinity)
+            .field("nice", &self.nice)
             .field(
                 "initialized",
                 &self.initialized.load(std::sync::atomic::Ordering::Relaxed),
@@ -55,11 +189,35 @@ impl BotGuardClient {
         Self {
             snapshot_path,
             user_agent,
+            cpu_affinity: Vec::new(),
+            nice: None,
             initialized: std::sync::atomic::AtomicBool::new(false),
             command_tx: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
+            worker_stopped: std::sync::Mutex::new(None),
         }
     }
 
+    /// Create a BotGuard client from the `[botguard]` settings
+    pub fn from_settings(settings: &crate::config::settings::BotGuardSettings) -> Self {
+        let snapshot_path = if settings.disable_snapshot {
+            None
+        } else {
+            settings.snapshot_path.clone()
+        };
+        Self::new(snapshot_path, settings.user_agent.clone())
+            .with_thread_priority(settings.cpu_affinity.clone(), settings.nice)
+    }
+
+    /// Pin the worker thread to `cpus` and set its niceness
+    ///
+    /// Lets the provider share a host with CPU-heavy jobs such as transcoding.
+    /// Only supported on Linux; elsewhere the options are ignored with a warning.
+    pub fn with_thread_priority(mut self, cpu_affinity: Vec<usize>, nice: Option<i32>) -> Self {
+        self.cpu_affinity = cpu_affinity;
+        self.nice = nice;
+        self
+    }
+
     /// Initialize the BotGuard client configuration and start the worker thread
     pub async fn initialize(&self) -> Result<()> {
         // Check if already initialized
@@ -67,28 +225,33 @@ impl BotGuardClient {
             return Ok(());
         }
 
+        // The worker's single long-lived runtime, built here so failures are
+        // reported to the caller instead of panicking on the worker thread
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| {
+                crate::Error::botguard(
+                    "runtime",
+                    &format!("Failed to create BotGuard worker runtime: {}", e),
+                )
+            })?;
+
         // Create command channel
         let (tx, mut rx) = mpsc::unbounded_channel::<BotGuardCommand>();
-
-        // Store the sender
-        {
-            let mut command_tx = self.command_tx.write().await;
-            *command_tx = Some(tx);
-        }
+        let (stopped_tx, stopped_rx) = oneshot::channel::<()>();
 
         let snapshot_path = self.snapshot_path.clone();
         let user_agent = self.user_agent.clone();
+        let cpu_affinity = self.cpu_affinity.clone();
+        let nice = self.nice;
 
         // Spawn a dedicated thread for the BotGuard worker
         // This thread will own a single Botguard instance and process all requests
-        std::thread::spawn(move || {
-            // Create a tokio runtime for this thread
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .expect("Failed to create BotGuard worker runtime");
-
-            rt.block_on(async move {
+        let worker = move || {
+            apply_thread_priority(&cpu_affinity, nice);
+
+            runtime.block_on(async move {
                 // Ensure snapshot directory exists if snapshot path is configured
                 if let Some(ref path) = snapshot_path
                     && let Some(parent) = path.parent()
@@ -97,19 +260,57 @@ impl BotGuardClient {
                     tracing::warn!("Failed to create snapshot directory: {}", e);
                 }
 
-                // Initialize Botguard once
-                let mut builder = rustypipe_botguard::Botguard::builder();
-
+                // Discard snapshots that are corrupted or from another version
                 if let Some(ref path) = snapshot_path {
-                    builder = builder.snapshot_path(path);
+                    super::snapshot::prepare(path);
                 }
 
-                if let Some(ref ua) = user_agent {
-                    builder = builder.user_agent(ua);
-                }
+                // BotGuard overwrites its snapshot in place, so it gets a
+                // staged copy that is committed once completely written
+                let staging_path = snapshot_path.as_deref().and_then(|path| {
+                    super::snapshot::stage(path)
+                        .inspect_err(|e| {
+                            tracing::warn!("Failed to stage BotGuard snapshot, not using it: {}", e)
+                        })
+                        .ok()
+                });
 
-                let mut botguard = match builder.init().await {
+                // Initialize Botguard once
+                let builder = || {
+                    let mut builder = rustypipe_botguard::Botguard::builder();
+                    if let Some(ref path) = staging_path {
+                        builder = builder.snapshot_path(path);
+                    }
+                    if let Some(ref ua) = user_agent {
+                        builder = builder.user_agent(ua);
+                    }
+                    builder
+                };
+
+                let mut botguard = match builder().init().await {
                     Ok(bg) => bg,
+                    // A snapshot that passed verification can still fail to
+                    // load; quarantine it and start from scratch once
+                    Err(e) if staging_path.as_ref().is_some_and(|path| path.exists()) => {
+                        tracing::warn!("Failed to initialize BotGuard from snapshot: {}", e);
+                        if let Some(ref path) = snapshot_path
+                            && let Err(e) = super::snapshot::quarantine(path)
+                        {
+                            tracing::warn!("Failed to quarantine BotGuard snapshot: {}", e);
+                        }
+                        if let Some(ref path) = staging_path
+                            && let Err(e) = std::fs::remove_file(path)
+                        {
+                            tracing::warn!("Failed to remove staged BotGuard snapshot: {}", e);
+                        }
+                        match builder().init().await {
+                            Ok(bg) => bg,
+                            Err(e) => {
+                                tracing::error!("Failed to initialize BotGuard worker: {}", e);
+                                return;
+                            }
+                        }
+                    }
                     Err(e) => {
                         tracing::error!("Failed to initialize BotGuard worker: {}", e);
                         return;
@@ -133,10 +334,13 @@ impl BotGuardClient {
                             });
                             let _ = response.send(result);
                         }
-                        BotGuardCommand::GetExpiryInfo { response } => {
-                            let lifetime = botguard.lifetime();
-                            let valid_until = botguard.valid_until();
-                            let _ = response.send(Some((valid_until, lifetime)));
+                        BotGuardCommand::GetInstanceInfo { response } => {
+                            let _ = response.send(InstanceInfo {
+                                created_at: botguard.created_at(),
+                                valid_until: botguard.valid_until(),
+                                lifetime: botguard.lifetime(),
+                                from_snapshot: botguard.is_from_snapshot(),
+                            });
                         }
                         BotGuardCommand::Shutdown => {
                             tracing::info!("BotGuard worker shutting down");
@@ -152,12 +356,41 @@ impl BotGuardClient {
                 // The write_snapshot() method consumes the Botguard instance and properly
                 // extracts the snapshot data before dropping the V8 isolate.
                 match botguard.write_snapshot().await {
-                    true => tracing::debug!("BotGuard snapshot written during shutdown"),
+                    true => {
+                        tracing::debug!("BotGuard snapshot written during shutdown");
+                        if let Some(ref path) = snapshot_path
+                            && staging_path.is_some()
+                            && let Err(e) = super::snapshot::commit(path)
+                        {
+                            tracing::warn!("Failed to commit BotGuard snapshot: {}", e);
+                        }
+                    }
                     false => tracing::warn!("BotGuard snapshot write failed or not configured"),
                 }
                 tracing::info!("BotGuard worker stopped");
             });
-        });
+            drop(runtime);
+            let _ = stopped_tx.send(());
+        };
+        std::thread::Builder::new()
+            .name("botguard-worker".to_string())
+            .spawn(worker)
+            .map_err(|e| {
+                crate::Error::botguard(
+                    "worker_spawn",
+                    &format!("Failed to spawn BotGuard worker thread: {}", e),
+                )
+            })?;
+
+        // Store the sender
+        {
+            let mut command_tx = self.command_tx.write().await;
+            *command_tx = Some(tx);
+        }
+        *self
+            .worker_stopped
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(stopped_rx);
 
         self.initialized
             .store(true, std::sync::atomic::Ordering::Relaxed);
@@ -238,8 +471,7 @@ impl BotGuardClient {
             self.initialized
                 .store(false, std::sync::atomic::Ordering::Relaxed);
 
-            // Give the worker thread time to shutdown
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            self.wait_for_worker_stop().await;
         }
 
         // Initialize fresh instance
@@ -248,6 +480,13 @@ impl BotGuardClient {
 
     /// Get expiry information from the BotGuard worker
     pub async fn get_expiry_info(&self) -> Option<(OffsetDateTime, u32)> {
+        self.instance_info()
+            .await
+            .map(|info| (info.valid_until, info.lifetime))
+    }
+
+    /// Get the state of the instance kept by the worker, `None` if it isn't running
+    async fn instance_info(&self) -> Option<InstanceInfo> {
         if !self.initialized.load(std::sync::atomic::Ordering::Relaxed) {
             return None;
         }
@@ -264,13 +503,13 @@ impl BotGuardClient {
         // Send command and wait for response
         let (response_tx, response_rx) = oneshot::channel();
         command_tx
-            .send(BotGuardCommand::GetExpiryInfo {
+            .send(BotGuardCommand::GetInstanceInfo {
                 response: response_tx,
             })
             .ok()?;
 
         // Wait for response
-        response_rx.await.ok()?
+        response_rx.await.ok()
     }
 
     /// Save snapshot of current BotGuard instance to configured snapshot path
@@ -304,20 +543,16 @@ impl BotGuardClient {
         }
     }
 
-    /// Check if the last BotGuard instance was created from snapshot
-    /// Note: Always returns false in worker-based implementation
+    /// Check if the running BotGuard instance was created from snapshot
     pub async fn is_from_snapshot(&self) -> bool {
-        // In worker-based implementation, we can't easily determine this
-        // without creating a new instance, which defeats the purpose
-        false
+        self.instance_info()
+            .await
+            .is_some_and(|info| info.from_snapshot)
     }
 
-    /// Get creation time of the last BotGuard instance
-    /// Note: Returns None in worker-based implementation
+    /// Get creation time of the running BotGuard instance
     pub async fn created_at(&self) -> Option<OffsetDateTime> {
-        // In worker-based implementation, we can't determine this
-        // without creating a new instance
-        None
+        self.instance_info().await.map(|info| info.created_at)
     }
 
     /// Shutdown the BotGuard worker thread and wait for it to complete.
@@ -348,12 +583,31 @@ impl BotGuardClient {
         self.initialized
             .store(false, std::sync::atomic::Ordering::Relaxed);
 
-        // Give the worker thread time to shutdown and cleanup V8 isolate
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the worker thread to cleanup the V8 isolate
+        self.wait_for_worker_stop().await;
 
         tracing::debug!("BotGuard client shutdown complete");
     }
 
+    /// Wait until the worker thread has stopped, up to [`WORKER_SHUTDOWN_TIMEOUT`]
+    async fn wait_for_worker_stop(&self) {
+        let stopped = self
+            .worker_stopped
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(stopped) = stopped
+            && tokio::time::timeout(WORKER_SHUTDOWN_TIMEOUT, stopped)
+                .await
+                .is_err()
+        {
+            tracing::warn!(
+                "BotGuard worker did not stop within {:?}",
+                WORKER_SHUTDOWN_TIMEOUT
+            );
+        }
+    }
+
     /// Synchronous shutdown for use in Drop trait or when tokio runtime is not available.
     /// This is a best-effort cleanup that sends the shutdown command without waiting.
     pub fn shutdown_sync(&self) {
@@ -374,6 +628,83 @@ impl BotGuardClient {
     }
 }
 
+#[async_trait::async_trait]
+impl BotGuardProvider for BotGuardClient {
+    async fn initialize(&self) -> Result<()> {
+        BotGuardClient::initialize(self).await
+    }
+
+    async fn is_initialized(&self) -> bool {
+        BotGuardClient::is_initialized(self).await
+    }
+
+    async fn reinitialize(&self) -> Result<()> {
+        BotGuardClient::reinitialize(self).await
+    }
+
+    async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+        BotGuardClient::generate_po_token(self, identifier).await
+    }
+
+    async fn get_expiry_info(&self) -> Option<(OffsetDateTime, u32)> {
+        BotGuardClient::get_expiry_info(self).await
+    }
+
+    async fn shutdown(&self) {
+        BotGuardClient::shutdown(self).await
+    }
+}
+
+/// Pin the calling thread to `cpus` and set its niceness
+#[cfg(target_os = "linux")]
+fn apply_thread_priority(cpus: &[usize], nice: Option<i32>) {
+    if !cpus.is_empty() {
+        // SAFETY: cpu_set_t is plain data, and CPU_SET is bounds-checked below
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for &cpu in cpus.iter().filter(|&&cpu| cpu < libc::CPU_SETSIZE as usize) {
+                libc::CPU_SET(cpu, &mut set);
+            }
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        if result == 0 {
+            tracing::info!("BotGuard worker pinned to CPUs {:?}", cpus);
+        } else {
+            tracing::warn!(
+                "Failed to pin BotGuard worker to CPUs {:?}: {}",
+                cpus,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    if let Some(nice) = nice {
+        // Linux applies niceness per thread when given a thread id
+        // SAFETY: gettid and setpriority have no memory safety requirements
+        let result = unsafe {
+            let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+            libc::setpriority(libc::PRIO_PROCESS, tid, nice)
+        };
+        if result == 0 {
+            tracing::info!("BotGuard worker niceness set to {}", nice);
+        } else {
+            tracing::warn!(
+                "Failed to set BotGuard worker niceness to {}: {}",
+                nice,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Thread affinity and niceness are only supported on Linux
+#[cfg(not(target_os = "linux"))]
+fn apply_thread_priority(cpus: &[usize], nice: Option<i32>) {
+    if !cpus.is_empty() || nice.is_some() {
+        tracing::warn!("BotGuard cpu_affinity and nice are only supported on Linux, ignoring");
+    }
+}
+
 // Explicit trait implementations for thread safety
 // BotGuardClient uses AtomicBool and owned types, making it Send + Sync safe
 unsafe impl Send for BotGuardClient {}
@@ -413,6 +744,31 @@ mod tests {
         assert!(!client.is_initialized().await);
     }
 
+    #[test]
+    fn test_botguard_client_with_thread_priority() {
+        let client = BotGuardClient::new(None, None).with_thread_priority(vec![0, 2], Some(10));
+        assert_eq!(client.cpu_affinity, vec![0, 2]);
+        assert_eq!(client.nice, Some(10));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_thread_priority() {
+        // Runs on its own thread so the test thread keeps its priority
+        std::thread::spawn(|| {
+            apply_thread_priority(&[0], Some(5));
+            let nice = unsafe {
+                libc::getpriority(
+                    libc::PRIO_PROCESS,
+                    libc::syscall(libc::SYS_gettid) as libc::id_t,
+                )
+            };
+            assert_eq!(nice, 5);
+        })
+        .join()
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn test_generate_po_token_without_initialization() {
         let client = BotGuardClient::new(None, None);
@@ -483,6 +839,22 @@ mod tests {
 
         let duration = time_until_expiry.unwrap();
         assert!(duration > time::Duration::ZERO);
+
+        // The worker keeps one instance, answered without rebuilding it
+        let created_at = client.created_at().await.unwrap();
+        client.generate_po_token("dQw4w9WgXcQ").await.unwrap();
+        assert_eq!(client.created_at().await, Some(created_at));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_worker() {
+        let client = BotGuardClient::new(None, None);
+        client.initialize().await.unwrap();
+        assert!(client.worker_stopped.lock().unwrap().is_some());
+
+        client.shutdown().await;
+        assert!(!client.is_initialized().await);
+        assert!(client.worker_stopped.lock().unwrap().is_none());
     }
 
     #[tokio::test]
@@ -0,0 +1,199 @@
+//! Session manager builder
+//!
+//! Assembles a [`SessionManager`] the way the server does: the persistent
+//! cache and usage analytics follow the settings unless replaced, and
+//! [`SessionManagerBuilder::start`] spawns the background tasks the settings
+//! ask for. Applications embedding the provider use it to get the same
+//! behavior as `bgutil-pot server` with their own stores plugged in.
+
+use super::{DefaultBotGuardProvider, SessionManager, UsageStore};
+use crate::{
+    config::Settings,
+    utils::{
+        cache::{FileCache, get_cache_path_for},
+        progress::ProgressReporter,
+    },
+};
+use std::sync::Arc;
+
+/// Builder for a [`SessionManager`]
+///
+/// ```rust
+/// use bgutil_ytdlp_pot_provider::{Settings, session::SessionManagerBuilder};
+///
+/// let manager = SessionManagerBuilder::new(Settings::default()).build();
+/// # let _ = manager;
+/// ```
+#[derive(Debug)]
+pub struct SessionManagerBuilder {
+    settings: Settings,
+    file_cache: Option<FileCache>,
+    usage: Option<Arc<UsageStore>>,
+    progress: Option<ProgressReporter>,
+    botguard: Option<DefaultBotGuardProvider>,
+}
+
+impl SessionManagerBuilder {
+    /// Start building a session manager configured by `settings`
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            file_cache: None,
+            usage: None,
+            progress: None,
+            botguard: None,
+        }
+    }
+
+    /// Persist session data to `file_cache`
+    ///
+    /// Used instead of the cache file picked by `cache.persist_server_cache`.
+    pub fn with_file_cache(mut self, file_cache: FileCache) -> Self {
+        self.file_cache = Some(file_cache);
+        self
+    }
+
+    /// Count usage in `usage`
+    ///
+    /// Used instead of the store created by `server.usage_analytics`.
+    pub fn with_usage_store(mut self, usage: Arc<UsageStore>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Report request stages to `progress` as they start and finish
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Mint with `botguard`
+    ///
+    /// Used instead of the BotGuard client configured by `[botguard]`, e.g.
+    /// a [`FakeBotGuard`](super::fake_botguard::FakeBotGuard) in tests built
+    /// with the `fake-tokens` feature.
+    pub fn with_botguard(mut self, botguard: impl Into<DefaultBotGuardProvider>) -> Self {
+        self.botguard = Some(botguard.into());
+        self
+    }
+
+    /// Build the session manager without starting background tasks
+    pub fn build(self) -> SessionManager {
+        self.assemble().0
+    }
+
+    /// Build the session manager and start its background tasks
+    ///
+    /// Depending on the settings these flush the file cache, save the usage
+    /// analytics, remove expired session data, apply the retention limits and
+    /// refresh tokens close to expiry. They stop on [`SessionManager::shutdown`]. Must be called
+    /// within a Tokio runtime.
+    pub fn start(self) -> Arc<SessionManager> {
+        let settings = self.settings.clone();
+        let (session_manager, usage) = self.assemble();
+        let session_manager = Arc::new(session_manager);
+
+        let flush_interval = std::time::Duration::from_secs(settings.cache.flush_interval.max(1));
+        if session_manager.has_file_cache() {
+            session_manager.spawn_flush_task(flush_interval);
+        }
+        if let Some(usage) = &usage {
+            session_manager.track_task(&usage.spawn_save_task(flush_interval));
+        }
+        session_manager.enable_speculative_refresh();
+        if settings.token.cache_cleanup_interval > 0 {
+            session_manager.spawn_cleanup_task(std::time::Duration::from_secs(
+                settings.token.cache_cleanup_interval * 60,
+            ));
+        }
+        if settings.cache.retention_max_size > 0 || settings.cache.retention_max_age_days > 0 {
+            session_manager.spawn_retention_task(std::time::Duration::from_secs(
+                settings.cache.retention_interval.max(1),
+            ));
+        }
+        session_manager
+    }
+
+    /// Create the session manager and its stores, returning the usage store
+    fn assemble(self) -> (SessionManager, Option<Arc<UsageStore>>) {
+        let settings = self.settings;
+        let file_cache = self.file_cache.or_else(|| default_file_cache(&settings));
+        let usage = self.usage.or_else(|| {
+            settings
+                .server
+                .usage_analytics
+                .then(|| create_usage_store(&settings))
+        });
+
+        let mut session_manager = match self.botguard {
+            Some(botguard) => SessionManager::new_with_botguard(settings, botguard),
+            None => SessionManager::new(settings),
+        };
+        if let Some(file_cache) = file_cache {
+            session_manager = session_manager.with_file_cache(file_cache);
+        }
+        if let Some(usage) = &usage {
+            session_manager = session_manager.with_usage_store(usage.clone());
+        }
+        if let Some(progress) = self.progress {
+            session_manager = session_manager.with_progress(progress);
+        }
+        (session_manager, usage)
+    }
+}
+
+/// The file cache enabled by `cache.persist_server_cache`, if any
+fn default_file_cache(settings: &Settings) -> Option<FileCache> {
+    if !(settings.cache.persist_server_cache && settings.cache.enable_file_cache) {
+        return None;
+    }
+    match get_cache_path_for(&settings.cache) {
+        Ok(cache_path) => {
+            tracing::info!("Persisting session data cache to {:?}", cache_path);
+            Some(
+                FileCache::new(cache_path)
+                    .with_revealed_bindings(settings.logging.reveal_content_bindings),
+            )
+        }
+        Err(e) => {
+            tracing::warn!("Failed to resolve cache path, not persisting cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Create the usage analytics store, persisted next to the file cache
+fn create_usage_store(settings: &Settings) -> Arc<UsageStore> {
+    let mut usage = UsageStore::new(settings.server.usage_retention_days);
+    if settings.cache.enable_file_cache {
+        match get_cache_path_for(&settings.cache) {
+            Ok(cache_path) => {
+                let usage_path = cache_path.with_file_name("usage.json");
+                tracing::info!("Persisting usage analytics to {:?}", usage_path);
+                usage = usage.with_file(usage_path);
+            }
+            Err(e) => tracing::warn!("Failed to resolve cache path, not persisting usage: {}", e),
+        }
+    }
+    Arc::new(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_builder_plugs_custom_file_cache() {
+        let dir = tempdir().unwrap();
+        let manager = SessionManagerBuilder::new(Settings::default())
+            .with_file_cache(FileCache::new(dir.path().join("cache.json")))
+            .start();
+        assert!(manager.has_file_cache());
+        manager.shutdown().await;
+
+        // The default settings don't persist the server cache
+        let manager = SessionManagerBuilder::new(Settings::default()).build();
+        assert!(!manager.has_file_cache());
+    }
+}
@@ -0,0 +1,360 @@
+//! Precomputed cache keys
+//!
+//! Cache keys are hashed once when they are created and the hash is reused by
+//! every map operation afterwards, so hot-path lookups and inserts neither
+//! rehash nor reallocate the key string.
+//!
+//! [`SessionCacheKey`] and [`MinterCacheKey`] name the dimensions of the
+//! session data and minter cache keys, and convert into a [`CacheKey`]
+//! through their `Display` form.
+
+use crate::types::PotTokenType;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// Cache key with a precomputed 64-bit hash
+///
+/// Cloning is cheap (reference counted), and hashing only feeds the stored
+/// hash into the hasher.
+#[derive(Clone)]
+pub struct CacheKey {
+    /// Precomputed hash of `key`
+    hash: u64,
+    /// Human-readable key, shown by `/minter_cache` and used in cache files
+    key: Arc<str>,
+}
+
+impl CacheKey {
+    /// Create a cache key from a string
+    pub fn new(key: impl AsRef<str>) -> Self {
+        let key = key.as_ref();
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            key: Arc::from(key),
+        }
+    }
+
+    /// Create a cache key from the concatenation of `parts`
+    ///
+    /// Equal to [`Self::new`] of the concatenated string, but the parts are
+    /// hashed in place and copied into the key once, instead of formatting
+    /// an intermediate string first.
+    pub fn from_parts(parts: &[&str]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        let mut key = String::with_capacity(parts.iter().map(|part| part.len()).sum());
+        for part in parts {
+            hasher.write(part.as_bytes());
+            key.push_str(part);
+        }
+        // Terminate like `str::hash`, so the hash matches `Self::new`
+        hasher.write_u8(0xff);
+        Self {
+            hash: hasher.finish(),
+            key: Arc::from(key),
+        }
+    }
+
+    /// Get the precomputed hash
+    pub fn hash_value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Get the key as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+}
+
+impl PartialEq for CacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl Eq for CacheKey {}
+
+impl Hash for CacheKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl fmt::Debug for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.key, f)
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key)
+    }
+}
+
+impl From<&str> for CacheKey {
+    fn from(key: &str) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<String> for CacheKey {
+    fn from(key: String) -> Self {
+        Self::new(key)
+    }
+}
+
+impl Serialize for CacheKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.key)
+    }
+}
+
+impl<'de> Deserialize<'de> for CacheKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Typed key of the session data cache
+///
+/// Displays as the bare content binding unless a namespace or a token type
+/// other than the one inferred from the binding is set, so keys stay
+/// compatible with existing cache files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionCacheKey {
+    /// Content binding the token is minted for
+    pub binding: String,
+    /// Type of the cached token
+    pub token_type: PotTokenType,
+    /// Namespace separating caches sharing a session manager, e.g. a tenant
+    pub namespace: Option<String>,
+}
+
+impl SessionCacheKey {
+    /// Create a key for `binding`, inferring the token type from its format
+    pub fn new(binding: impl Into<String>) -> Self {
+        let binding = binding.into();
+        Self {
+            token_type: PotTokenType::for_content_binding(&binding),
+            binding,
+            namespace: None,
+        }
+    }
+
+    /// Set the token type
+    pub fn with_token_type(mut self, token_type: PotTokenType) -> Self {
+        self.token_type = token_type;
+        self
+    }
+
+    /// Set the namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+}
+
+impl fmt::Display for SessionCacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{}/", namespace)?;
+        }
+        f.write_str(&self.binding)?;
+        if self.token_type != PotTokenType::for_content_binding(&self.binding) {
+            write!(f, "#{}", self.token_type.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&SessionCacheKey> for CacheKey {
+    fn from(key: &SessionCacheKey) -> Self {
+        Self::new(key.to_string())
+    }
+}
+
+impl From<SessionCacheKey> for CacheKey {
+    fn from(key: SessionCacheKey) -> Self {
+        Self::from(&key)
+    }
+}
+
+/// Typed key of the minter cache
+///
+/// A minter bound to a remote host is keyed by that host alone; otherwise by
+/// its proxy and source address, hashed into `proxy_hash`. Displays as the
+/// remote host or the readable proxy label shown by `/minter_cache`.
+#[derive(Debug, Clone)]
+pub struct MinterCacheKey {
+    /// Hash of the proxy and source address label
+    pub proxy_hash: u64,
+    /// Remote host the minter is bound to
+    pub remote_host: Option<String>,
+    /// Readable proxy and source address label, e.g. `proxy:http://proxy:8080`
+    label: String,
+}
+
+impl MinterCacheKey {
+    /// Create a key from a proxy label and optional remote host
+    pub fn new(label: impl Into<String>, remote_host: Option<&str>) -> Self {
+        let label = label.into();
+        let mut hasher = DefaultHasher::new();
+        label.hash(&mut hasher);
+        Self {
+            proxy_hash: hasher.finish(),
+            remote_host: remote_host.map(str::to_string),
+            label,
+        }
+    }
+
+    /// Get the readable proxy and source address label
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl PartialEq for MinterCacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.remote_host, &other.remote_host) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => self.proxy_hash == other.proxy_hash && self.label == other.label,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MinterCacheKey {}
+
+impl Hash for MinterCacheKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.remote_host {
+            Some(remote_host) => remote_host.hash(state),
+            None => state.write_u64(self.proxy_hash),
+        }
+    }
+}
+
+impl fmt::Display for MinterCacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.remote_host.as_deref().unwrap_or(&self.label))
+    }
+}
+
+impl From<&MinterCacheKey> for CacheKey {
+    fn from(key: &MinterCacheKey) -> Self {
+        Self::new(key.to_string())
+    }
+}
+
+impl From<MinterCacheKey> for CacheKey {
+    fn from(key: MinterCacheKey) -> Self {
+        Self::from(&key)
+    }
+}
+
+/// Hasher that passes through the precomputed [`CacheKey`] hash
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheKeyHasher(u64);
+
+impl Hasher for CacheKeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // Only reached if something other than a CacheKey is hashed;
+        // fold the bytes in so the hasher stays correct.
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+/// Map keyed by [`CacheKey`] that reuses the precomputed hashes
+pub type CacheKeyMap<V> = HashMap<CacheKey, V, BuildHasherDefault<CacheKeyHasher>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_keys_share_hash() {
+        let a = CacheKey::new("proxy:http://proxy:8080");
+        let b = CacheKey::from("proxy:http://proxy:8080".to_string());
+
+        assert_eq!(a, b);
+        assert_eq!(a.hash_value(), b.hash_value());
+        assert_ne!(a, CacheKey::new("default"));
+    }
+
+    #[test]
+    fn test_from_parts_matches_new() {
+        let key = CacheKey::from_parts(&["proxy:", "http://proxy:8080"]);
+        assert_eq!(key, CacheKey::new("proxy:http://proxy:8080"));
+        assert_eq!(
+            key.hash_value(),
+            CacheKey::new("proxy:http://proxy:8080").hash_value()
+        );
+        assert_eq!(
+            CacheKey::from_parts(&["default", "", ""]),
+            CacheKey::new("default")
+        );
+    }
+
+    #[test]
+    fn test_cache_key_map_lookup() {
+        let mut map = CacheKeyMap::default();
+        map.insert(CacheKey::new("video_a"), 1);
+        map.insert(CacheKey::new("video_b"), 2);
+
+        assert_eq!(map.get(&CacheKey::new("video_a")), Some(&1));
+        assert_eq!(map.get(&CacheKey::new("video_b")), Some(&2));
+        assert_eq!(map.get(&CacheKey::new("video_c")), None);
+    }
+
+    #[test]
+    fn test_session_cache_key_display() {
+        let key = SessionCacheKey::new("dQw4w9WgXcQ");
+        assert_eq!(key.token_type, PotTokenType::ContentBound);
+        assert_eq!(CacheKey::from(&key), CacheKey::new("dQw4w9WgXcQ"));
+
+        let typed = key.clone().with_token_type(PotTokenType::ColdStart);
+        assert_eq!(typed.to_string(), "dQw4w9WgXcQ#cold_start");
+        assert_ne!(CacheKey::from(&typed), CacheKey::from(&key));
+
+        let namespaced = key.with_namespace("tenant");
+        assert_eq!(namespaced.to_string(), "tenant/dQw4w9WgXcQ");
+    }
+
+    #[test]
+    fn test_minter_cache_key_identity() {
+        let proxy = MinterCacheKey::new("proxy:http://proxy:8080", None);
+        assert_eq!(proxy.to_string(), "proxy:http://proxy:8080");
+        assert_eq!(proxy, MinterCacheKey::new("proxy:http://proxy:8080", None));
+        assert_ne!(proxy, MinterCacheKey::new("default", None));
+
+        // The remote host alone identifies bound minters
+        let bound = MinterCacheKey::new("proxy:http://proxy:8080", Some("192.168.1.100"));
+        assert_eq!(bound, MinterCacheKey::new("default", Some("192.168.1.100")));
+        assert_eq!(CacheKey::from(&bound), CacheKey::new("192.168.1.100"));
+    }
+
+    #[test]
+    fn test_cache_key_serializes_as_string() {
+        let key = CacheKey::new("192.168.1.100");
+        assert_eq!(key.to_string(), "192.168.1.100");
+        assert_eq!(serde_json::to_string(&key).unwrap(), "\"192.168.1.100\"");
+
+        let parsed: CacheKey = serde_json::from_str("\"192.168.1.100\"").unwrap();
+        assert_eq!(parsed, key);
+    }
+}
@@ -0,0 +1,97 @@
+//! Fault injection for resilience testing
+//!
+//! Enabled by the `chaos` feature. Fails or delays Innertube requests and
+//! BotGuard calls with the probabilities of the `[chaos]` settings, so
+//! operators and CI can check that retries, proxy rotation and the fallback
+//! token behave as designed. Never enable it in production.
+
+use crate::{Result, config::settings::ChaosSettings};
+use std::time::Duration;
+
+/// Injects the faults configured in `[chaos]`
+#[derive(Debug, Clone)]
+pub struct FaultInjector {
+    settings: ChaosSettings,
+}
+
+impl FaultInjector {
+    /// Create an injector of `settings`, `None` if they inject no faults
+    pub fn from_settings(settings: &ChaosSettings) -> Option<Self> {
+        settings.is_enabled().then(|| {
+            tracing::warn!("Built with the chaos feature: injecting faults");
+            Self {
+                settings: settings.clone(),
+            }
+        })
+    }
+
+    /// Delay or fail an Innertube request about to be sent
+    pub async fn innertube(&self) -> Result<()> {
+        self.maybe_delay("Innertube request").await;
+        if fastrand::f64() < self.settings.innertube_error_rate {
+            tracing::warn!("Chaos: failing Innertube request with 500");
+            return Err(crate::Error::VisitorData {
+                reason: "API request failed with status: 500 Internal Server Error (injected)"
+                    .to_string(),
+                context: Some("chaos".to_string()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Delay or time out a BotGuard call about to be made
+    pub async fn botguard(&self) -> Result<()> {
+        self.maybe_delay("BotGuard call").await;
+        if fastrand::f64() < self.settings.botguard_timeout_rate {
+            tracing::warn!("Chaos: timing out BotGuard call");
+            return Err(crate::Error::timeout("BotGuard call (injected)", 0));
+        }
+        Ok(())
+    }
+
+    async fn maybe_delay(&self, operation: &str) {
+        if fastrand::f64() < self.settings.slow_response_rate {
+            tracing::warn!(
+                "Chaos: delaying {} by {}ms",
+                operation,
+                self.settings.slow_response_ms
+            );
+            tokio::time::sleep(Duration::from_millis(self.settings.slow_response_ms)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_faults_follow_probabilities() {
+        assert!(FaultInjector::from_settings(&ChaosSettings::default()).is_none());
+
+        let injector = FaultInjector::from_settings(&ChaosSettings {
+            innertube_error_rate: 1.0,
+            slow_response_rate: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+        let started = tokio::time::Instant::now();
+        assert!(matches!(
+            injector.innertube().await,
+            Err(crate::Error::VisitorData { .. })
+        ));
+        assert_eq!(started.elapsed(), Duration::from_millis(2000));
+        assert!(injector.botguard().await.is_ok());
+
+        let injector = FaultInjector::from_settings(&ChaosSettings {
+            botguard_timeout_rate: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(injector.innertube().await.is_ok());
+        assert!(matches!(
+            injector.botguard().await,
+            Err(crate::Error::Timeout { .. })
+        ));
+    }
+}
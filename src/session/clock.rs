@@ -0,0 +1,119 @@
+//! System clock skew detection
+//!
+//! BotGuard embeds the local time in the tokens it mints, so a provider on a
+//! host with a skewed clock gets tokens rejected for no visible reason. The
+//! `Date` header of Innertube responses tells how far the local clock is off;
+//! a warning is logged once the skew exceeds `token.clock_skew_warn_secs`.
+
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+/// Marks that no skew was measured yet
+const UNMEASURED: i64 = i64::MIN;
+
+/// Latest skew of the local clock against Innertube, shared by its clients
+#[derive(Debug, Clone)]
+pub struct ClockSkew {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Skew above which a warning is logged
+    warn_threshold: Duration,
+    /// Innertube time minus local time in milliseconds
+    skew_ms: AtomicI64,
+    /// Set while the skew is above the threshold and was warned about
+    warned: AtomicBool,
+}
+
+impl ClockSkew {
+    /// Create a tracker warning once the skew exceeds `warn_threshold`
+    pub fn new(warn_threshold: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                warn_threshold,
+                skew_ms: AtomicI64::new(UNMEASURED),
+                warned: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Create a tracker with the `token.clock_skew_warn_secs` threshold
+    pub fn from_settings(settings: &crate::config::Settings) -> Self {
+        Self::new(Duration::seconds(
+            settings.token.clock_skew_warn_secs.min(i64::MAX as u64) as i64,
+        ))
+    }
+
+    /// Innertube time minus local time, `None` before the first response
+    pub fn skew(&self) -> Option<Duration> {
+        match self.inner.skew_ms.load(Ordering::Relaxed) {
+            UNMEASURED => None,
+            ms => Some(Duration::milliseconds(ms)),
+        }
+    }
+
+    /// Compare the `Date` header of a response received at `received_at`
+    ///
+    /// The header has a resolution of one second, so smaller skews read as 0.
+    pub fn observe(&self, date: &str, received_at: DateTime<Utc>) {
+        let Ok(server_time) = DateTime::parse_from_rfc2822(date) else {
+            tracing::debug!("Ignoring unparsable Date header: {}", date);
+            return;
+        };
+        let skew = server_time.with_timezone(&Utc) - received_at;
+        // The header is truncated to whole seconds
+        let skew = if skew.num_milliseconds().abs() < 1000 {
+            Duration::zero()
+        } else {
+            skew
+        };
+        self.inner
+            .skew_ms
+            .store(skew.num_milliseconds(), Ordering::Relaxed);
+
+        let skewed = skew.abs() > self.inner.warn_threshold;
+        if skewed && !self.inner.warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                "System clock is {} seconds {} YouTube's; tokens may be rejected, sync the clock (e.g. with NTP)",
+                skew.num_seconds().abs(),
+                if skew > Duration::zero() {
+                    "behind"
+                } else {
+                    "ahead of"
+                }
+            );
+        } else if !skewed && self.inner.warned.swap(false, Ordering::Relaxed) {
+            tracing::info!("System clock is back in sync with YouTube's");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_skew() {
+        let clock = ClockSkew::new(Duration::seconds(60));
+        assert_eq!(clock.skew(), None);
+
+        let received_at = DateTime::parse_from_rfc3339("2024-08-25T10:00:00.500Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        clock.observe("Sun, 25 Aug 2024 10:00:00 GMT", received_at);
+        assert_eq!(clock.skew(), Some(Duration::zero()));
+
+        // The local clock is 5 minutes ahead
+        clock.observe("Sun, 25 Aug 2024 09:55:00 GMT", received_at);
+        assert_eq!(clock.skew(), Some(Duration::milliseconds(-300_500)));
+        assert!(clock.inner.warned.load(Ordering::Relaxed));
+
+        clock.observe("not a date", received_at);
+        assert_eq!(clock.skew(), Some(Duration::milliseconds(-300_500)));
+        clock.observe("Sun, 25 Aug 2024 10:00:30 GMT", received_at);
+        assert!(!clock.inner.warned.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,192 @@
+//! Outbound connection statistics
+//!
+//! Counts the connections the HTTP client opens per upstream host, so churn
+//! against YouTube through flaky proxies shows up in `GET /stats` instead of
+//! only as slow minting. The client reuses pooled connections without
+//! resolving their host again, so every host resolution starts a new
+//! connection. The resolver records the host for the connector layer, which
+//! times the connection and records whether it was established.
+//!
+//! The HTTP client doesn't expose its connection pool, so active and idle
+//! connections aren't counted.
+
+use crate::types::UpstreamConnections;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Host label of connections to IP addresses, which aren't resolved
+const UNRESOLVED_HOST: &str = "(ip address)";
+
+tokio::task_local! {
+    /// Host resolved by the connection being opened in the current task
+    static CONNECTING_HOST: Arc<Mutex<Option<String>>>;
+}
+
+/// Connection counters of one upstream host
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    /// Connections opened
+    created: u64,
+    /// Connection attempts that failed
+    failed: u64,
+    /// Time spent establishing the opened connections
+    connect_time: Duration,
+}
+
+/// Connection counters per upstream host, shared by the resolver and layer
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionStats {
+    hosts: Arc<Mutex<BTreeMap<String, Counters>>>,
+}
+
+impl ConnectionStats {
+    /// Count the connections of the client built by `builder`
+    pub fn instrument(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        builder
+            .dns_resolver(Arc::new(RecordingResolver))
+            .connector_layer(ConnectionStatsLayer {
+                stats: self.clone(),
+            })
+    }
+
+    /// Get the counters of every upstream host connected to so far
+    pub fn snapshot(&self) -> Vec<UpstreamConnections> {
+        let hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts
+            .iter()
+            .map(|(host, counters)| UpstreamConnections {
+                upstream: host.clone(),
+                connections_created: counters.created,
+                connect_failures: counters.failed,
+                avg_connect_ms: counters
+                    .connect_time
+                    .checked_div(counters.created as u32)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+            })
+            .collect()
+    }
+
+    /// Count a connection attempt to `host` that took `elapsed`
+    fn record(&self, host: String, established: bool, elapsed: Duration) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let counters = hosts.entry(host).or_default();
+        if established {
+            counters.created += 1;
+            counters.connect_time += elapsed;
+        } else {
+            counters.failed += 1;
+        }
+    }
+}
+
+/// System resolver recording the resolved host for [`ConnectionStatsService`]
+#[derive(Debug)]
+struct RecordingResolver;
+
+impl Resolve for RecordingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let _ = CONNECTING_HOST.try_with(|connecting| {
+            *connecting.lock().unwrap_or_else(|e| e.into_inner()) = Some(host.clone());
+        });
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connector layer counting the connections the client opens
+#[derive(Debug, Clone)]
+struct ConnectionStatsLayer {
+    stats: ConnectionStats,
+}
+
+impl<S> tower::Layer<S> for ConnectionStatsLayer {
+    type Service = ConnectionStatsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectionStatsService {
+            inner,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Connector counting the connections opened by the connector it wraps
+#[derive(Debug, Clone)]
+struct ConnectionStatsService<S> {
+    inner: S,
+    stats: ConnectionStats,
+}
+
+impl<S, R> tower::Service<R> for ConnectionStatsService<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        let stats = self.stats.clone();
+        Box::pin(async move {
+            let host = Arc::new(Mutex::new(None));
+            let started = Instant::now();
+            let result = CONNECTING_HOST.scope(host.clone(), connecting).await;
+            let host = host
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .unwrap_or_else(|| UNRESOLVED_HOST.to_string());
+            stats.record(host, result.is_ok(), started.elapsed());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+    #[tokio::test]
+    async fn test_connections_counted_per_host() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let url = format!("http://localhost:{}/", server.address().port());
+
+        let stats = ConnectionStats::default();
+        let client = stats
+            .instrument(reqwest::Client::builder().no_proxy())
+            .build()
+            .unwrap();
+        for _ in 0..3 {
+            client.get(&url).send().await.unwrap();
+        }
+        // Nothing listens on the discard port
+        let _ = client.get("http://localhost:9/").send().await;
+
+        // Pooled connections are reused
+        let upstreams = stats.snapshot();
+        assert_eq!(upstreams.len(), 1);
+        assert_eq!(upstreams[0].upstream, "localhost");
+        assert_eq!(upstreams[0].connections_created, 1);
+        assert_eq!(upstreams[0].connect_failures, 1);
+    }
+}
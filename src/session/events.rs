@@ -0,0 +1,131 @@
+//! Server event broadcasting
+//!
+//! Publishes token minted, cache invalidated and error events to any number
+//! of subscribers, such as the `GET /events` server-sent events stream.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before slow subscribers start lagging
+const EVENT_CAPACITY: usize = 256;
+
+/// A server event with the time it happened
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerEvent {
+    /// When the event happened
+    pub timestamp: DateTime<Utc>,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: ServerEventKind,
+}
+
+/// Kinds of server events
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEventKind {
+    /// A new POT token was minted
+    TokenMinted {
+        /// Content binding of the token, hashed unless `logging.reveal_content_bindings` is set
+        content_binding: String,
+        /// When the token expires
+        expires_at: DateTime<Utc>,
+    },
+    /// Caches were invalidated
+    CacheInvalidated {
+        /// What was invalidated: `caches`, `integrity_token`, `visitor_data` or `purge`
+        scope: String,
+    },
+    /// A POT request failed
+    Error {
+        /// Error category, e.g. `network`
+        category: String,
+        /// Error message
+        message: String,
+    },
+}
+
+impl ServerEvent {
+    /// Create an event happening now
+    pub fn new(kind: ServerEventKind) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind,
+        }
+    }
+
+    /// Get the event name, e.g. `token_minted`
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ServerEventKind::TokenMinted { .. } => "token_minted",
+            ServerEventKind::CacheInvalidated { .. } => "cache_invalidated",
+            ServerEventKind::Error { .. } => "error",
+        }
+    }
+}
+
+/// Broadcasts server events to subscribers
+#[derive(Debug)]
+pub struct EventBus {
+    /// Broadcast channel sender
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Publish an event; events without subscribers are dropped
+    pub fn publish(&self, kind: ServerEventKind) {
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(ServerEvent::new(kind));
+        }
+    }
+
+    /// Subscribe to events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_bus_delivers_to_subscribers() {
+        let bus = EventBus::default();
+
+        // Events without subscribers are dropped
+        bus.publish(ServerEventKind::CacheInvalidated {
+            scope: "caches".to_string(),
+        });
+
+        let mut receiver = bus.subscribe();
+        bus.publish(ServerEventKind::Error {
+            category: "network".to_string(),
+            message: "connection refused".to_string(),
+        });
+
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.name(), "error");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = ServerEvent::new(ServerEventKind::CacheInvalidated {
+            scope: "integrity_token".to_string(),
+        });
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "cache_invalidated");
+        assert_eq!(json["scope"], "integrity_token");
+        assert!(json["timestamp"].is_string());
+    }
+}
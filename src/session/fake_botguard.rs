@@ -0,0 +1,161 @@
+//! Deterministic fake BotGuard provider
+//!
+//! Built with the `fake-tokens` feature for downstream CI such as the yt-dlp
+//! plugin tests and container smoke tests, and used only when asked for with
+//! [`FAKE_TOKENS_ENV`] or
+//! [`SessionManagerBuilder::with_botguard`](super::SessionManagerBuilder::with_botguard). Tokens are an HMAC-SHA256 of the
+//! content binding, so the full request plumbing can be verified reproducibly
+//! without network access or V8. The tokens are NOT accepted by YouTube.
+
+use super::botguard::BotGuardProvider;
+use crate::Result;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use time::OffsetDateTime;
+
+/// Environment variable that, set to `1`, makes the session manager mint fake tokens
+pub const FAKE_TOKENS_ENV: &str = "BGUTIL_FAKE_TOKENS";
+
+/// Environment variable overriding the HMAC key
+pub const FAKE_TOKEN_KEY_ENV: &str = "BGUTIL_FAKE_TOKEN_KEY";
+
+/// HMAC key used when [`FAKE_TOKEN_KEY_ENV`] is unset
+const DEFAULT_FAKE_TOKEN_KEY: &str = "bgutil-fake-tokens";
+
+/// Lifetime reported for the fake BotGuard instance, as for a real one
+const FAKE_LIFETIME_SECS: u32 = 6 * 3600;
+
+/// BotGuard provider minting deterministic HMAC-based tokens
+#[derive(Debug)]
+pub struct FakeBotGuard {
+    /// HMAC key
+    key: Vec<u8>,
+    /// Set once initialized
+    initialized: AtomicBool,
+}
+
+impl FakeBotGuard {
+    /// Create a fake provider signing tokens with `key`
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            initialized: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a fake provider keyed from [`FAKE_TOKEN_KEY_ENV`] or the default key
+    ///
+    /// Takes the `[botguard]` settings for parity with
+    /// [`BotGuardClient::from_settings`](super::BotGuardClient::from_settings);
+    /// they don't apply to fake tokens.
+    pub fn from_settings(_settings: &crate::config::settings::BotGuardSettings) -> Self {
+        let key = std::env::var(FAKE_TOKEN_KEY_ENV)
+            .unwrap_or_else(|_| DEFAULT_FAKE_TOKEN_KEY.to_string());
+        Self::new(key)
+    }
+
+    /// Check if fake tokens were asked for with [`FAKE_TOKENS_ENV`]
+    pub fn requested() -> bool {
+        std::env::var(FAKE_TOKENS_ENV).is_ok_and(|value| value.trim() == "1")
+    }
+
+    /// Compute the token for `identifier`
+    ///
+    /// Two HMAC blocks give a 64-byte token, close to the size of a real one.
+    pub fn token_for(&self, identifier: &str) -> String {
+        let mut token = Vec::with_capacity(64);
+        for block in [1u8, 2] {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+            mac.update(&[block]);
+            mac.update(identifier.as_bytes());
+            token.extend_from_slice(&mac.finalize().into_bytes());
+        }
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token)
+    }
+}
+
+#[async_trait::async_trait]
+impl BotGuardProvider for FakeBotGuard {
+    async fn initialize(&self) -> Result<()> {
+        if !self.initialized.swap(true, Ordering::Relaxed) {
+            tracing::warn!("Minting fake POT tokens, YouTube rejects them");
+        }
+        Ok(())
+    }
+
+    async fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Relaxed)
+    }
+
+    async fn reinitialize(&self) -> Result<()> {
+        self.initialize().await
+    }
+
+    async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+        Ok(self.token_for(identifier))
+    }
+
+    async fn get_expiry_info(&self) -> Option<(OffsetDateTime, u32)> {
+        Some((
+            OffsetDateTime::now_utc() + time::Duration::seconds(FAKE_LIFETIME_SECS.into()),
+            FAKE_LIFETIME_SECS,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fake_tokens_are_deterministic() {
+        let botguard = FakeBotGuard::new("key");
+        botguard.initialize().await.unwrap();
+        assert!(botguard.is_initialized().await);
+
+        let token = botguard.generate_po_token("dQw4w9WgXcQ").await.unwrap();
+        assert_eq!(token, FakeBotGuard::new("key").token_for("dQw4w9WgXcQ"));
+        assert_ne!(token, botguard.token_for("other"));
+        assert_ne!(
+            token,
+            FakeBotGuard::new("other key").token_for("dQw4w9WgXcQ")
+        );
+        // 64 bytes in unpadded base64
+        assert_eq!(token.len(), 86);
+    }
+
+    #[tokio::test]
+    async fn test_fake_expiry_info() {
+        let (valid_until, lifetime) = FakeBotGuard::new("key").get_expiry_info().await.unwrap();
+        assert_eq!(lifetime, FAKE_LIFETIME_SECS);
+        assert!(valid_until > OffsetDateTime::now_utc());
+    }
+
+    #[test]
+    fn test_feature_alone_keeps_botguard() {
+        let settings = crate::config::settings::BotGuardSettings::default();
+        if !FakeBotGuard::requested() {
+            assert!(matches!(
+                crate::session::DefaultBotGuardProvider::from_settings(&settings),
+                crate::session::DefaultBotGuardProvider::BotGuard(_)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_plugs_fake_botguard() {
+        let fake = FakeBotGuard::new("key");
+        let expected = fake.token_for("dQw4w9WgXcQ");
+        let manager =
+            crate::session::SessionManagerBuilder::new(crate::config::Settings::default())
+                .with_botguard(fake)
+                .build();
+
+        let request = crate::types::PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.po_token, expected);
+    }
+}
@@ -3,20 +3,103 @@
 //! This module handles communication with YouTube's internal Innertube API
 //! to generate visitor data and retrieve challenge information.
 
+use super::clock::ClockSkew;
+use super::recording::{InnertubeRecorder, InnertubeReplayer, Interaction, RecordedResponse};
 use crate::Result;
-use reqwest::Client;
+use crate::config::settings::{InnertubeSettings, redact_header};
+use crate::types::ResponseContext;
+use reqwest::{
+    Client, RequestBuilder,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// User agent of Innertube requests
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// Endpoint answering with little more than fresh visitor data
+const VISITOR_ID_ENDPOINT: &str = "visitor_id?prettyPrint=false";
+
+/// Interface language and region of generated Innertube client contexts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientLocale {
+    /// Interface language, e.g. `en`
+    pub hl: String,
+    /// Region, e.g. `US`
+    pub gl: String,
+}
+
+impl ClientLocale {
+    /// The `innertube.hl` and `innertube.gl` locale of `settings`
+    pub fn from_settings(settings: &InnertubeSettings) -> Self {
+        Self {
+            hl: settings.hl.clone(),
+            gl: settings.gl.clone(),
+        }
+    }
+
+    /// This locale with the `client.hl` and `client.gl` of an Innertube
+    /// context applied
+    pub fn with_overrides(&self, context: &serde_json::Value) -> Self {
+        let field = |name: &str| {
+            context
+                .get("client")
+                .and_then(|client| client.get(name))
+                .and_then(|value| value.as_str())
+                .filter(|value| !value.trim().is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            hl: field("hl").unwrap_or_else(|| self.hl.clone()),
+            gl: field("gl").unwrap_or_else(|| self.gl.clone()),
+        }
+    }
+}
 
 /// Trait for Innertube API operations to enable testing with mocks
 #[async_trait::async_trait]
-pub trait InnertubeProvider {
+pub trait InnertubeProvider: Send + Sync {
     /// Generate visitor data from YouTube's Innertube API
     async fn generate_visitor_data(&self) -> Result<String>;
 
+    /// Generate visitor data, sending `headers` on top of the configured ones
+    /// in a client context of `locale` instead of the configured one
+    ///
+    /// Providers that don't issue HTTP requests ignore the headers and locale.
+    async fn generate_visitor_data_with_headers(
+        &self,
+        headers: &HashMap<String, String>,
+        locale: Option<&ClientLocale>,
+    ) -> Result<String> {
+        let _ = (headers, locale);
+        self.generate_visitor_data().await
+    }
+
     /// Get challenge data from Innertube /att/get endpoint
     async fn get_challenge(
         &self,
         context: &crate::types::InnertubeContext,
     ) -> crate::Result<crate::types::ChallengeData>;
+
+    /// Get challenge data, sending `headers` on top of the configured ones
+    ///
+    /// Providers that don't issue HTTP requests ignore the headers.
+    async fn get_challenge_with_headers(
+        &self,
+        context: &crate::types::InnertubeContext,
+        headers: &HashMap<String, String>,
+    ) -> crate::Result<crate::types::ChallengeData> {
+        let _ = headers;
+        self.get_challenge(context).await
+    }
+
+    /// Context of the latest Innertube response, `None` before the first one
+    ///
+    /// Providers that don't issue HTTP requests have none.
+    fn response_context(&self) -> Option<ResponseContext> {
+        None
+    }
 }
 
 /// Innertube API client
@@ -26,93 +109,395 @@ pub struct InnertubeClient {
     client: Client,
     /// Base URL for Innertube API
     base_url: String,
+    /// API key sent as `X-Goog-Api-Key`
+    api_key: Option<String>,
+    /// Locale of generated client contexts
+    locale: ClientLocale,
+    /// Extra headers sent with every request
+    headers: HashMap<String, String>,
+    /// Send the current request's trace context as `traceparent`
+    propagate_trace_context: bool,
+    /// Records every interaction, from `network.innertube_record`
+    recorder: Option<Arc<InnertubeRecorder>>,
+    /// Serves recorded responses instead of sending requests, from `network.innertube_replay`
+    replayer: Option<Arc<InnertubeReplayer>>,
+    /// Skew of the local clock, measured from response `Date` headers
+    clock_skew: Option<ClockSkew>,
+    /// Context of the latest response
+    response_context: std::sync::Mutex<Option<ResponseContext>>,
 }
 
 impl InnertubeClient {
     /// Create new Innertube client
     pub fn new(client: Client) -> Self {
+        Self::from_settings(client, &InnertubeSettings::default())
+    }
+
+    /// Create an Innertube client with the base URL and API key of `settings`
+    pub fn from_settings(client: Client, settings: &InnertubeSettings) -> Self {
+        let mut innertube =
+            Self::new_with_base_url(client, settings.base_url.trim_end_matches('/').to_string());
+        innertube.api_key = settings.api_key.clone();
+        innertube.locale = ClientLocale::from_settings(settings);
+        innertube
+    }
+
+    /// Create new Innertube client with custom base URL (for testing)
+    pub fn new_with_base_url(client: Client, base_url: String) -> Self {
         Self {
             client,
-            base_url: "https://www.youtube.com/youtubei/v1".to_string(),
+            base_url,
+            api_key: None,
+            locale: ClientLocale::from_settings(&InnertubeSettings::default()),
+            headers: HashMap::new(),
+            propagate_trace_context: false,
+            recorder: None,
+            replayer: None,
+            clock_skew: None,
+            response_context: std::sync::Mutex::new(None),
         }
     }
 
-    /// Create new Innertube client with custom base URL (for testing)
-    pub fn new_with_base_url(client: Client, base_url: String) -> Self {
-        Self { client, base_url }
+    /// Set extra headers sent with every request
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
     }
-}
 
-#[async_trait::async_trait]
-impl InnertubeProvider for InnertubeClient {
-    /// Generate visitor data
-    ///
-    /// Corresponds to TypeScript: `generateVisitorData` method (L230-241)
-    async fn generate_visitor_data(&self) -> Result<String> {
-        use serde_json::json;
+    /// Send the trace context of the current server request with every request
+    pub fn with_trace_propagation(mut self, enabled: bool) -> Self {
+        self.propagate_trace_context = enabled;
+        self
+    }
 
-        let request_body = json!({
-            "context": {
-                "client": {
-                    "clientName": "WEB",
-                    "clientVersion": "2.20240822.03.00",
-                    "hl": "en",
-                    "gl": "US"
-                }
-            },
-            "browseId": "FEwhat_to_watch"
-        });
+    /// Record every request and its response with `recorder`
+    pub fn with_recorder(mut self, recorder: Arc<InnertubeRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
 
-        let response = self
+    /// Serve the responses of `replayer` instead of sending requests
+    pub fn with_replayer(mut self, replayer: Arc<InnertubeReplayer>) -> Self {
+        self.replayer = Some(replayer);
+        self
+    }
+
+    /// Measure the skew of the local clock into `clock_skew`
+    pub fn with_clock_skew(mut self, clock_skew: ClockSkew) -> Self {
+        self.clock_skew = Some(clock_skew);
+        self
+    }
+
+    /// POST `body` to `endpoint`, or replay the recorded response
+    ///
+    /// Fails with a message if the request couldn't be sent or read.
+    async fn exchange(
+        &self,
+        endpoint: &str,
+        body: &serde_json::Value,
+        headers: &HashMap<String, String>,
+    ) -> std::result::Result<RecordedResponse, String> {
+        let route = endpoint.split('?').next().unwrap_or(endpoint);
+        if let Some(replayer) = &self.replayer {
+            tracing::debug!("Replaying recorded Innertube response for {}", route);
+            return replayer
+                .next(route)
+                .ok_or_else(|| format!("No recorded Innertube response for {}", route));
+        }
+
+        let mut request = self
             .client
-            .post(format!("{}/browse", self.base_url))
+            .post(format!("{}/{}", self.base_url, endpoint))
             .header("Content-Type", "application/json")
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .json(&request_body)
+            .header("User-Agent", USER_AGENT)
+            .json(body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("X-Goog-Api-Key", api_key);
+        }
+        let response = self
+            .apply_headers(request, headers)
             .send()
             .await
-            .map_err(|e| {
-                tracing::error!("Failed to send request to Innertube API: {}", e);
-                crate::Error::VisitorData {
-                    reason: format!("Network request failed: {}", e),
-                    context: Some("innertube".to_string()),
-                }
-            })?;
+            .map_err(|e| format!("Network request failed: {}", e))?;
+        let received_at = chrono::Utc::now();
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let (retry_after, date) = (
+            header(reqwest::header::RETRY_AFTER),
+            header(reqwest::header::DATE),
+        );
+        if let (Some(clock_skew), Some(date)) = (&self.clock_skew, &date) {
+            clock_skew.observe(date, received_at);
+        }
+        let recorded = RecordedResponse {
+            status: response.status().as_u16(),
+            retry_after,
+            date,
+            body: response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read response: {}", e))?,
+        };
 
-        if !response.status().is_success() {
-            let status = response.status();
-            tracing::error!("Innertube API returned error status: {}", status);
+        if let Some(recorder) = &self.recorder {
+            let request_headers = [
+                ("Content-Type", "application/json"),
+                ("User-Agent", USER_AGENT),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .chain(self.api_key.as_ref().map(|api_key| {
+                (
+                    "X-Goog-Api-Key".to_string(),
+                    redact_header("x-goog-api-key", api_key),
+                )
+            }))
+            .chain(
+                self.headers
+                    .iter()
+                    .chain(headers)
+                    .map(|(name, value)| (name.clone(), redact_header(name, value))),
+            )
+            .collect();
+            recorder
+                .record(&Interaction {
+                    recorded_at: chrono::Utc::now(),
+                    endpoint: route.to_string(),
+                    request_headers,
+                    request_body: body.clone(),
+                    response: recorded.clone(),
+                })
+                .await;
+        }
+        Ok(recorded)
+    }
+
+    /// Extract `responseContext.visitorData` from a successful response of `endpoint`
+    fn visitor_data_from(&self, endpoint: &str, response: &RecordedResponse) -> Result<String> {
+        let status = status_code(response);
+        if !status.is_success() {
             return Err(crate::Error::VisitorData {
                 reason: format!("API request failed with status: {}", status),
                 context: Some("innertube".to_string()),
             });
         }
 
-        let json_response: serde_json::Value = response.json().await.map_err(|e| {
-            tracing::error!("Failed to parse Innertube API response: {}", e);
-            crate::Error::VisitorData {
+        let json_response: serde_json::Value =
+            serde_json::from_str(&response.body).map_err(|e| crate::Error::VisitorData {
                 reason: format!("Failed to parse JSON response: {}", e),
                 context: Some("innertube".to_string()),
-            }
-        })?;
+            })?;
+        self.observe_response_context(endpoint, &json_response);
 
-        let visitor_data = json_response
+        json_response
             .get("responseContext")
             .and_then(|ctx| ctx.get("visitorData"))
             .and_then(|data| data.as_str())
-            .ok_or_else(|| {
-                tracing::error!("Visitor data not found in Innertube API response");
+            .map(str::to_string)
+            .ok_or_else(|| crate::Error::VisitorData {
+                reason: "Visitor data not found in API response".to_string(),
+                context: Some("innertube".to_string()),
+            })
+    }
+
+    /// Keep the response context of a response to `endpoint`
+    fn observe_response_context(&self, endpoint: &str, response: &serde_json::Value) {
+        if let Some(context) = ResponseContext::parse(endpoint, response) {
+            *self
+                .response_context
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(context);
+        }
+    }
+
+    /// Add the configured headers and `overrides` to a request
+    ///
+    /// Headers replace the defaults of the same name, and `overrides` win
+    /// over the configured headers. Invalid headers are skipped.
+    fn apply_headers(
+        &self,
+        builder: RequestBuilder,
+        overrides: &HashMap<String, String>,
+    ) -> RequestBuilder {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in self.headers.iter().chain(overrides) {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    header_map.insert(name, value);
+                }
+                _ => tracing::warn!("Skipping invalid Innertube header '{}'", name),
+            }
+        }
+        if self.propagate_trace_context
+            && let Some(context) = crate::utils::trace_context::TraceContext::current()
+            && let Ok(value) = HeaderValue::from_str(&context.child().header_value())
+        {
+            header_map.insert(crate::utils::trace_context::TRACEPARENT, value);
+        }
+
+        if header_map.is_empty() {
+            return builder;
+        }
+        tracing::debug!(
+            "Sending Innertube headers: {}",
+            redact_headers(self.headers.iter().chain(overrides))
+        );
+        builder.headers(header_map)
+    }
+}
+
+/// Map a `429 Too Many Requests` response to [`crate::Error::RateLimit`]
+fn rate_limit_error(response: &RecordedResponse) -> Option<crate::Error> {
+    if response.status != reqwest::StatusCode::TOO_MANY_REQUESTS.as_u16() {
+        return None;
+    }
+
+    let retry_after = response
+        .retry_after
+        .as_deref()
+        .and_then(crate::session::network::parse_retry_after);
+    tracing::warn!(
+        "Innertube rate limited the request, retry after {:?} seconds",
+        retry_after
+    );
+    Some(crate::Error::rate_limit(
+        "Innertube returned 429 Too Many Requests",
+        retry_after,
+    ))
+}
+
+/// HTTP status of a recorded response
+fn status_code(response: &RecordedResponse) -> reqwest::StatusCode {
+    reqwest::StatusCode::from_u16(response.status)
+        .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Check that every header name and value is valid
+pub fn validate_headers(headers: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(crate::Error::validation(
+                "headers".to_string(),
+                format!("Invalid header name: {:?}", name),
+            ));
+        }
+        if HeaderValue::from_str(value).is_err() {
+            return Err(crate::Error::validation(
+                "headers".to_string(),
+                format!("Invalid value for header {}", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Format headers for logging, redacting credentials and identifiers
+pub fn redact_headers<'a>(headers: impl IntoIterator<Item = (&'a String, &'a String)>) -> String {
+    let mut entries: Vec<String> = headers
+        .into_iter()
+        .map(|(name, value)| format!("{}: {}", name, redact_header(name, value)))
+        .collect();
+    entries.sort();
+    entries.join(", ")
+}
+
+#[async_trait::async_trait]
+impl InnertubeProvider for InnertubeClient {
+    /// Generate visitor data
+    ///
+    /// Corresponds to TypeScript: `generateVisitorData` method (L230-241)
+    async fn generate_visitor_data(&self) -> Result<String> {
+        self.generate_visitor_data_with_headers(&HashMap::new(), None)
+            .await
+    }
+
+    /// Generate visitor data from the `visitor_id` endpoint
+    ///
+    /// Its response is a few hundred bytes, against hundreds of kilobytes for
+    /// the `FEwhat_to_watch` browse response. Falls back to browse when the
+    /// endpoint answers with an error or without visitor data.
+    async fn generate_visitor_data_with_headers(
+        &self,
+        headers: &HashMap<String, String>,
+        locale: Option<&ClientLocale>,
+    ) -> Result<String> {
+        use serde_json::json;
+
+        let locale = locale.unwrap_or(&self.locale);
+        let client_context = json!({
+            "client": {
+                "clientName": "WEB",
+                "clientVersion": "2.20240822.03.00",
+                "hl": locale.hl,
+                "gl": locale.gl
+            }
+        });
+
+        let request_body = json!({ "context": client_context });
+        match self
+            .exchange(VISITOR_ID_ENDPOINT, &request_body, headers)
+            .await
+        {
+            Ok(response) => {
+                if let Some(error) = rate_limit_error(&response) {
+                    return Err(error);
+                }
+                match self.visitor_data_from("visitor_id", &response) {
+                    Ok(visitor_data) => {
+                        tracing::debug!("Successfully generated visitor data: {}", visitor_data);
+                        return Ok(visitor_data);
+                    }
+                    Err(e) => tracing::debug!("{}, falling back to browse", e),
+                }
+            }
+            // Recordings made before the endpoint was used only contain browse
+            Err(reason) if self.replayer.is_some() => {
+                tracing::debug!("{}, falling back to browse", reason);
+            }
+            Err(reason) => {
+                tracing::error!("Failed to send request to Innertube API: {}", reason);
+                return Err(crate::Error::VisitorData {
+                    reason,
+                    context: Some("innertube".to_string()),
+                });
+            }
+        }
+
+        let request_body = json!({
+            "context": client_context,
+            "browseId": "FEwhat_to_watch"
+        });
+
+        let response = self
+            .exchange("browse", &request_body, headers)
+            .await
+            .map_err(|reason| {
+                tracing::error!("Failed to send request to Innertube API: {}", reason);
                 crate::Error::VisitorData {
-                    reason: "Visitor data not found in API response".to_string(),
+                    reason,
                     context: Some("innertube".to_string()),
                 }
             })?;
 
+        if let Some(error) = rate_limit_error(&response) {
+            return Err(error);
+        }
+        let visitor_data = self
+            .visitor_data_from("browse", &response)
+            .inspect_err(|e| {
+                tracing::error!("Failed to get visitor data from Innertube browse: {}", e);
+            })?;
+
         tracing::debug!("Successfully generated visitor data: {}", visitor_data);
-        Ok(visitor_data.to_string())
+        Ok(visitor_data)
     }
 
     /// Get challenge data from Innertube /att/get endpoint
@@ -121,6 +506,16 @@ impl InnertubeProvider for InnertubeClient {
     async fn get_challenge(
         &self,
         context: &crate::types::InnertubeContext,
+    ) -> crate::Result<crate::types::ChallengeData> {
+        self.get_challenge_with_headers(context, &HashMap::new())
+            .await
+    }
+
+    /// Get challenge data from the /att/get endpoint with extra headers
+    async fn get_challenge_with_headers(
+        &self,
+        context: &crate::types::InnertubeContext,
+        headers: &HashMap<String, String>,
     ) -> crate::Result<crate::types::ChallengeData> {
         use serde_json::json;
 
@@ -132,23 +527,18 @@ impl InnertubeProvider for InnertubeClient {
         });
 
         let response = self
-            .client
-            .post(format!("{}/att/get?prettyPrint=false", self.base_url))
-            .header("Content-Type", "application/json")
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .json(&request_body)
-            .send()
+            .exchange("att/get?prettyPrint=false", &request_body, headers)
             .await
-            .map_err(|e| {
-                tracing::error!("Failed to send request to Innertube att/get: {}", e);
-                crate::Error::network(format!("Network request failed: {}", e))
+            .map_err(|reason| {
+                tracing::error!("Failed to send request to Innertube att/get: {}", reason);
+                crate::Error::network(reason)
             })?;
 
-        if !response.status().is_success() {
-            let status = response.status();
+        if let Some(error) = rate_limit_error(&response) {
+            return Err(error);
+        }
+        let status = status_code(&response);
+        if !status.is_success() {
             tracing::error!("Innertube att/get returned error status: {}", status);
             return Err(crate::Error::network(format!(
                 "API request failed with status: {}",
@@ -156,10 +546,12 @@ impl InnertubeProvider for InnertubeClient {
             )));
         }
 
-        let json_response: serde_json::Value = response.json().await.map_err(|e| {
-            tracing::error!("Failed to parse Innertube att/get response: {}", e);
-            crate::Error::network(format!("Failed to parse JSON response: {}", e))
-        })?;
+        let json_response: serde_json::Value =
+            serde_json::from_str(&response.body).map_err(|e| {
+                tracing::error!("Failed to parse Innertube att/get response: {}", e);
+                crate::Error::network(format!("Failed to parse JSON response: {}", e))
+            })?;
+        self.observe_response_context("att/get", &json_response);
 
         // Extract bgChallenge from response
         let bg_challenge = json_response.get("bgChallenge").ok_or_else(|| {
@@ -213,6 +605,13 @@ impl InnertubeProvider for InnertubeClient {
         tracing::debug!("Successfully retrieved challenge data from Innertube");
         Ok(challenge_data)
     }
+
+    fn response_context(&self) -> Option<ResponseContext> {
+        self.response_context
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
 }
 
 impl InnertubeClient {
@@ -284,6 +683,260 @@ mod tests {
         assert!(!generated_visitor_data.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_generate_visitor_data_prefers_visitor_id_endpoint() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/visitor_id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": {"visitorData": "CgtsaWdodHdlaWdodA%3D%3D"}
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/visitor_id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": {}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": {"visitorData": "Cgticm93c2U%3D"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let innertube =
+            InnertubeClient::new_with_base_url(Client::new(), mock_server.uri() + "/youtubei/v1");
+        assert!(innertube.response_context().is_none());
+        assert_eq!(
+            innertube.generate_visitor_data().await.unwrap(),
+            "CgtsaWdodHdlaWdodA%3D%3D"
+        );
+        assert_eq!(innertube.response_context().unwrap().endpoint, "visitor_id");
+        // Falls back to browse when the endpoint has no visitor data
+        assert_eq!(
+            innertube.generate_visitor_data().await.unwrap(),
+            "Cgticm93c2U%3D"
+        );
+        assert_eq!(innertube.response_context().unwrap().endpoint, "browse");
+    }
+
+    #[tokio::test]
+    async fn test_custom_base_url_and_api_key() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/mirror/youtubei/v1/visitor_id"))
+            .and(wiremock::matchers::header("X-Goog-Api-Key", "AIzaSyTest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": {"visitorData": "CgttaXJyb3I%3D"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let innertube = InnertubeClient::from_settings(
+            Client::new(),
+            &InnertubeSettings {
+                base_url: format!("{}/mirror/youtubei/v1/", mock_server.uri()),
+                api_key: Some("AIzaSyTest".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            innertube.generate_visitor_data().await.unwrap(),
+            "CgttaXJyb3I%3D"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data_locale() {
+        let mock_server = MockServer::start().await;
+        for (hl, gl, visitor_data) in [("de", "DE", "CgJkZQ%3D%3D"), ("ja", "DE", "CgJqYQ%3D%3D")] {
+            Mock::given(method("POST"))
+                .and(path("/youtubei/v1/visitor_id"))
+                .and(wiremock::matchers::body_partial_json(json!({
+                    "context": {"client": {"hl": hl, "gl": gl}}
+                })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "responseContext": {"visitorData": visitor_data}
+                })))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let settings = InnertubeSettings {
+            base_url: format!("{}/youtubei/v1", mock_server.uri()),
+            hl: "de".to_string(),
+            gl: "DE".to_string(),
+            ..Default::default()
+        };
+        let innertube = InnertubeClient::from_settings(Client::new(), &settings);
+        assert_eq!(
+            innertube.generate_visitor_data().await.unwrap(),
+            "CgJkZQ%3D%3D"
+        );
+
+        // The request's context overrides the configured language only
+        let locale = ClientLocale::from_settings(&settings)
+            .with_overrides(&json!({"client": {"hl": "ja", "gl": ""}}));
+        assert_eq!(
+            innertube
+                .generate_visitor_data_with_headers(&HashMap::new(), Some(&locale))
+                .await
+                .unwrap(),
+            "CgJqYQ%3D%3D"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data_sends_extra_headers() {
+        use wiremock::matchers::header;
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .and(header("Accept-Language", "de-DE"))
+            .and(header("X-Custom", "override"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": { "visitorData": "CgtDZjBSbE5uZDJlQQ%3D%3D" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let innertube = InnertubeClient::new_with_base_url(
+            Client::new(),
+            format!("{}/youtubei/v1", mock_server.uri()),
+        )
+        .with_headers(HashMap::from([
+            ("Accept-Language".to_string(), "de-DE".to_string()),
+            ("X-Custom".to_string(), "config".to_string()),
+        ]));
+
+        let overrides = HashMap::from([("x-custom".to_string(), "override".to_string())]);
+        let result = innertube
+            .generate_visitor_data_with_headers(&overrides, None)
+            .await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_get_challenge_sends_extra_headers() {
+        use wiremock::matchers::header;
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/att/get"))
+            .and(header("Accept-Language", "de-DE"))
+            .and(header("X-Custom", "override"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "bgChallenge": {
+                    "interpreterUrl": {
+                        "privateDoNotAccessOrElseTrustedResourceUrlWrappedValue": "//www.google.com/js/bg/test.js"
+                    },
+                    "interpreterHash": "hash",
+                    "program": "program",
+                    "globalName": "globalName"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let innertube = InnertubeClient::new_with_base_url(
+            Client::new(),
+            format!("{}/youtubei/v1", mock_server.uri()),
+        )
+        .with_headers(HashMap::from([
+            ("Accept-Language".to_string(), "de-DE".to_string()),
+            ("X-Custom".to_string(), "config".to_string()),
+        ]));
+
+        let overrides = HashMap::from([("x-custom".to_string(), "override".to_string())]);
+        let challenge = innertube
+            .get_challenge_with_headers(&Default::default(), &overrides)
+            .await
+            .unwrap();
+        assert_eq!(challenge.global_name, "globalName");
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data_propagates_trace_context() {
+        use crate::utils::trace_context::TraceContext;
+        use wiremock::matchers::header_regex;
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .and(header_regex(
+                "traceparent",
+                "^00-4bf92f3577b34da6a3ce929d0e0e4736-[0-9a-f]{16}-01$",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": { "visitorData": "CgtDZjBSbE5uZDJlQQ%3D%3D" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let innertube = InnertubeClient::new_with_base_url(
+            Client::new(),
+            format!("{}/youtubei/v1", mock_server.uri()),
+        )
+        .with_trace_propagation(true);
+
+        let context =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        let result = context.scope(innertube.generate_visitor_data()).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data_rate_limited() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "42"))
+            .mount(&mock_server)
+            .await;
+
+        let innertube = InnertubeClient::new_with_base_url(
+            Client::new(),
+            format!("{}/youtubei/v1", mock_server.uri()),
+        );
+
+        let result = innertube.generate_visitor_data().await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::RateLimit {
+                retry_after: Some(42),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_validate_and_redact_headers() {
+        let headers = HashMap::from([
+            ("Accept-Language".to_string(), "en-US".to_string()),
+            ("X-Goog-Visitor-Id".to_string(), "secret".to_string()),
+        ]);
+        assert!(validate_headers(&headers).is_ok());
+        assert_eq!(
+            redact_headers(&headers),
+            "Accept-Language: en-US, X-Goog-Visitor-Id: <redacted>"
+        );
+
+        let invalid = HashMap::from([("Bad Header".to_string(), "x".to_string())]);
+        assert!(validate_headers(&invalid).is_err());
+        let invalid = HashMap::from([("X-Ok".to_string(), "line\nbreak".to_string())]);
+        assert!(validate_headers(&invalid).is_err());
+    }
+
     #[tokio::test]
     async fn test_generate_visitor_data_network_error() {
         // Arrange
@@ -370,4 +1023,47 @@ mod tests {
         assert!(base_url.contains("youtube.com"));
         assert!(has_client);
     }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "responseContext": {"visitorData": "CgtyZWNvcmRlZA%3D%3D"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let recording = dir.path().join("innertube.jsonl");
+
+        let innertube =
+            InnertubeClient::new_with_base_url(Client::new(), mock_server.uri() + "/youtubei/v1")
+                .with_headers(HashMap::from([(
+                    "Cookie".to_string(),
+                    "SID=secret".to_string(),
+                )]))
+                .with_recorder(Arc::new(InnertubeRecorder::new(&recording)));
+        let recorded = innertube.generate_visitor_data().await.unwrap();
+        let content = std::fs::read_to_string(&recording).unwrap();
+        assert!(content.contains("<redacted>"));
+        assert!(!content.contains("secret"));
+
+        // Replay never contacts the server
+        let innertube = InnertubeClient::new_with_base_url(
+            Client::new(),
+            "http://127.0.0.1:9/youtubei/v1".to_string(),
+        )
+        .with_replayer(Arc::new(InnertubeReplayer::load(&recording).unwrap()));
+        assert_eq!(innertube.generate_visitor_data().await.unwrap(), recorded);
+        assert!(
+            innertube
+                .get_challenge(&Default::default())
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("No recorded Innertube response for att/get")
+        );
+    }
 }
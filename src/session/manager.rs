@@ -45,28 +45,110 @@
 //! - Cache key format: `{content_binding}:{context}`
 //! - Automatic expiration and cleanup
 
+#[cfg(any(test, feature = "legacy-pot-context"))]
+use crate::types::{PotContext, PotTokenResult};
 use crate::{
     Result,
     config::Settings,
     types::{
-        PotContext, PotRequest, PotResponse, PotTokenResult, PotTokenType, SessionData,
-        TokenMinterEntry,
+        CacheDump, CacheImportResponse, MinterInfo, PotRequest, PotResponse, PotTokenType,
+        PurgeRequest, SessionData, TokenMinterEntry,
+    },
+    utils::{
+        cache::FileCache,
+        privacy::LoggedBinding,
+        progress::ProgressReporter,
+        timeline::{RequestTimeline, TraceRecorder},
     },
 };
+use bytes::Bytes;
 use chrono::{DateTime, Duration, Utc};
 use reqwest::Client;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+#[cfg(any(test, feature = "legacy-pot-context"))]
 use std::time::SystemTime;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, OnceCell};
+use tokio_util::sync::CancellationToken;
+
+use super::botguard::BotGuardProvider;
+use super::events::ServerEventKind;
+use super::innertube::ClientLocale;
+use super::usage::UsageOutcome;
+use super::{CacheKey, CacheKeyMap, ProxySpec, SessionCacheKey, ShardedMap};
+
+/// Minimum time between refreshes of stale visitor data after failed mints
+const VISITOR_DATA_REFRESH_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Cooldown after an upstream 429 without a `Retry-After` header
+const DEFAULT_RATE_LIMIT_COOLDOWN_SECS: u64 = 60;
 
-use super::ProxySpec;
+/// URL used to pick the environment proxy for outbound YouTube requests
+static PROXY_TARGET_URL: std::sync::LazyLock<reqwest::Url> = std::sync::LazyLock::new(|| {
+    reqwest::Url::parse("https://www.youtube.com/").expect("valid YouTube URL")
+});
 
 /// Session data cache type
 pub type SessionDataCaches = HashMap<String, SessionData>;
 
+/// Point-in-time view of the session data cache sharing entries with the live cache
+pub type SessionDataSnapshot = Vec<(CacheKey, Arc<SessionData>)>;
+
 /// Minter cache type
-pub type MinterCache = HashMap<String, TokenMinterEntry>;
+pub type MinterCache = CacheKeyMap<TokenMinterEntry>;
+
+/// Starts a background refresh of the token for a content binding
+///
+/// Set by [`SessionManagerGeneric::enable_speculative_refresh`], which holds
+/// the `Arc` needed to spawn the refresh from a request.
+struct SpeculativeRefresh(Box<dyn Fn(PotRequest, CacheKey) + Send + Sync>);
+
+impl std::fmt::Debug for SpeculativeRefresh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SpeculativeRefresh")
+    }
+}
+
+/// What happens to a token being minted when its request is cancelled
+///
+/// Configured by `token.cancelled_mint`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CancelledMint {
+    /// Mint and cache the token, answering the client retrying from the cache
+    #[default]
+    Complete,
+    /// Stop waiting for the token, saving the work for other requests
+    Abort,
+}
+
+impl CancelledMint {
+    /// Parse a cancelled mint handling name (`complete` or `abort`)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "complete" => Some(Self::Complete),
+            "abort" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Run `phase` of token generation until `cancel` is cancelled
+///
+/// The phase is dropped on cancellation, so it must not leave shared state
+/// half updated when interrupted at an await point.
+async fn until_cancelled<R>(
+    cancel: &CancellationToken,
+    operation: &str,
+    phase: impl std::future::Future<Output = Result<R>>,
+) -> Result<R> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(crate::Error::cancelled(operation)),
+        result = phase => result,
+    }
+}
 
 /// Convenience type alias for SessionManager with default InnertubeClient
 pub type SessionManager = SessionManagerGeneric<crate::session::innertube::InnertubeClient>;
@@ -75,23 +157,67 @@ pub type SessionManager = SessionManagerGeneric<crate::session::innertube::Inner
 #[derive(Debug)]
 pub struct SessionManagerGeneric<
     T: crate::session::innertube::InnertubeProvider = crate::session::innertube::InnertubeClient,
+    B: BotGuardProvider = crate::session::botguard::DefaultBotGuardProvider,
 > {
     /// Configuration settings
     settings: Arc<Settings>,
     /// HTTP client for requests
     http_client: Client,
+    /// Connections opened by the HTTP client per upstream host
+    connections: super::connections::ConnectionStats,
+    /// Skew of the system clock against Innertube
+    clock_skew: super::clock::ClockSkew,
     /// Cache for session data keyed by content binding
-    session_data_caches: RwLock<SessionDataCaches>,
+    session_data_caches: ShardedMap<Arc<SessionData>>,
     /// Cache for minter instances
-    minter_cache: RwLock<MinterCache>,
+    minter_cache: ShardedMap<TokenMinterEntry>,
     /// Request key for BotGuard API
     request_key: String,
-    /// Token TTL in hours
+    /// Token TTL in hours, `token.ttl_hours`
     token_ttl_hours: i64,
     /// Innertube provider for visitor data generation
     innertube_provider: Arc<T>,
-    /// BotGuard client for POT token generation
-    botguard_client: crate::session::botguard::BotGuardClient,
+    /// BotGuard provider for POT token generation
+    botguard_client: B,
+    /// Request timeline recorder, enabled by `logging.trace_file`
+    trace_recorder: Option<Arc<TraceRecorder>>,
+    /// Progress events reporter, enabled by `--progress-json` in script mode
+    progress: Option<Arc<ProgressReporter>>,
+    /// Persistent tier behind the in-memory session data cache
+    file_cache: Option<Arc<FileCache>>,
+    /// Set once the persistent tier has been read into memory
+    file_cache_loaded: OnceCell<()>,
+    /// Session data written to memory but not yet flushed to the persistent tier
+    pending_writes: Mutex<SessionDataSnapshot>,
+    /// Set when the minters changed since they were last flushed
+    minters_changed: std::sync::atomic::AtomicBool,
+    /// Mint outcomes per proxy
+    proxy_health: super::network::ProxyHealth,
+    /// Request counters for the admin API
+    stats: super::stats::RequestStats,
+    /// Token request outcomes against the `server.slo_*` targets
+    slo: super::slo::SloTracker,
+    /// Request budget for Innertube and BotGuard requests
+    upstream_limiter: super::network::UpstreamLimiter,
+    /// Server events for `GET /events` subscribers
+    events: super::events::EventBus,
+    /// Daily usage counters, when usage analytics are enabled
+    usage: Option<Arc<super::usage::UsageStore>>,
+    /// Background tasks cancelled on shutdown
+    background_tasks: std::sync::Mutex<Vec<tokio::task::AbortHandle>>,
+    /// Visitor data for requests without a content binding, kept until it looks stale
+    visitor_data: Mutex<Option<String>>,
+    /// When stale visitor data was last discarded after a failed mint
+    visitor_data_refreshed_at: std::sync::Mutex<Option<Instant>>,
+    /// Background refresh of tokens close to expiry, from `token.refresh_ahead_fraction`
+    speculative_refresh: std::sync::OnceLock<SpeculativeRefresh>,
+    /// Session data keys with a background refresh in flight
+    refreshing: std::sync::Mutex<std::collections::HashSet<CacheKey>>,
+    /// Handling of tokens being minted for cancelled requests
+    cancelled_mint: CancelledMint,
+    /// Faults injected into Innertube and BotGuard calls, from `[chaos]`
+    #[cfg(feature = "chaos")]
+    faults: Option<super::chaos::FaultInjector>,
 }
 
 impl SessionManagerGeneric<crate::session::innertube::InnertubeClient> {
@@ -114,34 +240,51 @@ impl SessionManagerGeneric<crate::session::innertube::InnertubeClient> {
     /// let manager = SessionManager::new(settings);
     /// ```
     pub fn new(settings: Settings) -> Self {
-        let http_client = Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .build()
-            .expect("Failed to create HTTP client");
-
-        let innertube_client = crate::session::innertube::InnertubeClient::new(http_client.clone());
-
-        // Create BotGuard client with configuration
-        let snapshot_path = if settings.botguard.disable_snapshot {
-            None
-        } else {
-            settings.botguard.snapshot_path.clone()
-        };
-        let botguard_client = crate::session::botguard::BotGuardClient::new(
-            snapshot_path,
-            settings.botguard.user_agent.clone(),
+        let botguard_client = crate::session::botguard::DefaultBotGuardProvider::from_settings(
+            &botguard_settings(&settings),
         );
+        Self::new_with_botguard(settings, botguard_client)
+    }
 
-        Self {
-            settings: Arc::new(settings),
-            http_client,
-            session_data_caches: RwLock::new(HashMap::new()),
-            minter_cache: RwLock::new(HashMap::new()),
-            request_key: "O43z0dpjhgX20SCx4KAo".to_string(), // Hardcoded API key from TS
-            token_ttl_hours: 6,                              // Default from TS implementation
-            innertube_provider: Arc::new(innertube_client),
-            botguard_client,
+    /// Creates a session manager minting with `botguard` instead of the
+    /// BotGuard client configured by `[botguard]`
+    pub fn new_with_botguard(
+        settings: Settings,
+        botguard: crate::session::botguard::DefaultBotGuardProvider,
+    ) -> Self {
+        let connections = super::connections::ConnectionStats::default();
+        let http_client = build_http_client(&settings, &connections);
+        let clock_skew = super::clock::ClockSkew::from_settings(&settings);
+        let mut innertube_client = crate::session::innertube::InnertubeClient::from_settings(
+            http_client.clone(),
+            &settings.innertube,
+        )
+        .with_headers(settings.network.extra_headers.clone())
+        .with_trace_propagation(settings.network.propagate_trace_context)
+        .with_clock_skew(clock_skew.clone());
+        if let Some(path) = &settings.network.innertube_record {
+            tracing::info!("Recording Innertube interactions to {}", path.display());
+            innertube_client = innertube_client
+                .with_recorder(Arc::new(super::recording::InnertubeRecorder::new(path)));
+        }
+        if let Some(path) = &settings.network.innertube_replay {
+            // Never fall back to contacting Innertube when asked to replay
+            let replayer = super::recording::InnertubeReplayer::load(path).unwrap_or_else(|e| {
+                tracing::error!("{}, Innertube requests will fail", e);
+                Default::default()
+            });
+            tracing::info!("Replaying Innertube interactions from {}", path.display());
+            innertube_client = innertube_client.with_replayer(Arc::new(replayer));
         }
+
+        Self::from_parts(
+            settings,
+            http_client,
+            connections,
+            clock_skew,
+            innertube_client,
+            botguard,
+        )
     }
 }
 
@@ -152,38 +295,142 @@ where
 {
     /// Creates a new session manager with a custom innertube provider for testing
     pub fn new_with_provider(settings: Settings, provider: P) -> Self {
-        let http_client = Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .build()
-            .expect("Failed to create HTTP client");
-
-        // Create BotGuard client with configuration
-        let snapshot_path = if settings.botguard.disable_snapshot {
-            None
-        } else {
-            settings.botguard.snapshot_path.clone()
-        };
-        let botguard_client = crate::session::botguard::BotGuardClient::new(
-            snapshot_path,
-            settings.botguard.user_agent.clone(),
+        let botguard_client = crate::session::botguard::DefaultBotGuardProvider::from_settings(
+            &botguard_settings(&settings),
         );
+        Self::new_with_providers(settings, provider, botguard_client)
+    }
+}
+
+/// The `[botguard]` settings with the snapshot path resolved
+///
+/// Picks the snapshot keyed by user agent and proxy when
+/// `botguard.snapshot_dir` is set.
+fn botguard_settings(settings: &Settings) -> crate::config::settings::BotGuardSettings {
+    crate::config::settings::BotGuardSettings {
+        snapshot_path: super::snapshot::resolve_snapshot_path(settings),
+        ..settings.botguard.clone()
+    }
+}
+
+/// Build the HTTP client shared by the session manager and Innertube
+fn build_http_client(
+    settings: &Settings,
+    connections: &super::connections::ConnectionStats,
+) -> Client {
+    connections
+        .instrument(super::network::configure_client(
+            Client::builder()
+                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"),
+            &settings.network,
+        ))
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+impl<T, B> SessionManagerGeneric<T, B>
+where
+    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug,
+    B: BotGuardProvider + std::fmt::Debug,
+{
+    /// Creates a session manager with custom Innertube and BotGuard providers
+    ///
+    /// Lets tests run the whole pipeline without network or V8, and lets
+    /// alternative BotGuard backends be swapped in.
+    pub fn new_with_providers(
+        settings: Settings,
+        innertube_provider: T,
+        botguard_provider: B,
+    ) -> Self {
+        let connections = super::connections::ConnectionStats::default();
+        let http_client = build_http_client(&settings, &connections);
+        let clock_skew = super::clock::ClockSkew::from_settings(&settings);
+        Self::from_parts(
+            settings,
+            http_client,
+            connections,
+            clock_skew,
+            innertube_provider,
+            botguard_provider,
+        )
+    }
+
+    /// Assemble a session manager from its providers
+    fn from_parts(
+        settings: Settings,
+        http_client: Client,
+        connections: super::connections::ConnectionStats,
+        clock_skew: super::clock::ClockSkew,
+        innertube_provider: T,
+        botguard_provider: B,
+    ) -> Self {
+        let trace_recorder = settings
+            .logging
+            .trace_file
+            .as_ref()
+            .map(|path| Arc::new(TraceRecorder::new(path)));
+        let upstream_limiter = super::network::UpstreamLimiter::from_settings(&settings.network);
+        let slo = super::slo::SloTracker::from_settings(&settings);
+        let cancelled_mint =
+            CancelledMint::parse(&settings.token.cancelled_mint).unwrap_or_else(|| {
+                tracing::warn!(
+                    "Unknown cancelled mint handling '{}', using complete",
+                    settings.token.cancelled_mint
+                );
+                CancelledMint::Complete
+            });
+        #[cfg(feature = "chaos")]
+        let faults = super::chaos::FaultInjector::from_settings(&settings.chaos);
+        #[cfg(not(feature = "chaos"))]
+        if settings.chaos.is_enabled() {
+            tracing::warn!("Ignoring [chaos]: built without the chaos feature");
+        }
+
+        // Clamped for settings that weren't validated
+        let token_ttl_hours = settings
+            .token
+            .ttl_hours
+            .clamp(1, crate::config::settings::MAX_TTL_HOURS) as i64;
 
         Self {
             settings: Arc::new(settings),
             http_client,
-            session_data_caches: RwLock::new(HashMap::new()),
-            minter_cache: RwLock::new(HashMap::new()),
-            request_key: "O43z0dpjhgX20SCx4KAo".to_string(),
-            token_ttl_hours: 6,
-            innertube_provider: Arc::new(provider),
-            botguard_client,
+            connections,
+            clock_skew,
+            session_data_caches: ShardedMap::with_weigher(|data| data.approx_size()),
+            minter_cache: ShardedMap::new(),
+            request_key: "O43z0dpjhgX20SCx4KAo".to_string(), // Hardcoded API key from TS
+            token_ttl_hours,
+            innertube_provider: Arc::new(innertube_provider),
+            botguard_client: botguard_provider,
+            trace_recorder,
+            progress: None,
+            file_cache: None,
+            file_cache_loaded: OnceCell::new(),
+            pending_writes: Mutex::new(Vec::new()),
+            minters_changed: Default::default(),
+            proxy_health: Default::default(),
+            stats: Default::default(),
+            slo,
+            events: Default::default(),
+            upstream_limiter,
+            usage: None,
+            background_tasks: Default::default(),
+            visitor_data: Mutex::new(None),
+            visitor_data_refreshed_at: std::sync::Mutex::new(None),
+            speculative_refresh: std::sync::OnceLock::new(),
+            refreshing: Default::default(),
+            cancelled_mint,
+            #[cfg(feature = "chaos")]
+            faults,
         }
     }
 }
 
-impl<T> SessionManagerGeneric<T>
+impl<T, B> SessionManagerGeneric<T, B>
 where
     T: crate::session::innertube::InnertubeProvider + std::fmt::Debug,
+    B: BotGuardProvider + std::fmt::Debug,
 {
     /// Generates a POT token for the given request.
     ///
@@ -237,23 +484,155 @@ where
     ///
     /// Corresponds to TypeScript implementation: `generatePoToken` method (L485-569)
     pub async fn generate_pot_token(&self, request: &PotRequest) -> Result<PotResponse> {
-        // Initialize BotGuard client before token generation
-        self.initialize_botguard().await?;
+        self.generate_pot_token_cancellable(request, &CancellationToken::new())
+            .await
+    }
+
+    /// Like [`Self::generate_pot_token`], giving up once `cancel` is
+    /// cancelled, see [`Self::generate_pot_response_body_cancellable`]
+    pub async fn generate_pot_token_cancellable(
+        &self,
+        request: &PotRequest,
+        cancel: &CancellationToken,
+    ) -> Result<PotResponse> {
+        self.generate_session_data(request, cancel)
+            .await
+            .map(|session_data| {
+                PotResponse::from_session_data(session_data)
+                    .with_single_use(request.is_single_use())
+            })
+    }
 
-        let content_binding = self.get_content_binding(request).await?;
+    /// Generates a POT token, returning the serialized [`PotResponse`]
+    ///
+    /// Cache hits are answered with the body serialized when the token was
+    /// cached, saving the serialization on hot content bindings.
+    pub async fn generate_pot_response_body(&self, request: &PotRequest) -> Result<Bytes> {
+        self.generate_pot_response_body_cancellable(request, &CancellationToken::new())
+            .await
+    }
 
-        // Clean up expired cache entries
-        self.cleanup_caches().await;
+    /// Like [`Self::generate_pot_response_body`], giving up once `cancel` is
+    /// cancelled, e.g. when the client disconnected
+    ///
+    /// Cancellation stops BotGuard initialization, visitor data generation
+    /// and the integrity token request with [`crate::Error::Cancelled`]. Once
+    /// a minter is at hand the token is still minted and cached, so the
+    /// client retrying gets it from the cache, unless `token.cancelled_mint`
+    /// is `abort`.
+    pub async fn generate_pot_response_body_cancellable(
+        &self,
+        request: &PotRequest,
+        cancel: &CancellationToken,
+    ) -> Result<Bytes> {
+        let session_data = self.generate_session_data(request, cancel).await?;
+        if request.is_single_use() {
+            let response = PotResponse::from_session_data(session_data).with_single_use(true);
+            return Ok(serde_json::to_vec(&response)?.into());
+        }
+        Ok(session_data.response_body())
+    }
 
-        // Check cache first unless bypass_cache is true
-        if !request.bypass_cache.unwrap_or(false)
-            && let Some(cached_data) = self.get_cached_session_data(&content_binding).await
-        {
+    /// Generate or look up the session data answering `request`
+    async fn generate_session_data(
+        &self,
+        request: &PotRequest,
+        cancel: &CancellationToken,
+    ) -> Result<SessionData> {
+        let started = Instant::now();
+        let mut timeline = RequestTimeline::start(self.trace_recorder.clone())
+            .with_progress(self.progress.clone());
+        self.stats.record_request();
+        let mut result = self
+            .generate_pot_token_traced(request, &mut timeline, cancel)
+            .await;
+        let failed = result.as_ref().map_or(true, |data| data.fallback);
+        if failed && !cancel.is_cancelled() && self.refresh_stale_visitor_data(request).await {
+            // Retried once; the cooldown keeps persistent failures from looping
+            match self
+                .generate_pot_token_traced(request, &mut timeline, cancel)
+                .await
+            {
+                Err(e) if result.is_ok() => {
+                    tracing::warn!("Retry with new visitor data failed: {}", e);
+                }
+                retried => result = retried,
+            }
+        }
+        timeline.finish(result.is_ok()).await;
+        if let Err(crate::Error::Cancelled { operation }) = &result {
+            // Nobody is waiting for the token, which isn't a server error
+            tracing::debug!("POT generation cancelled during {}", operation);
+            return result;
+        }
+        // Fallback tokens count against the objective, they signal trouble
+        self.slo.record(
+            result.as_ref().is_ok_and(|data| !data.fallback),
+            started.elapsed(),
+        );
+        if let Err(e) = &result {
+            self.record_usage(request, UsageOutcome::Error);
+            self.stats.record_error(e);
+            self.events.publish(ServerEventKind::Error {
+                category: e.category().to_string(),
+                message: e.to_string(),
+            });
+        }
+        result
+    }
+
+    /// Token generation pipeline, recording each phase into `timeline`
+    ///
+    /// Phases waiting on BotGuard or Innertube give up once `cancel` is
+    /// cancelled. Minting is only interrupted with `token.cancelled_mint`
+    /// set to `abort`: it's quick compared to the phases before, and
+    /// dropping a minted token wastes it. A minted token is always cached.
+    async fn generate_pot_token_traced(
+        &self,
+        request: &PotRequest,
+        timeline: &mut RequestTimeline,
+        cancel: &CancellationToken,
+    ) -> Result<SessionData> {
+        // BotGuard initialization and visitor data generation don't depend on
+        // each other, so a cold request runs them concurrently. Both phases
+        // are recorded as ending when the slower one finishes.
+        let botguard_phase = timeline.begin("botguard_init");
+        let visitor_data_phase = timeline.begin("visitor_data");
+        let ((), content_binding) = until_cancelled(cancel, "botguard_init", async {
+            tokio::try_join!(
+                self.initialize_botguard(),
+                self.get_content_binding(request)
+            )
+        })
+        .await?;
+        timeline.record("botguard_init", botguard_phase);
+        timeline.record("visitor_data", visitor_data_phase);
+        let mut session_key = SessionCacheKey::new(content_binding.as_str());
+        if let Some(token_type) = request.token_type {
+            session_key = session_key.with_token_type(token_type);
+        }
+        let session_key = CacheKey::from(session_key);
+
+        // Check cache first unless bypass_cache is true, single-use tokens
+        // are always fresh and `token.enable_cache` turns caching off
+        let cacheable = self.settings.token.enable_cache && !request.is_single_use();
+        let phase = timeline.begin("cache_lookup");
+        let cached_data = if request.bypass_cache.unwrap_or(false) || !cacheable {
+            None
+        } else {
+            self.get_cached_session_data(&session_key).await
+        };
+        timeline.record("cache_lookup", phase);
+
+        if let Some(cached_data) = cached_data {
             tracing::info!(
                 "POT for {} still fresh, returning cached token",
-                content_binding
+                self.logged_binding(&content_binding)
             );
-            return Ok(PotResponse::from_session_data(cached_data));
+            self.stats.record_cache_hit();
+            self.record_usage(request, UsageOutcome::Cached);
+            self.refresh_ahead(request, &content_binding, session_key, &cached_data);
+            return Ok(cached_data);
         }
 
         // Generate proxy specification
@@ -262,29 +641,176 @@ where
         // Create cache key for minter
         let cache_key = self.create_cache_key(&proxy_spec, request)?;
 
-        // Get or create token minter
-        let token_minter = self
-            .get_or_create_token_minter(&cache_key, request, &proxy_spec)
+        let started = Instant::now();
+        let minted = async {
+            // Get or create token minter, recording the challenge and
+            // integrity token phases when a new one is needed
+            let minter = until_cancelled(
+                cancel,
+                "integrity_token",
+                self.get_or_create_token_minter(&cache_key, request, &proxy_spec, timeline),
+            )
             .await?;
 
-        // Mint POT token
-        let session_data = self.mint_pot_token(&content_binding, &token_minter).await?;
+            // Mint POT token, falling back to the websafe token of the minter
+            let phase = timeline.begin("mint");
+            let mint = self.mint_pot_token(&content_binding);
+            let minted = match self.cancelled_mint {
+                CancelledMint::Complete => mint.await,
+                CancelledMint::Abort => until_cancelled(cancel, "mint", mint).await,
+            };
+            let session_data = match minted {
+                Ok(session_data) => session_data,
+                Err(e @ crate::Error::Cancelled { .. }) => return Err(e),
+                Err(e) => {
+                    let Some(fallback) = &minter.websafe_fallback_token else {
+                        return Err(e);
+                    };
+                    tracing::warn!(
+                        "Minting POT for {} failed, returning websafe fallback token: {}",
+                        self.logged_binding(&content_binding),
+                        e
+                    );
+                    self.stats.record_error(&e);
+                    SessionData::new(fallback.as_str(), content_binding.as_str(), minter.expiry)
+                        .with_fallback(true)
+                }
+            };
+            timeline.record("mint", phase);
+            Ok::<_, crate::Error>(session_data)
+        }
+        .await;
+        if let Some(proxy) = &proxy_spec.proxy_url {
+            match &minted {
+                Ok(_) => self.proxy_health.record_success(proxy, started.elapsed()),
+                Err(_) => self.proxy_health.record_failure(proxy),
+            }
+        }
+        let mut session_data = minted?;
+
+        if let Some(response_context) = self.innertube_provider.response_context() {
+            session_data = session_data.with_response_context(response_context);
+        }
 
-        // Cache the result
-        self.cache_session_data(&content_binding, &session_data)
-            .await;
+        // Bind the token to visitor data generated here, also when its
+        // binding comes back in a later request or a background refresh
+        let generated_visitor_data = request.content_binding.is_none()
+            || self
+                .session_data_caches
+                .read(&session_key, |cached| {
+                    cached.is_some_and(|cached| {
+                        cached.visitor_data.as_deref() == Some(content_binding.as_str())
+                    })
+                })
+                .await;
+        if generated_visitor_data {
+            session_data = session_data.with_visitor_data(content_binding.as_str());
+        }
+
+        // Cache the result, serialized once for both this response and cache
+        // hits, unless the caller uses the token once only or caching is
+        // disabled. Fallback tokens aren't cached so the next request mints
+        // a real one.
+        if !cacheable || session_data.fallback {
+            tracing::debug!(
+                "Not caching single-use, uncached or fallback POT for {}",
+                self.logged_binding(&content_binding)
+            );
+        } else {
+            session_data.response_body();
+            self.cache_session_data(session_key, &session_data).await;
+        }
+        self.record_usage(request, UsageOutcome::Minted);
+
+        Ok(session_data)
+    }
+
+    /// Start a background refresh of `cached`, served to `request`, if it is
+    /// within `token.refresh_ahead_fraction` of expiring
+    ///
+    /// At most one refresh runs per content binding; the cached token keeps
+    /// being served until the fresh one replaces it.
+    fn refresh_ahead(
+        &self,
+        request: &PotRequest,
+        content_binding: &str,
+        key: CacheKey,
+        cached: &SessionData,
+    ) {
+        let Some(refresh) = self.speculative_refresh.get() else {
+            return;
+        };
+        let lifetime = self.token_lifetime();
+        let threshold =
+            lifetime.num_milliseconds() as f64 * self.settings.token.refresh_ahead_fraction;
+        if ((cached.expires_at - Utc::now()).num_milliseconds() as f64) >= threshold {
+            return;
+        }
+        if !self
+            .refreshing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone())
+        {
+            return;
+        }
+
+        tracing::info!(
+            "POT for {} expires soon, refreshing it in the background",
+            self.logged_binding(content_binding)
+        );
+        // Mint for the resolved binding so the refresh reuses the same visitor data
+        let request = request
+            .clone()
+            .with_content_binding(content_binding)
+            .with_bypass_cache(true);
+        (refresh.0)(request, key);
+    }
 
-        Ok(PotResponse::from_session_data(session_data))
+    /// Mint and cache a fresh token for a [`Self::refresh_ahead`] refresh
+    async fn refresh_session_data(&self, request: PotRequest, key: CacheKey) {
+        let mut timeline = RequestTimeline::start(None);
+        if let Err(e) = self
+            .generate_pot_token_traced(&request, &mut timeline, &CancellationToken::new())
+            .await
+        {
+            tracing::warn!("Background token refresh failed: {}", e);
+        }
+        self.refreshing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key);
     }
 
     /// Generate visitor data for new sessions
     ///
     /// Corresponds to TypeScript: `generateVisitorData` method (L230-241)
     pub async fn generate_visitor_data(&self) -> Result<String> {
+        self.generate_visitor_data_with_headers(&HashMap::new(), None)
+            .await
+    }
+
+    /// Generate visitor data, sending extra headers to Innertube in a client
+    /// context of `locale` instead of the configured one
+    async fn generate_visitor_data_with_headers(
+        &self,
+        headers: &HashMap<String, String>,
+        locale: Option<&ClientLocale>,
+    ) -> Result<String> {
         tracing::info!("Generating visitor data using Innertube API");
 
         // Use the injected Innertube provider
-        let visitor_data = self.innertube_provider.generate_visitor_data().await?;
+        let visitor_data = self
+            .upstream(None, async {
+                #[cfg(feature = "chaos")]
+                if let Some(faults) = &self.faults {
+                    faults.innertube().await?;
+                }
+                self.innertube_provider
+                    .generate_visitor_data_with_headers(headers, locale)
+                    .await
+            })
+            .await?;
 
         if visitor_data.is_empty() {
             return Err(crate::Error::VisitorData {
@@ -308,17 +834,235 @@ where
         Ok(visitor_data)
     }
 
+    /// Get challenge data from Innertube for `request`
+    ///
+    /// Sends the request's `innertube_headers` like visitor data generation
+    /// does, in the client context of its `innertube_context`.
+    pub async fn get_challenge(&self, request: &PotRequest) -> Result<crate::types::ChallengeData> {
+        let headers = request.innertube_headers.clone().unwrap_or_default();
+        crate::session::innertube::validate_headers(&headers)?;
+
+        let client = request
+            .innertube_context
+            .as_ref()
+            .and_then(|context| context.get("client"));
+        let field = |name: &str| {
+            client
+                .and_then(|client| client.get(name))
+                .and_then(|value| value.as_str())
+        };
+        let mut client_info = crate::types::ClientInfo::new();
+        if let Some(remote_host) = field("remoteHost") {
+            client_info = client_info.with_remote_host(remote_host);
+        }
+        if let Some(visitor_data) = field("visitorData") {
+            client_info = client_info.with_visitor_data(visitor_data);
+        }
+
+        self.upstream(None, async {
+            #[cfg(feature = "chaos")]
+            if let Some(faults) = &self.faults {
+                faults.innertube().await?;
+            }
+            self.innertube_provider
+                .get_challenge_with_headers(
+                    &crate::types::InnertubeContext::new(client_info),
+                    &headers,
+                )
+                .await
+        })
+        .await
+    }
+
+    /// Send an upstream Innertube or BotGuard request within the budget
+    ///
+    /// Fails fast while a rate limit cooldown is in effect, waits for budget
+    /// through `proxy`, and starts a cooldown when upstream rate limits us.
+    async fn upstream<R>(
+        &self,
+        proxy: Option<&str>,
+        request: impl Future<Output = Result<R>>,
+    ) -> Result<R> {
+        // Don't hammer upstream while it asks us to back off
+        if let Some(remaining) = self.upstream_limiter.cooldown_remaining() {
+            return Err(crate::Error::rate_limit(
+                "Innertube rate limit cooldown in effect",
+                Some(remaining.as_secs().max(1)),
+            ));
+        }
+
+        self.upstream_limiter.acquire(proxy).await;
+        request.await.inspect_err(|e| {
+            if let crate::Error::RateLimit { retry_after, .. } = e {
+                self.upstream_limiter
+                    .start_cooldown(std::time::Duration::from_secs(
+                        retry_after.unwrap_or(DEFAULT_RATE_LIMIT_COOLDOWN_SECS),
+                    ));
+            }
+        })
+    }
+
     /// Invalidate all cached tokens and minters
     ///
     /// Corresponds to TypeScript: `invalidateCaches` method (L200-203)
     pub async fn invalidate_caches(&self) -> Result<()> {
-        let mut session_cache = self.session_data_caches.write().await;
-        session_cache.clear();
+        self.session_data_caches.clear().await;
+
+        if let Some(file_cache) = &self.file_cache {
+            self.pending_writes.lock().await.clear();
+            // Don't read the invalidated entries back in
+            let _ = self.file_cache_loaded.set(());
+            file_cache.save_cache(SessionDataCaches::new()).await?;
+        }
+
+        self.minter_cache.clear().await;
+        self.minters_changed.store(true, Ordering::Relaxed);
 
-        let mut minter_cache = self.minter_cache.write().await;
-        minter_cache.clear();
+        *self.visitor_data.lock().await = None;
 
         tracing::info!("All caches invalidated");
+        self.events.publish(ServerEventKind::CacheInvalidated {
+            scope: "caches".to_string(),
+        });
+        Ok(())
+    }
+
+    /// Remove every token cached for the content bindings matched by `request`
+    ///
+    /// Purges the in-memory cache, pending writes and the persistent tier,
+    /// and forgets the shared visitor data if it is purged. Returns the
+    /// number of cached tokens removed.
+    pub async fn purge(&self, request: &PurgeRequest) -> Result<usize> {
+        // Purge entries only stored in the persistent tier too
+        self.load_file_cache().await;
+
+        let purged = self
+            .session_data_caches
+            .retain(|key, _| !request.matches(key.as_str()))
+            .await;
+
+        if let Some(file_cache) = &self.file_cache {
+            self.pending_writes
+                .lock()
+                .await
+                .retain(|(key, _)| !request.matches(key.as_str()));
+            file_cache
+                .save_snapshot(&self.session_data_snapshot(false).await)
+                .await?;
+        }
+
+        {
+            let mut visitor_data = self.visitor_data.lock().await;
+            if visitor_data
+                .as_deref()
+                .is_some_and(|visitor_data| request.matches(visitor_data))
+            {
+                *visitor_data = None;
+            }
+        }
+
+        tracing::info!("Purged {} cached tokens", purged);
+        self.events.publish(ServerEventKind::CacheInvalidated {
+            scope: "purge".to_string(),
+        });
+        Ok(purged)
+    }
+
+    /// Export the unexpired session data and token minters
+    ///
+    /// Includes entries only stored in the persistent tier.
+    pub async fn export_caches(&self) -> CacheDump {
+        self.load_file_cache().await;
+        let session_data = self
+            .session_data_snapshot(true)
+            .await
+            .into_iter()
+            .map(|(key, data)| (key.as_str().to_string(), SessionData::clone(&data)))
+            .collect();
+        let minters = self
+            .minter_cache
+            .entries()
+            .await
+            .into_iter()
+            .filter(|(_, minter)| !minter.is_expired())
+            .map(|(key, minter)| (key.as_str().to_string(), minter))
+            .collect();
+
+        CacheDump {
+            version: crate::utils::version::get_version().to_string(),
+            exported_at: Utc::now(),
+            session_data,
+            minters,
+        }
+    }
+
+    /// Import caches exported by [`Self::export_caches`]
+    ///
+    /// Expired entries are skipped, and cached entries expiring later than
+    /// the imported ones are kept. Imported session data is written to the
+    /// persistent tier on the next [`Self::flush_caches`].
+    pub async fn import_caches(&self, dump: CacheDump) -> CacheImportResponse {
+        self.load_file_cache().await;
+        let mut imported = CacheImportResponse {
+            session_data: 0,
+            minters: 0,
+        };
+
+        for (key, data) in dump.session_data {
+            let key = CacheKey::from(key);
+            let newer = self
+                .session_data_caches
+                .read(&key, |cached| {
+                    cached.is_none_or(|cached| cached.expires_at < data.expires_at)
+                })
+                .await;
+            if !data.is_expired() && newer {
+                self.cache_session_data(key, &data).await;
+                imported.session_data += 1;
+            }
+        }
+
+        for (key, minter) in dump.minters {
+            let key = CacheKey::from(key);
+            let expiry = minter.expiry;
+            if !minter.is_expired()
+                && self
+                    .minter_cache
+                    .insert_if(key, minter, |cached| {
+                        cached.is_none_or(|cached| cached.expiry < expiry)
+                    })
+                    .await
+            {
+                imported.minters += 1;
+            }
+        }
+        if imported.minters > 0 {
+            self.minters_changed.store(true, Ordering::Relaxed);
+        }
+
+        tracing::info!(
+            "Imported {} session data entries and {} token minters",
+            imported.session_data,
+            imported.minters
+        );
+        imported
+    }
+
+    /// Discard the visitor data reused for requests without a content binding
+    ///
+    /// For clients reporting that tokens bound to it were rejected. Tokens
+    /// cached for the visitor data are dropped, and new visitor data is
+    /// generated on the next request without a content binding.
+    pub async fn invalidate_visitor_data(&self) -> Result<()> {
+        let Some(visitor_data) = self.visitor_data.lock().await.take() else {
+            return Ok(());
+        };
+        self.remove_session_data(&visitor_data).await;
+
+        tracing::info!("Visitor data invalidated");
+        self.events.publish(ServerEventKind::CacheInvalidated {
+            scope: "visitor_data".to_string(),
+        });
         Ok(())
     }
 
@@ -326,32 +1070,144 @@ where
     ///
     /// Corresponds to TypeScript: `invalidateIT` method (L205-209)
     pub async fn invalidate_integrity_tokens(&self) -> Result<()> {
-        let mut minter_cache = self.minter_cache.write().await;
         let expired_time = DateTime::from_timestamp(0, 0).unwrap_or_else(Utc::now);
-
-        for (_, minter) in minter_cache.iter_mut() {
-            minter.expiry = expired_time;
-        }
+        self.minter_cache
+            .update_all(|_, minter| minter.expiry = expired_time)
+            .await;
+        self.minters_changed.store(true, Ordering::Relaxed);
 
         tracing::info!("All integrity tokens marked as expired");
+        self.events.publish(ServerEventKind::CacheInvalidated {
+            scope: "integrity_token".to_string(),
+        });
         Ok(())
     }
 
+    /// Get the token minter for `request`, creating it if needed
+    ///
+    /// The minter is picked by the request's proxy and Innertube context like
+    /// for [`Self::generate_pot_token`], and cached the same way.
+    pub async fn get_or_create_minter(&self, request: &PotRequest) -> Result<MinterInfo> {
+        self.initialize_botguard().await?;
+        let proxy_spec = self.create_proxy_spec(request).await?;
+        let cache_key = self.create_cache_key(&proxy_spec, request)?;
+        let minter = self
+            .get_or_create_token_minter(
+                &cache_key,
+                request,
+                &proxy_spec,
+                &mut RequestTimeline::start(None),
+            )
+            .await?;
+        Ok(MinterInfo::from_entry(cache_key.to_string(), &minter))
+    }
+
     /// Get minter cache keys for debugging
     ///
     /// Corresponds to TypeScript: server response in main.ts (L110-113)
     pub async fn get_minter_cache_keys(&self) -> Result<Vec<String>> {
-        let cache = self.minter_cache.read().await;
-        Ok(cache.keys().cloned().collect())
+        let keys = self.minter_cache.keys().await;
+        Ok(keys.iter().map(CacheKey::to_string).collect())
+    }
+
+    /// Get request counters and recent errors
+    pub fn request_stats(&self) -> super::stats::RequestStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    /// Compliance of token requests with the `server.slo_*` targets
+    pub fn slo_report(&self) -> crate::types::SloResponse {
+        self.slo.report()
+    }
+
+    /// Skew of the system clock against Innertube, `None` before the first response
+    pub fn clock_skew(&self) -> Option<Duration> {
+        self.clock_skew.skew()
+    }
+
+    /// Lifetime of minted tokens
+    ///
+    /// With `token.correct_clock_skew`, the measured skew is taken off, so
+    /// tokens stamped with a skewed local time are refreshed in time.
+    fn token_lifetime(&self) -> Duration {
+        let lifetime = Duration::hours(self.token_ttl_hours);
+        match self.clock_skew.skew() {
+            Some(skew) if self.settings.token.correct_clock_skew => {
+                (lifetime - skew.abs()).max(Duration::minutes(1))
+            }
+            _ => lifetime,
+        }
+    }
+
+    /// Context of the latest Innertube response, `None` before the first one
+    pub fn innertube_response_context(&self) -> Option<crate::types::ResponseContext> {
+        self.innertube_provider.response_context()
+    }
+
+    /// Connections opened by the HTTP client per upstream host
+    pub fn upstream_connections(&self) -> Vec<crate::types::UpstreamConnections> {
+        self.connections.snapshot()
+    }
+
+    /// Get the usage counts of the last `days` days, `None` if analytics are disabled
+    pub fn usage(&self, days: u32) -> Option<Vec<super::usage::UsageEntry>> {
+        self.usage.as_ref().map(|usage| usage.query(days))
+    }
+
+    /// Count a request in the usage analytics, if enabled
+    fn record_usage(&self, request: &PotRequest, outcome: UsageOutcome) {
+        if let Some(usage) = &self.usage {
+            // Requests without a content binding mint a session-bound token
+            let token_type = request.token_type.unwrap_or_else(|| {
+                request
+                    .content_binding
+                    .as_deref()
+                    .map_or(PotTokenType::SessionBound, |binding| {
+                        self.determine_token_type(binding)
+                    })
+            });
+            usage.record(token_type, outcome);
+        }
+    }
+
+    /// Subscribe to token minted, cache invalidated and error events
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<super::events::ServerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Get the cached minters and their expiry, soonest first
+    pub async fn minter_expiries(&self) -> Vec<crate::types::MinterStatus> {
+        let mut minters: Vec<_> = self
+            .minter_cache
+            .entries()
+            .await
+            .into_iter()
+            .map(|(key, entry)| crate::types::MinterStatus {
+                key: key.to_string(),
+                expires_at: entry.expiry,
+            })
+            .collect();
+        minters.sort_by_key(|minter| minter.expires_at);
+        minters
+    }
+
+    /// Get mint success, failure and latency scores per proxy
+    pub fn proxy_health_scores(&self) -> Vec<super::network::ProxyScore> {
+        self.proxy_health.scores()
     }
 
     /// Set session data caches (for script mode with file cache)
     ///
     /// Corresponds to TypeScript: `setYoutubeSessionDataCaches` method
     pub async fn set_session_data_caches(&self, caches: SessionDataCaches) {
-        let mut cache = self.session_data_caches.write().await;
-        *cache = caches;
-        tracing::debug!("Set session data caches with {} entries", cache.len());
+        let len = caches.len();
+        self.session_data_caches.clear().await;
+        for (key, data) in caches {
+            self.session_data_caches
+                .insert(CacheKey::from(key), Arc::new(data))
+                .await;
+        }
+        tracing::debug!("Set session data caches with {} entries", len);
     }
 
     /// Get session data caches with optional cleanup
@@ -362,21 +1218,224 @@ where
             self.cleanup_caches().await;
         }
 
-        let cache = self.session_data_caches.read().await;
-        cache.clone()
+        self.session_data_caches
+            .entries()
+            .await
+            .into_iter()
+            .map(|(key, data)| (key.to_string(), SessionData::clone(&data)))
+            .collect()
     }
 
-    // Private helper methods...
+    /// Get a snapshot of the session data cache with optional cleanup
+    ///
+    /// Unlike [`Self::get_session_data_caches`], entries are shared with the
+    /// live cache instead of deep-copied, so persisting a large cache does not
+    /// duplicate every token in memory.
+    pub async fn session_data_snapshot(&self, cleanup: bool) -> SessionDataSnapshot {
+        if cleanup {
+            self.cleanup_caches().await;
+        }
 
-    /// Get content binding from request or generate visitor data
-    async fn get_content_binding(&self, request: &PotRequest) -> Result<String> {
+        self.session_data_caches.entries().await
+    }
+
+    /// Get unexpired cached session data for `key`
+    ///
+    /// Keys are built from a [`SessionCacheKey`], e.g.
+    /// `CacheKey::from(SessionCacheKey::new(content_binding))`.
+    /// Reads through to the persistent tier on the first miss.
+    pub async fn get_cached_session_data(&self, key: &CacheKey) -> Option<SessionData> {
+        if let Some(data) = self.lookup_session_data(key).await {
+            return Some(data);
+        }
+
+        if self.load_file_cache().await {
+            self.lookup_session_data(key).await
+        } else {
+            None
+        }
+    }
+
+    /// Cache session data under `key`, e.g. to pre-seed the cache
+    ///
+    /// The entry is written behind to the persistent tier, if any, on the
+    /// next [`Self::flush_caches`]. Once the cache holds
+    /// `token.max_cache_entries` entries, or more than `token.max_cache_bytes`
+    /// bytes when set, the entries expiring first in the shard of `key` are
+    /// evicted, see [`ShardedMap::evict_near`]; concurrent inserts may
+    /// briefly exceed the limits.
+    pub async fn cache_session_data(&self, key: CacheKey, data: &SessionData) {
+        let data = Arc::new(data.clone());
+        if self.file_cache.is_some() {
+            self.pending_writes
+                .lock()
+                .await
+                .push((key.clone(), Arc::clone(&data)));
+        }
+
+        let cache = &self.session_data_caches;
+        if cache.len() >= self.settings.token.max_cache_entries.max(1)
+            && !cache.contains_key(&key).await
+        {
+            cache.evict_near(&key, |data| data.expires_at).await;
+        }
+        cache.insert(key.clone(), data).await;
+
+        let max_bytes = self.settings.token.max_cache_bytes;
+        if max_bytes > 0 {
+            let mut evicted = 0;
+            // Keep the entry just cached
+            while cache.bytes() as u64 > max_bytes
+                && cache.len() > 1
+                && cache
+                    .evict_near(&key, |data| data.expires_at)
+                    .await
+                    .is_some()
+            {
+                evicted += 1;
+            }
+            if evicted > 0 {
+                tracing::debug!(
+                    "Evicted {} cached tokens to stay within {} bytes",
+                    evicted,
+                    max_bytes
+                );
+            }
+        }
+    }
+
+    /// Number of entries in the session data cache, expired ones included
+    pub async fn cached_session_count(&self) -> usize {
+        self.session_data_caches.len()
+    }
+
+    /// Approximate memory of the session data cache in bytes
+    pub fn cached_session_bytes(&self) -> usize {
+        self.session_data_caches.bytes()
+    }
+
+    /// Remove expired session data from the cache
+    ///
+    /// Returns the number of entries removed. Write locks are only taken
+    /// when an entry has expired. Lookups skip expired entries, so this only
+    /// reclaims memory; it runs in the background, see
+    /// [`Self::spawn_cleanup_task`].
+    pub async fn cleanup_caches(&self) -> usize {
+        let now = Utc::now();
+        let has_expired = self
+            .session_data_caches
+            .any(|_, data| data.expires_at <= now)
+            .await;
+        if !has_expired {
+            return 0;
+        }
+
+        self.session_data_caches
+            .retain(|_, data| data.expires_at > now)
+            .await
+    }
+
+    // Private helper methods...
+
+    /// Show `content_binding` in logs and events as configured
+    fn logged_binding<'a>(&self, content_binding: &'a str) -> LoggedBinding<'a> {
+        LoggedBinding::new(
+            content_binding,
+            self.settings.logging.reveal_content_bindings,
+        )
+    }
+
+    /// Get content binding from request or generate visitor data
+    async fn get_content_binding(&self, request: &PotRequest) -> Result<String> {
         match &request.content_binding {
             Some(binding) => Ok(binding.clone()),
-            None => {
-                tracing::warn!("No content binding provided, generating visitor data...");
-                self.generate_visitor_data().await
+            None => match (&request.innertube_headers, self.requested_locale(request)) {
+                (None, None) => self.shared_visitor_data().await,
+                (headers, locale) => {
+                    tracing::warn!("No content binding provided, generating visitor data...");
+                    let headers = headers.clone().unwrap_or_default();
+                    crate::session::innertube::validate_headers(&headers)?;
+                    self.generate_visitor_data_with_headers(&headers, locale.as_ref())
+                        .await
+                }
+            },
+        }
+    }
+
+    /// Locale of the request's Innertube context, `None` if it is the
+    /// configured one
+    ///
+    /// Visitor data is generated for the locale of the user's yt-dlp session,
+    /// as a mismatch can get tokens rejected.
+    fn requested_locale(&self, request: &PotRequest) -> Option<ClientLocale> {
+        let configured = ClientLocale::from_settings(&self.settings.innertube);
+        let requested = configured.with_overrides(request.innertube_context.as_ref()?);
+        (requested != configured).then_some(requested)
+    }
+
+    /// Get the visitor data reused for requests without a content binding
+    ///
+    /// Generated on first use, and again after it was discarded as stale.
+    async fn shared_visitor_data(&self) -> Result<String> {
+        let mut visitor_data = self.visitor_data.lock().await;
+        if let Some(visitor_data) = &*visitor_data {
+            return Ok(visitor_data.clone());
+        }
+        tracing::warn!("No content binding provided, generating visitor data...");
+        let generated = self.generate_visitor_data().await?;
+        *visitor_data = Some(generated.clone());
+        Ok(generated)
+    }
+
+    /// Discard the shared visitor data after a failed request that used it
+    ///
+    /// Returns whether it was discarded and the request should be retried.
+    /// At most one refresh happens per [`VISITOR_DATA_REFRESH_COOLDOWN`], so
+    /// failures unrelated to the visitor data don't regenerate it in a loop.
+    async fn refresh_stale_visitor_data(&self, request: &PotRequest) -> bool {
+        if request.content_binding.is_some()
+            || request.innertube_headers.is_some()
+            || self.requested_locale(request).is_some()
+        {
+            return false;
+        }
+        let mut visitor_data = self.visitor_data.lock().await;
+        if visitor_data.is_none() {
+            return false;
+        }
+        {
+            let mut refreshed_at = self
+                .visitor_data_refreshed_at
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if refreshed_at.is_some_and(|at| at.elapsed() < VISITOR_DATA_REFRESH_COOLDOWN) {
+                return false;
             }
+            *refreshed_at = Some(Instant::now());
         }
+        let Some(stale) = visitor_data.take() else {
+            return false;
+        };
+        drop(visitor_data);
+
+        tracing::warn!("Token generation with cached visitor data failed, regenerating it");
+        self.remove_session_data(&stale).await;
+        self.events.publish(ServerEventKind::CacheInvalidated {
+            scope: "visitor_data".to_string(),
+        });
+        true
+    }
+
+    /// Drop the session data cached for `content_binding`
+    async fn remove_session_data(&self, content_binding: &str) {
+        // Don't read the entry back in from the persistent tier later
+        self.load_file_cache().await;
+        let key = CacheKey::from(SessionCacheKey::new(content_binding));
+        self.session_data_caches.remove(&key).await;
+        self.pending_writes
+            .lock()
+            .await
+            .retain(|(pending, _)| *pending != key);
     }
 
     /// Create proxy specification from request
@@ -385,14 +1444,16 @@ where
 
         // Set proxy URL from request or environment
         if let Some(proxy) = &request.proxy {
+            super::network::validate_proxy_url(proxy)?;
             proxy_spec = proxy_spec.with_proxy(proxy);
         } else {
-            // Check environment variables like TypeScript does
-            if let Ok(proxy) = std::env::var("HTTPS_PROXY")
-                .or_else(|_| std::env::var("HTTP_PROXY"))
-                .or_else(|_| std::env::var("ALL_PROXY"))
-            {
-                proxy_spec = proxy_spec.with_proxy(proxy);
+            // Fall back to the proxy environment variables for YouTube requests
+            let proxy_env = super::network::ProxyEnvironment::from_env();
+            if let Some(proxy) = proxy_env.proxy_for(&PROXY_TARGET_URL) {
+                match super::network::validate_proxy_url(proxy) {
+                    Ok(()) => proxy_spec = proxy_spec.with_proxy(proxy),
+                    Err(e) => tracing::warn!("Ignoring proxy from environment: {}", e),
+                }
             }
         }
 
@@ -409,7 +1470,7 @@ where
     }
 
     /// Create cache key for minter cache
-    fn create_cache_key(&self, proxy_spec: &ProxySpec, request: &PotRequest) -> Result<String> {
+    fn create_cache_key(&self, proxy_spec: &ProxySpec, request: &PotRequest) -> Result<CacheKey> {
         // Extract remote host from innertube context if available
         let remote_host = request
             .innertube_context
@@ -418,68 +1479,128 @@ where
             .and_then(|client| client.get("remoteHost"))
             .and_then(|host| host.as_str());
 
-        Ok(proxy_spec.cache_key(remote_host))
+        Ok(proxy_spec.hashed_cache_key(remote_host))
     }
 
-    /// Get cached session data
-    async fn get_cached_session_data(&self, content_binding: &str) -> Option<SessionData> {
-        let cache = self.session_data_caches.read().await;
-        cache.get(content_binding).cloned()
+    /// Look up session data in memory
+    async fn lookup_session_data(&self, key: &CacheKey) -> Option<SessionData> {
+        let now = Utc::now();
+        self.session_data_caches
+            .read(key, |data| {
+                data.filter(|data| data.expires_at > now)
+                    .map(|data| SessionData::clone(data))
+            })
+            .await
     }
 
-    /// Cache session data
-    async fn cache_session_data(&self, content_binding: &str, data: &SessionData) {
-        let mut cache = self.session_data_caches.write().await;
-        cache.insert(content_binding.to_string(), data.clone());
-    }
+    /// Read the persistent tier into memory if it has not been read yet
+    ///
+    /// Returns `true` if entries may have been added to memory. Entries
+    /// already in memory take precedence.
+    async fn load_file_cache(&self) -> bool {
+        let Some(file_cache) = &self.file_cache else {
+            return false;
+        };
+        if self.file_cache_loaded.initialized() {
+            return false;
+        }
 
-    /// Clean up expired cache entries
-    async fn cleanup_caches(&self) {
-        let mut cache = self.session_data_caches.write().await;
-        let now = Utc::now();
-        cache.retain(|_, data| data.expires_at > now);
+        self.file_cache_loaded
+            .get_or_init(|| async {
+                let loaded = file_cache.load_cache().await.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load file cache: {}", e);
+                    SessionDataCaches::new()
+                });
+                tracing::debug!(
+                    "Loaded {} session data entries from file cache",
+                    loaded.len()
+                );
+
+                for (key, data) in loaded {
+                    let key = CacheKey::from(key);
+                    self.session_data_caches
+                        .insert_if(key, Arc::new(data), |cached| cached.is_none())
+                        .await;
+                }
+
+                // Reuse minters of earlier processes, skipping their
+                // integrity token requests
+                let minters = file_cache.load_minters().await;
+                for (key, minter) in minters {
+                    self.minter_cache
+                        .insert_if(key, minter, |cached| cached.is_none())
+                        .await;
+                }
+            })
+            .await;
+        true
     }
 
     /// Get or create token minter
     async fn get_or_create_token_minter(
         &self,
-        cache_key: &str,
+        cache_key: &CacheKey,
         request: &PotRequest,
         proxy_spec: &ProxySpec,
+        timeline: &mut RequestTimeline,
     ) -> Result<TokenMinterEntry> {
+        self.load_file_cache().await;
+
         // Check if we have a valid cached minter
+        if let Some(minter) = self.minter_cache.get(cache_key).await
+            && !minter.is_expired()
         {
-            let cache = self.minter_cache.read().await;
-            if let Some(minter) = cache.get(cache_key)
-                && !minter.is_expired()
-            {
-                return Ok(minter.clone());
-            }
+            return Ok(minter);
         }
 
         // Generate new minter
         tracing::info!("POT minter expired or not found, generating new one");
-        let new_minter = self.generate_token_minter(request, proxy_spec).await?;
+        let new_minter = self
+            .upstream(
+                proxy_spec.proxy_url.as_deref(),
+                self.generate_token_minter(request, proxy_spec, timeline),
+            )
+            .await?;
 
         // Cache the new minter
-        {
-            let mut cache = self.minter_cache.write().await;
-            cache.insert(cache_key.to_string(), new_minter.clone());
-        }
+        self.minter_cache
+            .insert(cache_key.clone(), new_minter.clone())
+            .await;
+        self.minters_changed.store(true, Ordering::Relaxed);
 
         Ok(new_minter)
     }
 
     /// Generate token minter using real BotGuard integration
     ///
+    /// Records a `challenge` phase for getting a solved BotGuard challenge,
+    /// refetched when the current one expired, and an `integrity_token`
+    /// phase for requesting the integrity token with it.
+    ///
     /// Corresponds to TypeScript: `generateTokenMinter` method (L318-408)
     async fn generate_token_minter(
         &self,
         _request: &PotRequest,
         _proxy_spec: &ProxySpec,
+        timeline: &mut RequestTimeline,
     ) -> Result<TokenMinterEntry> {
         tracing::info!("Generating real token minter with BotGuard integration");
 
+        let phase = timeline.begin("challenge");
+        let (expires_at, lifetime_secs) = self.current_botguard_challenge().await?;
+        timeline.record("challenge", phase);
+
+        let phase = timeline.begin("integrity_token");
+        let minter = self
+            .create_token_minter_entry(expires_at, lifetime_secs)
+            .await?;
+        timeline.record("integrity_token", phase);
+        Ok(minter)
+    }
+
+    /// Get the expiry of the solved BotGuard challenge, fetching a new one
+    /// if it has expired
+    async fn current_botguard_challenge(&self) -> Result<(chrono::DateTime<chrono::Utc>, u32)> {
         // Initialize BotGuard client if needed
         self.initialize_botguard().await?;
 
@@ -491,43 +1612,38 @@ where
         // snapshot cache is not re-validated after expiry in long-running processes.
         // See: https://github.com/jim60105/bgutil-ytdlp-pot-provider-rs/issues/87
         let now = Utc::now();
-        if expires_at < now {
-            tracing::warn!(
-                "BotGuard snapshot has expired! expires_at={}, now={}. Reinitializing BotGuard...",
-                expires_at,
-                now
-            );
+        if expires_at >= now {
+            return Ok((expires_at, lifetime_secs));
+        }
+        tracing::warn!(
+            "BotGuard snapshot has expired! expires_at={}, now={}. Reinitializing BotGuard...",
+            expires_at,
+            now
+        );
 
-            // Reinitialize BotGuard to get fresh snapshot
-            self.botguard_client.reinitialize().await.map_err(|e| {
+        // Reinitialize BotGuard to get fresh snapshot
+        self.botguard_client.reinitialize().await.map_err(|e| {
+            crate::Error::token_generation(format!(
+                "Failed to reinitialize BotGuard after expiry: {}",
+                e
+            ))
+        })?;
+
+        // Get updated expiry information after reinitialization
+        let (new_expires_at, new_lifetime_secs) =
+            self.get_botguard_expiry_as_chrono().await.map_err(|e| {
                 crate::Error::token_generation(format!(
-                    "Failed to reinitialize BotGuard after expiry: {}",
+                    "Cannot get BotGuard expiry info after reinitialization: {}",
                     e
                 ))
             })?;
 
-            // Get updated expiry information after reinitialization
-            let (new_expires_at, new_lifetime_secs) =
-                self.get_botguard_expiry_as_chrono().await.map_err(|e| {
-                    crate::Error::token_generation(format!(
-                        "Cannot get BotGuard expiry info after reinitialization: {}",
-                        e
-                    ))
-                })?;
-
-            tracing::info!(
-                "BotGuard reinitialized successfully - new expires_at: {}, lifetime: {}s",
-                new_expires_at,
-                new_lifetime_secs
-            );
-
-            return self
-                .create_token_minter_entry(new_expires_at, new_lifetime_secs)
-                .await;
-        }
-
-        self.create_token_minter_entry(expires_at, lifetime_secs)
-            .await
+        tracing::info!(
+            "BotGuard reinitialized successfully - new expires_at: {}, lifetime: {}s",
+            new_expires_at,
+            new_lifetime_secs
+        );
+        Ok((new_expires_at, new_lifetime_secs))
     }
 
     /// Get BotGuard expiry information and convert to chrono types
@@ -559,7 +1675,6 @@ where
         // Generate an integrity token using BotGuard
         // For TokenMinter, we use a specific identifier that indicates this is for integrity purposes
         let integrity_token = self
-            .botguard_client
             .generate_po_token("integrity_token_request")
             .await
             .map_err(|e| {
@@ -576,12 +1691,14 @@ where
             mint_refresh_threshold
         );
 
+        // The BotGuard runtime doesn't expose the websafe fallback token
+        // upstream receives alongside its integrity token
         Ok(TokenMinterEntry::new(
             expires_at,
             integrity_token,
             lifetime_secs,
             mint_refresh_threshold,
-            None, // No websafe fallback token for now
+            None,
         ))
     }
 
@@ -599,23 +1716,26 @@ where
 
     /// Generate POT token using BotGuard client
     pub async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+        #[cfg(feature = "chaos")]
+        if let Some(faults) = &self.faults {
+            faults.botguard().await?;
+        }
         // Create new instance on demand since botguard is not Send+Sync
         self.botguard_client.generate_po_token(identifier).await
     }
 
-    /// Mint POT token using the BotGuard client (replaces WebPoMinter)
+    /// Mint POT token using the BotGuard client
     ///
     /// Corresponds to TypeScript: `tryMintPOT` method (L410-436)
     ///
     /// This implementation matches TypeScript behavior by directly using content_binding
     /// as the identifier for token generation, without complex token type determination
     /// or forced Innertube API calls.
-    async fn mint_pot_token(
-        &self,
-        content_binding: &str,
-        _token_minter: &TokenMinterEntry, // Keep for backward compatibility
-    ) -> Result<SessionData> {
-        tracing::info!("Generating POT for {}", content_binding);
+    async fn mint_pot_token(&self, content_binding: &str) -> Result<SessionData> {
+        tracing::info!(
+            "Generating POT for {}",
+            self.logged_binding(content_binding)
+        );
 
         // Ensure BotGuard is initialized
         if !self.botguard_client.is_initialized().await {
@@ -624,25 +1744,25 @@ where
 
         // Directly use content_binding as identifier (matching TypeScript behavior)
         // This avoids forced Innertube API calls and improves robustness
-        let po_token = self
-            .botguard_client
-            .generate_po_token(content_binding)
-            .await?;
+        let po_token = self.generate_po_token(content_binding).await?;
 
-        let expires_at = Utc::now() + Duration::hours(self.token_ttl_hours);
+        let expires_at = Utc::now() + self.token_lifetime();
 
         tracing::info!("Generated POT token: {}", po_token);
+        self.events.publish(ServerEventKind::TokenMinted {
+            content_binding: self.logged_binding(content_binding).to_string(),
+            expires_at,
+        });
 
         Ok(SessionData::new(po_token, content_binding, expires_at))
     }
 
-    /// Create POT context from content binding
+    /// Create POT context from content binding, for [`Self::try_mint_pot`]
     ///
-    /// NOTE: This method is currently unused after simplifying token generation to match
-    /// TypeScript behavior. It's kept for potential future use when we need more complex
-    /// token type determination logic.
-    #[allow(dead_code)]
-    async fn create_pot_context(&self, content_binding: &str) -> Result<PotContext> {
+    /// Unused since token generation was simplified to match the TypeScript
+    /// behavior; only built with the `legacy-pot-context` feature.
+    #[cfg(any(test, feature = "legacy-pot-context"))]
+    pub async fn create_pot_context(&self, content_binding: &str) -> Result<PotContext> {
         // Analyze content_binding to determine token type
         let token_type = self.determine_token_type(content_binding);
 
@@ -678,51 +1798,33 @@ where
 
     /// Determine token type from content binding
     ///
-    /// NOTE: This method is currently unused after simplifying token generation to match
-    /// TypeScript behavior. It's kept for potential future use.
-    #[allow(dead_code)]
+    /// Token generation itself doesn't depend on the type, matching the
+    /// TypeScript behavior; it classifies requests for the usage analytics.
     fn determine_token_type(&self, content_binding: &str) -> PotTokenType {
-        if self.is_video_id_format(content_binding) {
-            PotTokenType::ContentBound
-        } else if self.is_visitor_data_format(content_binding) {
-            PotTokenType::SessionBound
-        } else {
-            // Default to cold-start for unknown formats
-            PotTokenType::ColdStart
-        }
+        PotTokenType::for_content_binding(content_binding)
     }
 
     /// Check if string looks like a YouTube video ID
-    ///
-    /// NOTE: This method is currently unused after simplifying token generation to match
-    /// TypeScript behavior. It's kept for potential future use.
-    #[allow(dead_code)]
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     fn is_video_id_format(&self, s: &str) -> bool {
-        // YouTube video IDs are typically 11 characters, alphanumeric plus - and _
-        s.len() == 11
-            && s.chars()
-                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        crate::types::internal::is_video_id_format(s)
     }
 
     /// Check if string looks like visitor data
-    ///
-    /// NOTE: This method is currently unused after simplifying token generation to match
-    /// TypeScript behavior. It's kept for potential future use.
-    #[allow(dead_code)]
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     fn is_visitor_data_format(&self, s: &str) -> bool {
-        // Visitor data is typically longer and contains specific patterns
-        s.len() > 15
-            && s.chars()
-                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        crate::types::internal::is_visitor_data_format(s)
     }
 
     /// Try to mint POT token using BotGuard integration only
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     pub async fn try_mint_pot_with_fallback(&self, context: &PotContext) -> Result<PotTokenResult> {
         // Use rustypipe-botguard only - no fallback to placeholder tokens
         self.try_mint_pot(context).await
     }
 
     /// Main POT token generation method
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     pub async fn try_mint_pot(&self, context: &PotContext) -> Result<PotTokenResult> {
         match context.token_type {
             PotTokenType::SessionBound => self.generate_session_bound_token(context).await,
@@ -732,6 +1834,7 @@ where
     }
 
     /// Generate session-bound POT token using visitor_data as identifier
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     async fn generate_session_bound_token(&self, context: &PotContext) -> Result<PotTokenResult> {
         // Ensure BotGuard is initialized
         if !self.botguard_client.is_initialized().await {
@@ -756,6 +1859,7 @@ where
     }
 
     /// Generate content-bound POT token using video_id as identifier
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     async fn generate_content_bound_token(&self, context: &PotContext) -> Result<PotTokenResult> {
         // Ensure we have video_id
         let video_id = context
@@ -783,6 +1887,7 @@ where
     }
 
     /// Generate cold-start POT token using BotGuard
+    #[cfg(any(test, feature = "legacy-pot-context"))]
     async fn generate_cold_start_token(&self, context: &PotContext) -> Result<PotTokenResult> {
         // Ensure BotGuard is initialized
         if !self.botguard_client.is_initialized().await {
@@ -795,192 +1900,1321 @@ where
             .generate_po_token(&context.visitor_data)
             .await?;
 
-        let expires_at =
-            SystemTime::now() + std::time::Duration::from_secs(self.token_ttl_hours as u64 * 3600);
+        let expires_at =
+            SystemTime::now() + std::time::Duration::from_secs(self.token_ttl_hours as u64 * 3600);
+
+        Ok(PotTokenResult::new(
+            po_token,
+            PotTokenType::ColdStart,
+            expires_at,
+        ))
+    }
+
+    /// Get diagnostic information about the session manager
+    ///
+    /// This method provides access to internal configuration for testing and diagnostics
+    pub fn get_diagnostic_info(&self) -> (String, String) {
+        (self.request_key.clone(), self.settings.server.host.clone())
+    }
+
+    /// Check that HTTP client is accessible and configured
+    pub fn has_http_client(&self) -> bool {
+        // Access the http_client field to verify it's readable
+        format!("{:?}", self.http_client).contains("Client")
+    }
+
+    /// Back the in-memory session data cache with a persistent file cache
+    ///
+    /// Lookups that miss in memory read through to the file cache once, and
+    /// new entries are written behind on [`Self::flush_caches`].
+    pub fn with_file_cache(mut self, file_cache: FileCache) -> Self {
+        self.file_cache = Some(Arc::new(file_cache));
+        self
+    }
+
+    /// Count requests per day, token type and outcome in `usage`
+    pub fn with_usage_store(mut self, usage: Arc<super::usage::UsageStore>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Report request stages to `progress` as they start and finish
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Check if the session data cache is backed by a file cache
+    pub fn has_file_cache(&self) -> bool {
+        self.file_cache.is_some()
+    }
+
+    /// Write pending session data and changed token minters to the persistent tier
+    pub async fn flush_caches(&self) -> Result<()> {
+        let Some(file_cache) = &self.file_cache else {
+            return Ok(());
+        };
+
+        let pending = std::mem::take(&mut *self.pending_writes.lock().await);
+        if !pending.is_empty() {
+            tracing::debug!(
+                "Flushing {} session data entries to file cache",
+                pending.len()
+            );
+            for written in 0..pending.len() {
+                let (key, data) = &pending[written];
+                if let Err(e) = file_cache.upsert(key.as_str(), data).await {
+                    // Retry the unwritten entries on the next flush, ahead of
+                    // the ones queued since so newer data still wins
+                    let mut queued = self.pending_writes.lock().await;
+                    let newer = std::mem::take(&mut *queued);
+                    queued.extend(pending.into_iter().skip(written));
+                    queued.extend(newer);
+                    return Err(e);
+                }
+            }
+        }
+
+        if self.minters_changed.swap(false, Ordering::Relaxed) {
+            let minters = self.minter_cache.to_map().await;
+            if let Err(e) = file_cache.save_minters(&minters).await {
+                self.minters_changed.store(true, Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawn a task flushing pending session data every `interval`
+    pub fn spawn_flush_task(
+        self: &Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        T: Send + Sync + 'static,
+        B: 'static,
+    {
+        let manager = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.flush_caches().await {
+                    tracing::warn!("Failed to flush session data cache: {}", e);
+                }
+            }
+        });
+        self.track_task(&task);
+        task
+    }
+
+    /// Apply the `cache.retention_*` limits to the persistent stores
+    ///
+    /// Trims the file cache to `cache.retention_max_size`, dropping the tokens
+    /// expiring first, and removes BotGuard snapshots in
+    /// `botguard.snapshot_dir` not written for `cache.retention_max_age_days`,
+    /// except the one in use.
+    pub async fn enforce_retention(&self) -> Result<()> {
+        let cache = &self.settings.cache;
+        if cache.retention_max_size > 0
+            && let Some(file_cache) = &self.file_cache
+        {
+            // Pending writes count toward the limit too
+            self.flush_caches().await?;
+            let dropped = file_cache
+                .enforce_max_size(cache.retention_max_size)
+                .await?;
+            if dropped > 0 {
+                tracing::info!(
+                    "Dropped {} tokens from the file cache to stay within {} bytes",
+                    dropped,
+                    cache.retention_max_size
+                );
+            }
+        }
+
+        if cache.retention_max_age_days > 0
+            && let Some(dir) = self.settings.botguard.snapshot_dir.clone()
+        {
+            let max_age = std::time::Duration::from_secs(cache.retention_max_age_days * 86400);
+            let keep = super::snapshot::resolve_snapshot_path(&self.settings);
+            tokio::task::spawn_blocking(move || {
+                super::snapshot::remove_stale_snapshots(&dir, max_age, keep.as_deref())
+            })
+            .await
+            .map_err(|e| crate::Error::cache("retention", &e.to_string()))??;
+        }
+        Ok(())
+    }
+
+    /// Spawn a task applying the retention limits every `interval`
+    pub fn spawn_retention_task(
+        self: &Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        T: Send + Sync + 'static,
+        B: 'static,
+    {
+        let manager = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.enforce_retention().await {
+                    tracing::warn!("Failed to apply retention limits: {}", e);
+                }
+            }
+        });
+        self.track_task(&task);
+        task
+    }
+
+    /// Spawn a task removing expired session data every `interval`
+    pub fn spawn_cleanup_task(
+        self: &Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        T: Send + Sync + 'static,
+        B: 'static,
+    {
+        let manager = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                let removed = manager.cleanup_caches().await;
+                if removed > 0 {
+                    tracing::debug!("Removed {} expired session data entries", removed);
+                }
+            }
+        });
+        self.track_task(&task);
+        task
+    }
+
+    /// Refresh cached tokens in the background once a cache hit finds them
+    /// within `token.refresh_ahead_fraction` of expiring
+    ///
+    /// Has no effect when the fraction is 0. Refreshes are cancelled on
+    /// [`Self::shutdown`].
+    pub fn enable_speculative_refresh(self: &Arc<Self>)
+    where
+        T: 'static,
+        B: 'static,
+    {
+        if self.settings.token.refresh_ahead_fraction <= 0.0 {
+            return;
+        }
+        let manager = Arc::downgrade(self);
+        let _ = self
+            .speculative_refresh
+            .set(SpeculativeRefresh(Box::new(move |request, key| {
+                let Some(manager) = manager.upgrade() else {
+                    return;
+                };
+                let task = tokio::spawn({
+                    let manager = manager.clone();
+                    async move { manager.refresh_session_data(request, key).await }
+                });
+                manager.track_task(&task);
+            })));
+    }
+
+    /// Cancel `task` on [`Self::shutdown`]
+    pub fn track_task<R>(&self, task: &tokio::task::JoinHandle<R>) {
+        let mut tasks = self
+            .background_tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        // Short-lived tasks such as refreshes would otherwise pile up
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task.abort_handle());
+    }
+
+    /// Shutdown the session manager and all associated resources.
+    ///
+    /// Cancels the background tasks, flushes pending session data to the
+    /// file cache, saves the usage analytics and shuts the BotGuard client
+    /// down, which writes its snapshot and cleans up the V8 isolates,
+    /// preventing the "v8::OwnedIsolate for snapshot was leaked" warning.
+    /// It should be called before the process exits, in both server and
+    /// CLI mode.
+    pub async fn shutdown(&self) {
+        tracing::debug!("Shutting down session manager");
+        let tasks = std::mem::take(
+            &mut *self
+                .background_tasks
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        for task in tasks {
+            task.abort();
+        }
+        if let Err(e) = self.flush_caches().await {
+            tracing::warn!("Failed to flush session data cache: {}", e);
+        }
+        if let Some(usage) = &self.usage
+            && let Err(e) = usage.save()
+        {
+            tracing::warn!("Failed to save usage analytics: {}", e);
+        }
+        self.botguard_client.shutdown().await;
+        tracing::debug!("Session manager shutdown complete");
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, B> crate::client::TokenProvider for SessionManagerGeneric<T, B>
+where
+    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug,
+    B: BotGuardProvider + std::fmt::Debug,
+{
+    async fn generate_pot_token(&self, request: &PotRequest) -> Result<PotResponse> {
+        SessionManagerGeneric::generate_pot_token(self, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_manager_creation() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+        assert!(manager.session_data_caches.is_empty());
+    }
+
+    #[test]
+    fn test_token_lifetime_corrects_clock_skew() {
+        let mut settings = Settings::default();
+        settings.token.correct_clock_skew = true;
+        let manager = SessionManager::new(settings);
+        assert_eq!(manager.token_lifetime(), Duration::hours(6));
+
+        // The system clock is 10 minutes ahead of YouTube's
+        let received_at = Utc::now();
+        let date = (received_at - Duration::minutes(10)).to_rfc2822();
+        manager.clock_skew.observe(&date, received_at);
+        let lifetime = manager.token_lifetime();
+        assert!(lifetime <= Duration::hours(6) - Duration::minutes(10));
+        assert!(lifetime > Duration::hours(6) - Duration::minutes(11));
+    }
+
+    #[tokio::test]
+    async fn test_session_manager_fields_accessibility() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        // Verify all fields can be accessed and used
+        assert!(manager.session_data_caches.is_empty()); // Initial should be empty
+
+        let minter_cache_size = manager.minter_cache.len();
+        assert_eq!(minter_cache_size, 0); // Initial should be empty
+
+        // Verify other fields are accessible
+        assert!(!manager.request_key.is_empty());
+        assert_eq!(manager.token_ttl_hours, 6);
+
+        // Access fields through diagnostic methods to prove they're readable
+        let (request_key, server_host) = manager.get_diagnostic_info();
+        assert!(!request_key.is_empty());
+        assert_eq!(request_key, "O43z0dpjhgX20SCx4KAo");
+        assert!(!server_host.is_empty());
+
+        // Verify http_client field is accessible
+        assert!(manager.has_http_client());
+
+        // Verify method that uses the fields works
+        let request = PotRequest::new().with_content_binding("test_field_access");
+        let result = manager.generate_pot_token(&request).await;
+        assert!(result.is_ok()); // This exercises settings and http_client internally
+    }
+
+    #[tokio::test]
+    async fn test_generate_pot_token() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        let request = PotRequest::new().with_content_binding("test_video_id");
+
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.content_binding, "test_video_id");
+        assert!(!response.is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_token_caching() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        let request = PotRequest::new().with_content_binding("cached_video");
+
+        // First call should generate new token
+        let response1 = manager.generate_pot_token(&request).await.unwrap();
+
+        // Second call should return cached token
+        let response2 = manager.generate_pot_token(&request).await.unwrap();
+
+        assert_eq!(response1.po_token, response2.po_token);
+        assert_eq!(response1.expires_at, response2.expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_pre_seeded_cache() {
+        let manager = SessionManager::new(Settings::default());
+        let key = CacheKey::new("seeded_video");
+        manager
+            .cache_session_data(
+                key.clone(),
+                &SessionData::new("seeded", "seeded_video", Utc::now() + Duration::hours(1)),
+            )
+            .await;
+        manager
+            .cache_session_data(
+                CacheKey::new("stale_video"),
+                &SessionData::new("stale", "stale_video", Utc::now() - Duration::hours(1)),
+            )
+            .await;
+
+        let cached = manager.get_cached_session_data(&key).await.unwrap();
+        assert_eq!(cached.po_token, "seeded");
+        assert!(
+            manager
+                .get_cached_session_data(&CacheKey::new("stale_video"))
+                .await
+                .is_none()
+        );
+        assert_eq!(manager.cleanup_caches().await, 1);
+
+        // Requests are answered from the pre-seeded entry
+        let request = PotRequest::new().with_content_binding("seeded_video");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.po_token, "seeded");
+    }
+
+    #[tokio::test]
+    async fn test_cache_session_data_evicts_when_full() {
+        let mut settings = Settings::default();
+        settings.token.max_cache_entries = 2;
+        let manager = SessionManager::new(settings);
+        for (binding, hours) in [("first", 2), ("soonest", 1), ("last", 3)] {
+            manager
+                .cache_session_data(
+                    CacheKey::new(binding),
+                    &SessionData::new("token", binding, Utc::now() + Duration::hours(hours)),
+                )
+                .await;
+        }
+
+        let caches = manager.get_session_data_caches(false).await;
+        assert_eq!(caches.len(), 2);
+        assert!(!caches.contains_key("soonest"));
+        assert!(caches.contains_key("last"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_session_data_evicts_over_memory_budget() {
+        let data = |binding: &str, hours| {
+            SessionData::new(
+                "a".repeat(1000),
+                binding,
+                Utc::now() + Duration::hours(hours),
+            )
+        };
+        let mut settings = Settings::default();
+        settings.token.max_cache_bytes = (data("first", 1).approx_size() * 5 / 2) as u64;
+        let manager = SessionManager::new(settings);
+        for (binding, hours) in [("first", 2), ("soonest", 1), ("last", 3)] {
+            manager
+                .cache_session_data(CacheKey::new(binding), &data(binding, hours))
+                .await;
+        }
+
+        let caches = manager.get_session_data_caches(false).await;
+        assert_eq!(caches.len(), 2);
+        assert!(!caches.contains_key("soonest"));
+        assert!(manager.cached_session_bytes() as u64 <= manager.settings.token.max_cache_bytes);
+
+        manager.invalidate_caches().await.unwrap();
+        assert_eq!(manager.cached_session_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bypass_cache() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        let request_cached = PotRequest::new().with_content_binding("bypass_test");
+
+        let request_bypass = PotRequest::new()
+            .with_content_binding("bypass_test")
+            .with_bypass_cache(true);
+
+        // First call to populate cache
+        let _response1 = manager.generate_pot_token(&request_cached).await.unwrap();
+
+        // Second call with bypass_cache should generate new token
+        let response2 = manager.generate_pot_token(&request_bypass).await.unwrap();
+        assert_eq!(response2.content_binding, "bypass_test");
+    }
+
+    #[tokio::test]
+    async fn test_token_provider() {
+        use crate::client::TokenProvider;
+
+        let manager = SessionManager::new(Settings::default());
+        let provider: &dyn TokenProvider = &manager;
+        let request = PotRequest::new().with_content_binding("provider_test");
+        let response = provider.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.content_binding, "provider_test");
+    }
+
+    #[tokio::test]
+    async fn test_token_settings_overrides() {
+        let mut settings = Settings::default();
+        settings.token.ttl_hours = 2;
+        settings.token.enable_cache = false;
+        let manager = SessionManager::new(settings);
+        assert_eq!(manager.token_lifetime(), Duration::hours(2));
+
+        let request = PotRequest::new().with_content_binding("uncached_test");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        let lifetime = response.expires_at - Utc::now();
+        assert!(
+            lifetime <= Duration::hours(2) && lifetime > Duration::hours(2) - Duration::minutes(1)
+        );
+        assert!(manager.session_data_caches.is_empty());
+
+        // Every request mints a new token
+        manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(manager.request_stats().cache_hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_single_use_tokens_are_not_cached() {
+        let manager = SessionManager::new(Settings::default());
+        let request = PotRequest::new()
+            .with_content_binding("single_use_test")
+            .with_single_use(true);
+
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert!(response.single_use);
+        assert!(manager.session_data_caches.is_empty());
+
+        let body = manager.generate_pot_response_body(&request).await.unwrap();
+        let response: PotResponse = serde_json::from_slice(&body).unwrap();
+        assert!(response.single_use);
+        assert!(manager.session_data_caches.is_empty());
+
+        // Reusable requests are cached and don't carry the flag
+        let request = PotRequest::new().with_content_binding("single_use_test");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert!(!response.single_use);
+        assert!(!manager.session_data_caches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_speculative_refresh_near_expiry() {
+        let mut settings = Settings::default();
+        settings.token.refresh_ahead_fraction = 0.5;
+        let manager = Arc::new(SessionManager::new(settings));
+        manager.enable_speculative_refresh();
+
+        let key = CacheKey::from(SessionCacheKey::new("refresh_test"));
+        let stale = SessionData::new("stale", "refresh_test", Utc::now() + Duration::minutes(1));
+        manager.cache_session_data(key.clone(), &stale).await;
+
+        // The cached token is served while the refresh runs in the background
+        let request = PotRequest::new().with_content_binding("refresh_test");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.po_token, "stale");
+
+        tokio::time::timeout(std::time::Duration::from_secs(30), async {
+            while manager.refreshing.lock().unwrap().contains(&key) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let refreshed = manager.lookup_session_data(&key).await.unwrap();
+        assert_ne!(refreshed.po_token, "stale");
+        assert!(refreshed.expires_at > stale.expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_caches() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        let request = PotRequest::new().with_content_binding("test_invalidate");
+
+        // Generate and cache a token
+        let _response = manager.generate_pot_token(&request).await.unwrap();
+
+        // Verify cache has content
+        assert!(!manager.session_data_caches.is_empty());
+
+        // Invalidate caches
+        manager.invalidate_caches().await.unwrap();
+
+        // Verify cache is empty
+        assert!(manager.session_data_caches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_data_snapshot_shares_entries() {
+        let manager = SessionManager::new(Settings::default());
+
+        let mut caches = SessionDataCaches::new();
+        caches.insert(
+            "fresh".to_string(),
+            SessionData::new("token", "fresh", Utc::now() + Duration::hours(1)),
+        );
+        caches.insert(
+            "stale".to_string(),
+            SessionData::new("token", "stale", Utc::now() - Duration::hours(1)),
+        );
+        manager.set_session_data_caches(caches).await;
+
+        let snapshot = manager.session_data_snapshot(true).await;
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].0.as_str(), "fresh");
+
+        // The snapshot holds the same allocation as the live cache
+        let cached = manager.session_data_caches.get(&snapshot[0].0).await;
+        assert!(Arc::ptr_eq(&snapshot[0].1, &cached.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_file_cache_read_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+
+        let mut caches = SessionDataCaches::new();
+        caches.insert(
+            "persisted".to_string(),
+            SessionData::new(
+                "persisted_token",
+                "persisted",
+                Utc::now() + Duration::hours(1),
+            ),
+        );
+        FileCache::new(cache_path.clone())
+            .save_cache(caches)
+            .await
+            .unwrap();
+
+        let manager =
+            SessionManager::new(Settings::default()).with_file_cache(FileCache::new(cache_path));
+        assert!(manager.session_data_caches.is_empty());
+
+        let data = manager
+            .get_cached_session_data(&CacheKey::new("persisted"))
+            .await
+            .unwrap();
+        assert_eq!(data.po_token, "persisted_token");
+    }
+
+    #[tokio::test]
+    async fn test_file_cache_write_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let manager = SessionManager::new(Settings::default())
+            .with_file_cache(FileCache::new(cache_path.clone()));
+
+        let data = SessionData::new("new_token", "minted", Utc::now() + Duration::hours(1));
+        manager
+            .cache_session_data(CacheKey::new("minted"), &data)
+            .await;
+
+        // Nothing is persisted until flushed
+        let file_cache = FileCache::new(cache_path);
+        assert!(file_cache.load_cache().await.unwrap().is_empty());
+
+        manager.flush_caches().await.unwrap();
+        let loaded = file_cache.load_cache().await.unwrap();
+        assert_eq!(loaded["minted"].po_token, "new_token");
+        assert!(manager.pending_writes.lock().await.is_empty());
+
+        // Invalidation clears the persistent tier too
+        manager.invalidate_caches().await.unwrap();
+        assert!(file_cache.load_cache().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_unwritten_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        // The journal can't be created below a regular file
+        let blocker = dir.path().join("blocker");
+        std::fs::write(&blocker, "").unwrap();
+        let manager = SessionManager::new(Settings::default())
+            .with_file_cache(FileCache::new(blocker.join("cache.json")));
+
+        let expires_at = Utc::now() + Duration::hours(1);
+        for binding in ["first", "second"] {
+            let data = SessionData::new("token", binding, expires_at);
+            manager
+                .cache_session_data(CacheKey::new(binding), &data)
+                .await;
+        }
+
+        assert!(manager.flush_caches().await.is_err());
+        let pending: Vec<String> = manager
+            .pending_writes
+            .lock()
+            .await
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .collect();
+        assert_eq!(pending, ["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let expires_at = Utc::now() + Duration::hours(1);
+        let mut caches = SessionDataCaches::new();
+        caches.insert(
+            "tenant/dQw4w9WgXcQ".to_string(),
+            SessionData::new("persisted", "dQw4w9WgXcQ", expires_at),
+        );
+        FileCache::new(cache_path.clone())
+            .save_cache(caches)
+            .await
+            .unwrap();
+
+        let manager = SessionManager::new(Settings::default())
+            .with_file_cache(FileCache::new(cache_path.clone()));
+        for key in ["dQw4w9WgXcQ", "jNQXAC9IVRw"] {
+            manager
+                .cache_session_data(
+                    CacheKey::new(key),
+                    &SessionData::new("token", key, expires_at),
+                )
+                .await;
+        }
+
+        // Entries only in the persistent tier and pending writes are purged too
+        let purged = manager
+            .purge(&PurgeRequest::content_binding("dQw4w9WgXcQ"))
+            .await
+            .unwrap();
+        assert_eq!(purged, 2);
+        manager.flush_caches().await.unwrap();
+        let loaded = FileCache::new(cache_path).load_cache().await.unwrap();
+        assert_eq!(loaded.keys().collect::<Vec<_>>(), vec!["jNQXAC9IVRw"]);
+
+        let purged = manager
+            .purge(&PurgeRequest::namespace("tenant"))
+            .await
+            .unwrap();
+        assert_eq!(purged, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_task_removes_expired_entries() {
+        let manager = Arc::new(SessionManager::new(Settings::default()));
+        for (key, expires_at) in [
+            ("fresh", Utc::now() + Duration::hours(1)),
+            ("stale", Utc::now() - Duration::hours(1)),
+        ] {
+            manager
+                .cache_session_data(
+                    CacheKey::new(key),
+                    &SessionData::new("token", key, expires_at),
+                )
+                .await;
+        }
+
+        // Requests leave expired entries to the task
+        let request = PotRequest::new().with_content_binding("fresh");
+        manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(manager.get_session_data_caches(false).await.len(), 2);
+
+        let task = manager.spawn_cleanup_task(std::time::Duration::from_secs(60));
+        tokio::time::sleep(std::time::Duration::from_secs(61)).await;
+        let caches = manager.get_session_data_caches(false).await;
+        assert_eq!(caches.keys().collect::<Vec<_>>(), vec!["fresh"]);
+
+        manager.shutdown().await;
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_and_cancels_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let usage_path = dir.path().join("usage.json");
+        let usage = Arc::new(super::super::usage::UsageStore::new(7).with_file(usage_path.clone()));
+        let manager = Arc::new(
+            SessionManager::new(Settings::default())
+                .with_file_cache(FileCache::new(cache_path.clone()))
+                .with_usage_store(usage.clone()),
+        );
+        let flush_task = manager.spawn_flush_task(std::time::Duration::from_secs(3600));
+
+        let data = SessionData::new("new_token", "minted", Utc::now() + Duration::hours(1));
+        manager
+            .cache_session_data(CacheKey::new("minted"), &data)
+            .await;
+        usage.record(PotTokenType::ContentBound, UsageOutcome::Minted);
+
+        manager.shutdown().await;
+        assert!(flush_task.await.unwrap_err().is_cancelled());
+        let loaded = FileCache::new(cache_path).load_cache().await.unwrap();
+        assert_eq!(loaded["minted"].po_token, "new_token");
+        assert!(usage_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_generate_pot_token_records_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace_path = dir.path().join("trace.json");
+        let mut settings = Settings::default();
+        settings.logging.trace_file = Some(trace_path.clone());
+        let manager = SessionManager::new(settings);
+
+        let request = PotRequest::new().with_content_binding("traced_video");
+        manager.generate_pot_token(&request).await.unwrap();
+
+        // The trace-event array is left open for further requests
+        let content = std::fs::read_to_string(&trace_path).unwrap();
+        let content = format!("{}]", content.trim_end().trim_end_matches(','));
+        let events: Vec<serde_json::Value> = serde_json::from_str(&content).unwrap();
+        let names: Vec<&str> = events.iter().filter_map(|e| e["name"].as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "request",
+                "botguard_init",
+                "visitor_data",
+                "cache_lookup",
+                "challenge",
+                "integrity_token",
+                "mint"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data() {
+        let settings = Settings::default();
+        let manager = SessionManager::new(settings);
+
+        let visitor_data = manager.generate_visitor_data().await.unwrap();
+        assert!(!visitor_data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_generate_visitor_data_with_mock() {
+        // Create a mock provider
+        #[derive(Debug)]
+        struct MockInnertubeProvider;
+
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for MockInnertubeProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                Ok("mock_visitor_data_12345".to_string())
+            }
+
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                // Mock implementation
+                Ok(crate::types::ChallengeData {
+                    interpreter_url: crate::types::TrustedResourceUrl::new("//mock.url"),
+                    interpreter_hash: "mock_hash".to_string(),
+                    program: "mock_program".to_string(),
+                    global_name: "mockGlobal".to_string(),
+                    client_experiments_state_blob: Some("mock_blob".to_string()),
+                })
+            }
+        }
+
+        let mock_provider = MockInnertubeProvider;
+        let settings = Settings::default();
+        let manager = SessionManagerGeneric::new_with_provider(settings, mock_provider);
+
+        let visitor_data = manager.generate_visitor_data().await.unwrap();
+        assert_eq!(visitor_data, "mock_visitor_data_12345");
+    }
+
+    #[tokio::test]
+    async fn test_generate_pot_token_with_mock_botguard() {
+        #[derive(Debug)]
+        struct MockInnertubeProvider;
+
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for MockInnertubeProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                Ok("mock_visitor_data".to_string())
+            }
+
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                Err(crate::Error::network("not used"))
+            }
+        }
+
+        #[derive(Debug, Default)]
+        struct MockBotGuardProvider {
+            initialized: std::sync::atomic::AtomicBool,
+            mints: std::sync::atomic::AtomicUsize,
+        }
+
+        #[async_trait::async_trait]
+        impl BotGuardProvider for MockBotGuardProvider {
+            async fn initialize(&self) -> Result<()> {
+                self.initialized
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            }
+
+            async fn is_initialized(&self) -> bool {
+                self.initialized.load(std::sync::atomic::Ordering::SeqCst)
+            }
+
+            async fn reinitialize(&self) -> Result<()> {
+                self.initialize().await
+            }
+
+            async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+                self.mints.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(format!("mock_pot_{}", identifier))
+            }
+
+            async fn get_expiry_info(&self) -> Option<(time::OffsetDateTime, u32)> {
+                Some((
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(6),
+                    6 * 3600,
+                ))
+            }
+        }
+
+        let manager = SessionManagerGeneric::new_with_providers(
+            Settings::default(),
+            MockInnertubeProvider,
+            MockBotGuardProvider::default(),
+        );
+        let mut events = manager.subscribe_events();
+
+        let request = PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.po_token, "mock_pot_dQw4w9WgXcQ");
+        assert_eq!(response.content_binding, "dQw4w9WgXcQ");
+
+        // Events show a hash of the content binding
+        let event = events.try_recv().unwrap();
+        assert_eq!(
+            event.kind,
+            ServerEventKind::TokenMinted {
+                content_binding: crate::utils::privacy::binding_hash("dQw4w9WgXcQ"),
+                expires_at: response.expires_at,
+            }
+        );
 
-        Ok(PotTokenResult::new(
-            po_token,
-            PotTokenType::ColdStart,
-            expires_at,
-        ))
+        // Served from the cache without minting again
+        let mints = manager
+            .botguard_client
+            .mints
+            .load(std::sync::atomic::Ordering::SeqCst);
+        manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(
+            manager
+                .botguard_client
+                .mints
+                .load(std::sync::atomic::Ordering::SeqCst),
+            mints
+        );
     }
 
-    /// Get diagnostic information about the session manager
-    ///
-    /// This method provides access to internal configuration for testing and diagnostics
-    pub fn get_diagnostic_info(&self) -> (String, String) {
-        (self.request_key.clone(), self.settings.server.host.clone())
-    }
+    #[tokio::test]
+    async fn test_cancelled_requests() {
+        /// Never answers, like Innertube hanging
+        #[derive(Debug)]
+        struct HangingInnertubeProvider;
 
-    /// Check that HTTP client is accessible and configured
-    pub fn has_http_client(&self) -> bool {
-        // Access the http_client field to verify it's readable
-        format!("{:?}", self.http_client).contains("Client")
-    }
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for HangingInnertubeProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                std::future::pending().await
+            }
 
-    /// Shutdown the session manager and all associated resources.
-    ///
-    /// This method ensures proper cleanup of the BotGuard client and V8 isolates,
-    /// preventing the "v8::OwnedIsolate for snapshot was leaked" warning.
-    /// It should be called before the process exits, especially in CLI mode.
-    pub async fn shutdown(&self) {
-        tracing::debug!("Shutting down session manager");
-        self.botguard_client.shutdown().await;
-        tracing::debug!("Session manager shutdown complete");
-    }
-}
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                Err(crate::Error::network("not used"))
+            }
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        /// Cancels the request while minting, as if the client disconnected
+        #[derive(Debug, Default)]
+        struct CancellingBotGuardProvider {
+            cancel: CancellationToken,
+        }
 
-    #[tokio::test]
-    async fn test_session_manager_creation() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
-        assert!(manager.session_data_caches.read().await.is_empty());
-    }
+        #[async_trait::async_trait]
+        impl BotGuardProvider for CancellingBotGuardProvider {
+            async fn initialize(&self) -> Result<()> {
+                Ok(())
+            }
 
-    #[tokio::test]
-    async fn test_session_manager_fields_accessibility() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+            async fn is_initialized(&self) -> bool {
+                true
+            }
 
-        // Verify all fields can be accessed and used
-        assert!(manager.session_data_caches.read().await.len() == 0); // Initial should be empty
+            async fn reinitialize(&self) -> Result<()> {
+                Ok(())
+            }
 
-        let minter_cache_size = manager.minter_cache.read().await.len();
-        assert_eq!(minter_cache_size, 0); // Initial should be empty
+            async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+                // Not when the minter mints its integrity token
+                if identifier == "dQw4w9WgXcQ" {
+                    self.cancel.cancel();
+                }
+                tokio::task::yield_now().await;
+                Ok(format!("mock_pot_{}", identifier))
+            }
 
-        // Verify other fields are accessible
-        assert!(!manager.request_key.is_empty());
-        assert_eq!(manager.token_ttl_hours, 6);
+            async fn get_expiry_info(&self) -> Option<(time::OffsetDateTime, u32)> {
+                Some((
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(6),
+                    6 * 3600,
+                ))
+            }
+        }
 
-        // Access fields through diagnostic methods to prove they're readable
-        let (request_key, server_host) = manager.get_diagnostic_info();
-        assert!(!request_key.is_empty());
-        assert_eq!(request_key, "O43z0dpjhgX20SCx4KAo");
-        assert!(!server_host.is_empty());
+        let manager = SessionManagerGeneric::new_with_providers(
+            Settings::default(),
+            HangingInnertubeProvider,
+            CancellingBotGuardProvider::default(),
+        );
 
-        // Verify http_client field is accessible
-        assert!(manager.has_http_client());
+        // Waiting on Innertube gives up once cancelled, without counting an error
+        let cancel = CancellationToken::new();
+        let request = PotRequest::new();
+        let (result, ()) = tokio::join!(
+            manager.generate_pot_response_body_cancellable(&request, &cancel),
+            async {
+                tokio::task::yield_now().await;
+                cancel.cancel();
+            }
+        );
+        assert!(matches!(result, Err(crate::Error::Cancelled { .. })));
+        assert_eq!(manager.request_stats().errors_total, 0);
+
+        // Cancelled while minting, the token is still cached
+        let request = PotRequest::new().with_content_binding("dQw4w9WgXcQ");
+        let cancel = manager.botguard_client.cancel.clone();
+        manager
+            .generate_pot_response_body_cancellable(&request, &cancel)
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .get_cached_session_data(&CacheKey::new("dQw4w9WgXcQ"))
+                .await
+                .is_some()
+        );
 
-        // Verify method that uses the fields works
-        let request = PotRequest::new().with_content_binding("test_field_access");
-        let result = manager.generate_pot_token(&request).await;
-        assert!(result.is_ok()); // This exercises settings and http_client internally
+        // Unless minting for cancelled requests is aborted
+        let mut settings = Settings::default();
+        settings.token.cancelled_mint = "abort".to_string();
+        let manager = SessionManagerGeneric::new_with_providers(
+            settings,
+            HangingInnertubeProvider,
+            CancellingBotGuardProvider::default(),
+        );
+        let cancel = manager.botguard_client.cancel.clone();
+        let result = manager
+            .generate_pot_response_body_cancellable(&request, &cancel)
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::Cancelled { ref operation }) if operation == "mint"
+        ));
+        assert!(
+            manager
+                .get_cached_session_data(&CacheKey::new("dQw4w9WgXcQ"))
+                .await
+                .is_none()
+        );
     }
 
     #[tokio::test]
-    async fn test_generate_pot_token() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+    async fn test_stale_visitor_data_is_refreshed() {
+        #[derive(Debug, Default)]
+        struct CountingInnertubeProvider {
+            generated: std::sync::atomic::AtomicUsize,
+        }
 
-        let request = PotRequest::new().with_content_binding("test_video_id");
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for CountingInnertubeProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                let n = self
+                    .generated
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(format!("CgtWaXNpdG9yRGF0YV{}", n))
+            }
 
-        let response = manager.generate_pot_token(&request).await.unwrap();
-        assert_eq!(response.content_binding, "test_video_id");
-        assert!(!response.is_expired());
-    }
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                Err(crate::Error::network("not used"))
+            }
+        }
 
-    #[tokio::test]
-    async fn test_token_caching() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+        /// Rejects tokens for the first visitor data only
+        #[derive(Debug, Default)]
+        struct RejectingBotGuardProvider;
 
-        let request = PotRequest::new().with_content_binding("cached_video");
+        #[async_trait::async_trait]
+        impl BotGuardProvider for RejectingBotGuardProvider {
+            async fn initialize(&self) -> Result<()> {
+                Ok(())
+            }
 
-        // First call should generate new token
-        let response1 = manager.generate_pot_token(&request).await.unwrap();
+            async fn is_initialized(&self) -> bool {
+                true
+            }
 
-        // Second call should return cached token
-        let response2 = manager.generate_pot_token(&request).await.unwrap();
+            async fn reinitialize(&self) -> Result<()> {
+                Ok(())
+            }
 
-        assert_eq!(response1.po_token, response2.po_token);
-        assert_eq!(response1.expires_at, response2.expires_at);
-    }
+            async fn generate_po_token(&self, identifier: &str) -> Result<String> {
+                if identifier.ends_with("V0") {
+                    return Err(crate::Error::token_generation("stale visitor data"));
+                }
+                Ok(format!("pot_{}", identifier))
+            }
 
-    #[tokio::test]
-    async fn test_bypass_cache() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+            async fn get_expiry_info(&self) -> Option<(time::OffsetDateTime, u32)> {
+                Some((
+                    time::OffsetDateTime::now_utc() + time::Duration::hours(6),
+                    6 * 3600,
+                ))
+            }
+        }
 
-        let request_cached = PotRequest::new().with_content_binding("bypass_test");
+        let manager = SessionManagerGeneric::new_with_providers(
+            Settings::default(),
+            CountingInnertubeProvider::default(),
+            RejectingBotGuardProvider,
+        );
+        let generated = || {
+            manager
+                .innertube_provider
+                .generated
+                .load(std::sync::atomic::Ordering::SeqCst)
+        };
 
-        let request_bypass = PotRequest::new()
-            .with_content_binding("bypass_test")
-            .with_bypass_cache(true);
+        // The failed mint regenerates the visitor data and retries once
+        let request = PotRequest::new();
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.content_binding, "CgtWaXNpdG9yRGF0YV1");
+        assert_eq!(
+            response.visitor_data.as_deref(),
+            Some("CgtWaXNpdG9yRGF0YV1")
+        );
+        assert_eq!(generated(), 2);
 
-        // First call to populate cache
-        let _response1 = manager.generate_pot_token(&request_cached).await.unwrap();
+        // The visitor data is reused and its token served from the cache
+        let cached = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(cached.po_token, response.po_token);
+        assert_eq!(cached.visitor_data, response.visitor_data);
+        assert_eq!(generated(), 2);
 
-        // Second call with bypass_cache should generate new token
-        let response2 = manager.generate_pot_token(&request_bypass).await.unwrap();
-        assert_eq!(response2.content_binding, "bypass_test");
+        // It stays bound to the token minted again for it by the client
+        let request_with_binding = PotRequest::new()
+            .with_content_binding("CgtWaXNpdG9yRGF0YV1")
+            .with_bypass_cache(true);
+        let refreshed = manager
+            .generate_pot_token(&request_with_binding)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.visitor_data, response.visitor_data);
+        let supplied = manager
+            .generate_pot_token(&PotRequest::new().with_content_binding("CgtWaXNpdG9yRGF0YVx"))
+            .await
+            .unwrap();
+        assert_eq!(supplied.visitor_data, None);
+
+        // A client reporting a rejection gets new visitor data
+        manager.invalidate_visitor_data().await.unwrap();
+        let response = manager.generate_pot_token(&request).await.unwrap();
+        assert_eq!(response.content_binding, "CgtWaXNpdG9yRGF0YV2");
+        assert_eq!(generated(), 3);
     }
 
     #[tokio::test]
-    async fn test_invalidate_caches() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+    async fn test_get_challenge_sends_request_headers() {
+        #[derive(Debug, Default)]
+        struct HeaderRecordingProvider {
+            headers: std::sync::Mutex<Vec<HashMap<String, String>>>,
+        }
 
-        let request = PotRequest::new().with_content_binding("test_invalidate");
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for HeaderRecordingProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                unreachable!("only the headers variant is called")
+            }
 
-        // Generate and cache a token
-        let _response = manager.generate_pot_token(&request).await.unwrap();
+            async fn generate_visitor_data_with_headers(
+                &self,
+                headers: &HashMap<String, String>,
+                _locale: Option<&ClientLocale>,
+            ) -> Result<String> {
+                self.headers.lock().unwrap().push(headers.clone());
+                Ok("visitor_data_with_headers".to_string())
+            }
 
-        // Verify cache has content
-        assert!(!manager.session_data_caches.read().await.is_empty());
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                unreachable!("only the headers variant is called")
+            }
 
-        // Invalidate caches
-        manager.invalidate_caches().await.unwrap();
+            async fn get_challenge_with_headers(
+                &self,
+                context: &crate::types::InnertubeContext,
+                headers: &HashMap<String, String>,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                assert_eq!(context.client.remote_host.as_deref(), Some("203.0.113.7"));
+                self.headers.lock().unwrap().push(headers.clone());
+                Ok(crate::types::ChallengeData {
+                    interpreter_url: crate::types::TrustedResourceUrl::new("//example.com/vm.js"),
+                    interpreter_hash: "hash".to_string(),
+                    program: "program".to_string(),
+                    global_name: "globalName".to_string(),
+                    client_experiments_state_blob: None,
+                })
+            }
+        }
 
-        // Verify cache is empty
-        assert!(manager.session_data_caches.read().await.is_empty());
+        let manager = SessionManagerGeneric::new_with_provider(
+            Settings::default(),
+            HeaderRecordingProvider::default(),
+        );
+        let headers = HashMap::from([("Accept-Language".to_string(), "de-DE".to_string())]);
+        let request = PotRequest::new()
+            .with_innertube_headers(headers.clone())
+            .with_innertube_context(serde_json::json!({
+                "client": {"remoteHost": "203.0.113.7"}
+            }));
+
+        manager
+            .generate_visitor_data_with_headers(&headers, None)
+            .await
+            .unwrap();
+        manager.get_challenge(&request).await.unwrap();
+        let sent = manager.innertube_provider.headers.lock().unwrap().clone();
+        assert_eq!(sent, [headers.clone(), headers]);
     }
 
     #[tokio::test]
-    async fn test_generate_visitor_data() {
-        let settings = Settings::default();
-        let manager = SessionManager::new(settings);
+    async fn test_rate_limit_starts_cooldown() {
+        #[derive(Debug, Default)]
+        struct RateLimitedProvider {
+            calls: std::sync::atomic::AtomicUsize,
+        }
 
-        let visitor_data = manager.generate_visitor_data().await.unwrap();
-        assert!(!visitor_data.is_empty());
+        #[async_trait::async_trait]
+        impl crate::session::innertube::InnertubeProvider for RateLimitedProvider {
+            async fn generate_visitor_data(&self) -> Result<String> {
+                self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(crate::Error::rate_limit("429", Some(120)))
+            }
+
+            async fn get_challenge(
+                &self,
+                _context: &crate::types::InnertubeContext,
+            ) -> crate::Result<crate::types::ChallengeData> {
+                Err(crate::Error::rate_limit("429", None))
+            }
+        }
+
+        let manager = SessionManagerGeneric::new_with_provider(
+            Settings::default(),
+            RateLimitedProvider::default(),
+        );
+
+        let first = manager.generate_visitor_data().await;
+        assert!(matches!(first, Err(crate::Error::RateLimit { .. })));
+
+        // The cooldown answers without calling Innertube again
+        let second = manager.generate_visitor_data().await;
+        match second {
+            Err(crate::Error::RateLimit {
+                retry_after: Some(secs),
+                ..
+            }) => assert!((119..=120).contains(&secs)),
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+        assert_eq!(
+            manager
+                .innertube_provider
+                .calls
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
     }
 
     #[tokio::test]
-    async fn test_generate_visitor_data_with_mock() {
-        // Create a mock provider
+    async fn test_rate_limit_cooldown_covers_challenges() {
         #[derive(Debug)]
-        struct MockInnertubeProvider;
+        struct RetryForeverProvider;
 
         #[async_trait::async_trait]
-        impl crate::session::innertube::InnertubeProvider for MockInnertubeProvider {
+        impl crate::session::innertube::InnertubeProvider for RetryForeverProvider {
             async fn generate_visitor_data(&self) -> Result<String> {
-                Ok("mock_visitor_data_12345".to_string())
+                Err(crate::Error::rate_limit("429", Some(u64::MAX)))
             }
 
             async fn get_challenge(
                 &self,
                 _context: &crate::types::InnertubeContext,
             ) -> crate::Result<crate::types::ChallengeData> {
-                // Mock implementation
-                Ok(crate::types::ChallengeData {
-                    interpreter_url: crate::types::TrustedResourceUrl::new("//mock.url"),
-                    interpreter_hash: "mock_hash".to_string(),
-                    program: "mock_program".to_string(),
-                    global_name: "mockGlobal".to_string(),
-                    client_experiments_state_blob: Some("mock_blob".to_string()),
-                })
+                panic!("challenge requested during the cooldown")
             }
         }
 
-        let mock_provider = MockInnertubeProvider;
-        let settings = Settings::default();
-        let manager = SessionManagerGeneric::new_with_provider(settings, mock_provider);
+        let manager =
+            SessionManagerGeneric::new_with_provider(Settings::default(), RetryForeverProvider);
 
-        let visitor_data = manager.generate_visitor_data().await.unwrap();
-        assert_eq!(visitor_data, "mock_visitor_data_12345");
+        // A huge Retry-After starts a capped cooldown instead of panicking
+        let first = manager.generate_visitor_data().await;
+        assert!(matches!(first, Err(crate::Error::RateLimit { .. })));
+
+        let challenge = manager.get_challenge(&PotRequest::new()).await;
+        match challenge {
+            Err(crate::Error::RateLimit {
+                retry_after: Some(secs),
+                ..
+            }) => assert!(secs <= crate::session::network::MAX_COOLDOWN.as_secs()),
+            other => panic!("expected rate limit, got {:?}", other),
+        }
     }
 
     #[tokio::test]
@@ -1001,6 +3235,70 @@ mod tests {
         assert!(!cache_keys.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_export_and_import_caches() {
+        let source = SessionManager::new(Settings::default());
+        let expires_at = Utc::now() + Duration::hours(1);
+        source
+            .cache_session_data(
+                CacheKey::new("video"),
+                &SessionData::new("token", "video", expires_at),
+            )
+            .await;
+        source
+            .minter_cache
+            .insert(
+                CacheKey::new("minter"),
+                TokenMinterEntry::new(expires_at, "it", 3600, 300, None),
+            )
+            .await;
+
+        // Survives a round trip through JSON
+        let dump = source.export_caches().await;
+        let dump: CacheDump = serde_json::from_str(&serde_json::to_string(&dump).unwrap()).unwrap();
+        assert_eq!(dump.session_data.len(), 1);
+        assert_eq!(dump.minters.len(), 1);
+
+        let target = SessionManager::new(Settings::default());
+        let imported = target.import_caches(dump.clone()).await;
+        assert_eq!(
+            imported,
+            CacheImportResponse {
+                session_data: 1,
+                minters: 1
+            }
+        );
+        let data = target
+            .get_cached_session_data(&CacheKey::new("video"))
+            .await
+            .unwrap();
+        assert_eq!(data.po_token, "token");
+        assert_eq!(target.get_minter_cache_keys().await.unwrap(), ["minter"]);
+
+        // Entries already cached aren't replaced by ones expiring no later
+        let imported = target.import_caches(dump).await;
+        assert_eq!(imported.session_data + imported.minters, 0);
+    }
+
+    #[tokio::test]
+    async fn test_minters_persisted_in_file_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let manager = SessionManager::new(Settings::default())
+            .with_file_cache(FileCache::new(cache_path.clone()));
+        let request = PotRequest::new().with_content_binding("persisted_minter");
+        manager.generate_pot_token(&request).await.unwrap();
+        manager.flush_caches().await.unwrap();
+        let keys = manager.get_minter_cache_keys().await.unwrap();
+        manager.shutdown().await;
+
+        // A later process reuses the minter instead of creating one
+        let manager =
+            SessionManager::new(Settings::default()).with_file_cache(FileCache::new(cache_path));
+        assert!(manager.load_file_cache().await);
+        assert_eq!(manager.get_minter_cache_keys().await.unwrap(), keys);
+    }
+
     #[tokio::test]
     async fn test_proxy_spec_creation() {
         let settings = Settings::default();
@@ -1077,6 +3375,18 @@ mod tests {
         assert_eq!(cache_keys.len(), cache_keys_after.len());
     }
 
+    #[tokio::test]
+    async fn test_invalid_request_proxy_rejected() {
+        let manager = SessionManager::new(Settings::default());
+        let request = PotRequest::new()
+            .with_content_binding("test_invalid_proxy")
+            .with_proxy("sock5://proxy:1080");
+
+        let err = manager.create_proxy_spec(&request).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Proxy { .. }));
+        assert!(err.to_string().contains("did you mean socks5://proxy:1080"));
+    }
+
     #[tokio::test]
     async fn test_environment_proxy_detection() {
         use std::env;
@@ -1120,6 +3430,26 @@ mod tests {
         assert!(response.is_ok());
     }
 
+    #[test]
+    fn test_requested_locale() {
+        let manager = SessionManager::new(Settings::default());
+        let request =
+            |context: serde_json::Value| PotRequest::new().with_innertube_context(context);
+
+        assert!(manager.requested_locale(&PotRequest::new()).is_none());
+        let configured = request(serde_json::json!({"client": {"hl": "en", "gl": "US"}}));
+        assert!(manager.requested_locale(&configured).is_none());
+
+        let regional = request(serde_json::json!({"client": {"gl": "JP"}}));
+        assert_eq!(
+            manager.requested_locale(&regional),
+            Some(ClientLocale {
+                hl: "en".to_string(),
+                gl: "JP".to_string()
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_pot_token_type_detection() {
         let settings = Settings::default();
@@ -1326,12 +3656,19 @@ mod tests {
 // - i64 (Send + Sync)
 // - Arc<InnertubeClient> (Send + Sync)
 // - BotGuardClient (Send + Sync - explicit implementation above)
-unsafe impl<T> Send for SessionManagerGeneric<T> where
-    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug + Send + Sync
+// - Option<Arc<TraceRecorder>> (Send + Sync)
+// - Option<Arc<FileCache>> (Send + Sync)
+// - OnceCell<()> and Mutex<SessionDataSnapshot> (Send + Sync)
+unsafe impl<T, B> Send for SessionManagerGeneric<T, B>
+where
+    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug + Send + Sync,
+    B: BotGuardProvider,
 {
 }
 
-unsafe impl<T> Sync for SessionManagerGeneric<T> where
-    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug + Send + Sync
+unsafe impl<T, B> Sync for SessionManagerGeneric<T, B>
+where
+    T: crate::session::innertube::InnertubeProvider + std::fmt::Debug + Send + Sync,
+    B: BotGuardProvider,
 {
 }
@@ -5,11 +5,31 @@
 //! integration, Innertube API communication, and network handling.
 
 pub mod botguard;
+pub mod builder;
+pub mod cache_key;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod clock;
+pub mod connections;
+pub mod events;
+#[cfg(feature = "fake-tokens")]
+pub mod fake_botguard;
 pub mod innertube;
 pub mod manager;
 pub mod network;
+pub mod recording;
+pub mod sharded;
+pub mod slo;
+pub mod snapshot;
+pub mod stats;
+pub mod usage;
 
-pub use botguard::BotGuardClient;
+pub use botguard::{BotGuardClient, BotGuardProvider, DefaultBotGuardProvider};
+pub use builder::SessionManagerBuilder;
+pub use cache_key::{CacheKey, CacheKeyMap, MinterCacheKey, SessionCacheKey};
+pub use events::{ServerEvent, ServerEventKind};
 pub use innertube::{InnertubeClient, InnertubeProvider};
-pub use manager::{SessionManager, SessionManagerGeneric};
-pub use network::{NetworkManager, ProxySpec, RequestOptions};
+pub use manager::{CancelledMint, SessionManager, SessionManagerGeneric};
+pub use network::{NetworkManager, ProxyHealth, ProxyScore, ProxySpec, RequestOptions};
+pub use sharded::ShardedMap;
+pub use usage::{UsageEntry, UsageOutcome, UsageStore};
@@ -0,0 +1,184 @@
+//! Record and replay of Innertube interactions
+//!
+//! With `network.innertube_record` set, every Innertube request and its
+//! response are appended to a JSON lines file, with credentials and
+//! identifiers in the request headers redacted. With
+//! `network.innertube_replay` set, Innertube isn't contacted and the recorded
+//! responses are served back instead, so a "works for me" token rejection can
+//! be reproduced from the recording attached to a bug report.
+
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// One Innertube request and its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// When the response was received
+    pub recorded_at: DateTime<Utc>,
+    /// Endpoint below the Innertube base URL, e.g. `browse`
+    pub endpoint: String,
+    /// Request headers, sensitive values redacted
+    pub request_headers: BTreeMap<String, String>,
+    /// Request body
+    pub request_body: serde_json::Value,
+    /// Response received
+    pub response: RecordedResponse,
+}
+
+/// Response of an Innertube request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// `Retry-After` header, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<String>,
+    /// `Date` header, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// Response body
+    pub body: String,
+}
+
+/// Appends Innertube interactions to a JSON lines file
+#[derive(Debug)]
+pub struct InnertubeRecorder {
+    /// Recording file path
+    path: PathBuf,
+    /// Serializes appends so lines don't interleave
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl InnertubeRecorder {
+    /// Create a recorder appending to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Append `interaction` to the recording, logging failures
+    pub async fn record(&self, interaction: &Interaction) {
+        if let Err(e) = self.append(interaction).await {
+            tracing::warn!(
+                "Failed to record Innertube interaction to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    async fn append(&self, interaction: &Interaction) -> Result<()> {
+        let mut line = serde_json::to_vec(interaction)?;
+        line.push(b'\n');
+        let _guard = self.write_lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Serves the responses of a recording instead of contacting Innertube
+///
+/// Responses are served per endpoint in recorded order; once the recording
+/// of an endpoint is exhausted, its last response is repeated.
+#[derive(Debug, Default)]
+pub struct InnertubeReplayer {
+    /// Responses not yet served per endpoint, oldest first
+    responses: std::sync::Mutex<HashMap<String, VecDeque<RecordedResponse>>>,
+}
+
+impl InnertubeReplayer {
+    /// Load the recording at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid =
+            |message: String| crate::Error::config(String::from("innertube_replay"), message);
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            invalid(format!(
+                "Failed to read Innertube recording {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let mut responses: HashMap<String, VecDeque<RecordedResponse>> = HashMap::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let interaction: Interaction = serde_json::from_str(line).map_err(|e| {
+                invalid(format!(
+                    "Invalid Innertube recording {} line {}: {}",
+                    path.display(),
+                    number + 1,
+                    e
+                ))
+            })?;
+            responses
+                .entry(interaction.endpoint)
+                .or_default()
+                .push_back(interaction.response);
+        }
+        Ok(Self {
+            responses: std::sync::Mutex::new(responses),
+        })
+    }
+
+    /// Get the next recorded response of `endpoint`
+    pub fn next(&self, endpoint: &str) -> Option<RecordedResponse> {
+        let mut responses = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+        let queue = responses.get_mut(endpoint)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interaction(endpoint: &str, body: &str) -> Interaction {
+        Interaction {
+            recorded_at: Utc::now(),
+            endpoint: endpoint.to_string(),
+            request_headers: BTreeMap::new(),
+            request_body: serde_json::json!({}),
+            response: RecordedResponse {
+                status: 200,
+                retry_after: None,
+                date: None,
+                body: body.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_interactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("innertube.jsonl");
+        let recorder = InnertubeRecorder::new(&path);
+        recorder.record(&interaction("browse", "first")).await;
+        recorder.record(&interaction("att/get", "challenge")).await;
+        recorder.record(&interaction("browse", "second")).await;
+
+        let replayer = InnertubeReplayer::load(&path).unwrap();
+        assert_eq!(replayer.next("browse").unwrap().body, "first");
+        assert_eq!(replayer.next("browse").unwrap().body, "second");
+        // The last response is repeated
+        assert_eq!(replayer.next("browse").unwrap().body, "second");
+        assert_eq!(replayer.next("att/get").unwrap().body, "challenge");
+        assert!(replayer.next("player").is_none());
+    }
+}
@@ -0,0 +1,428 @@
+//! Sharded concurrent map for the session data and minter caches
+//!
+//! A single `RwLock` around a cache serializes every writer, and under
+//! high concurrency readers queue behind them too. [`ShardedMap`] splits the
+//! entries over [`SHARD_COUNT`] independently locked maps, picked by the
+//! precomputed [`CacheKey`] hash, so requests for different keys rarely
+//! contend. The number of entries is kept in a counter, so it is read
+//! without locking. Operations over every entry lock the shards one at a
+//! time and are not atomic across shards. A map created with
+//! [`ShardedMap::with_weigher`] also keeps the approximate memory of its
+//! values.
+//!
+//! Run `cargo bench --bench cache_contention` to compare it against a single
+//! lock.
+
+use super::{CacheKey, CacheKeyMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::RwLock;
+
+/// Number of shards of a [`ShardedMap`]
+pub const SHARD_COUNT: usize = 32;
+
+/// Map keyed by [`CacheKey`] split over independently locked shards
+#[derive(Debug)]
+pub struct ShardedMap<V> {
+    shards: Box<[RwLock<CacheKeyMap<V>>]>,
+    /// Approximate memory of a value in bytes
+    weigher: fn(&V) -> usize,
+    /// Sum of the weights of the values
+    bytes: AtomicUsize,
+    /// Number of entries
+    len: AtomicUsize,
+}
+
+impl<V> Default for ShardedMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> ShardedMap<V> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self::with_weigher(|_| 0)
+    }
+
+    /// Create an empty map keeping the sum of `weigher` over its values
+    ///
+    /// The weight of a value must not change while it is in the map, other
+    /// than through [`Self::update_all`].
+    pub fn with_weigher(weigher: fn(&V) -> usize) -> Self {
+        Self {
+            shards: (0..SHARD_COUNT)
+                .map(|_| RwLock::new(CacheKeyMap::default()))
+                .collect(),
+            weigher,
+            bytes: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sum of the weights of the values, see [`Self::with_weigher`]
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Account for `added` and `removed` values
+    fn reweigh(&self, added: Option<&V>, removed: Option<&V>) {
+        if let Some(added) = added {
+            self.bytes
+                .fetch_add((self.weigher)(added), Ordering::Relaxed);
+        }
+        if let Some(removed) = removed {
+            self.bytes
+                .fetch_sub((self.weigher)(removed), Ordering::Relaxed);
+        }
+    }
+
+    /// Account for `added` and `removed` entries, weights included
+    fn count(&self, added: Option<&V>, removed: Option<&V>) {
+        if added.is_some() {
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+        if removed.is_some() {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.reweigh(added, removed);
+    }
+
+    /// Shard holding `key`
+    fn shard(&self, key: &CacheKey) -> &RwLock<CacheKeyMap<V>> {
+        // The low bits pick the bucket and the top bits the control byte
+        // within a shard, so shards are picked by the middle bits; otherwise
+        // all keys of a shard would share their bucket bits
+        let index = (key.hash_value() >> 32) as usize % self.shards.len();
+        &self.shards[index]
+    }
+
+    /// Run `f` on the value of `key`, holding only its shard's read lock
+    pub async fn read<R>(&self, key: &CacheKey, f: impl FnOnce(Option<&V>) -> R) -> R {
+        f(self.shard(key).read().await.get(key))
+    }
+
+    /// Whether `key` is present
+    pub async fn contains_key(&self, key: &CacheKey) -> bool {
+        self.shard(key).read().await.contains_key(key)
+    }
+
+    /// Insert `value` under `key`, returning the value replaced
+    pub async fn insert(&self, key: CacheKey, value: V) -> Option<V> {
+        let mut shard = self.shard(&key).write().await;
+        self.count(Some(&value), None);
+        let replaced = shard.insert(key, value);
+        self.count(None, replaced.as_ref());
+        replaced
+    }
+
+    /// Insert `value` under `key` if `condition` holds for the current value
+    ///
+    /// The check and the insert happen under the same lock. Returns whether
+    /// the value was inserted.
+    pub async fn insert_if(
+        &self,
+        key: CacheKey,
+        value: V,
+        condition: impl FnOnce(Option<&V>) -> bool,
+    ) -> bool {
+        let mut shard = self.shard(&key).write().await;
+        if !condition(shard.get(&key)) {
+            return false;
+        }
+        self.count(Some(&value), None);
+        let replaced = shard.insert(key, value);
+        self.count(None, replaced.as_ref());
+        true
+    }
+
+    /// Remove `key`, returning its value
+    pub async fn remove(&self, key: &CacheKey) -> Option<V> {
+        let removed = self.shard(key).write().await.remove(key);
+        self.count(None, removed.as_ref());
+        removed
+    }
+
+    /// Remove the entry with the smallest `key_fn` near `key`, returning its key
+    ///
+    /// Only the shard of `key` is searched, under its lock alone, so evicting
+    /// on every insert doesn't scan the whole map; the whole map is searched
+    /// when that shard holds no other entry, or `key` itself has the smallest
+    /// `key_fn` in it. `key` is never removed.
+    pub async fn evict_near<K: Ord>(
+        &self,
+        key: &CacheKey,
+        key_fn: impl Fn(&V) -> K,
+    ) -> Option<CacheKey> {
+        {
+            let mut shard = self.shard(key).write().await;
+            if let Some((evicted, _)) = shard.iter().min_by_key(|(_, value)| key_fn(value))
+                && evicted != key
+            {
+                let evicted = evicted.clone();
+                let removed = shard.remove(&evicted);
+                self.count(None, removed.as_ref());
+                return Some(evicted);
+            }
+        }
+
+        let evicted = self
+            .min_key_where(key_fn, |candidate| candidate != key)
+            .await?;
+        self.remove(&evicted).await.map(|_| evicted)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Whether the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every entry
+    pub async fn clear(&self) {
+        for shard in &self.shards {
+            for (_, value) in shard.write().await.drain() {
+                self.count(None, Some(&value));
+            }
+        }
+    }
+
+    /// Whether any entry matches `predicate`, taking read locks only
+    pub async fn any(&self, mut predicate: impl FnMut(&CacheKey, &V) -> bool) -> bool {
+        for shard in &self.shards {
+            if shard
+                .read()
+                .await
+                .iter()
+                .any(|(key, value)| predicate(key, value))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Keep the entries matching `keep`, returning the number removed
+    pub async fn retain(&self, mut keep: impl FnMut(&CacheKey, &mut V) -> bool) -> usize {
+        let mut removed = 0;
+        for shard in &self.shards {
+            let mut shard = shard.write().await;
+            let before = shard.len();
+            shard.retain(|key, value| {
+                let kept = keep(key, value);
+                if !kept {
+                    self.count(None, Some(value));
+                }
+                kept
+            });
+            removed += before - shard.len();
+        }
+        removed
+    }
+
+    /// Update every value in place
+    pub async fn update_all(&self, mut update: impl FnMut(&CacheKey, &mut V)) {
+        for shard in &self.shards {
+            for (key, value) in shard.write().await.iter_mut() {
+                self.reweigh(None, Some(value));
+                update(key, value);
+                self.reweigh(Some(value), None);
+            }
+        }
+    }
+
+    /// Key of the entry with the smallest `key_fn`
+    pub async fn min_key_by<K: Ord>(&self, key_fn: impl Fn(&V) -> K) -> Option<CacheKey> {
+        self.min_key_where(key_fn, |_| true).await
+    }
+
+    /// Key of the entry with the smallest `key_fn` among keys matching `filter`
+    async fn min_key_where<K: Ord>(
+        &self,
+        key_fn: impl Fn(&V) -> K,
+        filter: impl Fn(&CacheKey) -> bool,
+    ) -> Option<CacheKey> {
+        let mut min: Option<(K, CacheKey)> = None;
+        for shard in &self.shards {
+            let shard = shard.read().await;
+            if let Some((key, value)) = shard
+                .iter()
+                .filter(|(key, _)| filter(key))
+                .min_by_key(|(_, value)| key_fn(value))
+            {
+                let candidate = key_fn(value);
+                if min
+                    .as_ref()
+                    .is_none_or(|(smallest, _)| candidate < *smallest)
+                {
+                    min = Some((candidate, key.clone()));
+                }
+            }
+        }
+        min.map(|(_, key)| key)
+    }
+
+    /// Keys of every entry
+    pub async fn keys(&self) -> Vec<CacheKey> {
+        let mut keys = Vec::new();
+        for shard in &self.shards {
+            keys.extend(shard.read().await.keys().cloned());
+        }
+        keys
+    }
+}
+
+impl<V: Clone> ShardedMap<V> {
+    /// Clone of the value of `key`
+    pub async fn get(&self, key: &CacheKey) -> Option<V> {
+        self.read(key, |value| value.cloned()).await
+    }
+
+    /// Clone of every entry
+    pub async fn entries(&self) -> Vec<(CacheKey, V)> {
+        let mut entries = Vec::new();
+        for shard in &self.shards {
+            entries.extend(
+                shard
+                    .read()
+                    .await
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        entries
+    }
+
+    /// Clone of every entry as a single map
+    pub async fn to_map(&self) -> CacheKeyMap<V> {
+        self.entries().await.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sharded_map() {
+        let map = ShardedMap::new();
+        assert!(map.is_empty());
+        for i in 0..100 {
+            map.insert(CacheKey::new(format!("video_{}", i)), i).await;
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&CacheKey::new("video_42")).await, Some(42));
+        assert!(map.contains_key(&CacheKey::new("video_99")).await);
+        assert_eq!(
+            map.min_key_by(|value| *value).await,
+            Some(CacheKey::new("video_0"))
+        );
+
+        // Entries are spread over the shards
+        let used = map
+            .shards
+            .iter()
+            .filter(|shard| !shard.try_read().unwrap().is_empty())
+            .count();
+        assert!(used > SHARD_COUNT / 2, "{} shards used", used);
+
+        assert_eq!(map.retain(|_, value| *value % 2 == 0).await, 50);
+        assert!(!map.any(|_, value| *value % 2 == 1).await);
+        map.update_all(|_, value| *value += 1).await;
+        assert_eq!(map.remove(&CacheKey::new("video_42")).await, Some(43));
+        assert_eq!(map.keys().await.len(), 49);
+
+        map.clear().await;
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_evict_near() {
+        let map = ShardedMap::new();
+        let keys: Vec<_> = (0..200)
+            .map(|i| CacheKey::new(format!("video_{}", i)))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key.clone(), i).await;
+        }
+
+        // The smallest entry of the key's shard goes, not the smallest overall
+        let near = &keys[150];
+        let shard_min = map
+            .shard(near)
+            .read()
+            .await
+            .iter()
+            .filter(|(key, _)| *key != near)
+            .min_by_key(|(_, value)| **value)
+            .map(|(key, _)| key.clone());
+        let shard_min = shard_min.expect("200 keys leave no shard with a single entry");
+        assert_eq!(map.evict_near(near, |value| *value).await, Some(shard_min));
+        assert_eq!(map.len(), 199);
+
+        // The key itself is kept when it is the only entry
+        let mut smallest = ShardedMap::new();
+        smallest.insert(CacheKey::new("only"), 0).await;
+        assert_eq!(
+            smallest.evict_near(&CacheKey::new("only"), |v| *v).await,
+            None
+        );
+
+        // When the key is the smallest of its shard, the smallest other entry
+        // of the whole map goes instead
+        let lowest = &keys[0];
+        map.insert(lowest.clone(), 0).await;
+        let mut next = None;
+        for key in &keys[1..] {
+            if map.contains_key(key).await {
+                next = Some(key.clone());
+                break;
+            }
+        }
+        assert_eq!(map.evict_near(lowest, |value| *value).await, next);
+        assert!(map.contains_key(lowest).await);
+
+        // A shard without other entries falls back to the whole map
+        smallest = ShardedMap::new();
+        smallest.insert(CacheKey::new("a"), 1).await;
+        assert_eq!(
+            smallest.evict_near(&CacheKey::new("absent"), |v| *v).await,
+            Some(CacheKey::new("a"))
+        );
+        assert!(smallest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_weighed_map() {
+        let map = ShardedMap::with_weigher(String::len);
+        map.insert(CacheKey::new("a"), "x".repeat(10)).await;
+        map.insert(CacheKey::new("b"), "x".repeat(20)).await;
+        assert_eq!(map.bytes(), 30);
+
+        // Replaced, conditionally inserted and removed values are accounted
+        map.insert(CacheKey::new("a"), "x".repeat(5)).await;
+        assert_eq!(map.bytes(), 25);
+        assert!(
+            !map.insert_if(CacheKey::new("b"), String::new(), |v| v.is_none())
+                .await
+        );
+        assert!(
+            map.insert_if(CacheKey::new("c"), "x".repeat(7), |v| v.is_none())
+                .await
+        );
+        assert_eq!(map.bytes(), 32);
+        map.remove(&CacheKey::new("b")).await;
+        assert_eq!(map.bytes(), 12);
+
+        map.update_all(|_, value| value.push('x')).await;
+        assert_eq!(map.bytes(), 14);
+        assert_eq!(map.retain(|_, value| value.len() > 6).await, 1);
+        assert_eq!(map.bytes(), 8);
+        map.clear().await;
+        assert_eq!(map.bytes(), 0);
+    }
+}
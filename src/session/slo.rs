@@ -0,0 +1,188 @@
+//! Service level objective tracking
+//!
+//! Counts token requests per second over a rolling window and compares their
+//! success rate and latency with the `server.slo_*` targets for `GET /slo`,
+//! so operators of shared instances can alert when the provider degrades
+//! before downloads start failing.
+
+use crate::{config::Settings, types::SloResponse};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token requests answered within one second
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Seconds since the tracker was created
+    second: u64,
+    /// Requests answered
+    requests: u64,
+    /// Requests that failed
+    failed: u64,
+    /// Requests that succeeded after the latency target
+    slow: u64,
+}
+
+/// Rolling window of token request outcomes
+#[derive(Debug)]
+pub struct SloTracker {
+    /// Fraction of requests that must succeed within the latency target
+    target: f64,
+    /// Latency target of a request
+    latency_target: Duration,
+    /// Length of the window in seconds
+    window_secs: u64,
+    /// Reference point of the bucket seconds
+    started: Instant,
+    /// Buckets of the window with requests, oldest first
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl SloTracker {
+    /// Create a tracker of `target` over `window`, with `latency_target`
+    pub fn new(target: f64, latency_target: Duration, window: Duration) -> Self {
+        Self {
+            target,
+            latency_target,
+            window_secs: window.as_secs().max(1),
+            started: Instant::now(),
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Create a tracker with the `server.slo_*` targets
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::new(
+            settings.server.slo_target,
+            Duration::from_millis(settings.server.slo_latency_ms),
+            Duration::from_secs(settings.server.slo_window),
+        )
+    }
+
+    /// Count a token request answered after `latency`
+    pub fn record(&self, success: bool, latency: Duration) {
+        self.record_at(Instant::now(), success, latency);
+    }
+
+    /// Report compliance over the window ending now
+    pub fn report(&self) -> SloResponse {
+        self.report_at(Instant::now())
+    }
+
+    fn record_at(&self, now: Instant, success: bool, latency: Duration) {
+        let second = now.saturating_duration_since(self.started).as_secs();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        self.prune(&mut buckets, second);
+        if buckets.back().is_none_or(|bucket| bucket.second != second) {
+            buckets.push_back(Bucket {
+                second,
+                requests: 0,
+                failed: 0,
+                slow: 0,
+            });
+        }
+        let bucket = buckets.back_mut().expect("bucket was just pushed");
+        bucket.requests += 1;
+        if !success {
+            bucket.failed += 1;
+        } else if latency > self.latency_target {
+            bucket.slow += 1;
+        }
+    }
+
+    fn report_at(&self, now: Instant) -> SloResponse {
+        let second = now.saturating_duration_since(self.started).as_secs();
+        let (requests, failed, slow) = {
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            self.prune(&mut buckets, second);
+            buckets
+                .iter()
+                .fold((0, 0, 0), |(requests, failed, slow), bucket| {
+                    (
+                        requests + bucket.requests,
+                        failed + bucket.failed,
+                        slow + bucket.slow,
+                    )
+                })
+        };
+
+        let succeeded = requests - failed;
+        let ratio = |part: u64, whole: u64| {
+            if whole == 0 {
+                1.0
+            } else {
+                part as f64 / whole as f64
+            }
+        };
+        // Requests failing or answered late may make up this many
+        let budget = requests as f64 - self.target * requests as f64;
+        let bad = (failed + slow) as f64;
+        let error_budget_remaining = if bad == 0.0 {
+            1.0
+        } else if budget <= 0.0 {
+            0.0
+        } else {
+            (1.0 - bad / budget).max(0.0)
+        };
+
+        SloResponse {
+            window_secs: self.window_secs,
+            target: self.target,
+            latency_target_ms: self.latency_target.as_millis() as u64,
+            requests,
+            success_rate: ratio(succeeded, requests),
+            latency_compliance: ratio(succeeded - slow, succeeded),
+            error_budget_remaining,
+            compliant: requests as f64 - bad >= self.target * requests as f64,
+        }
+    }
+
+    /// Drop the buckets that fell out of the window ending at `second`
+    fn prune(&self, buckets: &mut VecDeque<Bucket>, second: u64) {
+        while buckets
+            .front()
+            .is_some_and(|bucket| bucket.second + self.window_secs <= second)
+        {
+            buckets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slo_report() {
+        let tracker = SloTracker::new(0.9, Duration::from_secs(1), Duration::from_secs(60));
+        let at = |secs| tracker.started + Duration::from_secs(secs);
+
+        // Without requests everything is within the objective
+        let report = tracker.report_at(at(0));
+        assert_eq!(report.requests, 0);
+        assert_eq!(report.success_rate, 1.0);
+        assert!(report.compliant);
+
+        for _ in 0..18 {
+            tracker.record_at(at(0), true, Duration::from_millis(100));
+        }
+        tracker.record_at(at(10), false, Duration::from_millis(100));
+        tracker.record_at(at(20), true, Duration::from_secs(2));
+        let report = tracker.report_at(at(30));
+        assert_eq!(report.requests, 20);
+        assert_eq!(report.success_rate, 0.95);
+        assert_eq!(report.latency_compliance, 18.0 / 19.0);
+        // 2 bad requests spend the whole budget of 10% of 20
+        assert!(report.error_budget_remaining.abs() < 1e-9);
+        assert!(report.compliant);
+
+        tracker.record_at(at(30), false, Duration::from_millis(100));
+        assert!(!tracker.report_at(at(30)).compliant);
+
+        // The healthy requests leave the window first
+        let report = tracker.report_at(at(65));
+        assert_eq!(report.requests, 3);
+        assert!(!report.compliant);
+        assert_eq!(tracker.report_at(at(100)).requests, 0);
+    }
+}
@@ -0,0 +1,111 @@
+//! Request statistics for the admin API
+//!
+//! Counts POT requests, cache hits and failures, and keeps the most recent
+//! errors so operators can watch a running server via `GET /stats`.
+
+use crate::types::RecentError;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of recent errors kept for `GET /stats`
+const MAX_RECENT_ERRORS: usize = 20;
+
+/// Counters of POT requests handled by a session manager
+#[derive(Debug, Default)]
+pub struct RequestStats {
+    /// POT requests received
+    requests: AtomicU64,
+    /// Requests answered from the session data cache
+    cache_hits: AtomicU64,
+    /// Requests that failed
+    errors: AtomicU64,
+    /// Most recent errors, oldest first
+    recent_errors: Mutex<VecDeque<RecentError>>,
+}
+
+/// Point-in-time copy of [`RequestStats`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestStatsSnapshot {
+    /// POT requests received
+    pub requests_total: u64,
+    /// Requests answered from the session data cache
+    pub cache_hits: u64,
+    /// Requests that failed
+    pub errors_total: u64,
+    /// Most recent errors, newest first
+    pub recent_errors: Vec<RecentError>,
+}
+
+impl RequestStats {
+    /// Count a received request
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a request answered from the cache
+    pub fn record_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a failed request and remember its error
+    pub fn record_error(&self, error: &crate::Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+
+        let mut recent = self.recent_errors.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == MAX_RECENT_ERRORS {
+            recent.pop_front();
+        }
+        recent.push_back(RecentError {
+            timestamp: chrono::Utc::now(),
+            category: error.category().to_string(),
+            message: error.to_string(),
+        });
+    }
+
+    /// Take a snapshot of the counters
+    pub fn snapshot(&self) -> RequestStatsSnapshot {
+        let recent = self.recent_errors.lock().unwrap_or_else(|e| e.into_inner());
+        RequestStatsSnapshot {
+            requests_total: self.requests.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            errors_total: self.errors.load(Ordering::Relaxed),
+            recent_errors: recent.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_stats_counts() {
+        let stats = RequestStats::default();
+        stats.record_request();
+        stats.record_request();
+        stats.record_cache_hit();
+        stats.record_error(&crate::Error::network("first"));
+        stats.record_error(&crate::Error::cache("write", "second"));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests_total, 2);
+        assert_eq!(snapshot.cache_hits, 1);
+        assert_eq!(snapshot.errors_total, 2);
+        assert_eq!(snapshot.recent_errors[0].category, "cache");
+        assert_eq!(snapshot.recent_errors[1].category, "network");
+    }
+
+    #[test]
+    fn test_recent_errors_are_bounded() {
+        let stats = RequestStats::default();
+        for i in 0..MAX_RECENT_ERRORS + 5 {
+            stats.record_error(&crate::Error::network(format!("error {}", i)));
+        }
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.errors_total, (MAX_RECENT_ERRORS + 5) as u64);
+        assert_eq!(snapshot.recent_errors.len(), MAX_RECENT_ERRORS);
+        assert!(snapshot.recent_errors[0].message.contains("error 24"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Token usage analytics
+//!
+//! Counts POT requests per day, token type and outcome so operators of a
+//! shared instance can tell whether it is being abused, via
+//! `GET /stats/usage`. Counts are optionally persisted to a JSON file and
+//! survive restarts.
+
+use crate::types::PotTokenType;
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Outcome of a POT request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageOutcome {
+    /// A new token was minted
+    Minted,
+    /// The token was answered from the cache
+    Cached,
+    /// The request failed
+    Error,
+}
+
+/// Number of requests of one token type and outcome on one day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UsageEntry {
+    /// Day of the requests, in UTC
+    pub date: NaiveDate,
+    /// Requested token type
+    pub token_type: PotTokenType,
+    /// Outcome of the requests
+    pub outcome: UsageOutcome,
+    /// Number of requests
+    pub count: u64,
+}
+
+/// Key of a usage counter
+type UsageKey = (NaiveDate, PotTokenType, UsageOutcome);
+
+/// Daily usage counters, pruned after the retention period
+#[derive(Debug)]
+pub struct UsageStore {
+    /// Request counts
+    counts: Mutex<BTreeMap<UsageKey, u64>>,
+    /// Days of counts kept
+    retention_days: u32,
+    /// File the counts are persisted to
+    path: Option<PathBuf>,
+}
+
+impl UsageStore {
+    /// Create an in-memory store keeping `retention_days` days of counts
+    pub fn new(retention_days: u32) -> Self {
+        Self {
+            counts: Mutex::new(BTreeMap::new()),
+            retention_days: retention_days.max(1),
+            path: None,
+        }
+    }
+
+    /// Persist the counts to `path`, loading the counts already saved there
+    ///
+    /// A missing or unreadable file starts empty.
+    pub fn with_file(mut self, path: PathBuf) -> Self {
+        match std::fs::read(&path) {
+            Ok(content) => match serde_json::from_slice::<Vec<UsageEntry>>(&content) {
+                Ok(entries) => {
+                    let counts = self.counts.get_mut().unwrap_or_else(|e| e.into_inner());
+                    for entry in entries {
+                        *counts
+                            .entry((entry.date, entry.token_type, entry.outcome))
+                            .or_default() += entry.count;
+                    }
+                }
+                Err(e) => tracing::warn!("Ignoring invalid usage file {:?}: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to read usage file {:?}: {}", path, e),
+        }
+        self.path = Some(path);
+        self
+    }
+
+    /// Count a request
+    pub fn record(&self, token_type: PotTokenType, outcome: UsageOutcome) {
+        let today = Utc::now().date_naive();
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        *counts.entry((today, token_type, outcome)).or_default() += 1;
+    }
+
+    /// Get the counts of the last `days` days including today, newest first
+    pub fn query(&self, days: u32) -> Vec<UsageEntry> {
+        let since = Self::first_day(days);
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts
+            .iter()
+            .rev()
+            .take_while(|((date, _, _), _)| *date >= since)
+            .map(|(&(date, token_type, outcome), &count)| UsageEntry {
+                date,
+                token_type,
+                outcome,
+                count,
+            })
+            .collect()
+    }
+
+    /// Drop counts older than the retention period and save them to the file
+    pub fn save(&self) -> crate::Result<()> {
+        let since = Self::first_day(self.retention_days);
+        self.counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(date, _, _), _| *date >= since);
+        let entries = self.query(self.retention_days);
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(&entries)?)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Spawn a task saving the counts every `interval`
+    pub fn spawn_save_task(
+        self: &Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                if let Err(e) = store.save() {
+                    tracing::warn!("Failed to save usage analytics: {}", e);
+                }
+            }
+        })
+    }
+
+    /// First day of a period of `days` days ending today
+    fn first_day(days: u32) -> NaiveDate {
+        Utc::now().date_naive() - Duration::days(i64::from(days.max(1)) - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_counts() {
+        let store = UsageStore::new(30);
+        store.record(PotTokenType::ContentBound, UsageOutcome::Minted);
+        store.record(PotTokenType::ContentBound, UsageOutcome::Cached);
+        store.record(PotTokenType::ContentBound, UsageOutcome::Cached);
+        store.record(PotTokenType::SessionBound, UsageOutcome::Error);
+
+        let entries = store.query(7);
+        assert_eq!(entries.len(), 3);
+        let cached = entries
+            .iter()
+            .find(|entry| entry.outcome == UsageOutcome::Cached)
+            .unwrap();
+        assert_eq!(cached.token_type, PotTokenType::ContentBound);
+        assert_eq!(cached.count, 2);
+        assert_eq!(cached.date, Utc::now().date_naive());
+    }
+
+    #[test]
+    fn test_usage_persistence_and_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.json");
+        let old = Utc::now().date_naive() - Duration::days(10);
+        std::fs::write(
+            &path,
+            serde_json::to_vec(&[UsageEntry {
+                date: old,
+                token_type: PotTokenType::SessionBound,
+                outcome: UsageOutcome::Minted,
+                count: 4,
+            }])
+            .unwrap(),
+        )
+        .unwrap();
+
+        let store = UsageStore::new(30).with_file(path.clone());
+        store.record(PotTokenType::ContentBound, UsageOutcome::Minted);
+        assert_eq!(store.query(7).len(), 1);
+        assert_eq!(store.query(30).len(), 2);
+        store.save().unwrap();
+
+        // Counts past the retention period are dropped when saving
+        let store = UsageStore::new(7).with_file(path.clone());
+        assert_eq!(store.query(30).len(), 2);
+        store.save().unwrap();
+        let store = UsageStore::new(30).with_file(path);
+        assert_eq!(store.query(30).len(), 1);
+    }
+}
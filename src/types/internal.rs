@@ -2,10 +2,17 @@
 //!
 //! Defines the internal data types used for session management and BotGuard processing.
 
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
 use std::time::SystemTime;
 
+/// Bytes of a serialized `/get_pot` response besides its strings: keys,
+/// quotes and the expiry timestamp
+const RESPONSE_BODY_OVERHEAD: usize = 128;
+
 /// YouTube session data for caching
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionData {
@@ -15,6 +22,20 @@ pub struct SessionData {
     pub content_binding: String,
     /// Expiration timestamp
     pub expires_at: DateTime<Utc>,
+    /// Visitor data generated by the provider that the token is bound to,
+    /// `None` when the caller supplied the content binding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visitor_data: Option<String>,
+    /// Whether this is the websafe fallback token of the minter, returned
+    /// because minting a token for the content binding failed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fallback: bool,
+    /// Context of the latest Innertube response when the token was minted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_context: Option<ResponseContext>,
+    /// Serialized `/get_pot` response, shared by clones once initialized
+    #[serde(skip)]
+    response_body: OnceLock<Bytes>,
 }
 
 impl SessionData {
@@ -28,9 +49,74 @@ impl SessionData {
             po_token: po_token.into(),
             content_binding: content_binding.into(),
             expires_at,
+            visitor_data: None,
+            fallback: false,
+            response_context: None,
+            response_body: OnceLock::new(),
         }
     }
 
+    /// Set fallback flag
+    pub fn with_fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Set the visitor data generated by the provider for the token
+    pub fn with_visitor_data(mut self, visitor_data: impl Into<String>) -> Self {
+        self.visitor_data = Some(visitor_data.into());
+        self
+    }
+
+    /// Set the Innertube response context the token was minted under
+    pub fn with_response_context(mut self, response_context: ResponseContext) -> Self {
+        self.response_context = Some(response_context);
+        self
+    }
+
+    /// Get the JSON body of the [`crate::types::PotResponse`] for this data
+    ///
+    /// Serialized on first use only, so cache hits are served without
+    /// serializing the response again. Not updated when the fields are
+    /// changed afterwards.
+    pub fn response_body(&self) -> Bytes {
+        self.response_body
+            .get_or_init(|| {
+                let response = crate::types::PotResponse::new(
+                    self.po_token.as_str(),
+                    self.content_binding.as_str(),
+                    self.expires_at,
+                )
+                .with_fallback(self.fallback);
+                let response = match &self.visitor_data {
+                    Some(visitor_data) => response.with_visitor_data(visitor_data.as_str()),
+                    None => response,
+                };
+                serde_json::to_vec(&response)
+                    .expect("PotResponse serializes to JSON")
+                    .into()
+            })
+            .clone()
+    }
+
+    /// Approximate memory held by this data in bytes
+    ///
+    /// Counts the strings, the response context and the serialized response
+    /// body, which repeats the token, content binding and visitor data.
+    /// Depends on the fields only, so it is the same before and after the
+    /// body is serialized.
+    pub fn approx_size(&self) -> usize {
+        let visitor_data = self.visitor_data.as_ref().map_or(0, String::len);
+        let strings = self.po_token.len() + self.content_binding.len() + visitor_data;
+        std::mem::size_of::<Self>()
+            + strings * 2
+            + RESPONSE_BODY_OVERHEAD
+            + self
+                .response_context
+                .as_ref()
+                .map_or(0, ResponseContext::approx_size)
+    }
+
     /// Check if session data has expired
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
@@ -42,18 +128,164 @@ impl SessionData {
     }
 }
 
+/// Debugging metadata from the `responseContext` of an Innertube response
+///
+/// Tells which experiments and frontend served a session, which helps with
+/// tokens accepted in one region and rejected in another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseContext {
+    /// Endpoint that answered, e.g. `visitor_id`
+    pub endpoint: String,
+    /// When the response was received
+    pub received_at: DateTime<Utc>,
+    /// Address Innertube saw the request coming from, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_host: Option<String>,
+    /// Experiment IDs the response was served under
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub experiment_ids: Vec<String>,
+    /// Tracking parameters by service, e.g. `GFEEDBACK` or `CSI`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub service_tracking_params: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ResponseContext {
+    /// Approximate memory held by this context in bytes
+    fn approx_size(&self) -> usize {
+        let params: usize = self
+            .service_tracking_params
+            .iter()
+            .map(|(service, params)| {
+                service.len()
+                    + params
+                        .iter()
+                        .map(|(key, value)| key.len() + value.len())
+                        .sum::<usize>()
+            })
+            .sum();
+        self.endpoint.len()
+            + self.remote_host.as_ref().map_or(0, String::len)
+            + self.experiment_ids.iter().map(String::len).sum::<usize>()
+            + params
+    }
+
+    /// Parse the `responseContext` of `response` received from `endpoint`
+    ///
+    /// Returns `None` if the response has no response context.
+    pub fn parse(endpoint: &str, response: &serde_json::Value) -> Option<Self> {
+        let context = response.get("responseContext")?.as_object()?;
+        let mut service_tracking_params: BTreeMap<String, BTreeMap<String, String>> =
+            BTreeMap::new();
+        for service in context
+            .get("serviceTrackingParams")
+            .and_then(|services| services.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = service.get("service").and_then(|name| name.as_str()) else {
+                continue;
+            };
+            let params: BTreeMap<String, String> = service
+                .get("params")
+                .and_then(|params| params.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|param| {
+                    Some((
+                        param.get("key")?.as_str()?.to_string(),
+                        param.get("value")?.as_str()?.to_string(),
+                    ))
+                })
+                .collect();
+            service_tracking_params
+                .entry(name.to_string())
+                .or_default()
+                .extend(params);
+        }
+
+        let experiment_ids = service_tracking_params
+            .get("GFEEDBACK")
+            .and_then(|params| params.get("e"))
+            .map(|ids| {
+                ids.split(',')
+                    .filter(|id| !id.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let remote_host = service_tracking_params
+            .values()
+            .find_map(|params| params.get("remote_host"))
+            .cloned();
+
+        Some(Self {
+            endpoint: endpoint.to_string(),
+            received_at: Utc::now(),
+            remote_host,
+            experiment_ids,
+            service_tracking_params,
+        })
+    }
+}
+
 /// POT token types corresponding to different contexts
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default,
+)]
 pub enum PotTokenType {
     /// Session-bound POT token using visitor_data as identifier
     #[default]
+    #[serde(alias = "session_bound")]
     SessionBound,
     /// Content-bound POT token using video_id as identifier
+    #[serde(alias = "content_bound")]
     ContentBound,
     /// Cold-start POT token using placeholder implementation
+    #[serde(alias = "cold_start")]
     ColdStart,
 }
 
+impl PotTokenType {
+    /// Infer the token type from the format of a content binding
+    ///
+    /// Video IDs are content-bound, visitor data is session-bound, and
+    /// anything else is treated as cold-start.
+    pub fn for_content_binding(content_binding: &str) -> Self {
+        if is_video_id_format(content_binding) {
+            Self::ContentBound
+        } else if is_visitor_data_format(content_binding) {
+            Self::SessionBound
+        } else {
+            Self::ColdStart
+        }
+    }
+
+    /// Get the snake_case name of the token type, e.g. `content_bound`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SessionBound => "session_bound",
+            Self::ContentBound => "content_bound",
+            Self::ColdStart => "cold_start",
+        }
+    }
+}
+
+/// Check if a string looks like a YouTube video ID
+pub(crate) fn is_video_id_format(s: &str) -> bool {
+    // YouTube video IDs are typically 11 characters, alphanumeric plus - and _
+    s.len() == 11
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Check if a string looks like visitor data
+pub(crate) fn is_visitor_data_format(s: &str) -> bool {
+    // Visitor data is typically longer and contains specific patterns
+    s.len() > 15
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
 /// Context for POT token generation
 #[derive(Debug, Clone)]
 pub struct PotContext {
@@ -114,22 +346,32 @@ impl PotTokenResult {
 }
 
 /// BotGuard challenge data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Shared by challenges sent in POT requests and those fetched from
+/// Innertube. Serialized in the camelCase wire format used by yt-dlp and
+/// Innertube; the snake_case spelling is accepted as an alias.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ChallengeData {
     /// Interpreter URL information
+    #[serde(alias = "interpreter_url")]
     pub interpreter_url: TrustedResourceUrl,
     /// Interpreter hash
+    #[serde(alias = "interpreter_hash")]
     pub interpreter_hash: String,
     /// Challenge program
     pub program: String,
     /// Global VM name
+    #[serde(alias = "global_name")]
     pub global_name: String,
     /// Client experiments state blob
+    #[serde(alias = "client_experiments_state_blob")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub client_experiments_state_blob: Option<String>,
 }
 
 /// Trusted resource URL wrapper
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TrustedResourceUrl {
     /// The wrapped trusted resource URL value (Google's private field naming convention)
     #[serde(rename = "privateDoNotAccessOrElseTrustedResourceUrlWrappedValue")]
@@ -199,7 +441,7 @@ impl TrustedScript {
 }
 
 /// Token minter cache entry matching TypeScript TokenMinter
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenMinterEntry {
     /// Expiry time
     pub expiry: DateTime<Utc>,
@@ -314,6 +556,33 @@ mod tests {
         assert!(!session.is_expired());
     }
 
+    #[test]
+    fn test_session_data_response_body() {
+        let session = SessionData::new("token", "binding", Utc::now() + Duration::hours(6));
+        let body = session.response_body();
+        let response: crate::types::PotResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.po_token, "token");
+        assert_eq!(response.content_binding, "binding");
+        assert_eq!(response.expires_at, session.expires_at);
+
+        // Clones share the serialized body
+        assert_eq!(session.clone().response_body().as_ptr(), body.as_ptr());
+    }
+
+    #[test]
+    fn test_session_data_approx_size() {
+        let session = SessionData::new("a".repeat(160), "dQw4w9WgXcQ", Utc::now())
+            .with_visitor_data("CgtDZjBSbE5uZDJlQSij6bbFBjIKCgJVUxIEGgAgYA%3D%3D")
+            .with_fallback(true);
+        let size = session.approx_size();
+        let strings = 160 + 11 + 48;
+        assert!(
+            size >= std::mem::size_of::<SessionData>() + strings + session.response_body().len()
+        );
+        // Serializing the body doesn't change the estimate
+        assert_eq!(session.approx_size(), size);
+    }
+
     #[test]
     fn test_session_data_expiration() {
         let past_time = Utc::now() - Duration::hours(1);
@@ -323,6 +592,34 @@ mod tests {
         assert!(session.time_until_expiry().num_seconds() < 0);
     }
 
+    #[test]
+    fn test_response_context_parse() {
+        let response = serde_json::json!({
+            "responseContext": {
+                "visitorData": "CgtDZjBSbE5uZDJlQQ%3D%3D",
+                "serviceTrackingParams": [
+                    {"service": "GFEEDBACK", "params": [
+                        {"key": "logged_in", "value": "0"},
+                        {"key": "e", "value": "23804281,23946420,24004644"}
+                    ]},
+                    {"service": "CSI", "params": [
+                        {"key": "c", "value": "WEB"},
+                        {"key": "remote_host", "value": "203.0.113.7"}
+                    ]},
+                    {"params": [{"key": "ignored", "value": "x"}]}
+                ]
+            }
+        });
+        let context = ResponseContext::parse("visitor_id", &response).unwrap();
+        assert_eq!(context.endpoint, "visitor_id");
+        assert_eq!(context.remote_host.as_deref(), Some("203.0.113.7"));
+        assert_eq!(context.experiment_ids, ["23804281", "23946420", "24004644"]);
+        assert_eq!(context.service_tracking_params.len(), 2);
+        assert_eq!(context.service_tracking_params["CSI"]["c"], "WEB");
+
+        assert!(ResponseContext::parse("browse", &serde_json::json!({})).is_none());
+    }
+
     #[test]
     fn test_trusted_resource_url() {
         let url = TrustedResourceUrl::new("https://example.com");
@@ -434,6 +731,10 @@ mod tests {
         let cold_start = PotTokenType::ColdStart;
         let json = serde_json::to_string(&cold_start).unwrap();
         assert_eq!(json, "\"ColdStart\"");
+
+        // The snake_case names of `as_str` are accepted too
+        let parsed: PotTokenType = serde_json::from_str("\"content_bound\"").unwrap();
+        assert_eq!(parsed, content_bound);
     }
 
     #[test]
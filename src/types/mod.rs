@@ -7,5 +7,11 @@ pub mod request;
 pub mod response;
 
 pub use internal::*;
-pub use request::{InvalidateRequest, InvalidationType, PotRequest};
-pub use response::{ErrorResponse, MinterCacheResponse, PingResponse, PotResponse};
+pub use request::{InvalidateRequest, InvalidationType, PotRequest, PurgeRequest};
+#[cfg(feature = "provider")]
+pub use response::UsageResponse;
+pub use response::{
+    CacheDump, CacheImportResponse, ErrorResponse, MinterCacheResponse, MinterInfo, MinterStatus,
+    PingResponse, PotResponse, PurgeResponse, RecentError, SloResponse, StatsResponse,
+    UpstreamConnections,
+};
@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 
+pub use super::internal::{ChallengeData, TrustedResourceUrl};
+
 /// BotGuard challenge data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -14,63 +16,119 @@ pub enum Challenge {
     Data(ChallengeData),
 }
 
-/// Structured challenge data from BotGuard
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChallengeData {
-    /// Interpreter URL wrapper
-    #[serde(rename = "interpreterUrl")]
-    pub interpreter_url: InterpreterUrl,
-
-    /// Hash of the interpreter
-    #[serde(rename = "interpreterHash")]
-    pub interpreter_hash: String,
-
-    /// BotGuard program code
-    pub program: String,
-
-    /// Global name for the BotGuard instance
-    #[serde(rename = "globalName")]
-    pub global_name: String,
-
-    /// Client experiments state blob
-    #[serde(rename = "clientExperimentsStateBlob")]
-    pub client_experiments_state_blob: String,
+impl Challenge {
+    /// Get the structured challenge data
+    ///
+    /// String challenges are parsed as JSON challenge data, as sent by
+    /// plugin versions serializing the challenge twice. Returns `None` for
+    /// opaque string challenges.
+    pub fn to_data(&self) -> Option<ChallengeData> {
+        match self {
+            Self::Data(data) => Some(data.clone()),
+            Self::String(challenge) => serde_json::from_str(challenge).ok(),
+        }
+    }
 }
 
-/// Interpreter URL wrapper (Google's trusted resource URL format)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InterpreterUrl {
-    /// The actual URL wrapped in Google's trusted resource format
-    #[serde(rename = "privateDoNotAccessOrElseTrustedResourceUrlWrappedValue")]
-    pub private_do_not_access_or_else_trusted_resource_url_wrapped_value: String,
+impl From<ChallengeData> for Challenge {
+    fn from(data: ChallengeData) -> Self {
+        Self::Data(data)
+    }
 }
 
 /// Request for POT token generation
+///
+/// Fields are snake_case; the camelCase spelling sent by some plugin
+/// versions is accepted as an alias.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PotRequest {
     /// Content binding for the token (video ID, visitor data, etc.)
+    #[serde(alias = "contentBinding")]
     pub content_binding: Option<String>,
 
     /// Proxy configuration for requests
     pub proxy: Option<String>,
 
     /// Whether to bypass cache and generate fresh token
+    #[serde(alias = "bypassCache")]
+    #[serde(default, deserialize_with = "deserialize_flexible_bool")]
     pub bypass_cache: Option<bool>,
 
+    /// Whether the token is used once only, so it is neither served from nor
+    /// written to the cache
+    #[serde(alias = "singleUse")]
+    #[serde(default, deserialize_with = "deserialize_flexible_bool")]
+    pub single_use: Option<bool>,
+
     /// BotGuard challenge from Innertube (can be string or structured data)
     pub challenge: Option<Challenge>,
 
     /// Whether to disable challenges from Innertube
+    #[serde(alias = "disableInnertube")]
+    #[serde(default, deserialize_with = "deserialize_flexible_bool")]
     pub disable_innertube: Option<bool>,
 
     /// Whether to disable TLS certificate verification
+    #[serde(alias = "disableTlsVerification")]
+    #[serde(default, deserialize_with = "deserialize_flexible_bool")]
     pub disable_tls_verification: Option<bool>,
 
     /// Innertube context object
+    #[serde(alias = "innertubeContext")]
     pub innertube_context: Option<serde_json::Value>,
 
     /// Client-side IP address to bind to
+    #[serde(alias = "sourceAddress")]
     pub source_address: Option<String>,
+
+    /// Extra headers for Innertube requests, overriding `network.extra_headers`
+    #[serde(alias = "innertubeHeaders")]
+    pub innertube_headers: Option<std::collections::HashMap<String, String>>,
+
+    /// Token type requested, inferred from the content binding if unset
+    ///
+    /// Set by the `/get_pot/{alias}` endpoints of `server.token_type_aliases`.
+    #[serde(default, alias = "tokenType")]
+    pub token_type: Option<crate::types::PotTokenType>,
+}
+
+/// Deserialize an optional boolean leniently
+///
+/// Python clients may send `0`/`1` or strings instead of JSON booleans, so
+/// accept `true`/`false`, integers `0`/`1`, the strings `"true"`/`"false"`/
+/// `"1"`/`"0"`/`"yes"`/`"no"` (case-insensitive), and `null`.
+fn deserialize_flexible_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FlexibleBool {
+        Bool(bool),
+        Int(i64),
+        String(String),
+    }
+
+    match Option::<FlexibleBool>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(FlexibleBool::Bool(value)) => Ok(Some(value)),
+        Some(FlexibleBool::Int(0)) => Ok(Some(false)),
+        Some(FlexibleBool::Int(1)) => Ok(Some(true)),
+        Some(FlexibleBool::Int(other)) => Err(D::Error::custom(format!(
+            "invalid boolean value: {}, expected 0 or 1",
+            other
+        ))),
+        Some(FlexibleBool::String(value)) => match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Some(true)),
+            "false" | "0" | "no" => Ok(Some(false)),
+            _ => Err(D::Error::custom(format!(
+                "invalid boolean value: {:?}",
+                value
+            ))),
+        },
+    }
 }
 
 /// Challenge invalidation request
@@ -95,16 +153,83 @@ impl InvalidateRequest {
     pub fn integrity_token() -> Self {
         Self::new(InvalidationType::IntegrityToken)
     }
+
+    /// Create a visitor data invalidation request
+    pub fn visitor_data() -> Self {
+        Self::new(InvalidationType::VisitorData)
+    }
+}
+
+/// Request for `POST /purge`, removing everything stored for a content binding
+///
+/// At least one field must be set; with both, only entries matching both are
+/// removed. The camelCase spelling of the fields is accepted as an alias.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PurgeRequest {
+    /// Content binding to purge, in every namespace unless `namespace` is set
+    #[serde(default, alias = "contentBinding")]
+    pub content_binding: Option<String>,
+
+    /// Namespace to purge, every content binding in it unless `content_binding` is set
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+impl PurgeRequest {
+    /// Create a request purging `content_binding`
+    pub fn content_binding(content_binding: impl Into<String>) -> Self {
+        Self {
+            content_binding: Some(content_binding.into()),
+            namespace: None,
+        }
+    }
+
+    /// Create a request purging every content binding in `namespace`
+    pub fn namespace(namespace: impl Into<String>) -> Self {
+        Self {
+            content_binding: None,
+            namespace: Some(namespace.into()),
+        }
+    }
+
+    /// Whether the session cache key `key` is to be purged
+    ///
+    /// Keys are formatted by [`crate::session::SessionCacheKey`] as
+    /// `[<namespace>/]<binding>[#<token type>]`. A request without any field
+    /// matches nothing.
+    pub fn matches(&self, key: &str) -> bool {
+        if self.content_binding.is_none() && self.namespace.is_none() {
+            return false;
+        }
+        let (namespace, binding) = match key.split_once('/') {
+            Some((namespace, binding)) => (Some(namespace), binding),
+            None => (None, key),
+        };
+        let binding = binding
+            .split_once('#')
+            .map_or(binding, |(binding, _)| binding);
+        self.namespace
+            .as_deref()
+            .is_none_or(|expected| namespace == Some(expected))
+            && self
+                .content_binding
+                .as_deref()
+                .is_none_or(|expected| binding == expected)
+    }
 }
 
 /// Type of invalidation operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InvalidationType {
     /// Invalidate cached tokens
+    #[serde(alias = "caches")]
     Caches,
     /// Invalidate integrity token
     #[serde(rename = "IT")]
     IntegrityToken,
+    /// Invalidate the visitor data reused for requests without a content binding
+    #[serde(alias = "visitor_data")]
+    VisitorData,
 }
 
 impl Default for PotRequest {
@@ -113,11 +238,14 @@ impl Default for PotRequest {
             content_binding: None,
             proxy: None,
             bypass_cache: Some(false),
+            single_use: None,
             challenge: None,
             disable_innertube: Some(false),
             disable_tls_verification: Some(false),
             innertube_context: None,
             source_address: None,
+            innertube_headers: None,
+            token_type: None,
         }
     }
 }
@@ -146,6 +274,23 @@ impl PotRequest {
         self
     }
 
+    /// Set single-use flag
+    pub fn with_single_use(mut self, single_use: bool) -> Self {
+        self.single_use = Some(single_use);
+        self
+    }
+
+    /// Whether the token is used once only and must not be cached
+    pub fn is_single_use(&self) -> bool {
+        self.single_use.unwrap_or(false)
+    }
+
+    /// Set the token type, instead of inferring it from the content binding
+    pub fn with_token_type(mut self, token_type: crate::types::PotTokenType) -> Self {
+        self.token_type = Some(token_type);
+        self
+    }
+
     /// Set source address
     pub fn with_source_address(mut self, source_address: impl Into<String>) -> Self {
         self.source_address = Some(source_address.into());
@@ -181,6 +326,15 @@ impl PotRequest {
         self.innertube_context = Some(context);
         self
     }
+
+    /// Set extra headers for Innertube requests
+    pub fn with_innertube_headers(
+        mut self,
+        headers: std::collections::HashMap<String, String>,
+    ) -> Self {
+        self.innertube_headers = Some(headers);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -285,14 +439,11 @@ mod tests {
     #[test]
     fn test_challenge_data_variant() {
         let challenge_data = ChallengeData {
-            interpreter_url: InterpreterUrl {
-                private_do_not_access_or_else_trusted_resource_url_wrapped_value:
-                    "//www.google.com/js/test.js".to_string(),
-            },
+            interpreter_url: TrustedResourceUrl::new("//www.google.com/js/test.js"),
             interpreter_hash: "test_hash".to_string(),
             program: "test_program".to_string(),
             global_name: "test_global".to_string(),
-            client_experiments_state_blob: "test_blob".to_string(),
+            client_experiments_state_blob: Some("test_blob".to_string()),
         };
 
         let challenge = Challenge::Data(challenge_data);
@@ -338,21 +489,21 @@ mod tests {
             assert_eq!(data.interpreter_hash, "test_hash_123");
             assert_eq!(data.program, "program_data");
             assert_eq!(data.global_name, "global_name");
-            assert_eq!(data.client_experiments_state_blob, "blob_data");
+            assert_eq!(
+                data.client_experiments_state_blob.as_deref(),
+                Some("blob_data")
+            );
         }
     }
 
     #[test]
     fn test_pot_request_with_challenge_data() {
         let challenge_data = ChallengeData {
-            interpreter_url: InterpreterUrl {
-                private_do_not_access_or_else_trusted_resource_url_wrapped_value:
-                    "//www.google.com/js/test.js".to_string(),
-            },
+            interpreter_url: TrustedResourceUrl::new("//www.google.com/js/test.js"),
             interpreter_hash: "hash123".to_string(),
             program: "program123".to_string(),
             global_name: "global123".to_string(),
-            client_experiments_state_blob: "blob123".to_string(),
+            client_experiments_state_blob: Some("blob123".to_string()),
         };
 
         let request = PotRequest::new()
@@ -418,19 +569,174 @@ mod tests {
 
     #[test]
     fn test_interpreter_url_serialization() {
-        let url = InterpreterUrl {
-            private_do_not_access_or_else_trusted_resource_url_wrapped_value:
-                "//www.google.com/js/test.js".to_string(),
-        };
+        let url = TrustedResourceUrl::new("//www.google.com/js/test.js");
 
         let json = serde_json::to_string(&url).unwrap();
         assert!(json.contains("privateDoNotAccessOrElseTrustedResourceUrlWrappedValue"));
         assert!(json.contains("//www.google.com/js/test.js"));
 
-        let deserialized: InterpreterUrl = serde_json::from_str(&json).unwrap();
-        assert_eq!(
-            deserialized.private_do_not_access_or_else_trusted_resource_url_wrapped_value,
-            "//www.google.com/js/test.js"
-        );
+        let deserialized: TrustedResourceUrl = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.url(), "//www.google.com/js/test.js");
+    }
+
+    #[test]
+    fn test_challenge_to_data() {
+        let data = ChallengeData {
+            interpreter_url: TrustedResourceUrl::new("//www.google.com/js/test.js"),
+            interpreter_hash: "hash".to_string(),
+            program: "program".to_string(),
+            global_name: "global".to_string(),
+            client_experiments_state_blob: None,
+        };
+
+        assert_eq!(Challenge::from(data.clone()).to_data(), Some(data.clone()));
+        // Challenge data serialized into a string is parsed back
+        let json = serde_json::to_string(&data).unwrap();
+        assert!(!json.contains("clientExperimentsStateBlob"));
+        assert_eq!(Challenge::String(json).to_data(), Some(data));
+        assert_eq!(Challenge::String("opaque".to_string()).to_data(), None);
+    }
+    /// Parse a request with `field` set to the raw JSON `value`
+    fn parse_bool_field(field: &str, value: &str) -> serde_json::Result<PotRequest> {
+        serde_json::from_str(&format!(
+            r#"{{"content_binding": "test", "{}": {}}}"#,
+            field, value
+        ))
+    }
+
+    #[test]
+    fn test_flexible_bool_fields() {
+        let cases = [
+            ("true", Some(true)),
+            ("false", Some(false)),
+            ("1", Some(true)),
+            ("0", Some(false)),
+            (r#""true""#, Some(true)),
+            (r#""False""#, Some(false)),
+            (r#""1""#, Some(true)),
+            (r#""0""#, Some(false)),
+            (r#""yes""#, Some(true)),
+            (r#""no""#, Some(false)),
+            ("null", None),
+        ];
+
+        for field in [
+            "bypass_cache",
+            "single_use",
+            "disable_innertube",
+            "disable_tls_verification",
+        ] {
+            for (raw, expected) in cases {
+                let request = parse_bool_field(field, raw)
+                    .unwrap_or_else(|e| panic!("{} = {} should parse: {}", field, raw, e));
+                let actual = match field {
+                    "bypass_cache" => request.bypass_cache,
+                    "single_use" => request.single_use,
+                    "disable_innertube" => request.disable_innertube,
+                    _ => request.disable_tls_verification,
+                };
+                assert_eq!(actual, expected, "{} = {}", field, raw);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flexible_bool_rejects_invalid_values() {
+        for field in [
+            "bypass_cache",
+            "single_use",
+            "disable_innertube",
+            "disable_tls_verification",
+        ] {
+            for raw in ["2", "-1", r#""maybe""#, "[]"] {
+                assert!(
+                    parse_bool_field(field, raw).is_err(),
+                    "{} = {} should be rejected",
+                    field,
+                    raw
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_flexible_bool_fields_default_to_none() {
+        let request: PotRequest = serde_json::from_str(r#"{"content_binding": "test"}"#).unwrap();
+        assert_eq!(request.bypass_cache, None);
+        assert_eq!(request.single_use, None);
+        assert!(!request.is_single_use());
+        assert_eq!(request.disable_innertube, None);
+        assert_eq!(request.disable_tls_verification, None);
+    }
+    #[test]
+    fn test_pot_request_accepts_snake_and_camel_case() {
+        let fields = [
+            ("content_binding", "contentBinding", r#""video_id""#),
+            ("proxy", "proxy", r#""http://proxy:8080""#),
+            ("bypass_cache", "bypassCache", "true"),
+            ("single_use", "singleUse", "true"),
+            ("challenge", "challenge", r#""challenge_string""#),
+            ("disable_innertube", "disableInnertube", "true"),
+            ("disable_tls_verification", "disableTlsVerification", "true"),
+            ("innertube_context", "innertubeContext", r#"{"client": {}}"#),
+            ("source_address", "sourceAddress", r#""10.0.0.1""#),
+            (
+                "innertube_headers",
+                "innertubeHeaders",
+                r#"{"Accept-Language": "en"}"#,
+            ),
+            ("token_type", "tokenType", r#""content_bound""#),
+        ];
+
+        for (snake, camel, value) in fields {
+            for name in [snake, camel] {
+                let request: PotRequest =
+                    serde_json::from_str(&format!(r#"{{"{}": {}}}"#, name, value))
+                        .unwrap_or_else(|e| panic!("{} should deserialize: {}", name, e));
+                let is_set = match snake {
+                    "content_binding" => request.content_binding.is_some(),
+                    "proxy" => request.proxy.is_some(),
+                    "bypass_cache" => request.bypass_cache == Some(true),
+                    "single_use" => request.is_single_use(),
+                    "challenge" => request.challenge.is_some(),
+                    "disable_innertube" => request.disable_innertube == Some(true),
+                    "disable_tls_verification" => request.disable_tls_verification == Some(true),
+                    "innertube_context" => request.innertube_context.is_some(),
+                    "innertube_headers" => request.innertube_headers.is_some(),
+                    "token_type" => request.token_type.is_some(),
+                    _ => request.source_address.is_some(),
+                };
+                assert!(is_set, "{} was not applied", name);
+            }
+        }
+
+        // Serialization stays snake_case
+        let json = serde_json::to_value(PotRequest::new().with_content_binding("id")).unwrap();
+        assert_eq!(json["content_binding"], "id");
+        assert!(json.get("contentBinding").is_none());
+    }
+
+    #[test]
+    fn test_purge_request_matches() {
+        let request = PurgeRequest::content_binding("dQw4w9WgXcQ");
+        assert!(request.matches("dQw4w9WgXcQ"));
+        assert!(request.matches("tenant/dQw4w9WgXcQ"));
+        assert!(request.matches("dQw4w9WgXcQ#session_bound"));
+        assert!(!request.matches("jNQXAC9IVRw"));
+
+        let request = PurgeRequest::namespace("tenant");
+        assert!(request.matches("tenant/dQw4w9WgXcQ"));
+        assert!(!request.matches("dQw4w9WgXcQ"));
+        assert!(!request.matches("other/dQw4w9WgXcQ"));
+
+        let request = PurgeRequest {
+            namespace: Some("tenant".to_string()),
+            ..PurgeRequest::content_binding("dQw4w9WgXcQ")
+        };
+        assert!(request.matches("tenant/dQw4w9WgXcQ"));
+        assert!(!request.matches("tenant/jNQXAC9IVRw"));
+        assert!(!request.matches("dQw4w9WgXcQ"));
+
+        assert!(!PurgeRequest::default().matches("dQw4w9WgXcQ"));
     }
 }
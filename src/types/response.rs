@@ -1,24 +1,47 @@
 //! Response type definitions
 //!
 //! Defines the structure for POT token generation responses.
+//!
+//! Field casing follows what the yt-dlp plugin and the TypeScript server
+//! expect: `PotResponse` is camelCase, every other response is snake_case.
+//! Each type states its casing explicitly so it can't drift per field.
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Response for POT token generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PotResponse {
     /// The generated POT token
-    #[serde(rename = "poToken")]
     pub po_token: String,
 
     /// The content binding used for token generation
-    #[serde(rename = "contentBinding")]
     pub content_binding: String,
 
     /// Token expiration timestamp
-    #[serde(rename = "expiresAt")]
     pub expires_at: DateTime<Utc>,
+
+    /// Whether the token was minted for a single use and not cached
+    ///
+    /// Only present when set, so reusable tokens keep the original format.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub single_use: bool,
+
+    /// Visitor data the provider generated for the token
+    ///
+    /// Only present when the request had no content binding. Session-bound
+    /// tokens must be used together with this visitor data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visitor_data: Option<String>,
+
+    /// Whether minting failed and the minter's websafe fallback token was
+    /// returned instead
+    ///
+    /// The fallback token isn't bound to the content binding and isn't
+    /// cached. Only present when set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fallback: bool,
 }
 
 impl PotResponse {
@@ -32,9 +55,30 @@ impl PotResponse {
             po_token: po_token.into(),
             content_binding: content_binding.into(),
             expires_at,
+            single_use: false,
+            visitor_data: None,
+            fallback: false,
         }
     }
 
+    /// Set single-use flag
+    pub fn with_single_use(mut self, single_use: bool) -> Self {
+        self.single_use = single_use;
+        self
+    }
+
+    /// Set fallback flag
+    pub fn with_fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Set the visitor data the provider generated for the token
+    pub fn with_visitor_data(mut self, visitor_data: impl Into<String>) -> Self {
+        self.visitor_data = Some(visitor_data.into());
+        self
+    }
+
     /// Check if the token has expired
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
@@ -51,12 +95,16 @@ impl PotResponse {
             po_token: session_data.po_token,
             content_binding: session_data.content_binding,
             expires_at: session_data.expires_at,
+            single_use: false,
+            visitor_data: session_data.visitor_data,
+            fallback: session_data.fallback,
         }
     }
 }
 
 /// Ping response for health checks
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct PingResponse {
     /// Server uptime in seconds
     pub server_uptime: u64,
@@ -77,6 +125,7 @@ impl PingResponse {
 
 /// Error response for API errors
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct ErrorResponse {
     /// Error message
     pub error: String,
@@ -150,6 +199,7 @@ impl ErrorResponse {
 
 /// Minter cache keys response
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct MinterCacheResponse {
     /// List of cache keys
     pub cache_keys: Vec<String>,
@@ -184,11 +234,275 @@ impl MinterCacheResponse {
     }
 }
 
+/// Server statistics for operators, returned by `GET /stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StatsResponse {
+    /// Server uptime in seconds
+    pub server_uptime: u64,
+    /// POT requests received
+    pub requests_total: u64,
+    /// Requests answered from the session data cache
+    pub cache_hits: u64,
+    /// Requests that failed
+    pub errors_total: u64,
+    /// Cached minters and their expiry
+    pub minters: Vec<MinterStatus>,
+    /// Most recent errors, newest first
+    pub recent_errors: Vec<RecentError>,
+    /// Connections opened to each upstream host
+    #[serde(default)]
+    pub upstream_connections: Vec<UpstreamConnections>,
+    /// Requests answered with the response of an identical request, see
+    /// `server.dedupe_window_ms`
+    #[serde(default)]
+    pub duplicates_suppressed: u64,
+    /// Innertube time minus the system time in milliseconds, unknown before
+    /// the first Innertube response
+    #[serde(default)]
+    pub clock_skew_ms: Option<i64>,
+    /// Debugging metadata of the latest Innertube response, unknown before
+    /// the first one
+    #[serde(default)]
+    pub innertube_response_context: Option<super::ResponseContext>,
+    /// Tokens in the session data cache
+    #[serde(default)]
+    pub cache_entries: usize,
+    /// Approximate memory of the session data cache in bytes
+    #[serde(default)]
+    pub cache_bytes: u64,
+    /// Memory budget of the session data cache in bytes, unlimited if 0
+    #[serde(default)]
+    pub cache_max_bytes: u64,
+}
+
+impl StatsResponse {
+    /// Ratio of requests answered from the cache, between 0 and 1
+    pub fn cache_hit_ratio(&self) -> f64 {
+        if self.requests_total == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / self.requests_total as f64
+        }
+    }
+}
+
+/// Service level objective compliance, returned by `GET /slo`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SloResponse {
+    /// Rolling window covered, in seconds
+    pub window_secs: u64,
+    /// Fraction of requests that must succeed within the latency target
+    pub target: f64,
+    /// Latency target in milliseconds
+    pub latency_target_ms: u64,
+    /// Token requests answered in the window
+    pub requests: u64,
+    /// Fraction of requests that succeeded, 1 without requests
+    pub success_rate: f64,
+    /// Fraction of successful requests answered within the latency target
+    pub latency_compliance: f64,
+    /// Fraction of the error budget left, 0 once it is spent
+    pub error_budget_remaining: f64,
+    /// Whether the fraction of requests that succeeded within the latency
+    /// target meets the target
+    pub compliant: bool,
+}
+
+/// Usage analytics returned by `GET /stats/usage`
+#[cfg(feature = "provider")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UsageResponse {
+    /// Number of days covered, including today
+    pub days: u32,
+    /// Request counts per day, token type and outcome, newest first
+    pub entries: Vec<crate::session::UsageEntry>,
+}
+
+/// Result of `POST /purge`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PurgeResponse {
+    /// Number of cached tokens removed
+    pub purged: usize,
+}
+
+/// Caches exported by `GET /cache/export` and imported by `POST /cache/import`
+///
+/// Moves warm caches between provider instances, e.g. when migrating to a
+/// new host. Only unexpired entries are exported and imported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CacheDump {
+    /// Version of the provider that exported the caches
+    pub version: String,
+    /// When the caches were exported
+    pub exported_at: DateTime<Utc>,
+    /// Session data by cache key
+    #[serde(default)]
+    pub session_data: std::collections::BTreeMap<String, crate::types::SessionData>,
+    /// Token minters by cache key
+    #[serde(default)]
+    pub minters: std::collections::BTreeMap<String, crate::types::TokenMinterEntry>,
+}
+
+/// Result of `POST /cache/import`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CacheImportResponse {
+    /// Number of session data entries imported
+    pub session_data: usize,
+    /// Number of token minters imported
+    pub minters: usize,
+}
+
+/// Token minter returned by `POST /get_minter`
+///
+/// Lets advanced integrators mint many POTs client-side from one integrity
+/// token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MinterInfo {
+    /// Minter cache key
+    pub key: String,
+    /// BotGuard integrity token
+    pub integrity_token: String,
+    /// When the integrity token expires
+    pub expires_at: DateTime<Utc>,
+    /// Estimated integrity token TTL in seconds
+    pub estimated_ttl_secs: u32,
+    /// Seconds before expiry at which the minter should be refreshed
+    pub mint_refresh_threshold: u32,
+    /// Websafe fallback token
+    pub websafe_fallback_token: Option<String>,
+}
+
+impl MinterInfo {
+    /// Describe the minter cached under `key`
+    pub fn from_entry(key: impl Into<String>, minter: &crate::types::TokenMinterEntry) -> Self {
+        Self {
+            key: key.into(),
+            integrity_token: minter.integrity_token.clone(),
+            expires_at: minter.expiry,
+            estimated_ttl_secs: minter.estimated_ttl_secs,
+            mint_refresh_threshold: minter.mint_refresh_threshold,
+            websafe_fallback_token: minter.websafe_fallback_token.clone(),
+        }
+    }
+}
+
+/// Cached minter in a [`StatsResponse`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MinterStatus {
+    /// Minter cache key
+    pub key: String,
+    /// When the minter's integrity token expires
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Failed request in a [`StatsResponse`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RecentError {
+    /// When the error occurred
+    pub timestamp: DateTime<Utc>,
+    /// Error category, e.g. `network`
+    pub category: String,
+    /// Error message
+    pub message: String,
+}
+
+/// Connections opened to one upstream host, in a [`StatsResponse`]
+///
+/// Connections through a proxy are counted against the proxy host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UpstreamConnections {
+    /// Host connected to, e.g. `www.youtube.com`
+    pub upstream: String,
+    /// Connections established, pooled connections reused don't count
+    pub connections_created: u64,
+    /// Connection attempts that failed
+    pub connect_failures: u64,
+    /// Average time to establish a connection, in milliseconds
+    pub avg_connect_ms: u64,
+}
+
+/// Convert the top-level keys of a JSON object from snake_case to camelCase
+///
+/// Used by the camelCase compatibility mode (`server.camel_case_responses`).
+/// Nested values such as error `details` are left untouched.
+pub fn to_camel_case_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| (snake_to_camel(&key), value))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        other => other,
+    }
+}
+
+/// Convert a snake_case identifier to camelCase
+fn snake_to_camel(key: &str) -> String {
+    let mut result = String::with_capacity(key.len());
+    let mut upper_next = false;
+    for c in key.chars() {
+        if c == '_' && !result.is_empty() {
+            upper_next = true;
+        } else if upper_next {
+            result.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::Duration;
 
+    #[test]
+    fn test_response_field_casing() {
+        let pot = serde_json::to_value(PotResponse::new("t", "b", Utc::now())).unwrap();
+        let keys: Vec<_> = pot.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, vec!["contentBinding", "expiresAt", "poToken"]);
+        let single_use =
+            serde_json::to_value(PotResponse::new("t", "b", Utc::now()).with_single_use(true))
+                .unwrap();
+        assert_eq!(single_use["singleUse"], true);
+
+        let ping = serde_json::to_value(PingResponse::new(1, "1.0.0")).unwrap();
+        assert!(ping.get("server_uptime").is_some());
+
+        let minter = serde_json::to_value(MinterCacheResponse::empty()).unwrap();
+        assert!(minter.get("cache_keys").is_some());
+    }
+
+    #[test]
+    fn test_to_camel_case_keys() {
+        let ping = serde_json::to_value(PingResponse::new(42, "1.0.0")).unwrap();
+        let converted = to_camel_case_keys(ping);
+        assert_eq!(converted["serverUptime"], 42);
+        assert_eq!(converted["version"], "1.0.0");
+        assert!(converted.get("server_uptime").is_none());
+
+        // Already camelCase and nested keys are preserved
+        let value = serde_json::json!({"poToken": "t", "details": {"inner_key": 1}});
+        let converted = to_camel_case_keys(value);
+        assert_eq!(converted["poToken"], "t");
+        assert_eq!(converted["details"]["inner_key"], 1);
+
+        assert_eq!(snake_to_camel("cache_keys"), "cacheKeys");
+        assert_eq!(snake_to_camel("_private"), "_private");
+    }
+
     #[test]
     fn test_pot_response_creation() {
         let expires_at = Utc::now() + Duration::hours(6);
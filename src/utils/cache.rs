@@ -2,19 +2,47 @@
 //!
 //! Implements persistent storage for session data using JSON files,
 //! following XDG Base Directory Specification.
-
-use crate::{Result, session::manager::SessionDataCaches, types::SessionData};
+//!
+//! Single entry updates are appended to a JSONL journal next to the cache
+//! file and folded back into it once the journal grows past a threshold,
+//! so back-to-back script invocations don't rewrite the whole cache.
+//! Concurrent invocations lock `<cache>.lock` around every read and write,
+//! so a compaction never drops journal lines appended by another process.
+//!
+//! Token minters are kept in a separate file next to the cache file, so the
+//! cache file stays compatible with the TypeScript implementation.
+
+use crate::{
+    Result,
+    session::manager::{MinterCache, SessionDataCaches, SessionDataSnapshot},
+    types::{SessionData, TokenMinterEntry},
+    utils::privacy::LoggedBinding,
+};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::path::PathBuf;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use tracing::{debug, error, warn};
 
+/// Default number of journal entries that triggers compaction
+const DEFAULT_COMPACTION_THRESHOLD: usize = 100;
+
 /// File-based cache manager
 #[derive(Debug)]
 pub struct FileCache {
     /// Path to cache file
     cache_path: PathBuf,
+    /// Path to the JSONL journal of upserts not yet compacted into the cache file
+    journal_path: PathBuf,
+    /// Path to the token minters file
+    minters_path: PathBuf,
+    /// Path to the lock file guarding the cache file and journal
+    lock_path: PathBuf,
+    /// Number of journal entries that triggers compaction
+    compaction_threshold: usize,
+    /// Show content bindings in logs instead of a hash of them
+    reveal_bindings: bool,
 }
 
 /// Serializable cache entry for file storage
@@ -29,56 +57,373 @@ struct CacheEntry {
     /// Expiration timestamp (ISO 8601 format)
     #[serde(rename = "expiresAt")]
     expires_at: String,
+    /// Visitor data generated by the provider for the token
+    #[serde(
+        rename = "visitorData",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    visitor_data: Option<String>,
+}
+
+/// Journal line recording an upsert of a single cache entry
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalRecord<K, E> {
+    /// Cache key
+    key: K,
+    /// Entry stored under the key
+    #[serde(flatten)]
+    entry: E,
+}
+
+/// Borrowed view of a cache entry, serialized identically to [`CacheEntry`]
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    #[serde(rename = "poToken")]
+    po_token: &'a str,
+    #[serde(rename = "contentBinding")]
+    content_binding: &'a str,
+    #[serde(rename = "expiresAt")]
+    expires_at: String,
+    #[serde(rename = "visitorData", skip_serializing_if = "Option::is_none")]
+    visitor_data: Option<&'a str>,
+}
+
+impl<'a> From<&'a SessionData> for CacheEntryRef<'a> {
+    fn from(data: &'a SessionData) -> Self {
+        Self {
+            po_token: &data.po_token,
+            content_binding: &data.content_binding,
+            expires_at: data.expires_at.to_rfc3339(),
+            visitor_data: data.visitor_data.as_deref(),
+        }
+    }
+}
+
+/// Serializes a session data snapshot as the cache file map without copying entries
+struct SnapshotEntries<'a>(&'a SessionDataSnapshot);
+
+impl Serialize for SnapshotEntries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(key, data)| (key.as_str(), CacheEntryRef::from(data.as_ref()))),
+        )
+    }
 }
 
 impl FileCache {
     /// Create new file cache manager
     pub fn new(cache_path: PathBuf) -> Self {
-        Self { cache_path }
+        let journal_path = cache_path.with_extension("jsonl");
+        let minters_path = cache_path.with_extension("minters.json");
+        let lock_path = cache_path.with_extension("lock");
+        Self {
+            cache_path,
+            journal_path,
+            minters_path,
+            lock_path,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+            reveal_bindings: false,
+        }
+    }
+
+    /// Set the number of journal entries that triggers compaction
+    pub fn with_compaction_threshold(mut self, threshold: usize) -> Self {
+        self.compaction_threshold = threshold.max(1);
+        self
+    }
+
+    /// Show content bindings verbatim in logs, see `logging.reveal_content_bindings`
+    pub fn with_revealed_bindings(mut self, reveal: bool) -> Self {
+        self.reveal_bindings = reveal;
+        self
+    }
+
+    /// Get the journal file path
+    pub fn journal_path(&self) -> &std::path::Path {
+        &self.journal_path
     }
 
-    /// Load cache from file
+    /// Get the token minters file path
+    pub fn minters_path(&self) -> &std::path::Path {
+        &self.minters_path
+    }
+
+    /// Load the unexpired token minters
+    ///
+    /// A missing or unreadable file yields no minters, they are created
+    /// again on demand.
+    pub async fn load_minters(&self) -> MinterCache {
+        let content = match fs::read_to_string(&self.minters_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return MinterCache::default(),
+            Err(e) => {
+                warn!("Failed to read minters file {:?}: {}", self.minters_path, e);
+                return MinterCache::default();
+            }
+        };
+        let entries: std::collections::HashMap<String, TokenMinterEntry> =
+            match serde_json::from_str(&content) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!(
+                        "Ignored malformed minters file {:?}: {}",
+                        self.minters_path, e
+                    );
+                    return MinterCache::default();
+                }
+            };
+
+        let minters: MinterCache = entries
+            .into_iter()
+            .filter(|(_, minter)| !minter.is_expired())
+            .map(|(key, minter)| (key.into(), minter))
+            .collect();
+        debug!("Loaded {} token minters", minters.len());
+        minters
+    }
+
+    /// Save the unexpired token minters, replacing the saved ones
+    pub async fn save_minters(&self, minters: &MinterCache) -> Result<()> {
+        let entries: std::collections::BTreeMap<_, _> = minters
+            .iter()
+            .filter(|(_, minter)| !minter.is_expired())
+            .map(|(key, minter)| (key.as_str(), minter))
+            .collect();
+        let content = serde_json::to_string_pretty(&entries)?;
+
+        self.ensure_parent_dir().await?;
+        fs::write(&self.minters_path, content)
+            .await
+            .map_err(|e| crate::Error::cache("minters_write", &format!("Write failed: {}", e)))?;
+        debug!(
+            "Saved {} token minters to {:?}",
+            entries.len(),
+            self.minters_path
+        );
+        Ok(())
+    }
+
+    /// Load cache from file, replaying any journaled upserts
     ///
     /// Corresponds to TypeScript cache loading logic (L75-105)
     pub async fn load_cache(&self) -> Result<SessionDataCaches> {
-        if !self.cache_path.exists() {
+        // Without a cache directory there is nothing to lock or load
+        let _lock = self
+            .lock(false)
+            .await
+            .inspect_err(|e| debug!("Loading cache without lock: {}", e))
+            .ok();
+        self.read_cache().await
+    }
+
+    /// Read the cache file and replay the journal, with the lock held
+    async fn read_cache(&self) -> Result<SessionDataCaches> {
+        let mut session_caches = if !self.cache_path.exists() {
             debug!("Cache file does not exist: {:?}", self.cache_path);
-            return Ok(SessionDataCaches::new());
+            SessionDataCaches::new()
+        } else {
+            match fs::read_to_string(&self.cache_path).await {
+                Ok(content) => {
+                    debug!("Loading cache from: {:?}", self.cache_path);
+                    self.parse_cache_content(&content)?
+                }
+                Err(e) => {
+                    warn!("Failed to read cache file {:?}: {}", self.cache_path, e);
+                    SessionDataCaches::new()
+                }
+            }
+        };
+
+        self.replay_journal(&mut session_caches).await;
+        Ok(session_caches)
+    }
+
+    /// Record an upsert of a single entry
+    ///
+    /// The entry is appended to the journal; once the journal reaches the
+    /// compaction threshold it is folded into the cache file.
+    pub async fn upsert(&self, content_binding: &str, session_data: &SessionData) -> Result<()> {
+        let mut line = serde_json::to_string(&JournalRecord {
+            key: content_binding,
+            entry: CacheEntryRef::from(session_data),
+        })?;
+        line.push('\n');
+
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+        let mut journal = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path)
+            .await
+            .map_err(|e| crate::Error::cache("journal_open", &format!("Open failed: {}", e)))?;
+        journal
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| crate::Error::cache("journal_write", &format!("Append failed: {}", e)))?;
+        journal
+            .flush()
+            .await
+            .map_err(|e| crate::Error::cache("journal_write", &format!("Flush failed: {}", e)))?;
+        drop(journal);
+
+        if self.journal_len().await >= self.compaction_threshold {
+            self.compact_locked().await?;
+        }
+        Ok(())
+    }
+
+    /// Fold the journal into the cache file, dropping expired entries
+    pub async fn compact(&self) -> Result<()> {
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+        self.compact_locked().await
+    }
+
+    /// Fold the journal into the cache file, with the lock held
+    async fn compact_locked(&self) -> Result<()> {
+        let caches = self.read_cache().await?;
+        debug!(
+            "Compacting cache journal {:?} into {} entries",
+            self.journal_path,
+            caches.len()
+        );
+        self.write_caches(caches).await
+    }
+
+    /// Trim the cache file and journal to about `max_size` bytes
+    ///
+    /// Does nothing while they fit. Otherwise the journal is compacted and the
+    /// entries expiring first are dropped until the cache file fits. Returns
+    /// the number of entries dropped.
+    pub async fn enforce_max_size(&self, max_size: u64) -> Result<usize> {
+        if self.disk_size().await <= max_size {
+            return Ok(0);
         }
 
-        match fs::read_to_string(&self.cache_path).await {
-            Ok(content) => {
-                debug!("Loading cache from: {:?}", self.cache_path);
-                self.parse_cache_content(&content)
+        let _lock = self.lock(true).await?;
+        let mut entries: Vec<_> = self.read_cache().await?.into_iter().collect();
+        entries.sort_by_key(|(_, data)| std::cmp::Reverse(data.expires_at));
+        // Opening and closing braces of the map
+        let mut size = 4;
+        let mut kept = SessionDataCaches::new();
+        let mut dropped = 0;
+        for (key, data) in entries {
+            // A single-entry map is indented like an entry of the whole file
+            let entry_size = serde_json::to_string_pretty(&std::collections::BTreeMap::from([(
+                key.as_str(),
+                CacheEntryRef::from(&data),
+            )]))?
+            .len() as u64;
+            if dropped == 0 && size + entry_size <= max_size {
+                size += entry_size;
+                kept.insert(key, data);
+            } else {
+                dropped += 1;
             }
-            Err(e) => {
-                warn!("Failed to read cache file {:?}: {}", self.cache_path, e);
-                Ok(SessionDataCaches::new())
+        }
+
+        debug!(
+            "Trimming cache file {:?} to {} entries, about {} bytes",
+            self.cache_path,
+            kept.len(),
+            size
+        );
+        self.write_caches(kept).await?;
+        Ok(dropped)
+    }
+
+    /// Size of the cache file and journal on disk in bytes
+    async fn disk_size(&self) -> u64 {
+        let mut size = 0;
+        for path in [&self.cache_path, &self.journal_path] {
+            if let Ok(metadata) = fs::metadata(path).await {
+                size += metadata.len();
             }
         }
+        size
     }
 
     /// Save cache to file
     ///
     /// Corresponds to TypeScript cache saving logic (L117-127)
     pub async fn save_cache(&self, caches: SessionDataCaches) -> Result<()> {
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+        self.write_caches(caches).await
+    }
+
+    /// Write session data to the cache file, with the lock held
+    async fn write_caches(&self, caches: SessionDataCaches) -> Result<()> {
         let cache_entries = self.convert_to_cache_entries(caches);
         let content = serde_json::to_string_pretty(&cache_entries)?;
+        self.write_cache_file(content).await
+    }
 
-        // Ensure parent directory exists
-        if let Some(parent) = self.cache_path.parent()
-            && let Err(e) = fs::create_dir_all(parent).await
-        {
-            error!("Failed to create cache directory {:?}: {}", parent, e);
-            return Err(crate::Error::cache(
-                "directory_creation",
-                &format!("Directory creation failed: {}", e),
-            ));
-        }
+    /// Save a session data snapshot to file
+    ///
+    /// Serializes straight from the shared entries, so saving does not
+    /// deep-copy the cache first.
+    pub async fn save_snapshot(&self, snapshot: &SessionDataSnapshot) -> Result<()> {
+        let content = serde_json::to_string_pretty(&SnapshotEntries(snapshot))?;
+        self.ensure_parent_dir().await?;
+        let _lock = self.lock(true).await?;
+        self.write_cache_file(content).await
+    }
+
+    /// Lock the cache file and journal, until the returned file is dropped
+    ///
+    /// An advisory lock on a separate file, since the cache file itself is
+    /// replaced by renaming. `exclusive` for writers, shared for readers.
+    async fn lock(&self, exclusive: bool) -> Result<std::fs::File> {
+        let path = self.lock_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            if exclusive {
+                file.lock()?;
+            } else {
+                file.lock_shared()?;
+            }
+            Ok(file)
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+        .map_err(|e| crate::Error::cache("file_lock", &format!("Lock failed: {}", e)))
+    }
 
-        match fs::write(&self.cache_path, content).await {
+    /// Write serialized cache content to the cache file
+    ///
+    /// Written to a temporary file renamed over the cache file, so a crash
+    /// mid-write leaves the previous cache file intact. The cache file then
+    /// holds every entry, so the journal is discarded. Callers hold the lock.
+    async fn write_cache_file(&self, content: String) -> Result<()> {
+        let mut temp_path = self.cache_path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let written = async {
+            let mut file = fs::File::create(&temp_path).await?;
+            file.write_all(content.as_bytes()).await?;
+            file.sync_all().await?;
+            fs::rename(&temp_path, &self.cache_path).await
+        };
+        match written.await {
             Ok(_) => {
                 debug!("Cache saved to: {:?}", self.cache_path);
+                if let Err(e) = fs::remove_file(&self.journal_path).await
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    warn!(
+                        "Failed to remove cache journal {:?}: {}",
+                        self.journal_path, e
+                    );
+                }
                 Ok(())
             }
             Err(e) => {
@@ -91,6 +436,68 @@ impl FileCache {
         }
     }
 
+    /// Ensure the cache directory exists
+    async fn ensure_parent_dir(&self) -> Result<()> {
+        if let Some(parent) = self.cache_path.parent()
+            && let Err(e) = fs::create_dir_all(parent).await
+        {
+            error!("Failed to create cache directory {:?}: {}", parent, e);
+            return Err(crate::Error::cache(
+                "directory_creation",
+                &format!("Directory creation failed: {}", e),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply journaled upserts on top of the loaded cache, later lines winning
+    async fn replay_journal(&self, session_caches: &mut SessionDataCaches) {
+        let content = match fs::read_to_string(&self.journal_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                warn!(
+                    "Failed to read cache journal {:?}: {}",
+                    self.journal_path, e
+                );
+                return;
+            }
+        };
+
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let record: JournalRecord<String, CacheEntry> = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("Ignored malformed cache journal line: {}", e);
+                    continue;
+                }
+            };
+
+            match self.parse_cache_entry(&record.key, record.entry) {
+                Ok(session_data) => {
+                    session_caches.insert(record.key, session_data);
+                }
+                Err(e) => {
+                    session_caches.remove(&record.key);
+                    debug!("Dropped journaled cache entry for '{}': {}", record.key, e);
+                }
+            }
+        }
+    }
+
+    /// Count the entries in the journal
+    async fn journal_len(&self) -> usize {
+        fs::read_to_string(&self.journal_path)
+            .await
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
     /// Parse cache content from JSON
     fn parse_cache_content(&self, content: &str) -> Result<SessionDataCaches> {
         let cache_entries: std::collections::HashMap<String, CacheEntry> =
@@ -110,7 +517,11 @@ impl FileCache {
                     session_caches.insert(content_binding, session_data);
                 }
                 Err(e) => {
-                    warn!("Ignored cache entry for '{}': {}", content_binding, e);
+                    warn!(
+                        "Ignored cache entry for '{}': {}",
+                        LoggedBinding::new(&content_binding, self.reveal_bindings),
+                        e
+                    );
                 }
             }
         }
@@ -132,11 +543,11 @@ impl FileCache {
             return Err(crate::Error::cache("validation", "Entry has expired"));
         }
 
-        Ok(SessionData::new(
-            entry.po_token,
-            content_binding,
-            expires_at,
-        ))
+        let session_data = SessionData::new(entry.po_token, content_binding, expires_at);
+        Ok(match entry.visitor_data {
+            Some(visitor_data) => session_data.with_visitor_data(visitor_data),
+            None => session_data,
+        })
     }
 
     /// Convert session data to cache entries for serialization
@@ -151,6 +562,7 @@ impl FileCache {
                     po_token: session_data.po_token,
                     content_binding: session_data.content_binding.clone(),
                     expires_at: session_data.expires_at.to_rfc3339(),
+                    visitor_data: session_data.visitor_data,
                 };
                 (content_binding, entry)
             })
@@ -175,6 +587,18 @@ pub fn get_cache_path() -> anyhow::Result<PathBuf> {
     Ok(cache_dir.join("cache.json"))
 }
 
+/// Get the cache file path for the given cache settings
+///
+/// Uses `cache_dir` when configured, otherwise the XDG cache path.
+pub fn get_cache_path_for(
+    settings: &crate::config::settings::CacheSettings,
+) -> anyhow::Result<PathBuf> {
+    match &settings.cache_dir {
+        Some(cache_dir) => Ok(PathBuf::from(cache_dir).join("cache.json")),
+        None => get_cache_path(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,6 +618,11 @@ mod tests {
             "test_video_id".to_string(),
             SessionData::new("test_token", "test_video_id", expires_at),
         );
+        session_caches.insert(
+            "CgtWaXNpdG9yRGF0YQ".to_string(),
+            SessionData::new("visitor_token", "CgtWaXNpdG9yRGF0YQ", expires_at)
+                .with_visitor_data("CgtWaXNpdG9yRGF0YQ"),
+        );
 
         // Save cache
         file_cache.save_cache(session_caches.clone()).await.unwrap();
@@ -201,10 +630,204 @@ mod tests {
         // Load cache
         let loaded_caches = file_cache.load_cache().await.unwrap();
 
-        assert_eq!(loaded_caches.len(), 1);
+        assert_eq!(loaded_caches.len(), 2);
         let loaded_entry = loaded_caches.get("test_video_id").unwrap();
         assert_eq!(loaded_entry.po_token, "test_token");
         assert_eq!(loaded_entry.content_binding, "test_video_id");
+        assert_eq!(loaded_entry.visitor_data, None);
+        let loaded_entry = loaded_caches.get("CgtWaXNpdG9yRGF0YQ").unwrap();
+        assert_eq!(
+            loaded_entry.visitor_data.as_deref(),
+            Some("CgtWaXNpdG9yRGF0YQ")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_snapshot_matches_save_cache_format() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let file_cache = FileCache::new(temp_file.path().to_path_buf());
+
+        let expires_at = Utc::now() + Duration::hours(6);
+        let snapshot = vec![(
+            crate::session::CacheKey::new("test_video_id"),
+            std::sync::Arc::new(SessionData::new("test_token", "test_video_id", expires_at)),
+        )];
+        file_cache.save_snapshot(&snapshot).await.unwrap();
+
+        let content = tokio::fs::read_to_string(temp_file.path()).await.unwrap();
+        let entries: std::collections::HashMap<String, CacheEntry> =
+            serde_json::from_str(&content).unwrap();
+        assert_eq!(entries["test_video_id"].po_token, "test_token");
+
+        let loaded_caches = file_cache.load_cache().await.unwrap();
+        assert_eq!(loaded_caches.len(), 1);
+        assert_eq!(loaded_caches["test_video_id"].expires_at, expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let file_cache = FileCache::new(cache_path.clone());
+        let mut caches = SessionDataCaches::new();
+        for hours in 1..=10 {
+            let binding = format!("video_{:02}", hours);
+            caches.insert(
+                binding.clone(),
+                SessionData::new("token", binding, Utc::now() + Duration::hours(hours)),
+            );
+        }
+        file_cache.save_cache(caches).await.unwrap();
+        let size = std::fs::metadata(&cache_path).unwrap().len();
+        assert_eq!(file_cache.enforce_max_size(size).await.unwrap(), 0);
+
+        // The entries expiring first are dropped
+        let dropped = file_cache.enforce_max_size(size / 2).await.unwrap();
+        assert!(dropped >= 5, "{}", dropped);
+        assert!(std::fs::metadata(&cache_path).unwrap().len() <= size / 2);
+        let loaded = file_cache.load_cache().await.unwrap();
+        assert_eq!(loaded.len(), 10 - dropped);
+        assert!(loaded.contains_key("video_10"));
+        assert!(!loaded.contains_key("video_01"));
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_minters() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_cache = FileCache::new(dir.path().join("cache.json"));
+        assert!(file_cache.load_minters().await.is_empty());
+
+        let mut minters = MinterCache::default();
+        minters.insert(
+            "fresh".into(),
+            TokenMinterEntry::new(Utc::now() + Duration::hours(6), "it", 21600, 300, None),
+        );
+        minters.insert(
+            "expired".into(),
+            TokenMinterEntry::new(Utc::now() - Duration::hours(1), "it", 21600, 300, None),
+        );
+        file_cache.save_minters(&minters).await.unwrap();
+        assert_eq!(
+            file_cache.minters_path(),
+            dir.path().join("cache.minters.json")
+        );
+
+        let loaded = file_cache.load_minters().await;
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[&"fresh".into()].integrity_token, "it");
+    }
+
+    #[tokio::test]
+    async fn test_upsert_appends_to_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_cache = FileCache::new(dir.path().join("cache.json"));
+
+        let expires_at = Utc::now() + Duration::hours(6);
+        file_cache
+            .upsert(
+                "video_a",
+                &SessionData::new("token_a", "video_a", expires_at),
+            )
+            .await
+            .unwrap();
+
+        // Only the journal is written until compaction
+        assert!(!dir.path().join("cache.json").exists());
+        assert_eq!(file_cache.journal_len().await, 1);
+
+        let loaded_caches = file_cache.load_cache().await.unwrap();
+        assert_eq!(loaded_caches["video_a"].po_token, "token_a");
+    }
+
+    #[tokio::test]
+    async fn test_journal_overrides_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_cache = FileCache::new(dir.path().join("cache.json"));
+
+        let expires_at = Utc::now() + Duration::hours(6);
+        let mut session_caches = SessionDataCaches::new();
+        session_caches.insert(
+            "video_a".to_string(),
+            SessionData::new("old_token", "video_a", expires_at),
+        );
+        file_cache.save_cache(session_caches).await.unwrap();
+
+        file_cache
+            .upsert(
+                "video_a",
+                &SessionData::new("new_token", "video_a", expires_at),
+            )
+            .await
+            .unwrap();
+        let mut journal = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(file_cache.journal_path())
+            .await
+            .unwrap();
+        journal.write_all(b"not json\n").await.unwrap();
+
+        let loaded_caches = file_cache.load_cache().await.unwrap();
+        assert_eq!(loaded_caches.len(), 1);
+        assert_eq!(loaded_caches["video_a"].po_token, "new_token");
+    }
+
+    #[tokio::test]
+    async fn test_journal_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_cache = FileCache::new(dir.path().join("cache.json")).with_compaction_threshold(2);
+
+        let expires_at = Utc::now() + Duration::hours(6);
+        file_cache
+            .upsert(
+                "video_a",
+                &SessionData::new("token_a", "video_a", expires_at),
+            )
+            .await
+            .unwrap();
+        file_cache
+            .upsert(
+                "video_b",
+                &SessionData::new("token_b", "video_b", expires_at),
+            )
+            .await
+            .unwrap();
+
+        // Reaching the threshold folds the journal into the cache file
+        assert!(!file_cache.journal_path().exists());
+        let content = tokio::fs::read_to_string(dir.path().join("cache.json"))
+            .await
+            .unwrap();
+        let entries: std::collections::HashMap<String, CacheEntry> =
+            serde_json::from_str(&content).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["video_b"].po_token, "token_b");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_upserts_survive_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let expires_at = Utc::now() + Duration::hours(6);
+
+        // One cache per task, like separate script invocations
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let cache_path = cache_path.clone();
+                tokio::spawn(async move {
+                    let key = format!("video_{}", i);
+                    FileCache::new(cache_path)
+                        .with_compaction_threshold(3)
+                        .upsert(&key, &SessionData::new("token", &key, expires_at))
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let loaded = FileCache::new(cache_path).load_cache().await.unwrap();
+        assert_eq!(loaded.len(), 16);
     }
 
     #[tokio::test]
@@ -227,12 +850,14 @@ mod tests {
             po_token: "expired_token".to_string(),
             content_binding: "expired_video".to_string(),
             expires_at: (Utc::now() - Duration::hours(1)).to_rfc3339(), // Expired
+            visitor_data: None,
         };
 
         let valid_entry = CacheEntry {
             po_token: "valid_token".to_string(),
             content_binding: "valid_video".to_string(),
             expires_at: (Utc::now() + Duration::hours(1)).to_rfc3339(), // Valid
+            visitor_data: None,
         };
 
         let mut cache_entries = std::collections::HashMap::new();
@@ -269,6 +894,17 @@ mod tests {
         assert!(result.is_empty());
     }
 
+    #[test]
+    fn test_get_cache_path_for_cache_dir() {
+        let settings = crate::config::settings::CacheSettings {
+            cache_dir: Some("/tmp/custom_cache".to_string()),
+            ..Default::default()
+        };
+
+        let cache_path = get_cache_path_for(&settings).unwrap();
+        assert_eq!(cache_path, PathBuf::from("/tmp/custom_cache/cache.json"));
+    }
+
     #[test]
     fn test_get_cache_path_with_xdg() {
         unsafe {
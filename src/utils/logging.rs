@@ -0,0 +1,107 @@
+//! Logging initialization
+//!
+//! Shared by server mode, script mode and the examples. Initializing twice is
+//! harmless: when a global subscriber is already installed, e.g. by an
+//! embedding application or an earlier test, it is kept instead of panicking.
+
+use crate::config::settings::LoggingSettings;
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Handle returned by [`init`]
+#[derive(Debug)]
+pub struct LoggingGuard {
+    /// Whether this call installed the global subscriber
+    installed: bool,
+}
+
+impl LoggingGuard {
+    /// Whether this call installed the global subscriber
+    ///
+    /// `false` when another subscriber was already installed and kept.
+    pub fn installed(&self) -> bool {
+        self.installed
+    }
+}
+
+/// Build the log filter
+///
+/// Precedence: `logging.verbose` (debug), then the `RUST_LOG` environment
+/// variable, then the `logging.filter` directives, then `logging.level`.
+pub fn env_filter(logging: &LoggingSettings) -> EnvFilter {
+    build_filter(logging, std::env::var("RUST_LOG").ok())
+}
+
+/// Build the log filter given the `RUST_LOG` value
+fn build_filter(logging: &LoggingSettings, rust_log: Option<String>) -> EnvFilter {
+    if logging.verbose {
+        EnvFilter::new("debug")
+    } else if let Some(rust_log) = rust_log {
+        EnvFilter::new(rust_log)
+    } else if let Some(filter) = &logging.filter {
+        EnvFilter::new(filter)
+    } else {
+        EnvFilter::new(&logging.level)
+    }
+}
+
+/// Install the global subscriber logging to stderr, unless one is installed
+///
+/// Logs go to stderr so they never mix with the JSON script mode prints on
+/// stdout.
+pub fn init(logging: &LoggingSettings) -> LoggingGuard {
+    let installed = tracing_subscriber::registry()
+        .with(env_filter(logging))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .is_ok();
+    if !installed {
+        tracing::debug!("Logging already initialized, keeping the existing subscriber");
+    }
+    LoggingGuard { installed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_is_idempotent() {
+        let logging = LoggingSettings::default();
+        let first = init(&logging);
+        let second = init(&logging);
+        // At most one call installs the subscriber, none panics
+        assert!(!(first.installed() && second.installed()));
+        assert!(!second.installed());
+    }
+
+    #[test]
+    fn test_filter_directives() {
+        let logging = LoggingSettings {
+            level: "error".to_string(),
+            filter: Some("bgutil_ytdlp_pot_provider=debug,hyper=warn".to_string()),
+            ..Default::default()
+        };
+        let filter = build_filter(&logging, None).to_string();
+        assert!(
+            filter.contains("bgutil_ytdlp_pot_provider=debug"),
+            "{}",
+            filter
+        );
+        assert!(filter.contains("hyper=warn"), "{}", filter);
+
+        // RUST_LOG takes precedence over the configured directives
+        let filter = build_filter(&logging, Some("warn".to_string())).to_string();
+        assert_eq!(filter, "warn");
+    }
+
+    #[test]
+    fn test_verbose_filter() {
+        let logging = LoggingSettings {
+            verbose: true,
+            level: "error".to_string(),
+            ..Default::default()
+        };
+        let filter = format!("{:?}", env_filter(&logging)).to_lowercase();
+        assert!(filter.contains("debug"), "{}", filter);
+    }
+}
@@ -0,0 +1,151 @@
+//! Message catalog for user-facing CLI messages
+//!
+//! Messages printed to the terminal by the CLI are looked up here in the
+//! language set by `logging.locale` instead of being hardcoded at the call
+//! sites. `tracing` log records stay in English so they remain searchable.
+
+use std::fmt::Display;
+
+/// Language of user-facing CLI messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English
+    #[default]
+    En,
+    /// Traditional Chinese (Taiwan)
+    ZhTw,
+}
+
+/// A user-facing CLI message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    /// `--data-sync-id` was passed; takes no arguments
+    DeprecatedDataSyncId,
+    /// `--visitor-data` was passed; takes no arguments
+    DeprecatedVisitorData,
+    /// The cache path could not be resolved; takes the error
+    CachePathFailed,
+    /// Token generation failed; takes the error
+    GenerationFailed,
+    /// The configuration could not be loaded; takes the error
+    ConfigLoadFailed,
+    /// The configuration is invalid and nothing was generated; takes the error
+    ConfigInvalid,
+    /// Generate mode was run without the `generate` subcommand; takes no arguments
+    BareFlagsDeprecated,
+}
+
+impl Locale {
+    /// Locale names accepted by `logging.locale`
+    pub const SUPPORTED: &'static [&'static str] = &["en", "zh-TW"];
+
+    /// Parse a locale name such as `en`, `en-US`, `zh-TW` or `zh_TW.UTF-8`
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.split('.').next().unwrap_or_default();
+        let name = name.replace('_', "-").to_lowercase();
+        match name.as_str() {
+            "en" => Some(Self::En),
+            _ if name.starts_with("en-") => Some(Self::En),
+            "zh-tw" | "zh-hant" | "zh-hant-tw" => Some(Self::ZhTw),
+            _ => None,
+        }
+    }
+
+    /// Get the locale configured in `logging.locale`, English if unsupported
+    pub fn from_settings(settings: &crate::Settings) -> Self {
+        Self::parse(&settings.logging.locale).unwrap_or_default()
+    }
+
+    /// Get the locale from the `LOG_LOCALE` environment variable
+    ///
+    /// For messages printed before the configuration is loaded.
+    pub fn from_env() -> Self {
+        std::env::var("LOG_LOCALE")
+            .ok()
+            .and_then(|name| Self::parse(&name))
+            .unwrap_or_default()
+    }
+
+    /// Get the message template, with a `{}` placeholder per argument
+    pub fn template(self, message: Message) -> &'static str {
+        match (self, message) {
+            (Self::En, Message::DeprecatedDataSyncId) => {
+                "Data sync id is deprecated, use --content-binding instead"
+            }
+            (Self::ZhTw, Message::DeprecatedDataSyncId) => {
+                "Data sync id 已棄用，請改用 --content-binding"
+            }
+            (Self::En, Message::DeprecatedVisitorData) => {
+                "Visitor data is deprecated, use --content-binding instead"
+            }
+            (Self::ZhTw, Message::DeprecatedVisitorData) => {
+                "Visitor data 已棄用，請改用 --content-binding"
+            }
+            (Self::En, Message::CachePathFailed) => "Failed to resolve cache path. Error: {}",
+            (Self::ZhTw, Message::CachePathFailed) => "無法解析快取路徑。錯誤：{}",
+            (Self::En, Message::GenerationFailed) => "Failed while generating POT. Error: {}",
+            (Self::ZhTw, Message::GenerationFailed) => "產生 POT 時失敗。錯誤：{}",
+            (Self::En, Message::ConfigLoadFailed) => {
+                "Warning: Failed to load configuration: {}. Using defaults."
+            }
+            (Self::ZhTw, Message::ConfigLoadFailed) => "警告：無法載入設定：{}。將使用預設值。",
+            (Self::En, Message::ConfigInvalid) => "Failed to load configuration. Error: {}",
+            (Self::ZhTw, Message::ConfigInvalid) => "無法載入設定。錯誤：{}",
+            (Self::En, Message::BareFlagsDeprecated) => {
+                "Warning: Running generate mode without a subcommand is deprecated, use `bgutil-pot generate` instead"
+            }
+            (Self::ZhTw, Message::BareFlagsDeprecated) => {
+                "警告：不加子命令執行 generate 模式已棄用，請改用 `bgutil-pot generate`"
+            }
+        }
+    }
+
+    /// Format a message, filling its placeholders with `args` in order
+    pub fn format(self, message: Message, args: &[&dyn Display]) -> String {
+        let mut parts = self.template(message).split("{}");
+        let mut out = parts.next().unwrap_or_default().to_string();
+        let mut args = args.iter();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+            out.push_str(part);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("zh-TW"), Some(Locale::ZhTw));
+        assert_eq!(Locale::parse("zh_tw"), Some(Locale::ZhTw));
+        assert_eq!(Locale::parse("zh-Hant"), Some(Locale::ZhTw));
+        assert_eq!(Locale::parse("fr"), None);
+        for name in Locale::SUPPORTED {
+            assert!(Locale::parse(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_format_message() {
+        let error = "disk full";
+        assert_eq!(
+            Locale::En.format(Message::CachePathFailed, &[&error]),
+            "Failed to resolve cache path. Error: disk full"
+        );
+        assert_eq!(
+            Locale::ZhTw.format(Message::CachePathFailed, &[&error]),
+            "無法解析快取路徑。錯誤：disk full"
+        );
+        assert_eq!(
+            Locale::ZhTw.format(Message::DeprecatedVisitorData, &[]),
+            "Visitor data 已棄用，請改用 --content-binding"
+        );
+    }
+}
@@ -2,7 +2,19 @@
 //!
 //! This module contains utility functions used throughout the application.
 
+#[cfg(feature = "provider")]
 pub mod cache;
+#[cfg(feature = "provider")]
+pub mod logging;
+pub mod messages;
+#[cfg(feature = "provider")]
+pub mod privacy;
+#[cfg(feature = "provider")]
+pub mod progress;
+#[cfg(feature = "provider")]
+pub mod timeline;
+#[cfg(feature = "provider")]
+pub mod trace_context;
 pub mod version;
 
 pub use version::{VERSION, get_version};
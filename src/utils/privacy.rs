@@ -0,0 +1,69 @@
+//! Content binding privacy
+//!
+//! Content bindings identify what a user watches (video IDs) or who they are
+//! (visitor data), so logs and server events show a short stable hash of
+//! them instead, unless `logging.reveal_content_bindings` is set. The hash
+//! is the same across restarts, so requests for the same binding can still
+//! be correlated.
+
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Write};
+
+/// Hex digits of the SHA-256 shown for a hidden content binding
+const HASH_LEN: usize = 12;
+
+/// A content binding as written to logs, hashed unless revealed
+#[derive(Debug, Clone, Copy)]
+pub struct LoggedBinding<'a> {
+    binding: &'a str,
+    reveal: bool,
+}
+
+impl<'a> LoggedBinding<'a> {
+    /// Wrap `binding`, showing it verbatim only if `reveal` is set
+    pub fn new(binding: &'a str, reveal: bool) -> Self {
+        Self { binding, reveal }
+    }
+}
+
+impl Display for LoggedBinding<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reveal {
+            f.write_str(self.binding)
+        } else {
+            f.write_str(&binding_hash(self.binding))
+        }
+    }
+}
+
+/// Get the short stable hash standing in for `binding`, e.g. `#1f2e3d4c5b6a`
+pub fn binding_hash(binding: &str) -> String {
+    Sha256::digest(binding.as_bytes())
+        .iter()
+        .take(HASH_LEN / 2)
+        .fold(String::from("#"), |mut hash, byte| {
+            let _ = write!(hash, "{:02x}", byte);
+            hash
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logged_binding() {
+        let hidden = LoggedBinding::new("dQw4w9WgXcQ", false).to_string();
+        assert_eq!(hidden.len(), HASH_LEN + 1);
+        assert!(hidden.starts_with('#'));
+        assert!(!hidden.contains("dQw4w9WgXcQ"));
+        // Stable, and distinct per binding
+        assert_eq!(hidden, binding_hash("dQw4w9WgXcQ"));
+        assert_ne!(hidden, binding_hash("jNQXAC9IVRw"));
+
+        assert_eq!(
+            LoggedBinding::new("dQw4w9WgXcQ", true).to_string(),
+            "dQw4w9WgXcQ"
+        );
+    }
+}
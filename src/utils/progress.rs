@@ -0,0 +1,143 @@
+//! Machine-readable progress events
+//!
+//! Emits one JSON object per line as the stages of a POT request start and
+//! finish, so frontends wrapping script mode can show progress during a
+//! cold BotGuard initialization instead of a frozen spinner.
+
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A single progress event, serialized as one JSONL line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    /// A stage started
+    StageStarted {
+        /// Stage name, e.g. `botguard_init`
+        stage: &'a str,
+        /// Milliseconds since the reporter was created
+        elapsed_ms: u64,
+    },
+    /// A stage finished
+    StageFinished {
+        /// Stage name, e.g. `botguard_init`
+        stage: &'a str,
+        /// Stage duration in milliseconds
+        duration_ms: u64,
+        /// Milliseconds since the reporter was created
+        elapsed_ms: u64,
+        /// Outcome, only set for the whole `request` stage
+        #[serde(skip_serializing_if = "Option::is_none")]
+        success: Option<bool>,
+    },
+}
+
+/// Writes progress events as JSON lines
+pub struct ProgressReporter {
+    /// Destination of the events
+    writer: Mutex<Box<dyn Write + Send>>,
+    /// Reference point for `elapsed_ms`
+    epoch: Instant,
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("epoch", &self.epoch)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProgressReporter {
+    /// Create a reporter writing to `writer`
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Create a reporter writing to stderr
+    pub fn stderr() -> Self {
+        Self::new(std::io::stderr())
+    }
+
+    /// Report that `stage` started
+    pub fn stage_started(&self, stage: &str) {
+        self.emit(&ProgressEvent::StageStarted {
+            stage,
+            elapsed_ms: self.elapsed_ms(),
+        });
+    }
+
+    /// Report that `stage`, started at `phase_start`, finished
+    pub fn stage_finished(&self, stage: &str, phase_start: Instant, success: Option<bool>) {
+        self.emit(&ProgressEvent::StageFinished {
+            stage,
+            duration_ms: phase_start.elapsed().as_millis() as u64,
+            elapsed_ms: self.elapsed_ms(),
+            success,
+        });
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    /// Write one event line; progress output never fails the request
+    fn emit(&self, event: &ProgressEvent<'_>) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(writer, "{}", line).and_then(|()| writer.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Writer appending into a shared buffer
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_progress_events_are_jsonl() {
+        let buffer = SharedBuffer::default();
+        let reporter = ProgressReporter::new(buffer.clone());
+
+        let start = Instant::now();
+        reporter.stage_started("botguard_init");
+        reporter.stage_finished("botguard_init", start, None);
+        reporter.stage_finished("request", start, Some(true));
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["event"], "stage_started");
+        assert_eq!(events[0]["stage"], "botguard_init");
+        assert_eq!(events[1]["event"], "stage_finished");
+        assert!(events[1]["duration_ms"].is_u64());
+        assert!(events[1].get("success").is_none());
+        assert_eq!(events[2]["success"], true);
+    }
+}
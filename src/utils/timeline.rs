@@ -0,0 +1,356 @@
+//! Request timeline recording for offline latency analysis
+//!
+//! Records the duration of each phase of a POT request (cache lookup,
+//! visitor data, challenge fetch, integrity token generation, minting) and
+//! appends the finished timelines to a Chrome trace-event file in the JSON
+//! Array Format, one event per line. The closing `]` is optional in that
+//! format, so the file can be loaded in `chrome://tracing` or Perfetto at
+//! any time, also while the server is still writing it.
+
+use super::progress::ProgressReporter;
+use crate::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Maximum number of trace events kept in memory before the oldest are dropped
+const MAX_TRACE_EVENTS: usize = 10_000;
+
+/// A single Chrome trace "complete" event (`"ph": "X"`)
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEvent {
+    /// Phase name shown in the trace viewer
+    pub name: String,
+    /// Event category
+    pub cat: &'static str,
+    /// Event type, always `X` (complete event)
+    pub ph: &'static str,
+    /// Start timestamp in microseconds since the recorder was created
+    pub ts: u64,
+    /// Duration in microseconds
+    pub dur: u64,
+    /// Process ID
+    pub pid: u32,
+    /// Thread ID, used as the request sequence number so each request gets its own row
+    pub tid: u64,
+    /// Additional event arguments
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub args: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Collects request timelines and writes them to a trace-event JSON file
+#[derive(Debug)]
+pub struct TraceRecorder {
+    /// Output file path
+    path: PathBuf,
+    /// Reference point for all timestamps
+    epoch: Instant,
+    /// Sequence number for the next request
+    next_request_id: AtomicU64,
+    /// Most recent events, oldest first
+    events: std::sync::Mutex<VecDeque<TraceEvent>>,
+    /// Trace file, opened again on the next write if creating it failed
+    file: Mutex<Option<tokio::fs::File>>,
+}
+
+impl TraceRecorder {
+    /// Create a new recorder writing to the given path
+    ///
+    /// The trace file is created, or truncated, right away.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = match create_trace_file(&path) {
+            Ok(file) => Some(tokio::fs::File::from_std(file)),
+            Err(e) => {
+                tracing::warn!("Failed to create trace file {}: {}", path.display(), e);
+                None
+            }
+        };
+        Self {
+            path,
+            epoch: Instant::now(),
+            next_request_id: AtomicU64::new(1),
+            events: std::sync::Mutex::new(VecDeque::new()),
+            file: Mutex::new(file),
+        }
+    }
+
+    /// Get the output file path
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Get a snapshot of the most recent events, up to [`MAX_TRACE_EVENTS`]
+    pub fn events(&self) -> Vec<TraceEvent> {
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        events.iter().cloned().collect()
+    }
+
+    /// Append the events of a finished timeline to the trace file
+    ///
+    /// Only the new events are written, so recording stays cheap however
+    /// long the server has been tracing.
+    async fn record(&self, timeline_events: Vec<TraceEvent>) -> Result<()> {
+        let mut lines = Vec::new();
+        for event in &timeline_events {
+            serde_json::to_writer(&mut lines, event)?;
+            lines.extend_from_slice(b",\n");
+        }
+        {
+            let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            events.extend(timeline_events);
+            while events.len() > MAX_TRACE_EVENTS {
+                events.pop_front();
+            }
+        }
+
+        let mut file = self.file.lock().await;
+        let file = match &mut *file {
+            Some(file) => file,
+            None => {
+                let path = self.path.clone();
+                let created = tokio::task::spawn_blocking(move || create_trace_file(&path))
+                    .await
+                    .unwrap_or_else(|e| Err(std::io::Error::other(e)))?;
+                file.insert(tokio::fs::File::from_std(created))
+            }
+        };
+        file.write_all(&lines).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Create or truncate the trace file at `path` and open its array
+fn create_trace_file(path: &Path) -> std::io::Result<std::fs::File> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(b"[\n")?;
+    Ok(file)
+}
+
+/// Timeline of the phases of a single request
+///
+/// A timeline created without a recorder is a no-op, so callers can record
+/// phases unconditionally.
+#[derive(Debug)]
+pub struct RequestTimeline {
+    /// Recorder receiving the timeline, if tracing is enabled
+    recorder: Option<Arc<TraceRecorder>>,
+    /// Request sequence number
+    request_id: u64,
+    /// Time the request started
+    started: Instant,
+    /// Phases recorded so far
+    events: Vec<TraceEvent>,
+    /// Progress reporter notified as phases start and finish
+    progress: Option<Arc<ProgressReporter>>,
+}
+
+impl RequestTimeline {
+    /// Start a new timeline, recording into `recorder` if tracing is enabled
+    pub fn start(recorder: Option<Arc<TraceRecorder>>) -> Self {
+        let request_id = recorder
+            .as_ref()
+            .map(|r| r.next_request_id.fetch_add(1, Ordering::Relaxed))
+            .unwrap_or_default();
+
+        Self {
+            recorder,
+            request_id,
+            started: Instant::now(),
+            events: Vec::new(),
+            progress: None,
+        }
+    }
+
+    /// Report phase progress to `progress`, starting with the request itself
+    pub fn with_progress(mut self, progress: Option<Arc<ProgressReporter>>) -> Self {
+        if let Some(progress) = &progress {
+            progress.stage_started("request");
+        }
+        self.progress = progress;
+        self
+    }
+
+    /// Mark the start of a phase, returning its start time for [`Self::record`]
+    pub fn begin(&self, name: &str) -> Instant {
+        if let Some(progress) = &self.progress {
+            progress.stage_started(name);
+        }
+        Instant::now()
+    }
+
+    /// Check if this timeline is recording
+    pub fn is_enabled(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Record a phase that started at `phase_start` and ends now
+    pub fn record(&mut self, name: &str, phase_start: Instant) {
+        if let Some(progress) = &self.progress {
+            progress.stage_finished(name, phase_start, None);
+        }
+        if let Some(event) = self.event(name, phase_start, serde_json::Map::new()) {
+            self.events.push(event);
+        }
+    }
+
+    /// Finish the timeline and hand it to the recorder
+    ///
+    /// Failures to write the trace file are logged and never affect the request.
+    pub async fn finish(mut self, success: bool) {
+        if let Some(progress) = &self.progress {
+            progress.stage_finished("request", self.started, Some(success));
+        }
+        let mut args = serde_json::Map::new();
+        args.insert("success".to_string(), success.into());
+        let Some(event) = self.event("request", self.started, args) else {
+            return;
+        };
+        self.events.insert(0, event);
+
+        if let Some(recorder) = self.recorder.take()
+            && let Err(e) = recorder.record(std::mem::take(&mut self.events)).await
+        {
+            tracing::warn!(
+                "Failed to write request trace to {:?}: {}",
+                recorder.path(),
+                e
+            );
+        }
+    }
+
+    /// Build a complete event for a phase, or `None` if tracing is disabled
+    fn event(
+        &self,
+        name: &str,
+        phase_start: Instant,
+        args: serde_json::Map<String, serde_json::Value>,
+    ) -> Option<TraceEvent> {
+        let recorder = self.recorder.as_ref()?;
+        let ts = phase_start.saturating_duration_since(recorder.epoch);
+        Some(TraceEvent {
+            name: name.to_string(),
+            cat: "pot",
+            ph: "X",
+            ts: ts.as_micros() as u64,
+            dur: phase_start.elapsed().as_micros() as u64,
+            pid: std::process::id(),
+            tid: self.request_id,
+            args,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Parse a trace file, closing the array left open while recording
+    fn read_trace(path: &std::path::Path) -> Vec<serde_json::Value> {
+        let content = std::fs::read_to_string(path).unwrap();
+        let content = format!("{}]", content.trim_end().trim_end_matches(','));
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_disabled_timeline_is_noop() {
+        let mut timeline = RequestTimeline::start(None);
+        assert!(!timeline.is_enabled());
+
+        timeline.record("cache_lookup", Instant::now());
+        assert!(timeline.events.is_empty());
+        timeline.finish(true).await;
+    }
+
+    #[tokio::test]
+    async fn test_timeline_reports_progress() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("progress.jsonl");
+        let progress = Arc::new(ProgressReporter::new(std::fs::File::create(&path).unwrap()));
+
+        let mut timeline = RequestTimeline::start(None).with_progress(Some(progress));
+        let phase = timeline.begin("botguard_init");
+        timeline.record("botguard_init", phase);
+        timeline.finish(false).await;
+
+        let events: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let summary: Vec<_> = events
+            .iter()
+            .map(|e| (e["event"].as_str().unwrap(), e["stage"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("stage_started", "request"),
+                ("stage_started", "botguard_init"),
+                ("stage_finished", "botguard_init"),
+                ("stage_finished", "request"),
+            ]
+        );
+        assert_eq!(events[3]["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_timeline_writes_chrome_trace_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("traces").join("trace.json");
+        let recorder = Arc::new(TraceRecorder::new(&path));
+
+        let mut timeline = RequestTimeline::start(Some(recorder.clone()));
+        timeline.record("cache_lookup", Instant::now());
+        timeline.record("mint", Instant::now());
+        timeline.finish(true).await;
+
+        let events = read_trace(&path);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["name"], "request");
+        assert_eq!(events[0]["args"]["success"], true);
+        assert_eq!(events[1]["name"], "cache_lookup");
+        assert_eq!(events[2]["name"], "mint");
+        assert!(events.iter().all(|e| e["ph"] == "X" && e["tid"] == 1));
+    }
+
+    #[tokio::test]
+    async fn test_requests_get_distinct_thread_ids() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("trace.json");
+        std::fs::write(&path, "left over from an earlier run").unwrap();
+        let recorder = Arc::new(TraceRecorder::new(&path));
+        // Truncated as soon as the recorder is created
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[\n");
+
+        RequestTimeline::start(Some(recorder.clone()))
+            .finish(true)
+            .await;
+        RequestTimeline::start(Some(recorder.clone()))
+            .finish(false)
+            .await;
+
+        let events = recorder.events();
+        assert_eq!(events.len(), 2);
+        assert_ne!(events[0].tid, events[1].tid);
+        assert_eq!(events[1].args["success"], false);
+
+        // Each timeline is appended, one event per line
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        assert_eq!(read_trace(&path).len(), 2);
+    }
+}
@@ -0,0 +1,139 @@
+//! W3C trace context propagation
+//!
+//! Parses the `traceparent` header of incoming requests so log spans carry
+//! the caller's trace ID, and makes the context available to outbound
+//! requests of the same task, which send it on as a child span when
+//! `network.propagate_trace_context` is enabled.
+
+use std::hash::BuildHasher;
+
+/// Header carrying the trace context
+pub const TRACEPARENT: &str = "traceparent";
+
+tokio::task_local! {
+    /// Trace context of the request handled by the current task
+    static CURRENT: TraceContext;
+}
+
+/// A W3C `traceparent` trace context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// Trace ID, 32 lowercase hex digits
+    pub trace_id: String,
+    /// ID of the calling span, 16 lowercase hex digits
+    pub parent_id: String,
+    /// Trace flags, bit 0 is "sampled"
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value such as
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+    ///
+    /// Returns `None` for malformed values and the all-zero IDs the
+    /// specification declares invalid.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, parent_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        // Version 00 has exactly four fields, later versions may append more
+        if version == "ff" || !is_hex(version, 2) || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Get a context for a new span of the same trace, with this one as parent
+    pub fn child(&self) -> Self {
+        let span_id = std::collections::hash_map::RandomState::new().hash_one(&self.parent_id);
+        Self {
+            trace_id: self.trace_id.clone(),
+            // A zero span ID is invalid
+            parent_id: format!("{:016x}", span_id.max(1)),
+            flags: self.flags,
+        }
+    }
+
+    /// Format as a `traceparent` header value
+    pub fn header_value(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.parent_id, self.flags)
+    }
+
+    /// Run `future` with this as the current trace context
+    pub async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Get the trace context of the current task, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+}
+
+/// Whether `value` is `len` lowercase hex digits
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_traceparent() {
+        let context = TraceContext::parse(HEADER).unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_id, "00f067aa0ba902b7");
+        assert_eq!(context.flags, 1);
+        assert_eq!(context.header_value(), HEADER);
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(TraceContext::parse(invalid).is_none(), "{}", invalid);
+        }
+        // Later versions may carry more fields
+        assert!(
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra")
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_child_and_scope() {
+        let context = TraceContext::parse(HEADER).unwrap();
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.parent_id, context.parent_id);
+        assert!(TraceContext::parse(&child.header_value()).is_some());
+
+        assert!(TraceContext::current().is_none());
+        let current = context
+            .clone()
+            .scope(async { TraceContext::current() })
+            .await;
+        assert_eq!(current, Some(context));
+    }
+}
@@ -28,6 +28,39 @@ fn test_help_flag() {
         .stdout(predicate::str::contains("bypass-cache"));
 }
 
+#[test]
+fn test_generate_subcommand_help() {
+    let mut cmd = cargo_bin_cmd!("bgutil-pot");
+    cmd.args(["generate", "--help"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("content-binding"))
+        .stdout(predicate::str::contains("Exit codes:"));
+}
+
+#[test]
+fn test_generate_subcommand_deprecated_flag() {
+    let mut cmd = cargo_bin_cmd!("bgutil-pot");
+    cmd.args(["generate", "--visitor-data", "deprecated_value"]);
+
+    cmd.assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("Visitor data is deprecated"))
+        .stderr(predicate::str::contains("without a subcommand").not());
+}
+
+#[test]
+fn test_bare_flags_warn_about_deprecation() {
+    let mut cmd = cargo_bin_cmd!("bgutil-pot");
+    cmd.args(["--data-sync-id", "deprecated_value"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "without a subcommand is deprecated",
+    ));
+}
+
 #[test]
 fn test_deprecated_visitor_data_flag() {
     let mut cmd = cargo_bin_cmd!("bgutil-pot");
@@ -35,7 +68,7 @@ fn test_deprecated_visitor_data_flag() {
 
     cmd.assert()
         .failure()
-        .code(1)
+        .code(5)
         .stderr(predicate::str::contains("deprecated"));
 }
 
@@ -46,7 +79,7 @@ fn test_deprecated_data_sync_id_flag() {
 
     cmd.assert()
         .failure()
-        .code(1)
+        .code(5)
         .stderr(predicate::str::contains("deprecated"));
 }
 
@@ -133,3 +166,192 @@ fn test_server_config_flag_with_help() {
         .success()
         .stdout(predicate::str::contains("--config"));
 }
+
+#[cfg(unix)]
+#[test]
+fn test_server_workers_lock_their_own_data_dirs() {
+    use std::time::{Duration, Instant};
+
+    let data_dir = TempDir::new().unwrap();
+    let config = data_dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+[botguard]
+snapshot_path = {:?}
+
+[cache]
+cache_dir = {:?}
+persist_server_cache = true
+"#,
+            data_dir
+                .path()
+                .join("snapshots")
+                .join("botguard_snapshot.bin"),
+            data_dir.path().join("cache")
+        ),
+    )
+    .unwrap();
+
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_bgutil-pot"))
+        .args([
+            "server",
+            "--host",
+            "127.0.0.1",
+            "--port",
+            "0",
+            "--workers",
+            "2",
+        ])
+        .arg("--config")
+        .arg(&config)
+        .spawn()
+        .unwrap();
+
+    // Each worker locks its own snapshot and cache directories
+    let lock_files: Vec<_> = ["snapshots", "cache"]
+        .iter()
+        .flat_map(|dir| (0..2).map(move |index| (dir, index)))
+        .map(|(dir, index)| {
+            data_dir
+                .path()
+                .join(dir)
+                .join(format!("worker-{}", index))
+                .join(".bgutil-pot.lock")
+        })
+        .collect();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let locked = |path: &std::path::PathBuf| {
+        std::fs::read_to_string(path).is_ok_and(|pid| !pid.trim().is_empty())
+    };
+    while !lock_files.iter().all(locked) && Instant::now() < deadline {
+        assert!(server.try_wait().unwrap().is_none(), "server exited");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let all_locked = lock_files.iter().all(locked);
+
+    // SIGTERM shuts the parent down gracefully, which stops the workers
+    std::process::Command::new("kill")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    server.wait().unwrap();
+    assert!(all_locked, "workers didn't lock {:?}", lock_files);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_server_binds_ephemeral_port() {
+    let data_dir = TempDir::new().unwrap();
+    let port_file = data_dir.path().join("server.port");
+    let mut server = spawn_server(data_dir.path(), &port_file, &[]);
+
+    let port = wait_for_port(&mut server, &port_file);
+    let response = http_get(port, "/ping");
+    stop_server(server);
+
+    assert_ne!(port, 0);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_server_workers_answer_requests() {
+    use std::time::{Duration, Instant};
+
+    let data_dir = TempDir::new().unwrap();
+    let port_file = data_dir.path().join("server.port");
+    let mut server = spawn_server(data_dir.path(), &port_file, &["--workers", "1"]);
+    let port = wait_for_port(&mut server, &port_file);
+
+    // The balancer answers 503 until the worker process is ready
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut response = http_get(port, "/ping");
+    while !response.starts_with("HTTP/1.1 200") && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(200));
+        response = http_get(port, "/ping");
+    }
+    stop_server(server);
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+}
+
+/// Start `bgutil-pot server` on an ephemeral port written to `port_file`,
+/// keeping its snapshot and cache in `data_dir`
+#[cfg(unix)]
+fn spawn_server(
+    data_dir: &std::path::Path,
+    port_file: &std::path::Path,
+    args: &[&str],
+) -> std::process::Child {
+    let config = data_dir.join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[botguard]\nsnapshot_path = {:?}\n\n[cache]\ncache_dir = {:?}\n",
+            data_dir.join("snapshots").join("botguard_snapshot.bin"),
+            data_dir.join("cache")
+        ),
+    )
+    .unwrap();
+
+    std::process::Command::new(env!("CARGO_BIN_EXE_bgutil-pot"))
+        .args(["server", "--host", "127.0.0.1", "--port", "0"])
+        .arg("--port-file")
+        .arg(port_file)
+        .arg("--config")
+        .arg(&config)
+        .args(args)
+        .spawn()
+        .unwrap()
+}
+
+/// Wait for `server` to write the port it listens on to `port_file`
+#[cfg(unix)]
+fn wait_for_port(server: &mut std::process::Child, port_file: &std::path::Path) -> u16 {
+    use std::time::{Duration, Instant};
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        if let Some(port) = std::fs::read_to_string(port_file)
+            .ok()
+            .and_then(|port| port.trim().parse().ok())
+        {
+            return port;
+        }
+        assert!(server.try_wait().unwrap().is_none(), "server exited");
+        assert!(Instant::now() < deadline, "server didn't write its port");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Send `GET path` to the server on `port`, returning the raw response
+#[cfg(unix)]
+fn http_get(port: u16, path: &str) -> String {
+    use std::io::{Read, Write};
+
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(30)))
+        .unwrap();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n",
+        path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// Shut `server` down gracefully with SIGTERM
+#[cfg(unix)]
+fn stop_server(mut server: std::process::Child) {
+    std::process::Command::new("kill")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    server.wait().unwrap();
+}
@@ -161,6 +161,8 @@ fn test_default_config_path() {
 }
 
 #[cfg(unix)]
+// Runs the `bgutil-pot` binary, which needs the provider
+#[cfg(feature = "provider")]
 #[test]
 fn test_bgutil_config_with_server_cli() {
     let _lock = ENV_TEST_MUTEX.lock().unwrap();
@@ -202,6 +204,8 @@ port = 4416
     );
 }
 
+// Runs the `bgutil-pot` binary, which needs the provider
+#[cfg(feature = "provider")]
 #[test]
 fn test_cli_args_override_everything() {
     let _lock = ENV_TEST_MUTEX.lock().unwrap();
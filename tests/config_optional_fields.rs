@@ -103,6 +103,231 @@ max_body_size = 2097152
     assert_eq!(settings.server.port, 4416); // Default value
 }
 
+#[test]
+fn test_server_camel_case_responses_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+camel_case_responses = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.server.camel_case_responses);
+    assert_eq!(settings.server.port, 4416); // Default value
+}
+
+#[test]
+fn test_server_admin_token_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+admin_token = "s3cret"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.admin_token.as_deref(), Some("s3cret"));
+    assert_eq!(settings.server.port, 4416); // Default value
+}
+
+#[test]
+fn test_server_admin_token_file_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+admin_token_file = "/run/secrets/admin_token"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.server.admin_token_file,
+        Some(std::path::PathBuf::from("/run/secrets/admin_token"))
+    );
+    assert!(settings.server.admin_token.is_none()); // Default value
+}
+
+#[test]
+fn test_server_usage_analytics_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+usage_analytics = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.server.usage_analytics);
+    assert_eq!(settings.server.usage_retention_days, 90); // Default value
+}
+
+#[test]
+fn test_server_usage_retention_days_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+usage_retention_days = 30
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.usage_retention_days, 30);
+    assert!(!settings.server.usage_analytics); // Default value
+}
+
+#[test]
+fn test_server_daemon_idle_timeout_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+daemon_idle_timeout = 60
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.daemon_idle_timeout, 60);
+}
+
+#[test]
+fn test_server_slo_target_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+slo_target = 0.995
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.slo_target, 0.995);
+}
+
+#[test]
+fn test_server_slo_latency_ms_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+slo_latency_ms = 2000
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.slo_latency_ms, 2000);
+}
+
+#[test]
+fn test_server_slo_window_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+slo_window = 600
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.slo_window, 600);
+}
+
+#[test]
+fn test_server_dedupe_window_ms_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+dedupe_window_ms = 2000
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.dedupe_window_ms, 2000);
+}
+
+#[test]
+fn test_server_port_range_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+port_range = "4416-4426"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.server.port_range().unwrap(), Some(4416..=4426));
+    assert_eq!(settings.server.port, 4416); // Default value
+}
+
+#[test]
+fn test_server_port_file_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+port_file = "/run/bgutil-pot.port"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.server.port_file,
+        Some(std::path::PathBuf::from("/run/bgutil-pot.port"))
+    );
+    assert_eq!(settings.server.port_range, None); // Default value
+}
+
+#[test]
+fn test_server_token_type_aliases_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[server]
+token_type_aliases = {{ gvs = "session_bound", player = "content_bound" }}
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.server.token_type_aliases.get("player"),
+        Some(&bgutil_ytdlp_pot_provider::types::PotTokenType::ContentBound)
+    );
+    assert_eq!(settings.server.port_file, None); // Default value
+}
+
 #[test]
 fn test_server_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
@@ -121,6 +346,19 @@ fn test_server_empty_section() {
     assert_eq!(settings.server.timeout.as_secs(), 30);
     assert!(settings.server.enable_cors);
     assert_eq!(settings.server.max_body_size, 1024 * 1024);
+    assert!(!settings.server.camel_case_responses);
+    assert!(settings.server.admin_token.is_none());
+    assert!(settings.server.admin_token_file.is_none());
+    assert!(!settings.server.usage_analytics);
+    assert_eq!(settings.server.usage_retention_days, 90);
+    assert_eq!(settings.server.daemon_idle_timeout, 300);
+    assert_eq!(settings.server.slo_target, 0.99);
+    assert_eq!(settings.server.slo_latency_ms, 5000);
+    assert_eq!(settings.server.slo_window, 3600);
+    assert_eq!(settings.server.dedupe_window_ms, 0);
+    assert_eq!(settings.server.port_range, None);
+    assert_eq!(settings.server.port_file, None);
+    assert!(settings.server.token_type_aliases.is_empty());
 }
 
 #[test]
@@ -231,530 +469,1171 @@ pot_generation_timeout = 60
 }
 
 #[test]
-fn test_token_empty_section() {
+fn test_token_refresh_ahead_fraction_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[token]
+refresh_ahead_fraction = 0.2
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.token.refresh_ahead_fraction, 0.2);
+}
+
+#[test]
+fn test_token_cancelled_mint_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[token]
+cancelled_mint = "abort"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.token.cancelled_mint, "abort");
+}
+
+#[test]
+fn test_token_correct_clock_skew_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[token]
+correct_clock_skew = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.token.correct_clock_skew);
+}
+
+#[test]
+fn test_token_max_cache_bytes_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[token]
+max_cache_bytes = 1048576
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.token.max_cache_bytes, 1048576);
+}
+
+#[test]
+fn test_token_empty_section() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[token]
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    // All fields should use defaults
+    assert_eq!(settings.token.ttl_hours, 6);
+    assert!(settings.token.enable_cache);
+    assert_eq!(settings.token.max_cache_entries, 1000);
+    assert_eq!(settings.token.cache_cleanup_interval, 60);
+    assert_eq!(settings.token.pot_cache_duration, 1800);
+    assert_eq!(settings.token.pot_generation_timeout, 30);
+    assert_eq!(settings.token.refresh_ahead_fraction, 0.0);
+    assert_eq!(settings.token.cancelled_mint, "complete");
+    assert_eq!(settings.token.clock_skew_warn_secs, 60);
+    assert_eq!(settings.token.max_cache_bytes, 0);
+}
+
+#[test]
+fn test_logging_level_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+level = "debug"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.logging.level, "debug");
+    assert!(!settings.logging.verbose); // Default value
+    assert_eq!(settings.logging.format, "text"); // Default value
+    assert!(settings.logging.log_requests); // Default value
+}
+
+#[test]
+fn test_logging_verbose_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+verbose = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.logging.verbose);
+    assert_eq!(settings.logging.level, "info"); // Default value
+}
+
+#[test]
+fn test_logging_format_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+format = "json"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.logging.format, "json");
+    assert_eq!(settings.logging.level, "info"); // Default value
+}
+
+#[test]
+fn test_logging_log_requests_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+log_requests = false
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(!settings.logging.log_requests);
+    assert_eq!(settings.logging.level, "info"); // Default value
+}
+
+#[test]
+fn test_logging_trace_file_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+trace_file = "/tmp/bgutil-trace.json"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.logging.trace_file,
+        Some(std::path::PathBuf::from("/tmp/bgutil-trace.json"))
+    );
+    assert_eq!(settings.logging.level, "info"); // Default value
+}
+
+#[test]
+fn test_logging_locale_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+locale = "zh-TW"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.logging.locale, "zh-TW");
+}
+
+#[test]
+fn test_logging_filter_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+filter = "bgutil_ytdlp_pot_provider=debug,hyper=warn"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.logging.filter.as_deref(),
+        Some("bgutil_ytdlp_pot_provider=debug,hyper=warn")
+    );
+}
+
+#[test]
+fn test_logging_reveal_content_bindings_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+reveal_content_bindings = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.logging.reveal_content_bindings);
+}
+
+#[test]
+fn test_logging_empty_section() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[logging]
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    // All fields should use defaults
+    assert_eq!(settings.logging.level, "info");
+    assert!(!settings.logging.verbose);
+    assert_eq!(settings.logging.format, "text");
+    assert!(settings.logging.log_requests);
+    assert!(settings.logging.trace_file.is_none());
+    assert_eq!(settings.logging.locale, "en");
+    assert!(settings.logging.filter.is_none());
+    assert!(!settings.logging.reveal_content_bindings);
+}
+
+#[test]
+fn test_network_https_proxy_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+https_proxy = "https://proxy.example.com:8080"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.network.https_proxy,
+        Some("https://proxy.example.com:8080".to_string())
+    );
+    assert_eq!(settings.network.http_proxy, None); // Default value
+    assert_eq!(settings.network.connect_timeout, 30); // Default value
+}
+
+#[test]
+fn test_network_http_proxy_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+http_proxy = "http://proxy.example.com:8080"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.network.http_proxy,
+        Some("http://proxy.example.com:8080".to_string())
+    );
+    assert_eq!(settings.network.https_proxy, None); // Default value
+}
+
+#[test]
+fn test_network_all_proxy_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+all_proxy = "socks5://proxy.example.com:1080"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.network.all_proxy,
+        Some("socks5://proxy.example.com:1080".to_string())
+    );
+    assert_eq!(settings.network.https_proxy, None); // Default value
+}
+
+#[test]
+fn test_network_connect_timeout_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+connect_timeout = 60
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.connect_timeout, 60);
+    assert_eq!(settings.network.request_timeout, 60); // Default value
+}
+
+#[test]
+fn test_network_request_timeout_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+request_timeout = 120
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.request_timeout, 120);
+    assert_eq!(settings.network.connect_timeout, 30); // Default value
+}
+
+#[test]
+fn test_network_max_retries_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+max_retries = 5
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.max_retries, 5);
+    assert_eq!(settings.network.retry_interval, 5000); // Default value
+}
+
+#[test]
+fn test_network_retry_interval_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+retry_interval = 10000
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.retry_interval, 10000);
+    assert_eq!(settings.network.max_retries, 3); // Default value
+}
+
+#[test]
+fn test_network_user_agent_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+user_agent = "Custom User Agent"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.user_agent, "Custom User Agent");
+    assert_eq!(settings.network.connect_timeout, 30); // Default value
+}
+
+#[test]
+fn test_network_pool_idle_timeout_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+pool_idle_timeout = 15
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.pool_idle_timeout, 15);
+    assert_eq!(settings.network.connect_timeout, 30); // Default value
+}
+
+#[test]
+fn test_network_http_version_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+http_version = "http1"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.http_version, "http1");
+    assert_eq!(settings.network.tls_min_version, None); // Default value
+}
+
+#[test]
+fn test_network_extra_headers_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+extra_headers = {{ "Accept-Language" = "de-DE" }}
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings
+            .network
+            .extra_headers
+            .get("Accept-Language")
+            .map(String::as_str),
+        Some("de-DE")
+    );
+    assert_eq!(settings.network.connect_timeout, 30); // Default value
+}
+
+#[test]
+fn test_network_upstream_qps_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+upstream_qps = 2.5
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.network.upstream_qps, 2.5);
+    assert_eq!(settings.network.upstream_burst, 5); // Default value
+}
+
+#[test]
+fn test_network_proxy_credentials_file_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+proxy_credentials_file = "/run/secrets/proxy"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.network.proxy_credentials_file,
+        Some(std::path::PathBuf::from("/run/secrets/proxy"))
+    );
+}
+
+#[test]
+fn test_network_propagate_trace_context_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+propagate_trace_context = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.network.propagate_trace_context);
+}
+
+#[test]
+fn test_network_innertube_record_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+innertube_record = "innertube.jsonl"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        settings.network.innertube_record,
+        Some("innertube.jsonl".into())
+    );
+}
+
+#[test]
+fn test_network_compression_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+compression = false
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(!settings.network.compression);
+}
+
+#[test]
+fn test_network_empty_section() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[network]
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    // All fields should use defaults
+    assert_eq!(settings.network.https_proxy, None);
+    assert_eq!(settings.network.http_proxy, None);
+    assert_eq!(settings.network.all_proxy, None);
+    assert_eq!(settings.network.connect_timeout, 30);
+    assert_eq!(settings.network.request_timeout, 60);
+    assert_eq!(settings.network.max_retries, 3);
+    assert_eq!(settings.network.retry_interval, 5000);
+    assert_eq!(
+        settings.network.user_agent,
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"
+    );
+    assert_eq!(settings.network.pool_idle_timeout, 90);
+    assert_eq!(settings.network.http_version, "auto");
+    assert!(settings.network.extra_headers.is_empty());
+    assert_eq!(settings.network.upstream_qps, 0.0);
+    assert!(settings.network.proxy_credentials_file.is_none());
+    assert!(!settings.network.propagate_trace_context);
+    assert_eq!(settings.network.innertube_record, None);
+    assert!(settings.network.compression);
+}
+
+#[test]
+fn test_botguard_request_key_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[botguard]
+request_key = "CustomRequestKey"
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.botguard.request_key, "CustomRequestKey");
+    assert!(settings.botguard.enable_vm); // Default value
+    assert_eq!(settings.botguard.vm_timeout, 30); // Default value
+}
+
+#[test]
+fn test_botguard_enable_vm_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[botguard]
+enable_vm = false
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(!settings.botguard.enable_vm);
+    assert_eq!(settings.botguard.request_key, "O43z0dpjhgX20SCx4KAo"); // Default value
+}
+
+#[test]
+fn test_botguard_vm_timeout_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[botguard]
+vm_timeout = 60
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert_eq!(settings.botguard.vm_timeout, 60);
+    assert!(settings.botguard.enable_vm); // Default value
+}
+
+#[test]
+fn test_botguard_disable_innertube_only() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+[botguard]
+disable_innertube = true
+        "#
+    )
+    .unwrap();
+
+    let settings = Settings::from_file(temp_file.path()).unwrap();
+    assert!(settings.botguard.disable_innertube);
+    assert!(settings.botguard.enable_vm); // Default value
+}
+
+#[test]
+fn test_botguard_challenge_endpoint_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[token]
+[botguard]
+challenge_endpoint = "https://custom.endpoint.com/challenge"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    // All fields should use defaults
-    assert_eq!(settings.token.ttl_hours, 6);
-    assert!(settings.token.enable_cache);
-    assert_eq!(settings.token.max_cache_entries, 1000);
-    assert_eq!(settings.token.cache_cleanup_interval, 60);
-    assert_eq!(settings.token.pot_cache_duration, 1800);
-    assert_eq!(settings.token.pot_generation_timeout, 30);
+    assert_eq!(
+        settings.botguard.challenge_endpoint,
+        Some("https://custom.endpoint.com/challenge".to_string())
+    );
+    assert!(settings.botguard.enable_vm); // Default value
 }
 
 #[test]
-fn test_logging_level_only() {
+fn test_botguard_user_agent_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[logging]
-level = "debug"
+[botguard]
+user_agent = "Custom BotGuard User Agent"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.logging.level, "debug");
-    assert!(!settings.logging.verbose); // Default value
-    assert_eq!(settings.logging.format, "text"); // Default value
-    assert!(settings.logging.log_requests); // Default value
+    assert_eq!(
+        settings.botguard.user_agent,
+        Some("Custom BotGuard User Agent".to_string())
+    );
+    assert!(settings.botguard.enable_vm); // Default value
 }
 
 #[test]
-fn test_logging_verbose_only() {
+fn test_botguard_disable_snapshot_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[logging]
-verbose = true
+[botguard]
+disable_snapshot = true
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(settings.logging.verbose);
-    assert_eq!(settings.logging.level, "info"); // Default value
+    assert!(settings.botguard.disable_snapshot);
+    assert!(settings.botguard.enable_vm); // Default value
 }
 
 #[test]
-fn test_logging_format_only() {
+fn test_botguard_cpu_affinity_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[logging]
-format = "json"
+[botguard]
+cpu_affinity = [0, 1]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.logging.format, "json");
-    assert_eq!(settings.logging.level, "info"); // Default value
+    assert_eq!(settings.botguard.cpu_affinity, vec![0, 1]);
+    assert!(settings.botguard.nice.is_none()); // Default value
 }
 
 #[test]
-fn test_logging_log_requests_only() {
+fn test_botguard_nice_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[logging]
-log_requests = false
+[botguard]
+nice = 10
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(!settings.logging.log_requests);
-    assert_eq!(settings.logging.level, "info"); // Default value
+    assert_eq!(settings.botguard.nice, Some(10));
+    assert!(settings.botguard.cpu_affinity.is_empty()); // Default value
 }
 
 #[test]
-fn test_logging_empty_section() {
+fn test_botguard_snapshot_dir_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[logging]
+[botguard]
+snapshot_dir = "/var/lib/bgutil/snapshots"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    // All fields should use defaults
-    assert_eq!(settings.logging.level, "info");
-    assert!(!settings.logging.verbose);
-    assert_eq!(settings.logging.format, "text");
-    assert!(settings.logging.log_requests);
+    assert_eq!(
+        settings.botguard.snapshot_dir,
+        Some(std::path::PathBuf::from("/var/lib/bgutil/snapshots"))
+    );
 }
 
 #[test]
-fn test_network_https_proxy_only() {
+fn test_botguard_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-https_proxy = "https://proxy.example.com:8080"
+[botguard]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(
-        settings.network.https_proxy,
-        Some("https://proxy.example.com:8080".to_string())
-    );
-    assert_eq!(settings.network.http_proxy, None); // Default value
-    assert_eq!(settings.network.connect_timeout, 30); // Default value
+    // All fields should use defaults
+    assert_eq!(settings.botguard.request_key, "O43z0dpjhgX20SCx4KAo");
+    assert!(settings.botguard.enable_vm);
+    assert_eq!(settings.botguard.vm_timeout, 30);
+    assert!(!settings.botguard.disable_innertube);
+    assert_eq!(settings.botguard.challenge_endpoint, None);
+    assert_eq!(settings.botguard.user_agent, None);
+    assert!(!settings.botguard.disable_snapshot);
+    assert!(settings.botguard.cpu_affinity.is_empty());
+    assert!(settings.botguard.nice.is_none());
+    assert_eq!(settings.botguard.snapshot_dir, None);
 }
 
 #[test]
-fn test_network_http_proxy_only() {
+fn test_cache_cache_dir_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-http_proxy = "http://proxy.example.com:8080"
+[cache]
+cache_dir = "/tmp/custom_cache"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
     assert_eq!(
-        settings.network.http_proxy,
-        Some("http://proxy.example.com:8080".to_string())
+        settings.cache.cache_dir,
+        Some("/tmp/custom_cache".to_string())
     );
-    assert_eq!(settings.network.https_proxy, None); // Default value
+    assert!(settings.cache.enable_file_cache); // Default value
+    assert_eq!(settings.cache.memory_cache_size, 100); // Default value
 }
 
 #[test]
-fn test_network_all_proxy_only() {
+fn test_cache_enable_file_cache_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-all_proxy = "socks5://proxy.example.com:1080"
+[cache]
+enable_file_cache = false
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(
-        settings.network.all_proxy,
-        Some("socks5://proxy.example.com:1080".to_string())
-    );
-    assert_eq!(settings.network.https_proxy, None); // Default value
+    assert!(!settings.cache.enable_file_cache);
+    assert_eq!(settings.cache.cache_dir, None); // Default value
 }
 
 #[test]
-fn test_network_connect_timeout_only() {
+fn test_cache_memory_cache_size_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-connect_timeout = 60
+[cache]
+memory_cache_size = 200
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.network.connect_timeout, 60);
-    assert_eq!(settings.network.request_timeout, 60); // Default value
+    assert_eq!(settings.cache.memory_cache_size, 200);
+    assert!(settings.cache.enable_file_cache); // Default value
 }
 
 #[test]
-fn test_network_request_timeout_only() {
+fn test_cache_enable_compression_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-request_timeout = 120
+[cache]
+enable_compression = true
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.network.request_timeout, 120);
-    assert_eq!(settings.network.connect_timeout, 30); // Default value
+    assert!(settings.cache.enable_compression);
+    assert!(settings.cache.enable_file_cache); // Default value
 }
 
 #[test]
-fn test_network_max_retries_only() {
+fn test_cache_persist_server_cache_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-max_retries = 5
+[cache]
+persist_server_cache = true
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.network.max_retries, 5);
-    assert_eq!(settings.network.retry_interval, 5000); // Default value
+    assert!(settings.cache.persist_server_cache);
+    assert_eq!(settings.cache.flush_interval, 30); // Default value
 }
 
 #[test]
-fn test_network_retry_interval_only() {
+fn test_cache_flush_interval_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-retry_interval = 10000
+[cache]
+flush_interval = 5
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.network.retry_interval, 10000);
-    assert_eq!(settings.network.max_retries, 3); // Default value
+    assert_eq!(settings.cache.flush_interval, 5);
+    assert!(!settings.cache.persist_server_cache); // Default value
 }
 
 #[test]
-fn test_network_user_agent_only() {
+fn test_cache_retention_max_size_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
-user_agent = "Custom User Agent"
+[cache]
+retention_max_size = 1048576
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.network.user_agent, "Custom User Agent");
-    assert_eq!(settings.network.connect_timeout, 30); // Default value
+    assert_eq!(settings.cache.retention_max_size, 1048576);
 }
 
 #[test]
-fn test_network_empty_section() {
+fn test_cache_retention_max_age_days_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[network]
+[cache]
+retention_max_age_days = 30
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    // All fields should use defaults
-    assert_eq!(settings.network.https_proxy, None);
-    assert_eq!(settings.network.http_proxy, None);
-    assert_eq!(settings.network.all_proxy, None);
-    assert_eq!(settings.network.connect_timeout, 30);
-    assert_eq!(settings.network.request_timeout, 60);
-    assert_eq!(settings.network.max_retries, 3);
-    assert_eq!(settings.network.retry_interval, 5000);
-    assert_eq!(
-        settings.network.user_agent,
-        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"
-    );
+    assert_eq!(settings.cache.retention_max_age_days, 30);
 }
 
 #[test]
-fn test_botguard_request_key_only() {
+fn test_cache_retention_interval_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-request_key = "CustomRequestKey"
+[cache]
+retention_interval = 600
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.botguard.request_key, "CustomRequestKey");
-    assert!(settings.botguard.enable_vm); // Default value
-    assert_eq!(settings.botguard.vm_timeout, 30); // Default value
+    assert_eq!(settings.cache.retention_interval, 600);
 }
 
 #[test]
-fn test_botguard_enable_vm_only() {
+fn test_cache_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-enable_vm = false
+[cache]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(!settings.botguard.enable_vm);
-    assert_eq!(settings.botguard.request_key, "O43z0dpjhgX20SCx4KAo"); // Default value
+    // All fields should use defaults
+    assert_eq!(settings.cache.cache_dir, None);
+    assert!(settings.cache.enable_file_cache);
+    assert_eq!(settings.cache.memory_cache_size, 100);
+    assert!(!settings.cache.enable_compression);
+    assert!(!settings.cache.persist_server_cache);
+    assert_eq!(settings.cache.flush_interval, 30);
+    assert_eq!(settings.cache.retention_max_size, 0);
+    assert_eq!(settings.cache.retention_max_age_days, 0);
+    assert_eq!(settings.cache.retention_interval, 3600);
 }
 
 #[test]
-fn test_botguard_vm_timeout_only() {
+fn test_runtime_worker_threads_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-vm_timeout = 60
+[runtime]
+worker_threads = 4
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.botguard.vm_timeout, 60);
-    assert!(settings.botguard.enable_vm); // Default value
+    assert_eq!(settings.runtime.worker_threads, Some(4));
+    assert_eq!(settings.runtime.max_blocking_threads, 512); // Default value
 }
 
 #[test]
-fn test_botguard_disable_innertube_only() {
+fn test_runtime_max_blocking_threads_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-disable_innertube = true
+[runtime]
+max_blocking_threads = 1024
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(settings.botguard.disable_innertube);
-    assert!(settings.botguard.enable_vm); // Default value
+    assert_eq!(settings.runtime.max_blocking_threads, 1024);
+    assert_eq!(settings.runtime.worker_threads, None); // Default value
 }
 
 #[test]
-fn test_botguard_challenge_endpoint_only() {
+fn test_runtime_thread_stack_size_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-challenge_endpoint = "https://custom.endpoint.com/challenge"
+[runtime]
+thread_stack_size = 4194304
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(
-        settings.botguard.challenge_endpoint,
-        Some("https://custom.endpoint.com/challenge".to_string())
-    );
-    assert!(settings.botguard.enable_vm); // Default value
+    assert_eq!(settings.runtime.thread_stack_size, Some(4194304));
+    assert_eq!(settings.runtime.max_blocking_threads, 512); // Default value
 }
 
 #[test]
-fn test_botguard_user_agent_only() {
+fn test_runtime_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-user_agent = "Custom BotGuard User Agent"
+[runtime]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(
-        settings.botguard.user_agent,
-        Some("Custom BotGuard User Agent".to_string())
-    );
-    assert!(settings.botguard.enable_vm); // Default value
+    // All fields should use defaults
+    assert_eq!(settings.runtime.worker_threads, None);
+    assert_eq!(settings.runtime.max_blocking_threads, 512);
+    assert_eq!(settings.runtime.thread_stack_size, None);
 }
 
 #[test]
-fn test_botguard_disable_snapshot_only() {
+fn test_chaos_innertube_error_rate_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
-disable_snapshot = true
+[chaos]
+innertube_error_rate = 0.25
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(settings.botguard.disable_snapshot);
-    assert!(settings.botguard.enable_vm); // Default value
+    assert_eq!(settings.chaos.innertube_error_rate, 0.25);
+    assert_eq!(settings.chaos.slow_response_ms, 2000); // Default value
 }
 
 #[test]
-fn test_botguard_empty_section() {
+fn test_chaos_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[botguard]
+[chaos]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
     // All fields should use defaults
-    assert_eq!(settings.botguard.request_key, "O43z0dpjhgX20SCx4KAo");
-    assert!(settings.botguard.enable_vm);
-    assert_eq!(settings.botguard.vm_timeout, 30);
-    assert!(!settings.botguard.disable_innertube);
-    assert_eq!(settings.botguard.challenge_endpoint, None);
-    assert_eq!(settings.botguard.user_agent, None);
-    assert!(!settings.botguard.disable_snapshot);
+    assert_eq!(settings.chaos.innertube_error_rate, 0.0);
+    assert_eq!(settings.chaos.botguard_timeout_rate, 0.0);
+    assert_eq!(settings.chaos.slow_response_rate, 0.0);
+    assert_eq!(settings.chaos.slow_response_ms, 2000);
+    assert!(!settings.chaos.is_enabled());
 }
 
 #[test]
-fn test_cache_cache_dir_only() {
+fn test_innertube_base_url_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[cache]
-cache_dir = "/tmp/custom_cache"
+[innertube]
+base_url = "http://127.0.0.1:8080/youtubei/v1"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
     assert_eq!(
-        settings.cache.cache_dir,
-        Some("/tmp/custom_cache".to_string())
+        settings.innertube.base_url,
+        "http://127.0.0.1:8080/youtubei/v1"
     );
-    assert!(settings.cache.enable_file_cache); // Default value
-    assert_eq!(settings.cache.memory_cache_size, 100); // Default value
+    assert_eq!(settings.innertube.api_key, None); // Default value
 }
 
 #[test]
-fn test_cache_enable_file_cache_only() {
+fn test_innertube_api_key_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[cache]
-enable_file_cache = false
+[innertube]
+api_key = "AIzaSyTest"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(!settings.cache.enable_file_cache);
-    assert_eq!(settings.cache.cache_dir, None); // Default value
+    assert_eq!(settings.innertube.api_key.as_deref(), Some("AIzaSyTest"));
+    assert_eq!(
+        settings.innertube.base_url,
+        "https://www.youtube.com/youtubei/v1"
+    ); // Default value
 }
 
 #[test]
-fn test_cache_memory_cache_size_only() {
+fn test_innertube_hl_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[cache]
-memory_cache_size = 200
+[innertube]
+hl = "ja"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert_eq!(settings.cache.memory_cache_size, 200);
-    assert!(settings.cache.enable_file_cache); // Default value
+    assert_eq!(settings.innertube.hl, "ja");
+    assert_eq!(settings.innertube.gl, "US"); // Default value
 }
 
 #[test]
-fn test_cache_enable_compression_only() {
+fn test_innertube_gl_only() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[cache]
-enable_compression = true
+[innertube]
+gl = "JP"
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
-    assert!(settings.cache.enable_compression);
-    assert!(settings.cache.enable_file_cache); // Default value
+    assert_eq!(settings.innertube.gl, "JP");
+    assert_eq!(settings.innertube.hl, "en"); // Default value
 }
 
 #[test]
-fn test_cache_empty_section() {
+fn test_innertube_empty_section() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
-[cache]
+[innertube]
         "#
     )
     .unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
     // All fields should use defaults
-    assert_eq!(settings.cache.cache_dir, None);
-    assert!(settings.cache.enable_file_cache);
-    assert_eq!(settings.cache.memory_cache_size, 100);
-    assert!(!settings.cache.enable_compression);
+    assert_eq!(
+        settings.innertube.base_url,
+        "https://www.youtube.com/youtubei/v1"
+    );
+    assert_eq!(settings.innertube.api_key, None);
+    assert_eq!(settings.innertube.hl, "en");
+    assert_eq!(settings.innertube.gl, "US");
 }
 
 #[test]
 fn test_empty_config_file() {
     let mut temp_file = NamedTempFile::new().unwrap();
-    writeln!(temp_file, "").unwrap();
+    writeln!(temp_file).unwrap();
 
     let settings = Settings::from_file(temp_file.path()).unwrap();
     // All sections and fields should use defaults
@@ -3,5 +3,4 @@
 //! Contains integration tests that test the overall functionality.
 
 pub mod basic_tests;
-pub mod cli_tests;
 pub mod server_tests;
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! Route registry parity tests
+//!
+//! Every endpoint of the registry must be served by the router and listed in
+//! the endpoint table of the API reference.
+
+use axum::http::StatusCode;
+use bgutil_ytdlp_pot_provider::{
+    config::Settings,
+    server::{
+        create_app,
+        routes::{Endpoint, markdown_table},
+    },
+    types::PotTokenType,
+};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn test_every_endpoint_is_mounted() {
+    let mut settings = Settings::default();
+    settings.server.usage_analytics = true;
+    settings.cache.enable_file_cache = false;
+    settings
+        .server
+        .token_type_aliases
+        .insert("gvs".to_string(), PotTokenType::SessionBound);
+    let app = create_app(settings);
+
+    for endpoint in Endpoint::ALL {
+        let uri = endpoint.path().replace("{alias}", "gvs");
+        // Invalid bodies are rejected by mounted handlers, without minting
+        let request = axum::http::Request::builder()
+            .uri(&uri)
+            .method(endpoint.method())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from("not json"))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(
+            !matches!(
+                response.status(),
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+            ),
+            "{} {} is not mounted: {}",
+            endpoint.method(),
+            uri,
+            response.status()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_endpoint_lists_every_endpoint() {
+    let request = axum::http::Request::builder()
+        .uri("/openapi.json")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response = create_app(Settings::default())
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    for endpoint in Endpoint::ALL {
+        let method = endpoint.method().as_str().to_lowercase();
+        assert!(
+            spec["paths"][endpoint.path()][&method].is_object(),
+            "{} {} is missing from the OpenAPI document",
+            endpoint.method(),
+            endpoint.path()
+        );
+    }
+}
+
+#[test]
+fn test_docs_list_every_endpoint() {
+    let docs = include_str!("../docs/api-reference.md");
+    let table = markdown_table();
+    assert!(
+        docs.contains(&table),
+        "The endpoint table of docs/api-reference.md is out of date, replace it with:\n{}",
+        table
+    );
+}
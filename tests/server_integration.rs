@@ -36,6 +36,49 @@ async fn test_server_ping_endpoint() {
     assert!(!ping_response.version.is_empty());
 }
 
+#[tokio::test]
+async fn test_server_rejects_camel_case_deprecated_fields() {
+    for body in [
+        r#"{"contentBinding": "test", "visitorData": "x"}"#,
+        r#"{"contentBinding": "test", "dataSyncId": "x"}"#,
+    ] {
+        let request = axum::http::Request::builder()
+            .uri("/get_pot")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+
+        let response = create_test_app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_server_camel_case_responses() {
+    let mut settings = Settings::default();
+    settings.server.camel_case_responses = true;
+    let app = create_app(settings);
+
+    let request = axum::http::Request::builder()
+        .uri("/ping")
+        .method("GET")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert!(json.get("serverUptime").is_some());
+    assert!(json.get("server_uptime").is_none());
+    assert!(json["version"].is_string());
+}
+
 #[tokio::test]
 async fn test_server_get_pot_endpoint() {
     let app = create_test_app();
@@ -91,6 +134,224 @@ async fn test_server_invalidate_endpoints() {
     assert_eq!(response.status(), StatusCode::NO_CONTENT);
 }
 
+#[tokio::test]
+async fn test_server_invalidate_it_expires_minters() {
+    let mut settings = Settings::default();
+    settings.server.admin_token = Some("s3cret".to_string());
+    let app = create_app(settings);
+    let send = |uri: &str, method: &str, body: Vec<u8>| {
+        let request = axum::http::Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer s3cret")
+            .header("Content-Type", "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        ServiceExt::<axum::http::Request<axum::body::Body>>::oneshot(app.clone(), request)
+    };
+    let minter_expiries = || async {
+        let response = send("/stats", "GET", Vec::new()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: StatsResponse = serde_json::from_slice(&body).unwrap();
+        stats
+            .minters
+            .into_iter()
+            .map(|minter| minter.expires_at)
+            .collect::<Vec<_>>()
+    };
+
+    // Seed a valid minter through the cache import
+    let expiry = chrono::Utc::now() + chrono::Duration::hours(6);
+    let dump = CacheDump {
+        version: "test".to_string(),
+        exported_at: chrono::Utc::now(),
+        session_data: Default::default(),
+        minters: [(
+            "proxy".to_string(),
+            TokenMinterEntry::new(expiry, "integrity_token", 43200, 100, None),
+        )]
+        .into(),
+    };
+    let response = send("/cache/import", "POST", serde_json::to_vec(&dump).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(minter_expiries().await, vec![expiry]);
+
+    let response = send("/invalidate_it", "POST", Vec::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let expiries = minter_expiries().await;
+    assert_eq!(expiries.len(), 1);
+    assert!(expiries[0] <= chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn test_server_purge_endpoint() {
+    let app = create_test_app();
+
+    for (body, expected) in [
+        (r#"{"content_binding": "dQw4w9WgXcQ"}"#, StatusCode::OK),
+        (r#"{"namespace": "tenant"}"#, StatusCode::OK),
+        ("{}", StatusCode::BAD_REQUEST),
+        ("not json", StatusCode::UNPROCESSABLE_ENTITY),
+    ] {
+        let request = axum::http::Request::builder()
+            .uri("/purge")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response =
+            ServiceExt::<axum::http::Request<axum::body::Body>>::oneshot(app.clone(), request)
+                .await
+                .unwrap();
+        assert_eq!(response.status(), expected, "{}", body);
+
+        if expected == StatusCode::OK {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["purged"], 0);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_server_invalidate_requires_admin_token() {
+    let mut settings = Settings::default();
+    settings.server.admin_token = Some("s3cret".to_string());
+    let app = create_app(settings);
+
+    for (authorization, expected) in [
+        (None, StatusCode::UNAUTHORIZED),
+        (Some("Bearer wrong"), StatusCode::UNAUTHORIZED),
+        (Some("Bearer s3cret"), StatusCode::NO_CONTENT),
+    ] {
+        let mut request = axum::http::Request::builder()
+            .uri("/invalidate_caches")
+            .method("POST");
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let request = request.body(axum::body::Body::empty()).unwrap();
+
+        let response =
+            ServiceExt::<axum::http::Request<axum::body::Body>>::oneshot(app.clone(), request)
+                .await
+                .unwrap();
+        assert_eq!(response.status(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_server_proxy_health_requires_admin_token() {
+    let mut settings = Settings::default();
+    settings.server.admin_token = Some("s3cret".to_string());
+    let app = create_app(settings);
+
+    for (authorization, expected) in [
+        (None, StatusCode::UNAUTHORIZED),
+        (Some("Bearer s3cret"), StatusCode::OK),
+    ] {
+        let mut request = axum::http::Request::builder().uri("/proxy_health");
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let request = request.body(axum::body::Body::empty()).unwrap();
+
+        let response =
+            ServiceExt::<axum::http::Request<axum::body::Body>>::oneshot(app.clone(), request)
+                .await
+                .unwrap();
+        assert_eq!(response.status(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_server_get_minter_endpoint() {
+    let mut settings = Settings::default();
+    settings.server.admin_token = Some("s3cret".to_string());
+    let app = create_app(settings);
+
+    let request = axum::http::Request::builder()
+        .uri("/get_minter")
+        .method("POST")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response =
+        ServiceExt::<axum::http::Request<axum::body::Body>>::oneshot(app.clone(), request)
+            .await
+            .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = axum::http::Request::builder()
+        .uri("/get_minter")
+        .method("POST")
+        .header("Authorization", "Bearer s3cret")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let minter: MinterInfo = serde_json::from_slice(&body).unwrap();
+    assert!(!minter.integrity_token.is_empty());
+    assert!(minter.expires_at > chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn test_server_index_page() {
+    let app = create_test_app();
+
+    let request = axum::http::Request::builder()
+        .uri("/")
+        .method("GET")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+}
+
+#[tokio::test]
+async fn test_server_usage_stats_endpoint() {
+    let request = || {
+        axum::http::Request::builder()
+            .uri("/stats/usage?days=3")
+            .method("GET")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+
+    // Disabled by default
+    let response = create_test_app().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let mut settings = Settings::default();
+    settings.server.usage_analytics = true;
+    settings.cache.enable_file_cache = false;
+    let response = create_app(settings).oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: UsageResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(usage.days, 3);
+    assert!(usage.entries.is_empty());
+}
+
 #[tokio::test]
 async fn test_server_minter_cache_endpoint() {
     let app = create_test_app();